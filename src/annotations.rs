@@ -0,0 +1,132 @@
+use image::{Rgba, RgbaImage};
+use std::sync::{Arc, Mutex};
+
+use crate::screen_feed::FrameEffect;
+
+/// Line width of every mark, in screen pixels.
+pub const STROKE_WIDTH: f32 = 4.0;
+
+/// Length of the two lines forming an arrow's head, in screen pixels.
+const ARROW_HEAD: f32 = 18.0;
+
+/// Marks drawn on the screen preview, newest last. Shared with the piped
+/// screen feed when they are burned into the recording.
+pub type SharedAnnotations = Arc<Mutex<Vec<Annotation>>>;
+
+/// Drawing tool of the annotation toolbar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnnotationTool {
+    #[default]
+    Pen,
+    Rectangle,
+    Arrow,
+}
+
+impl AnnotationTool {
+    pub const ALL: [AnnotationTool; 3] = [AnnotationTool::Pen, AnnotationTool::Rectangle, AnnotationTool::Arrow];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AnnotationTool::Pen => "✏ Pen",
+            AnnotationTool::Rectangle => "▭ Rectangle",
+            AnnotationTool::Arrow => "➡ Arrow",
+        }
+    }
+}
+
+/// One mark in screen pixel coordinates. A pen stroke keeps every point it
+/// passed through; rectangles and arrows keep where the drag started and
+/// where it is now.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub tool: AnnotationTool,
+    pub points: Vec<(f32, f32)>,
+    pub color: [u8; 3],
+}
+
+impl Annotation {
+    pub fn new(tool: AnnotationTool, at: (f32, f32), color: [u8; 3]) -> Self {
+        Self { tool, points: vec![at, at], color }
+    }
+
+    /// Extends a pen stroke, or moves a rectangle's far corner or an arrow's tip.
+    pub fn drag_to(&mut self, at: (f32, f32)) {
+        match self.tool {
+            AnnotationTool::Pen => {
+                let last = self.points.last().copied().unwrap_or(at);
+                // Skip sub-pixel moves so a held pointer doesn't pile up points
+                if (at.0 - last.0).abs() >= 1.0 || (at.1 - last.1).abs() >= 1.0 {
+                    self.points.push(at);
+                }
+            }
+            AnnotationTool::Rectangle | AnnotationTool::Arrow => {
+                self.points.truncate(1);
+                self.points.push(at);
+            }
+        }
+    }
+
+    /// The straight lines the mark is drawn with.
+    pub fn segments(&self) -> Vec<((f32, f32), (f32, f32))> {
+        let (Some(&from), Some(&to)) = (self.points.first(), self.points.last()) else {
+            return Vec::new();
+        };
+        match self.tool {
+            AnnotationTool::Pen => self.points.windows(2).map(|pair| (pair[0], pair[1])).collect(),
+            AnnotationTool::Rectangle => {
+                let corners = [from, (to.0, from.1), to, (from.0, to.1)];
+                (0..4).map(|i| (corners[i], corners[(i + 1) % 4])).collect()
+            }
+            AnnotationTool::Arrow => {
+                let [left, right] = arrow_head(from, to, ARROW_HEAD);
+                vec![(from, to), (to, left), (to, right)]
+            }
+        }
+    }
+}
+
+/// Ends of the two head lines of an arrow pointing from `from` to `to`,
+/// each `length` long and 30° off the shaft.
+pub fn arrow_head(from: (f32, f32), to: (f32, f32), length: f32) -> [(f32, f32); 2] {
+    let angle = (to.1 - from.1).atan2(to.0 - from.0);
+    let spread = std::f32::consts::PI / 6.0;
+    [angle + spread, angle - spread].map(|a| (to.0 - length * a.cos(), to.1 - length * a.sin()))
+}
+
+/// Draws `annotations` onto a frame whose top left is `origin` in screen
+/// pixels, i.e. the capture region's corner.
+pub fn burn_in(frame: &mut RgbaImage, annotations: &[Annotation], origin: (u32, u32)) {
+    let offset = |(x, y): (f32, f32)| (x - origin.0 as f32, y - origin.1 as f32);
+    for annotation in annotations {
+        for (from, to) in annotation.segments() {
+            draw_segment(frame, offset(from), offset(to), STROKE_WIDTH, annotation.color);
+        }
+    }
+}
+
+/// Frame effect burning the shared annotations into the piped screen feed.
+pub fn burn_in_effect(annotations: SharedAnnotations, origin: (u32, u32)) -> FrameEffect {
+    Box::new(move |frame, _| burn_in(frame, &annotations.lock().unwrap(), origin))
+}
+
+/// Draws a line `width` pixels thick with round ends by stamping discs
+/// along it, clipped to the frame.
+fn draw_segment(frame: &mut RgbaImage, from: (f32, f32), to: (f32, f32), width: f32, color: [u8; 3]) {
+    let radius = width / 2.0;
+    let r = radius.ceil() as i32;
+    let length = ((to.0 - from.0).powi(2) + (to.1 - from.1).powi(2)).sqrt();
+    let steps = (length * 2.0).ceil().max(1.0) as u32;
+    let (width, height) = (frame.width() as i32, frame.height() as i32);
+    for step in 0..=steps {
+        let t = step as f32 / steps as f32;
+        let (cx, cy) = (from.0 + (to.0 - from.0) * t, from.1 + (to.1 - from.1) * t);
+        let (px, py) = (cx.round() as i32, cy.round() as i32);
+        for y in (py - r).max(0)..=(py + r).min(height - 1) {
+            for x in (px - r).max(0)..=(px + r).min(width - 1) {
+                if (x as f32 - cx).powi(2) + (y as f32 - cy).powi(2) <= radius * radius {
+                    frame.put_pixel(x as u32, y as u32, Rgba([color[0], color[1], color[2], 255]));
+                }
+            }
+        }
+    }
+}
@@ -3,25 +3,55 @@ use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{available_hosts, Device, Host, HostId, SampleFormat, SupportedStreamConfig};
 use log::{info, error};
 use num_traits::ToPrimitive; // <-- added
+use std::collections::VecDeque;
 use std::env;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
-fn pick_best_input_host() -> Host {
-    // Honor explicit override for common Linux hosts we can reliably map
-    if let Ok(force) = env::var("OCTOCORD_AUDIO_HOST") {
-        if force.eq_ignore_ascii_case("alsa") {
-            if let Ok(h) = cpal::host_from_id(HostId::Alsa) {
-                return h;
-            }
-        }
-    }
+/// Suffix used to list render devices whose output can be captured (WASAPI loopback).
+pub const LOOPBACK_SUFFIX: &str = " (loopback)";
+
+/// Pitch of the device test tone.
+pub const TEST_TONE_HZ: f32 = 440.0;
 
-    // Prefer ALSA if available and have at least one input device, then others
+/// Peak level of the device test tone, well below full scale.
+const TEST_TONE_LEVEL: f32 = 0.2;
+
+/// Host to try before the rest of `available_hosts()` on this platform.
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd"))]
+fn preferred_host_id() -> Option<HostId> {
+    Some(HostId::Alsa)
+}
+
+#[cfg(target_os = "windows")]
+fn preferred_host_id() -> Option<HostId> {
+    Some(HostId::Wasapi)
+}
+
+#[cfg(target_os = "macos")]
+fn preferred_host_id() -> Option<HostId> {
+    Some(HostId::CoreAudio)
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "dragonfly",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "windows",
+    target_os = "macos"
+)))]
+fn preferred_host_id() -> Option<HostId> {
+    None
+}
+
+fn host_candidates() -> Vec<HostId> {
     let avail = available_hosts();
 
     let mut preferred: Vec<HostId> = Vec::new();
-    if avail.contains(&HostId::Alsa) {
-        preferred.push(HostId::Alsa);
+    if let Some(id) = preferred_host_id().filter(|id| avail.contains(id)) {
+        preferred.push(id);
     }
     // Add the rest to try something that works
     for id in avail {
@@ -29,10 +59,31 @@ fn pick_best_input_host() -> Host {
             preferred.push(id);
         }
     }
+    preferred
+}
+
+fn host_has_capture_devices(host: &Host) -> bool {
+    let has_inputs = host.input_devices().map(|mut it| it.next().is_some()).unwrap_or(false);
+    // On WASAPI every render device doubles as a loopback capture source
+    let has_loopback = cfg!(target_os = "windows")
+        && host.output_devices().map(|mut it| it.next().is_some()).unwrap_or(false);
+    has_inputs || has_loopback
+}
+
+fn pick_best_input_host() -> Host {
+    // Honor explicit override by host name (e.g. "alsa", "jack", "wasapi")
+    if let Ok(force) = env::var("OCTOCORD_AUDIO_HOST") {
+        if let Some(id) = available_hosts().into_iter().find(|id| id.name().eq_ignore_ascii_case(&force)) {
+            if let Ok(h) = cpal::host_from_id(id) {
+                return h;
+            }
+        }
+    }
 
-    for id in preferred {
+    // Prefer the platform's native host if it has at least one capture device, then others
+    for id in host_candidates() {
         if let Ok(h) = cpal::host_from_id(id) {
-            if h.input_devices().map(|mut it| it.next().is_some()).unwrap_or(false) {
+            if host_has_capture_devices(&h) {
                 info!("Audio host selected: {:?}", id);
                 return h;
             }
@@ -44,9 +95,13 @@ fn pick_best_input_host() -> Host {
 
 pub struct AudioRecorder {
     device: Device,
+    loopback: bool,
     stream: Option<cpal::Stream>,
     is_recording: Arc<Mutex<bool>>,
     audio_data: Arc<Mutex<Vec<f32>>>,
+    /// Copy of the input for `AudioMonitor`, so playback doesn't drain the meter buffer.
+    monitor_tap: Arc<Mutex<VecDeque<f32>>>,
+    monitoring: Arc<AtomicBool>,
     sample_rate: u32,
     channels: u16,
 }
@@ -54,28 +109,34 @@ pub struct AudioRecorder {
 impl AudioRecorder {
     pub fn new(device_name: &str) -> Result<Self> {
         let host = pick_best_input_host();
-        let device = find_audio_device(&host, device_name)?;
+        let (device, loopback) = match device_name.strip_suffix(LOOPBACK_SUFFIX) {
+            Some(render_name) => (find_loopback_device(&host, render_name)?, true),
+            None => (find_audio_device(&host, device_name)?, false),
+        };
         if let Ok(name) = device.name() {
-            info!("Audio input device: {}", name);
+            info!("Audio input device: {}{}", name, if loopback { LOOPBACK_SUFFIX } else { "" });
         }
 
         // Keep the default input config for metadata, but convert to SupportedStreamConfig when building stream
-        let default_conf = device.default_input_config()?;
+        let default_conf = default_capture_config(&device, loopback)?;
         let sample_rate = default_conf.sample_rate().0;
         let channels = default_conf.channels() as u16;
 
         Ok(Self {
             device,
+            loopback,
             stream: None,
             is_recording: Arc::new(Mutex::new(false)),
             audio_data: Arc::new(Mutex::new(Vec::new())),
+            monitor_tap: Arc::new(Mutex::new(VecDeque::new())),
+            monitoring: Arc::new(AtomicBool::new(false)),
             sample_rate,
             channels,
         })
     }
 
     pub fn start(&mut self) -> Result<()> {
-        let default_config = self.device.default_input_config()?;
+        let default_config = default_capture_config(&self.device, self.loopback)?;
         info!("Audio input config: {:?}", default_config);
 
         // Convert DefaultInputConfig -> SupportedStreamConfig for building the stream
@@ -124,6 +185,10 @@ impl AudioRecorder {
         let stream_config = config.clone().into();
 
         let device = self.device.clone();
+        let monitor_tap = Arc::clone(&self.monitor_tap);
+        let monitoring = Arc::clone(&self.monitoring);
+        // Anything older than this is dropped so monitoring latency can't build up
+        let monitor_limit = (self.sample_rate as usize * self.channels as usize) / 10;
         let stream = device.build_input_stream(
             &stream_config,
             move |data: &[T], _info: &cpal::InputCallbackInfo| {
@@ -140,6 +205,13 @@ impl AudioRecorder {
                     local.push(f);
                 }
 
+                if monitoring.load(Ordering::Relaxed) {
+                    let mut tap = monitor_tap.lock().unwrap();
+                    tap.extend(local.iter().copied());
+                    let excess = tap.len().saturating_sub(monitor_limit);
+                    tap.drain(..excess);
+                }
+
                 // Lock once and extend shared buffer
                 let mut audio_buffer = audio_data.lock().unwrap();
                 audio_buffer.extend_from_slice(&local);
@@ -178,6 +250,243 @@ impl AudioRecorder {
     pub fn is_recording(&self) -> bool {
         *self.is_recording.lock().unwrap()
     }
+
+    /// Whether the input is a render device's loopback, i.e. already what the speakers play.
+    pub fn is_loopback(&self) -> bool {
+        self.loopback
+    }
+}
+
+/// Plays the recorder's input back on the default output device ("hear yourself").
+/// Dropping it stops the output stream and closes the recorder's monitor tap.
+pub struct AudioMonitor {
+    stream: cpal::Stream,
+    monitoring: Arc<AtomicBool>,
+    monitor_tap: Arc<Mutex<VecDeque<f32>>>,
+    /// Linear volume stored as `f32` bits; 0 while muted.
+    volume: Arc<AtomicU32>,
+}
+
+impl AudioMonitor {
+    pub fn start(recorder: &AudioRecorder) -> Result<Self> {
+        if recorder.is_loopback() {
+            return Err(anyhow::anyhow!(
+                "Monitoring a loopback input would play the speakers back into themselves"
+            ));
+        }
+
+        let host = cpal::default_host();
+        let device = host
+            .default_output_device()
+            .ok_or_else(|| anyhow::anyhow!("No output device available for monitoring"))?;
+        let config = device.default_output_config()?;
+        if let Ok(name) = device.name() {
+            info!("Monitor output device: {} ({:?})", name, config);
+        }
+
+        let monitor_tap = Arc::clone(&recorder.monitor_tap);
+        let volume = Arc::new(AtomicU32::new(1.0f32.to_bits()));
+        let source = MonitorSource {
+            tap: Arc::clone(&monitor_tap),
+            volume: Arc::clone(&volume),
+            in_channels: recorder.channels.max(1) as usize,
+            // Nearest-sample rate conversion; fine for listening, never recorded
+            step: recorder.sample_rate as f64 / config.sample_rate().0 as f64,
+            position: 0.0,
+            frame: Vec::new(),
+        };
+
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => build_monitor_stream::<f32>(&device, &config, source)?,
+            SampleFormat::I16 => build_monitor_stream::<i16>(&device, &config, source)?,
+            SampleFormat::U16 => build_monitor_stream::<u16>(&device, &config, source)?,
+            fmt => return Err(anyhow::anyhow!("Unsupported monitor sample format: {:?}", fmt)),
+        };
+
+        monitor_tap.lock().unwrap().clear();
+        recorder.monitoring.store(true, Ordering::Relaxed);
+        stream.play()?;
+        info!("Audio monitoring started");
+
+        Ok(Self {
+            stream,
+            monitoring: Arc::clone(&recorder.monitoring),
+            monitor_tap,
+            volume,
+        })
+    }
+
+    /// Monitor volume in `0.0..=2.0`; muting keeps the stream running silently.
+    pub fn set_volume(&self, volume: f32, muted: bool) {
+        let volume = if muted { 0.0 } else { volume.clamp(0.0, 2.0) };
+        self.volume.store(volume.to_bits(), Ordering::Relaxed);
+    }
+}
+
+impl Drop for AudioMonitor {
+    fn drop(&mut self) {
+        self.monitoring.store(false, Ordering::Relaxed);
+        if let Err(e) = self.stream.pause() {
+            error!("Failed to pause monitor stream: {}", e);
+        }
+        self.monitor_tap.lock().unwrap().clear();
+        info!("Audio monitoring stopped");
+    }
+}
+
+/// A mono sine at `frequency` lasting `duration`, faded in and out over 10 ms
+/// so it starts and stops without a click.
+pub fn tone_samples(frequency: f32, sample_rate: u32, duration: Duration) -> Vec<f32> {
+    let count = (duration.as_secs_f64() * sample_rate as f64) as usize;
+    let fade = (sample_rate as usize / 100).clamp(1, count.max(2) / 2);
+    (0..count)
+        .map(|i| {
+            let envelope = (i.min(count - 1 - i) as f32 / fade as f32).min(1.0);
+            let phase = std::f32::consts::TAU * frequency * i as f32 / sample_rate as f32;
+            phase.sin() * TEST_TONE_LEVEL * envelope
+        })
+        .collect()
+}
+
+/// Plays a short tone on the default output device, the one the monitor
+/// uses, so the listening side of a device test can be checked too. Dropping
+/// it cuts the tone off.
+pub struct TestTone {
+    stream: cpal::Stream,
+}
+
+impl TestTone {
+    pub fn play(duration: Duration, volume: f32) -> Result<Self> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or_else(|| anyhow::anyhow!("No output device available for the test tone"))?;
+        let config = device.default_output_config()?;
+        let samples = tone_samples(TEST_TONE_HZ, config.sample_rate().0, duration);
+        // The monitor's mapping onto the output channels, fed from a finished buffer
+        let source = MonitorSource {
+            tap: Arc::new(Mutex::new(samples.into())),
+            volume: Arc::new(AtomicU32::new(volume.clamp(0.0, 2.0).to_bits())),
+            in_channels: 1,
+            step: 1.0,
+            position: 0.0,
+            frame: Vec::new(),
+        };
+
+        let stream = match config.sample_format() {
+            SampleFormat::F32 => build_monitor_stream::<f32>(&device, &config, source)?,
+            SampleFormat::I16 => build_monitor_stream::<i16>(&device, &config, source)?,
+            SampleFormat::U16 => build_monitor_stream::<u16>(&device, &config, source)?,
+            fmt => return Err(anyhow::anyhow!("Unsupported output sample format: {:?}", fmt)),
+        };
+        stream.play()?;
+        Ok(Self { stream })
+    }
+}
+
+impl Drop for TestTone {
+    fn drop(&mut self) {
+        if let Err(e) = self.stream.pause() {
+            error!("Failed to stop the test tone: {}", e);
+        }
+    }
+}
+
+/// Output-callback state: pulls input frames from the tap and maps them onto
+/// the output's channel count and rate.
+struct MonitorSource {
+    tap: Arc<Mutex<VecDeque<f32>>>,
+    volume: Arc<AtomicU32>,
+    in_channels: usize,
+    step: f64,
+    position: f64,
+    frame: Vec<f32>,
+}
+
+impl MonitorSource {
+    fn fill(&mut self, out: &mut [f32], out_channels: usize) {
+        let volume = f32::from_bits(self.volume.load(Ordering::Relaxed));
+        let mut tap = self.tap.lock().unwrap();
+        for out_frame in out.chunks_mut(out_channels.max(1)) {
+            // Advance through input frames; an empty tap (underrun) plays silence
+            self.position += self.step;
+            while self.position >= 1.0 {
+                self.position -= 1.0;
+                if tap.len() >= self.in_channels {
+                    self.frame.clear();
+                    self.frame.extend(tap.drain(..self.in_channels));
+                } else {
+                    self.frame.clear();
+                }
+            }
+            for (channel, sample) in out_frame.iter_mut().enumerate() {
+                let input = if self.frame.is_empty() {
+                    0.0
+                } else {
+                    self.frame[channel % self.frame.len()]
+                };
+                *sample = input * volume;
+            }
+        }
+    }
+}
+
+fn build_monitor_stream<T>(
+    device: &Device,
+    config: &SupportedStreamConfig,
+    mut source: MonitorSource,
+) -> Result<cpal::Stream>
+where
+    T: cpal::SizedSample + cpal::FromSample<f32>,
+{
+    let out_channels = config.channels() as usize;
+    let mut scratch: Vec<f32> = Vec::new();
+    let stream = device.build_output_stream(
+        &config.clone().into(),
+        move |data: &mut [T], _info: &cpal::OutputCallbackInfo| {
+            scratch.resize(data.len(), 0.0);
+            source.fill(&mut scratch, out_channels);
+            for (out, &sample) in data.iter_mut().zip(&scratch) {
+                *out = T::from_sample(sample.clamp(-1.0, 1.0));
+            }
+        },
+        |err| error!("Audio stream error (monitor): {}", err),
+        None,
+    )?;
+    Ok(stream)
+}
+
+/// Loopback streams run at the render device's mix format, so its output config
+/// is the one to open the capture stream with.
+fn default_capture_config(device: &Device, loopback: bool) -> Result<SupportedStreamConfig> {
+    if loopback {
+        Ok(device.default_output_config()?)
+    } else {
+        Ok(device.default_input_config()?)
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn find_loopback_device(host: &Host, render_name: &str) -> Result<Device> {
+    // cpal's WASAPI backend enables loopback when an input stream is built on a render device.
+    // No fallback to the default output: that would quietly record a different device.
+    host.output_devices()?
+        .find(|d| d.name().map(|n| n == render_name).unwrap_or(false))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "Output device '{}' for loopback capture not found on {:?}; it may be unplugged or renamed",
+                render_name,
+                host.id()
+            )
+        })
+}
+
+#[cfg(not(target_os = "windows"))]
+fn find_loopback_device(host: &Host, render_name: &str) -> Result<Device> {
+    Err(anyhow::anyhow!(
+        "Loopback capture of '{}' is only supported on Windows (host {:?})",
+        render_name,
+        host.id()
+    ))
 }
 
 fn find_audio_device(host: &Host, device_name: &str) -> Result<Device> {
@@ -209,18 +518,7 @@ fn find_audio_device(host: &Host, device_name: &str) -> Result<Device> {
 
 pub fn get_available_devices() -> Result<Vec<String>> {
     // Try preferred hosts first, then others
-    let mut try_ids: Vec<HostId> = Vec::new();
-    let avail = available_hosts();
-    if avail.contains(&HostId::Alsa) {
-        try_ids.push(HostId::Alsa);
-    }
-    for id in avail {
-        if !try_ids.contains(&id) {
-            try_ids.push(id);
-        }
-    }
-
-    for id in try_ids {
+    for id in host_candidates() {
         if let Ok(h) = cpal::host_from_id(id) {
             if let Ok(mut it) = h.input_devices() {
                 let mut device_names = Vec::new();
@@ -229,6 +527,7 @@ pub fn get_available_devices() -> Result<Vec<String>> {
                         device_names.push(name);
                     }
                 }
+                append_loopback_devices(&h, &mut device_names);
                 if !device_names.is_empty() {
                     return Ok(device_names);
                 }
@@ -252,13 +551,129 @@ pub fn get_available_devices() -> Result<Vec<String>> {
     Ok(device_names)
 }
 
+/// Lists render devices after the capture devices so desktop audio can be picked
+/// like any other input.
+#[cfg(target_os = "windows")]
+fn append_loopback_devices(host: &Host, device_names: &mut Vec<String>) {
+    if let Ok(outputs) = host.output_devices() {
+        for d in outputs {
+            if let Ok(name) = d.name() {
+                device_names.push(format!("{}{}", name, LOOPBACK_SUFFIX));
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "windows"))]
+fn append_loopback_devices(_host: &Host, _device_names: &mut Vec<String>) {}
+
+/// Fixed-size ring of the most recent samples, overwritten oldest-first.
+pub struct SampleRing {
+    samples: Vec<f32>,
+    head: usize,
+    len: usize,
+}
+
+impl SampleRing {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: vec![0.0; capacity.max(1)],
+            head: 0,
+            len: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn extend_from_slice(&mut self, data: &[f32]) {
+        let capacity = self.samples.len();
+        // Only the newest `capacity` samples can survive
+        let data = &data[data.len().saturating_sub(capacity)..];
+        for &sample in data {
+            self.samples[self.head] = sample;
+            self.head = (self.head + 1) % capacity;
+        }
+        self.len = (self.len + data.len()).min(capacity);
+    }
+
+    /// Sample `index` counted from the oldest retained sample.
+    pub fn get(&self, index: usize) -> Option<f32> {
+        if index >= self.len {
+            return None;
+        }
+        let capacity = self.samples.len();
+        let oldest = (self.head + capacity - self.len) % capacity;
+        Some(self.samples[(oldest + index) % capacity])
+    }
+
+    /// Min/max of each of `columns` equal slices, oldest first; empty slices report (0, 0).
+    pub fn envelope(&self, columns: usize, mut f: impl FnMut(usize, f32, f32)) {
+        for column in 0..columns {
+            let start = column * self.len / columns;
+            let end = ((column + 1) * self.len / columns).max(start + 1).min(self.len);
+            let (mut min, mut max) = (0.0f32, 0.0f32);
+            for i in start..end {
+                let sample = self.get(i).unwrap_or(0.0);
+                min = min.min(sample);
+                max = max.max(sample);
+            }
+            f(column, min, max);
+        }
+    }
+}
+
 pub struct AudioProcessor {
+    sample_rate: u32,
     channels: u16,
 }
 
+/// Release time used by the limiter when none is configured.
+pub const LIMITER_RELEASE_MS: f32 = 50.0;
+
+/// Left/right gains for a stereo balance in `-1.0..=1.0`; negative values favor the left channel.
+pub fn balance_gains(balance: f32) -> [f32; 2] {
+    let balance = balance.clamp(-1.0, 1.0);
+    [1.0 - balance.max(0.0), 1.0 + balance.min(0.0)]
+}
+
 impl AudioProcessor {
-    pub fn new(_sample_rate: u32, channels: u16) -> Self {
-        Self { channels }
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        Self { sample_rate, channels }
+    }
+
+    /// Scales interleaved samples by a gain per channel. Channels without an entry in `gains` are left as is.
+    pub fn apply_channel_gains(&self, data: &mut [f32], gains: &[f32]) {
+        for frame in data.chunks_mut(self.channels.max(1) as usize) {
+            for (sample, gain) in frame.iter_mut().zip(gains) {
+                *sample *= gain;
+            }
+        }
+    }
+
+    /// Peak limiter over interleaved samples. Frames above `threshold_db` (dBFS) are
+    /// pulled down instantly; the gain then recovers towards unity with an
+    /// exponential release so it doesn't pump on every peak. All channels share
+    /// one gain to keep the stereo image.
+    pub fn apply_limiter(&self, data: &mut [f32], threshold_db: f32, release_ms: f32) {
+        let threshold = 10f32.powf(threshold_db / 20.0);
+        let release_samples = (release_ms.max(0.0) / 1000.0) * self.sample_rate.max(1) as f32;
+        let release_coeff = if release_samples > 0.0 { (-1.0 / release_samples).exp() } else { 0.0 };
+
+        let mut gain = 1.0f32;
+        for frame in data.chunks_mut(self.channels.max(1) as usize) {
+            let peak = frame.iter().fold(0.0f32, |acc, s| acc.max(s.abs()));
+            let target = if peak > threshold { threshold / peak } else { 1.0 };
+            gain = if target < gain { target } else { target + (gain - target) * release_coeff };
+            for sample in frame.iter_mut() {
+                *sample *= gain;
+            }
+        }
     }
 
     pub fn mix_to_mono(&self, data: &[f32]) -> Vec<f32> {
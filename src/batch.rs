@@ -0,0 +1,204 @@
+use anyhow::{anyhow, Result};
+use std::fmt;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::config::{Config, OutputContainer};
+use crate::postprocess;
+use crate::video::{file_video_args, usable_hw_accel};
+
+/// Container for batch re-encodes, both H.264 + AAC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BatchContainer {
+    #[default]
+    Mp4,
+    Mkv,
+}
+
+impl BatchContainer {
+    pub fn parse(value: &str) -> Result<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "mp4" => Ok(BatchContainer::Mp4),
+            "mkv" => Ok(BatchContainer::Mkv),
+            _ => Err(anyhow!("Unknown container '{}' (expected mp4 or mkv)", value)),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        self.output_container().extension()
+    }
+
+    pub fn output_container(self) -> OutputContainer {
+        match self {
+            BatchContainer::Mp4 => OutputContainer::Mp4,
+            BatchContainer::Mkv => OutputContainer::Mkv,
+        }
+    }
+}
+
+/// Recordings in `dir` worth re-encoding: files in any output container,
+/// minus the separate webcam/video tracks and copies made by trim, merge,
+/// the intro/outro, sharing or an earlier batch. Sorted by name, which for
+/// recordings is also by date.
+pub fn find_recordings(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut found: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && OutputContainer::from_path(path).is_some())
+        .filter(|path| {
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            ![".webcam", ".video", ".trimmed", ".merged", ".final", ".share", ".reencoded"]
+                .iter()
+                .any(|suffix| stem.ends_with(suffix))
+        })
+        .collect();
+    found.sort();
+    Ok(found)
+}
+
+/// FLAC recordings in `dir` to convert to MP3, sorted by name.
+pub fn find_flac_recordings(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut found: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "flac"))
+        .collect();
+    found.sort();
+    Ok(found)
+}
+
+/// `<name>.<ext>` next to `input`, or `<name>.reencoded.<ext>` when that would
+/// overwrite the input.
+pub fn batch_output_path(input: &Path, container: BatchContainer) -> PathBuf {
+    let target = input.with_extension(container.extension());
+    if target == input {
+        let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+        input.with_file_name(format!("{}.reencoded.{}", stem, container.extension()))
+    } else {
+        target
+    }
+}
+
+/// ffmpeg arguments re-encoding one file with the recording's video quality
+/// and encoder from `config`. `realtime` reads the input at its native frame
+/// rate (`-re`) instead of as fast as possible, for feeding something that
+/// plays it live.
+pub fn batch_args(input: &Path, output: &Path, container: BatchContainer, realtime: bool, config: &Config) -> Vec<String> {
+    let (device, video) =
+        file_video_args(container.output_container(), config.hw_accel, config.video_quality, config.get_video_bitrate());
+    let mut args: Vec<String> = ["-y", "-hide_banner", "-loglevel", "error"].map(String::from).to_vec();
+    args.extend(device);
+    if realtime {
+        args.push("-re".to_string());
+    }
+    args.extend(["-i".to_string(), input.to_string_lossy().into_owned()]);
+    args.extend(["-map", "0:v?", "-map", "0:a?"].map(String::from));
+    args.extend(video);
+    args.extend(["-c:a".to_string(), "aac".to_string(), "-b:a".to_string(), format!("{}k", config.get_audio_bitrate())]);
+    args.extend(postprocess::container_flags(Some(container.output_container())));
+    args.push(output.to_string_lossy().into_owned());
+    args
+}
+
+#[derive(Debug)]
+pub struct BatchItem {
+    pub input: PathBuf,
+    pub result: Result<PathBuf>,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Default)]
+pub struct BatchSummary {
+    pub items: Vec<BatchItem>,
+}
+
+impl fmt::Display for BatchSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = |path: &Path| fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        let (mut before, mut after) = (0, 0);
+        for item in &self.items {
+            match &item.result {
+                Ok(output) => {
+                    before += size(&item.input);
+                    after += size(output);
+                    writeln!(f, "  ok      {} -> {} ({:.0} s)", item.input.display(), output.display(), item.elapsed.as_secs_f64())?;
+                }
+                Err(e) => writeln!(f, "  failed  {}: {}", item.input.display(), e)?,
+            }
+        }
+        let converted = self.items.iter().filter(|i| i.result.is_ok()).count();
+        write!(
+            f,
+            "{} of {} converted, {:.1} MB -> {:.1} MB",
+            converted,
+            self.items.len(),
+            before as f64 / 1_000_000.0,
+            after as f64 / 1_000_000.0
+        )
+    }
+}
+
+/// Re-encodes `files` one after another (or every recording in the output
+/// directory when empty) with the configured video quality, printing progress.
+pub fn run(config: &Config, files: &[PathBuf], container: BatchContainer, realtime: bool) -> Result<BatchSummary> {
+    let files = if files.is_empty() {
+        find_recordings(Path::new(config.get_output_directory()))?
+    } else {
+        files.to_vec()
+    };
+    if files.is_empty() {
+        return Err(anyhow!("No recordings found in {}", config.get_output_directory()));
+    }
+    let config = &Config { hw_accel: usable_hw_accel(&config.ffmpeg_path, config.hw_accel), ..config.clone() };
+
+    let mut summary = BatchSummary::default();
+    let count = files.len();
+    for (index, input) in files.into_iter().enumerate() {
+        let output = batch_output_path(&input, container);
+        let args = batch_args(&input, &output, container, realtime, config);
+        let name = input.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let duration = postprocess::probe_duration(&input).ok();
+        let started = Instant::now();
+        let mut report = |fraction: f32| {
+            print!("\r[{}/{}] {} {:>3.0}%", index + 1, count, name, fraction * 100.0);
+            let _ = std::io::stdout().flush();
+        };
+        report(0.0);
+        let result = postprocess::run_with_progress(&config.ffmpeg_path, &args, duration, None, &mut report);
+        println!();
+        if result.is_err() {
+            let _ = fs::remove_file(&output);
+        }
+        summary.items.push(BatchItem { input, result: result.map(|()| output), elapsed: started.elapsed() });
+    }
+    Ok(summary)
+}
+
+/// Converts `files` (or every FLAC in the output directory when empty) to
+/// MP3 at `bitrate_kbps`, one after another, printing progress.
+pub fn convert_mp3(config: &Config, files: &[PathBuf], bitrate_kbps: u32) -> Result<BatchSummary> {
+    let files = if files.is_empty() {
+        find_flac_recordings(Path::new(config.get_output_directory()))?
+    } else {
+        files.to_vec()
+    };
+    if files.is_empty() {
+        return Err(anyhow!("No FLAC recordings found in {}", config.get_output_directory()));
+    }
+
+    let mut summary = BatchSummary::default();
+    let count = files.len();
+    for (index, input) in files.into_iter().enumerate() {
+        let name = input.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let started = Instant::now();
+        let report = |fraction: f32| {
+            print!("\r[{}/{}] {} {:>3.0}%", index + 1, count, name, fraction * 100.0);
+            let _ = std::io::stdout().flush();
+        };
+        report(0.0);
+        let result = postprocess::convert_to_mp3(&config.ffmpeg_path, &input, bitrate_kbps, None, report);
+        println!();
+        summary.items.push(BatchItem { input, result, elapsed: started.elapsed() });
+    }
+    Ok(summary)
+}
@@ -0,0 +1,219 @@
+use anyhow::{anyhow, Context, Result};
+use image::DynamicImage;
+use std::fmt;
+use std::process::{Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+use crate::frame_budget::FrameBudget;
+use crate::pacing::FramePacing;
+use crate::screen::ScreenCapture;
+use crate::screen_feed::{self, FeedStats, ScreenFeed};
+use crate::webcam::WebcamCapture;
+
+/// Throughput of one capture source over the benchmark run.
+#[derive(Debug, Clone)]
+pub struct SourceStats {
+    pub name: String,
+    pub resolution: Option<(u32, u32)>,
+    pub frames: u64,
+    pub expected: u64,
+    /// Frames the capture thread discarded (queue full or memory budget used up).
+    pub dropped_in_queue: u64,
+    pub elapsed: Duration,
+    /// Longest gap between two consecutive frames.
+    pub worst_frame_time: Duration,
+}
+
+impl SourceStats {
+    fn new(name: String, expected: u64) -> Self {
+        Self {
+            name,
+            resolution: None,
+            frames: 0,
+            expected,
+            dropped_in_queue: 0,
+            elapsed: Duration::ZERO,
+            worst_frame_time: Duration::ZERO,
+        }
+    }
+
+    pub fn fps(&self) -> f64 {
+        self.frames as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.expected.saturating_sub(self.frames)
+    }
+
+    pub fn average_frame_time(&self) -> Option<Duration> {
+        (self.frames > 0).then(|| self.elapsed / self.frames as u32)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BenchmarkReport {
+    pub target_fps: u32,
+    pub duration: Duration,
+    pub sources: Vec<SourceStats>,
+    /// Overhead of piping screen frames into ffmpeg, measured when piped
+    /// screen capture is enabled.
+    pub pipe: Option<PipeStats>,
+}
+
+/// A run of the piped screen feed into an ffmpeg that discards the frames.
+#[derive(Debug, Clone, Copy)]
+pub struct PipeStats {
+    pub feed: FeedStats,
+    pub elapsed: Duration,
+}
+
+impl PipeStats {
+    pub fn fps(&self) -> f64 {
+        self.feed.frames as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Capture benchmark: {} fps target, {} s", self.target_fps, self.duration.as_secs())?;
+        for source in &self.sources {
+            writeln!(f)?;
+            writeln!(f, "{}", source.name)?;
+            if let Some((w, h)) = source.resolution {
+                writeln!(f, "  Resolution:        {}x{}", w, h)?;
+            }
+            writeln!(f, "  Frames:            {} of {} expected", source.frames, source.expected)?;
+            writeln!(f, "  Achieved fps:      {:.1}", source.fps())?;
+            writeln!(f, "  Dropped frames:    {}", source.dropped())?;
+            writeln!(f, "  Dropped in queue:  {}", source.dropped_in_queue)?;
+            match source.average_frame_time() {
+                Some(avg) => writeln!(f, "  Avg frame time:    {:.1} ms", avg.as_secs_f64() * 1000.0)?,
+                None => writeln!(f, "  Avg frame time:    n/a (no frames)")?,
+            }
+            writeln!(f, "  Worst frame time:  {:.1} ms", source.worst_frame_time.as_secs_f64() * 1000.0)?;
+        }
+        let sustainable = self
+            .sources
+            .iter()
+            .map(|s| s.fps().floor() as u32)
+            .min()
+            .unwrap_or(0);
+        if let Some(pipe) = &self.pipe {
+            let frames = (pipe.feed.frames + pipe.feed.dropped).max(1) as f64;
+            writeln!(f)?;
+            writeln!(f, "Piped screen feed")?;
+            writeln!(f, "  Frames:            {}", pipe.feed.frames)?;
+            writeln!(f, "  Dropped:           {}", pipe.feed.dropped)?;
+            writeln!(f, "  Achieved fps:      {:.1}", pipe.fps())?;
+            writeln!(f, "  Effects:           {:.2} ms per frame", pipe.feed.effect_time.as_secs_f64() * 1000.0 / frames)?;
+            writeln!(f, "  Pipe write:        {:.2} ms per frame", pipe.feed.average_write_time().as_secs_f64() * 1000.0)?;
+        }
+        writeln!(f)?;
+        write!(f, "Sustainable preview rate on this machine: about {} fps", sustainable)
+    }
+}
+
+/// Runs the preview capture for the configured screen (and webcam, if enabled)
+/// at the configured frame rate, without encoding, and counts frames received.
+pub fn run(config: &Config, duration: Duration) -> Result<BenchmarkReport> {
+    if !config.record_video && !config.record_webcam {
+        return Err(anyhow!("Nothing to benchmark: enable screen or webcam capture"));
+    }
+
+    let target_fps = config.frame_rate.max(1);
+    // No preview cap here: the point is to see what capture can sustain
+    let pacing = FramePacing::new(target_fps, None);
+    let budget = FrameBudget::new(config.frame_buffer_limit_mb);
+    let expected = (duration.as_secs_f64() * target_fps as f64) as u64;
+
+    let mut screen = None;
+    if config.record_video {
+        let index = config.default_screen.unwrap_or(0);
+        let mut capture = ScreenCapture::new(index, pacing.clone(), budget.clone())?;
+        capture.start()?;
+        screen = Some((capture, SourceStats::new(format!("Screen {}", index), expected)));
+    }
+
+    let mut webcam = None;
+    if config.record_webcam {
+        let name = config.default_webcam.clone().unwrap_or_else(|| "Default Webcam".to_string());
+        let mut capture = WebcamCapture::new(&name, pacing.clone(), budget.clone())?;
+        capture.start()?;
+        webcam = Some((capture, SourceStats::new(format!("Webcam {}", name), expected)));
+    }
+
+    let started = Instant::now();
+    let mut last_screen = started;
+    let mut last_webcam = started;
+    while started.elapsed() < duration {
+        if let Some((capture, stats)) = screen.as_mut() {
+            if let Some(frame) = capture.get_latest_frame() {
+                record_frame(stats, &frame, &mut last_screen);
+            }
+        }
+        if let Some((capture, stats)) = webcam.as_mut() {
+            if let Some(frame) = capture.get_latest_frame() {
+                record_frame(stats, &frame, &mut last_webcam);
+            }
+        }
+        thread::sleep(Duration::from_millis(1));
+    }
+    let elapsed = started.elapsed();
+
+    let mut sources = Vec::new();
+    if let Some((mut capture, mut stats)) = screen {
+        capture.stop()?;
+        stats.elapsed = elapsed;
+        stats.dropped_in_queue = capture.dropped_frames();
+        sources.push(stats);
+    }
+    if let Some((mut capture, mut stats)) = webcam {
+        capture.stop()?;
+        stats.elapsed = elapsed;
+        stats.dropped_in_queue = capture.dropped_frames();
+        sources.push(stats);
+    }
+
+    let pipe = if config.record_video && config.piped_screen_capture {
+        Some(run_pipe(config, target_fps, duration)?)
+    } else {
+        None
+    };
+
+    Ok(BenchmarkReport { target_fps, duration: elapsed, sources, pipe })
+}
+
+/// Feeds the screen into `ffmpeg -f null` the way a piped recording would,
+/// to measure what copying raw frames through the pipe costs.
+fn run_pipe(config: &Config, fps: u32, duration: Duration) -> Result<PipeStats> {
+    let source = screen_feed::resolve_feed_source(config.default_screen.unwrap_or(0), config.capture_region)?;
+    let mut child = Command::new(&config.ffmpeg_path)
+        .args(["-hide_banner", "-loglevel", "error"])
+        .args(screen_feed::rawvideo_input_args(source.size, fps))
+        .args(["-f", "null", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .context("Failed to start ffmpeg for the pipe benchmark")?;
+    let stdin = child.stdin.take().ok_or_else(|| anyhow!("ffmpeg has no stdin"))?;
+
+    let started = Instant::now();
+    let mut feed = ScreenFeed::start(source, fps, stdin, Arc::new(Mutex::new(Vec::new())));
+    thread::sleep(duration);
+    let stats = feed.stop();
+    let elapsed = started.elapsed();
+    let _ = child.wait();
+    Ok(PipeStats { feed: stats, elapsed })
+}
+
+fn record_frame(stats: &mut SourceStats, frame: &DynamicImage, last: &mut Instant) {
+    let now = Instant::now();
+    stats.worst_frame_time = stats.worst_frame_time.max(now.duration_since(*last));
+    *last = now;
+    stats.frames += 1;
+    stats.resolution.get_or_insert((frame.width(), frame.height()));
+}
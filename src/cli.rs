@@ -0,0 +1,286 @@
+use anyhow::{anyhow, Context, Result};
+use serde::Serialize;
+use std::path::PathBuf;
+
+use crate::batch::BatchContainer;
+use crate::config::{AudioQuality, Config, VideoQuality};
+use crate::video::OutputTarget;
+use crate::{audio, postprocess, screen, webcam};
+
+pub const USAGE: &str = "\
+Usage: discord-recorder [OPTIONS]
+
+Options:
+  --portable                 Keep config and recordings next to the executable
+  --output-dir <DIR>         Directory recordings are saved to
+  --video-quality <QUALITY>  low, medium, high, ultra or lossless
+  --audio-quality <QUALITY>  low, medium, high or lossless
+  --audio-device <NAME>      Audio input device (\"default\" for the system default)
+  --screen <INDEX>           Screen to capture, by index
+  --webcam <NAME>            Webcam device name
+  --audio / --no-audio       Record audio or not
+  --video / --no-video       Record the screen or not
+  --webcam-overlay / --no-webcam-overlay
+                             Include the webcam overlay or not
+  --separate-outputs         Save audio and video to separate files
+  --dry-run                  Print the ffmpeg command a recording would run, then exit
+  --output <-|FIFO>          With --dry-run, write the recording as one Matroska
+                             stream to stdout (-) or an existing named pipe
+  --benchmark[=SECONDS]      Measure preview capture throughput (default 10 s), then exit
+  --batch [FILE...]          Re-encode the given files, or every recording in the
+                             output directory, one at a time, then exit
+  --container <FORMAT>       With --batch, mp4 (default) or mkv
+  --realtime                 With --batch, read each file at its native rate (-re)
+                             rather than as fast as possible
+  --convert-mp3 [FILE...]    Convert the given FLAC files, or every FLAC in the
+                             output directory, to MP3 beside them, then exit
+  --mp3-bitrate <KBPS>       With --convert-mp3, 128, 192 (default), 256 or 320
+  --list-devices             Print screens, audio devices and webcams, then exit
+  --json                     With --list-devices, print JSON instead of a table
+  -V, --version              Print the version and exit
+  -h, --help                 Print this help and exit
+
+Flags that are not given keep the value from the config file.";
+
+const DEFAULT_BENCHMARK_SECS: u64 = 10;
+const DEFAULT_MP3_BITRATE: u32 = 192;
+
+/// Parsed command line. Every override is optional so unspecified flags keep the
+/// value loaded from the config file.
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    pub show_help: bool,
+    pub show_version: bool,
+    pub list_devices: bool,
+    pub dry_run: bool,
+    /// Recording destination other than the output directory.
+    pub output_target: Option<OutputTarget>,
+    /// Seconds to run the capture benchmark for.
+    pub benchmark: Option<u64>,
+    pub batch: bool,
+    /// Files given after `--batch`; empty means the whole output directory.
+    pub batch_files: Vec<PathBuf>,
+    pub batch_container: BatchContainer,
+    /// Pace batch inputs at their native rate. Never applies to live capture,
+    /// which already arrives in real time.
+    pub realtime: bool,
+    pub convert_mp3: bool,
+    /// Files given after `--convert-mp3`; empty means every FLAC in the
+    /// output directory.
+    pub convert_files: Vec<PathBuf>,
+    pub mp3_bitrate: u32,
+    pub json: bool,
+    pub portable: bool,
+    pub output_dir: Option<String>,
+    pub video_quality: Option<VideoQuality>,
+    pub audio_quality: Option<AudioQuality>,
+    pub audio_device: Option<String>,
+    pub screen: Option<usize>,
+    pub webcam: Option<String>,
+    pub record_audio: Option<bool>,
+    pub record_video: Option<bool>,
+    pub record_webcam: Option<bool>,
+    pub separate_outputs: Option<bool>,
+}
+
+impl CliArgs {
+    /// Parses arguments without the program name.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self> {
+        let mut cli = CliArgs { mp3_bitrate: DEFAULT_MP3_BITRATE, ..CliArgs::default() };
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            // Accept both `--flag value` and `--flag=value`
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None),
+            };
+            let mut value = || {
+                inline_value
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| anyhow!("{} requires a value", flag))
+            };
+
+            match flag.as_str() {
+                "-h" | "--help" => cli.show_help = true,
+                "-V" | "--version" => cli.show_version = true,
+                "--list-devices" => cli.list_devices = true,
+                "--dry-run" => cli.dry_run = true,
+                "--output" => cli.output_target = Some(OutputTarget::parse(&value()?)),
+                "--benchmark" => {
+                    // Only the `=` form takes a value, so a following flag isn't swallowed
+                    let seconds = match &inline_value {
+                        Some(raw) => raw
+                            .parse()
+                            .ok()
+                            .filter(|s| *s > 0)
+                            .ok_or_else(|| anyhow!("--benchmark expects a number of seconds, got '{}'", raw))?,
+                        None => DEFAULT_BENCHMARK_SECS,
+                    };
+                    cli.benchmark = Some(seconds);
+                }
+                "--batch" => cli.batch = true,
+                "--container" => cli.batch_container = BatchContainer::parse(&value()?)?,
+                "--realtime" => cli.realtime = true,
+                "--convert-mp3" => cli.convert_mp3 = true,
+                "--mp3-bitrate" => {
+                    let raw = value()?;
+                    cli.mp3_bitrate = raw
+                        .parse()
+                        .ok()
+                        .filter(|kbps| postprocess::MP3_BITRATES.contains(kbps))
+                        .ok_or_else(|| anyhow!("--mp3-bitrate expects 128, 192, 256 or 320, got '{}'", raw))?;
+                }
+                "--json" => cli.json = true,
+                "--portable" => cli.portable = true,
+                "--output-dir" => cli.output_dir = Some(value()?),
+                "--video-quality" => cli.video_quality = Some(parse_video_quality(&value()?)?),
+                "--audio-quality" => cli.audio_quality = Some(parse_audio_quality(&value()?)?),
+                "--audio-device" => cli.audio_device = Some(value()?),
+                "--screen" => {
+                    let raw = value()?;
+                    let index = raw
+                        .parse()
+                        .with_context(|| format!("--screen expects an index, got '{}'", raw))?;
+                    cli.screen = Some(index);
+                }
+                "--webcam" => cli.webcam = Some(value()?),
+                "--audio" => cli.record_audio = Some(true),
+                "--no-audio" => cli.record_audio = Some(false),
+                "--video" => cli.record_video = Some(true),
+                "--no-video" => cli.record_video = Some(false),
+                "--webcam-overlay" => cli.record_webcam = Some(true),
+                "--no-webcam-overlay" => cli.record_webcam = Some(false),
+                "--separate-outputs" => cli.separate_outputs = Some(true),
+                _ if cli.batch && !arg.starts_with('-') => cli.batch_files.push(PathBuf::from(&arg)),
+                _ if cli.convert_mp3 && !arg.starts_with('-') => cli.convert_files.push(PathBuf::from(&arg)),
+                _ => return Err(anyhow!("Unknown argument '{}'", arg)),
+            }
+        }
+        // Recordings made in the window always go to files
+        if cli.output_target.is_some() && !cli.dry_run {
+            return Err(anyhow!("--output only works with --dry-run"));
+        }
+
+        Ok(cli)
+    }
+
+    /// Merges the given flags over a loaded config.
+    pub fn apply_to(&self, config: &mut Config) {
+        if let Some(dir) = &self.output_dir {
+            config.set_output_directory(dir.clone());
+        }
+        if let Some(quality) = self.video_quality {
+            config.video_quality = quality;
+        }
+        if let Some(quality) = self.audio_quality {
+            config.audio_quality = quality;
+        }
+        if let Some(device) = &self.audio_device {
+            config.default_audio_device = if device == "default" { None } else { Some(device.clone()) };
+        }
+        if let Some(screen) = self.screen {
+            config.default_screen = Some(screen);
+        }
+        if let Some(webcam) = &self.webcam {
+            config.default_webcam = Some(webcam.clone());
+        }
+        if let Some(record_audio) = self.record_audio {
+            config.record_audio = record_audio;
+        }
+        if let Some(record_video) = self.record_video {
+            config.record_video = record_video;
+        }
+        if let Some(record_webcam) = self.record_webcam {
+            config.record_webcam = record_webcam;
+        }
+        if let Some(separate_outputs) = self.separate_outputs {
+            config.separate_outputs = separate_outputs;
+        }
+    }
+}
+
+fn parse_video_quality(value: &str) -> Result<VideoQuality> {
+    match value.to_ascii_lowercase().as_str() {
+        "low" => Ok(VideoQuality::Low),
+        "medium" => Ok(VideoQuality::Medium),
+        "high" => Ok(VideoQuality::High),
+        "ultra" => Ok(VideoQuality::Ultra),
+        "lossless" => Ok(VideoQuality::Lossless),
+        _ => Err(anyhow!("Unknown video quality '{}' (expected low, medium, high, ultra or lossless)", value)),
+    }
+}
+
+fn parse_audio_quality(value: &str) -> Result<AudioQuality> {
+    match value.to_ascii_lowercase().as_str() {
+        "low" => Ok(AudioQuality::Low),
+        "medium" => Ok(AudioQuality::Medium),
+        "high" => Ok(AudioQuality::High),
+        "lossless" => Ok(AudioQuality::Lossless),
+        _ => Err(anyhow!("Unknown audio quality '{}' (expected low, medium, high or lossless)", value)),
+    }
+}
+
+pub fn version_string() -> String {
+    format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))
+}
+
+#[derive(Serialize)]
+struct DeviceList {
+    screens: Vec<DeviceEntry>,
+    audio_devices: Vec<DeviceEntry>,
+    webcams: Vec<DeviceEntry>,
+}
+
+#[derive(Serialize)]
+struct DeviceEntry {
+    index: usize,
+    name: String,
+}
+
+fn entries(kind: &str, names: Result<Vec<String>>) -> Vec<DeviceEntry> {
+    match names {
+        Ok(names) => names
+            .into_iter()
+            .enumerate()
+            .map(|(index, name)| DeviceEntry { index, name })
+            .collect(),
+        Err(err) => {
+            eprintln!("Failed to list {}: {}", kind, err);
+            Vec::new()
+        }
+    }
+}
+
+/// Prints the devices the GUI would offer, with the indices `--screen` expects.
+pub fn print_devices(json: bool) -> Result<()> {
+    let devices = DeviceList {
+        screens: entries("screens", screen::get_available_screens()),
+        audio_devices: entries("audio devices", audio::get_available_devices()),
+        webcams: entries("webcams", webcam::get_available_webcams()),
+    };
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&devices)?);
+        return Ok(());
+    }
+
+    for (title, list) in [
+        ("Screens", &devices.screens),
+        ("Audio devices", &devices.audio_devices),
+        ("Webcams", &devices.webcams),
+    ] {
+        println!("{}", title);
+        if list.is_empty() {
+            println!("  (none)");
+        } else {
+            println!("  {:>5}  Name", "Index");
+            for entry in list {
+                println!("  {:>5}  {}", entry.index, entry.name);
+            }
+        }
+        println!();
+    }
+    Ok(())
+}
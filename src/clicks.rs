@@ -0,0 +1,148 @@
+use anyhow::{anyhow, Context, Result};
+use log::{debug, warn};
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader};
+use std::process::{Child, Command, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::runtime::runtime_handle;
+
+/// How long a click's ring keeps growing before it disappears.
+pub const CLICK_ANIMATION: Duration = Duration::from_millis(400);
+
+/// Clicks not yet collected by `poll`; older ones are dropped.
+const PENDING_CAPACITY: usize = 32;
+
+/// A mouse button press at root-window coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Click {
+    pub at: Instant,
+    pub x: i32,
+    pub y: i32,
+}
+
+/// Side of the square ring drawn `elapsed` after a click: it grows from a
+/// third of `size` to `size`, then `None` once the animation is over.
+pub fn click_ring_size(elapsed: Duration, size: u32) -> Option<u32> {
+    if elapsed >= CLICK_ANIMATION {
+        return None;
+    }
+    let progress = elapsed.as_secs_f32() / CLICK_ANIMATION.as_secs_f32();
+    let start = size as f32 / 3.0;
+    Some((start + (size as f32 - start) * progress).round().max(2.0) as u32)
+}
+
+/// Picks button presses out of `xinput test-xi2 --root` output, which prints
+/// each event as a header line followed by indented fields:
+///
+/// ```text
+/// EVENT type 15 (RawButtonPress)
+///     device: 11 (11)
+///     detail: 1
+/// ```
+#[derive(Debug, Default)]
+pub struct XinputParser {
+    in_press: bool,
+}
+
+impl XinputParser {
+    /// Feeds one line; returns the button number when it completes a press of
+    /// the left, middle or right button. Wheel "buttons" 4-7 are ignored.
+    pub fn feed(&mut self, line: &str) -> Option<u32> {
+        let line = line.trim();
+        if line.starts_with("EVENT type") {
+            self.in_press = line.ends_with("(RawButtonPress)");
+            return None;
+        }
+        if !self.in_press {
+            return None;
+        }
+        let button = line.strip_prefix("detail:")?.trim().parse().ok()?;
+        self.in_press = false;
+        (1..=3).contains(&button).then_some(button)
+    }
+}
+
+/// Parses `xdotool getmouselocation --shell` (`X=812` and `Y=403` lines).
+pub fn parse_mouse_location(text: &str) -> Option<(i32, i32)> {
+    let value = |key: &str| {
+        text.lines()
+            .find_map(|line| line.trim().strip_prefix(key)?.strip_prefix('='))
+            .and_then(|v| v.trim().parse().ok())
+    };
+    Some((value("X")?, value("Y")?))
+}
+
+/// Watches for mouse clicks anywhere on the X11 display while it is alive.
+/// Raw button events carry no position, so the pointer is looked up with
+/// `xdotool` as each click arrives.
+pub struct ClickWatcher {
+    child: Child,
+    pending: Arc<Mutex<VecDeque<Click>>>,
+}
+
+impl ClickWatcher {
+    pub fn start() -> Result<Self> {
+        if cfg!(any(windows, target_os = "macos")) {
+            return Err(anyhow!("Click highlighting needs an X11 session"));
+        }
+        if Command::new("xdotool").arg("version").stdout(Stdio::null()).stderr(Stdio::null()).status().is_err() {
+            return Err(anyhow!("Click highlighting needs xdotool"));
+        }
+        let mut child = Command::new("xinput")
+            .args(["test-xi2", "--root"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .context("Click highlighting needs xinput")?;
+        let stdout = child.stdout.take().ok_or_else(|| anyhow!("xinput has no output"))?;
+
+        let pending = Arc::new(Mutex::new(VecDeque::new()));
+        let queue = Arc::clone(&pending);
+        runtime_handle().spawn_blocking(move || {
+            let mut parser = XinputParser::default();
+            // Ends when the watcher is dropped and xinput is killed
+            for line in BufReader::new(stdout).lines().map_while(|l| l.ok()) {
+                if parser.feed(&line).is_none() {
+                    continue;
+                }
+                let at = Instant::now();
+                let Some((x, y)) = mouse_location() else {
+                    continue;
+                };
+                let mut queue = queue.lock().unwrap();
+                if queue.len() >= PENDING_CAPACITY {
+                    queue.pop_front();
+                }
+                queue.push_back(Click { at, x, y });
+            }
+            debug!("Click watcher stopped");
+        });
+        Ok(Self { child, pending })
+    }
+
+    /// Clicks since the last call, oldest first.
+    pub fn poll(&self) -> Vec<Click> {
+        self.pending.lock().unwrap().drain(..).collect()
+    }
+}
+
+impl Drop for ClickWatcher {
+    fn drop(&mut self) {
+        if let Err(e) = self.child.kill() {
+            warn!("Failed to stop xinput: {}", e);
+        }
+        let _ = self.child.wait();
+    }
+}
+
+fn mouse_location() -> Option<(i32, i32)> {
+    let output = Command::new("xdotool").args(["getmouselocation", "--shell"]).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| parse_mouse_location(&String::from_utf8_lossy(&output.stdout)))
+        .flatten()
+}
@@ -1,6 +1,39 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use fs2::FileExt;
+use notify::Watcher;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::i18n::Language;
+use crate::naming::DEFAULT_FILE_NAME_TEMPLATE;
+
+/// Name of the marker file that enables portable mode when placed next to the executable.
+pub const PORTABLE_MARKER: &str = "portable.txt";
+
+/// Longest auto-stop timer, 99:59:59.
+pub const AUTO_STOP_MAX_SECS: u32 = 99 * 3600 + 59 * 60 + 59;
+
+static PORTABLE_MODE: AtomicBool = AtomicBool::new(false);
+
+/// Forces portable mode (`--portable`), as if the marker file were present.
+pub fn enable_portable_mode() {
+    PORTABLE_MODE.store(true, Ordering::Relaxed);
+}
+
+/// Directory holding config and recordings in portable mode, if portable mode is active.
+pub fn portable_dir() -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+    if PORTABLE_MODE.load(Ordering::Relaxed) || exe_dir.join(PORTABLE_MARKER).exists() {
+        Some(exe_dir)
+    } else {
+        None
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
@@ -20,6 +53,486 @@ pub struct Config {
     pub use_pipewire_on_wayland: bool,
     #[serde(default)]
     pub enable_preview_overlay: bool,
+    #[serde(default)]
+    pub overlay_layout: OverlayLayout,
+    #[serde(default = "default_scenes")]
+    pub scenes: Vec<Scene>,
+    /// Frame around the webcam, in the preview overlay and the recording.
+    #[serde(default)]
+    pub overlay_border: OverlayBorder,
+    /// Green-screen removal for the webcam overlay.
+    #[serde(default)]
+    pub chroma_key: ChromaKey,
+    /// Experimental: blur the webcam outside a centered subject box.
+    #[serde(default)]
+    pub background_blur: BackgroundBlur,
+    /// Draw a growing ring where the mouse is clicked (X11 only).
+    #[serde(default)]
+    pub click_highlight: ClickHighlight,
+    /// ffmpeg binary for recording and post-processing; a bare name is
+    /// looked up on `PATH`.
+    #[serde(default = "default_ffmpeg_path")]
+    pub ffmpeg_path: String,
+    #[serde(default = "default_ffmpeg_quit_timeout_secs")]
+    pub ffmpeg_quit_timeout_secs: u64,
+    #[serde(default = "default_ffmpeg_terminate_timeout_secs")]
+    pub ffmpeg_terminate_timeout_secs: u64,
+    /// Pause ends the ffmpeg segment and resume starts a new one; on by
+    /// default, so configs written before it existed get it too.
+    #[serde(default = "default_true")]
+    pub seamless_pause: bool,
+    /// Unbuffered capture and x264 `zerolatency`; mostly useful when streaming.
+    #[serde(default)]
+    pub low_latency: bool,
+    /// Caps ffmpeg's encoder and filter threads; `None` lets ffmpeg decide.
+    #[serde(default)]
+    pub encoder_threads: Option<u32>,
+    /// Stereo balance in `-1.0..=1.0`; negative favors the left channel.
+    #[serde(default)]
+    pub audio_balance: f32,
+    #[serde(default)]
+    pub limiter_enabled: bool,
+    /// Ceiling for the limiter in dBFS.
+    #[serde(default = "default_limiter_threshold_db")]
+    pub limiter_threshold_db: f32,
+    /// Gain applied after the limiter, in dB.
+    #[serde(default)]
+    pub limiter_makeup_db: f32,
+    #[serde(default)]
+    pub mic_gate: MicGateMode,
+    /// Mix desktop audio in alongside the selected input device.
+    #[serde(default)]
+    pub record_system_audio: bool,
+    /// Desktop audio source; `None` uses the platform default monitor/loopback.
+    #[serde(default)]
+    pub system_audio_device: Option<String>,
+    #[serde(default)]
+    pub ducking_enabled: bool,
+    #[serde(default)]
+    pub ducking: DuckingSettings,
+    #[serde(default)]
+    pub audio_export_format: AudioExportFormat,
+    #[serde(default, alias = "container")]
+    pub output_container: OutputContainer,
+    /// Kept to what `output_container` takes on load, see `OutputContainer::allows`.
+    #[serde(default)]
+    pub video_codec: VideoCodec,
+    #[serde(default)]
+    pub audio_codec: AudioCodec,
+    #[serde(default)]
+    pub hw_accel: HwAccel,
+    /// Write the times the input clipped to a `.clips.txt` file next to the recording.
+    #[serde(default)]
+    pub log_clip_timestamps: bool,
+    /// Write the chapter markers and their notes as subtitles on stop.
+    #[serde(default)]
+    pub marker_subtitles: MarkerSubtitles,
+    /// Also mux the marker subtitles into the MKV files as a track.
+    #[serde(default)]
+    pub embed_marker_subtitles: bool,
+    /// Linear volume of the input played back while monitoring.
+    #[serde(default = "default_monitor_volume")]
+    pub monitor_volume: f32,
+    /// `None` follows the audio quality: 24-bit for Lossless, 16-bit otherwise.
+    #[serde(default)]
+    pub flac_bit_depth: Option<FlacBitDepth>,
+    /// Also write the raw webcam to `<name>.webcam.mkv` when recording the screen.
+    #[serde(default)]
+    pub separate_webcam: bool,
+    /// Draw the webcam onto the screen recording; turning this off only makes
+    /// sense together with `separate_webcam`.
+    #[serde(default = "default_true")]
+    pub composite_webcam: bool,
+    #[serde(default)]
+    pub log_verbosity: LogVerbosity,
+    /// Recording frame rate; the preview follows it up to `preview_fps_cap`.
+    #[serde(default = "default_frame_rate")]
+    pub frame_rate: u32,
+    #[serde(default = "default_preview_fps_cap")]
+    pub preview_fps_cap: Option<u32>,
+    /// Memory all queued preview frames together may use, in MiB.
+    #[serde(default = "default_frame_buffer_limit_mb")]
+    pub frame_buffer_limit_mb: u32,
+    /// Part of the selected screen to record as (x, y, width, height) in screen
+    /// pixels; `None` records the whole screen.
+    #[serde(default)]
+    pub capture_region: Option<(u32, u32, u32, u32)>,
+    /// Magnify around the cursor by this factor (X11 only); `None` records
+    /// the screen as it is.
+    #[serde(default)]
+    pub cursor_zoom: Option<f32>,
+    /// Capture the screen in-process and pipe raw frames to ffmpeg instead
+    /// of letting ffmpeg grab it, so effects can be drawn on the frames.
+    #[serde(default)]
+    pub piped_screen_capture: bool,
+    /// Draw the preview annotations into the recording; needs piped capture.
+    #[serde(default)]
+    pub burn_in_annotations: bool,
+    /// Reload the settings when `config.json` is edited outside the app.
+    #[serde(default)]
+    pub watch_config_file: bool,
+    /// Stop the recording by itself once it is `auto_stop_after_secs` long.
+    #[serde(default)]
+    pub auto_stop: bool,
+    #[serde(default = "default_auto_stop_after_secs")]
+    pub auto_stop_after_secs: u32,
+    /// Ask before stopping a recording longer than `confirm_stop_after_minutes`.
+    #[serde(default)]
+    pub confirm_long_stop: bool,
+    #[serde(default = "default_confirm_stop_after_minutes")]
+    pub confirm_stop_after_minutes: u32,
+    #[serde(default)]
+    pub hotkeys: StoredHotkeys,
+    #[serde(default)]
+    pub language: Language,
+    /// Overlay the measured preview frame rate on the previews.
+    #[serde(default)]
+    pub show_preview_fps: bool,
+    /// Share of the preview height given to the screen preview; the webcam
+    /// preview gets the rest.
+    #[serde(default = "default_preview_split")]
+    pub preview_split: f32,
+    /// Draw rule-of-thirds lines on the screen preview and snap the webcam
+    /// overlay to them while dragging.
+    #[serde(default)]
+    pub overlay_thirds_guides: bool,
+    /// Base name for new recordings; see `naming::expand_file_name` for tokens.
+    #[serde(default = "default_file_name_template")]
+    pub file_name_template: String,
+    /// Show the encoder stats panel with bitrate and file-size graphs.
+    #[serde(default)]
+    pub show_stats_panel: bool,
+    /// Grab `<name>.jpg` from each saved video.
+    #[serde(default)]
+    pub thumbnail_on_stop: bool,
+    /// Also write a `<name>.sheet.jpg` grid of frames; needs `thumbnail_on_stop`.
+    #[serde(default)]
+    pub contact_sheet_on_stop: bool,
+    /// Put the saved file's path (or folder, for several files) on the
+    /// clipboard when a recording finishes.
+    #[serde(default)]
+    pub copy_path_on_stop: bool,
+    /// Send the recording to `srt` as well as writing it to disk.
+    #[serde(default)]
+    pub stream_enabled: bool,
+    #[serde(default)]
+    pub srt: SrtSettings,
+    /// Hook run in the background after each recording is saved.
+    #[serde(default)]
+    pub upload: UploadSettings,
+    #[serde(default)]
+    pub bumpers: Bumpers,
+    /// Run `post_steps` after each recording. These are arbitrary shell
+    /// commands executed as the current user, so anyone able to edit this file
+    /// can run code through it; leave it off unless you wrote the steps.
+    #[serde(default)]
+    pub post_steps_enabled: bool,
+    #[serde(default)]
+    pub post_steps: Vec<PostStep>,
+}
+
+/// Folder for recordings inside the videos folder, named the way the
+/// platform's own folders are.
+#[cfg(any(windows, target_os = "macos"))]
+pub const RECORDINGS_FOLDER: &str = "Discord Recordings";
+#[cfg(not(any(windows, target_os = "macos")))]
+pub const RECORDINGS_FOLDER: &str = "discord-recordings";
+
+/// Where new installs record to: `RECORDINGS_FOLDER` in the user's videos
+/// folder (`dirs::video_dir`, i.e. Videos on Windows, Movies on macOS and
+/// `XDG_VIDEOS_DIR` on Linux), else in the usual videos folder under home.
+/// Configs that already name a directory keep it.
+pub fn default_output_directory(video_dir: Option<PathBuf>, home_dir: Option<PathBuf>) -> PathBuf {
+    let videos_name = if cfg!(target_os = "macos") { "Movies" } else { "Videos" };
+    video_dir
+        .or_else(|| home_dir.map(|home| home.join(videos_name)))
+        .unwrap_or_default()
+        .join(RECORDINGS_FOLDER)
+}
+
+fn default_preview_split() -> f32 {
+    2.0 / 3.0
+}
+
+fn default_auto_stop_after_secs() -> u32 {
+    3600
+}
+
+fn default_confirm_stop_after_minutes() -> u32 {
+    10
+}
+
+fn default_frame_buffer_limit_mb() -> u32 {
+    256
+}
+
+fn default_frame_rate() -> u32 {
+    60
+}
+
+fn default_preview_fps_cap() -> Option<u32> {
+    Some(30)
+}
+
+fn default_file_name_template() -> String {
+    DEFAULT_FILE_NAME_TEMPLATE.to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_monitor_volume() -> f32 {
+    1.0
+}
+
+/// Sidechain compression of desktop audio, keyed by the microphone.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct DuckingSettings {
+    /// Mic level in dBFS above which desktop audio is attenuated.
+    pub threshold_db: f32,
+    pub ratio: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
+}
+
+impl Default for DuckingSettings {
+    fn default() -> Self {
+        Self {
+            threshold_db: -30.0,
+            ratio: 6.0,
+            attack_ms: 20.0,
+            release_ms: 400.0,
+        }
+    }
+}
+
+/// Which stream fills the frame when screen and webcam are combined.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum OverlayLayout {
+    /// Small webcam over the full screen.
+    #[default]
+    WebcamOver,
+    /// Small screen over the full-frame webcam, for "just chatting".
+    ScreenOver,
+    /// Screen and webcam next to each other at the same height.
+    SideBySide,
+}
+
+impl OverlayLayout {
+    pub const ALL: [OverlayLayout; 3] = [OverlayLayout::WebcamOver, OverlayLayout::ScreenOver, OverlayLayout::SideBySide];
+}
+
+/// A named combination of sources and layout that can be switched to in one
+/// step between recordings.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Scene {
+    pub name: String,
+    pub record_video: bool,
+    pub record_webcam: bool,
+    /// How screen and webcam are combined when both are recorded.
+    #[serde(default)]
+    pub layout: OverlayLayout,
+}
+
+impl Scene {
+    fn new(name: &str, record_video: bool, record_webcam: bool, layout: OverlayLayout) -> Self {
+        Self { name: name.to_string(), record_video, record_webcam, layout }
+    }
+
+    /// Whether these settings are what the scene would set; the layout only
+    /// matters when both sources are on.
+    pub fn matches(&self, record_video: bool, record_webcam: bool, layout: OverlayLayout) -> bool {
+        self.record_video == record_video
+            && self.record_webcam == record_webcam
+            && (!(record_video && record_webcam) || self.layout == layout)
+    }
+
+    /// Makes this scene the one the app starts in next time.
+    pub fn remember_in(&self, config: &mut Config) {
+        config.record_video = self.record_video;
+        config.record_webcam = self.record_webcam;
+        config.overlay_layout = self.layout;
+        if self.record_video && self.record_webcam {
+            config.enable_preview_overlay = true;
+        }
+    }
+}
+
+fn default_scenes() -> Vec<Scene> {
+    vec![
+        Scene::new("Full screen", true, false, OverlayLayout::WebcamOver),
+        Scene::new("Webcam only", false, true, OverlayLayout::WebcamOver),
+        Scene::new("Screen + cam", true, true, OverlayLayout::WebcamOver),
+        Scene::new("Side by side", true, true, OverlayLayout::SideBySide),
+        Scene::new("Just chatting", true, true, OverlayLayout::ScreenOver),
+    ]
+}
+
+/// Solid frame drawn around the webcam overlay.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct OverlayBorder {
+    /// sRGB color.
+    pub color: [u8; 3],
+    /// Thickness in pixels; 0 draws no border.
+    pub width: u32,
+}
+
+impl Default for OverlayBorder {
+    fn default() -> Self {
+        Self {
+            color: [88, 101, 242],
+            width: 2,
+        }
+    }
+}
+
+/// Ring drawn into the recording around each mouse click.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ClickHighlight {
+    pub enabled: bool,
+    /// sRGB color.
+    pub color: [u8; 3],
+    /// Side of the ring at its largest, in screen pixels.
+    pub size: u32,
+}
+
+impl Default for ClickHighlight {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: [255, 200, 0],
+            size: 48,
+        }
+    }
+}
+
+/// Makes webcam pixels close to `color` transparent. `similarity` and
+/// `blend` follow ffmpeg's `colorkey`: the RGB distance (0..=1) below which a
+/// pixel is fully keyed out, and the width of the soft edge above it.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct ChromaKey {
+    #[serde(default)]
+    pub enabled: bool,
+    pub color: [u8; 3],
+    pub similarity: f32,
+    pub blend: f32,
+}
+
+impl Default for ChromaKey {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            color: [0, 177, 64],
+            similarity: 0.3,
+            blend: 0.1,
+        }
+    }
+}
+
+/// Fake portrait mode for the webcam: everything outside a centered box of
+/// `subject_width` x `subject_height` (fractions of the frame) is blurred.
+/// There's no segmentation, so the subject has to stay inside the box.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct BackgroundBlur {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Blur radius in webcam pixels.
+    pub radius: u32,
+    pub subject_width: f32,
+    pub subject_height: f32,
+}
+
+impl Default for BackgroundBlur {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            radius: 12,
+            subject_width: 0.5,
+            subject_height: 0.8,
+        }
+    }
+}
+
+/// Where and how to stream over SRT.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct SrtSettings {
+    /// `srt://host:port`, optionally with extra query options.
+    #[serde(default)]
+    pub url: String,
+    /// Receiver buffer in milliseconds; higher survives worse networks.
+    #[serde(default = "default_srt_latency_ms")]
+    pub latency_ms: u32,
+    /// Encrypts the stream when set; SRT wants 10 to 79 characters.
+    #[serde(default)]
+    pub passphrase: String,
+}
+
+fn default_srt_latency_ms() -> u32 {
+    120
+}
+
+impl Default for SrtSettings {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            latency_ms: default_srt_latency_ms(),
+            passphrase: String::new(),
+        }
+    }
+}
+
+/// A hotkey as saved in the config file; the GUI turns it into an egui shortcut.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StoredHotkey {
+    /// egui key name, e.g. "R" or "F8".
+    pub key: String,
+    #[serde(default)]
+    pub ctrl: bool,
+    #[serde(default)]
+    pub alt: bool,
+    #[serde(default)]
+    pub shift: bool,
+    #[serde(default)]
+    pub command: bool,
+    /// A disabled hotkey keeps its binding but is ignored.
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// Saved hotkeys; `None` keeps the built-in default for that action.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct StoredHotkeys {
+    #[serde(default)]
+    pub start_stop: Option<StoredHotkey>,
+    #[serde(default)]
+    pub pause_resume: Option<StoredHotkey>,
+    #[serde(default)]
+    pub toggle_webcam: Option<StoredHotkey>,
+    #[serde(default)]
+    pub add_marker: Option<StoredHotkey>,
+    #[serde(default)]
+    pub webcam_snapshot: Option<StoredHotkey>,
+    #[serde(default)]
+    pub next_scene: Option<StoredHotkey>,
+    #[serde(default)]
+    pub push_to_talk: Option<StoredHotkey>,
+}
+
+fn default_limiter_threshold_db() -> f32 {
+    -1.0
+}
+
+fn default_ffmpeg_path() -> String {
+    "ffmpeg".to_string()
+}
+
+fn default_ffmpeg_quit_timeout_secs() -> u64 {
+    5
+}
+
+fn default_ffmpeg_terminate_timeout_secs() -> u64 {
+    3
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -28,6 +541,8 @@ pub enum VideoQuality {
     Medium,
     High,
     Ultra,
+    /// x264 at QP 0 for editing; several times the size of Ultra.
+    Lossless,
 }
 
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
@@ -38,20 +553,345 @@ pub enum AudioQuality {
     Lossless,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// Container/codec for audio-only recordings and the separate audio track.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum AudioExportFormat {
+    #[default]
+    Flac,
+    /// Lossy; needs an ffmpeg built with libmp3lame.
+    Mp3,
+}
+
+impl AudioExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            AudioExportFormat::Flac => "flac",
+            AudioExportFormat::Mp3 => "mp3",
+        }
+    }
+}
+
+/// Container of recordings with video. MP4 plays inline in Discord and on
+/// phones; WebM takes only VP9 and Opus; MKV takes either pair.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum OutputContainer {
+    #[default]
+    Mkv,
+    Mp4,
+    WebM,
+}
+
+impl OutputContainer {
+    pub const ALL: [OutputContainer; 3] = [OutputContainer::Mkv, OutputContainer::Mp4, OutputContainer::WebM];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            OutputContainer::Mkv => "MKV",
+            OutputContainer::Mp4 => "MP4 (H.264 + AAC)",
+            OutputContainer::WebM => "WebM (VP9 + Opus)",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputContainer::Mkv => "mkv",
+            OutputContainer::Mp4 => "mp4",
+            OutputContainer::WebM => "webm",
+        }
+    }
+
+    /// The container a file with this extension was written in.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        Self::ALL.into_iter().find(|container| container.extension() == ext)
+    }
+
+    /// The codecs recordings in this container use unless told otherwise.
+    pub fn default_codecs(self) -> (VideoCodec, AudioCodec) {
+        match self {
+            OutputContainer::Mkv | OutputContainer::Mp4 => (VideoCodec::H264, AudioCodec::Aac),
+            OutputContainer::WebM => (VideoCodec::Vp9, AudioCodec::Opus),
+        }
+    }
+
+    /// Whether the container can hold these codecs. MP4 stays on H.264 and
+    /// AAC, the pair every phone and Discord's player handle.
+    pub fn allows(self, video: VideoCodec, audio: AudioCodec) -> bool {
+        match self {
+            OutputContainer::Mkv => true,
+            OutputContainer::Mp4 | OutputContainer::WebM => (video, audio) == self.default_codecs(),
+        }
+    }
+}
+
+/// Video codec of recordings; the H.264 encoder is picked by `HwAccel`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    /// Software only; needs an ffmpeg built with libvpx.
+    Vp9,
+}
+
+impl VideoCodec {
+    pub const ALL: [VideoCodec; 2] = [VideoCodec::H264, VideoCodec::Vp9];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            VideoCodec::H264 => "H.264",
+            VideoCodec::Vp9 => "VP9",
+        }
+    }
+}
+
+/// Codec of the audio track muxed with the video.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum AudioCodec {
+    #[default]
+    Aac,
+    /// Needs an ffmpeg built with libopus.
+    Opus,
+}
+
+impl AudioCodec {
+    pub const ALL: [AudioCodec; 2] = [AudioCodec::Aac, AudioCodec::Opus];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            AudioCodec::Aac => "AAC",
+            AudioCodec::Opus => "Opus",
+        }
+    }
+}
+
+/// Encoder for H.264 video. The hardware ones take the load off the CPU at
+/// high resolutions; WebM recordings always use the software VP9 encoder.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Default)]
+pub enum HwAccel {
+    #[default]
+    Software,
+    /// NVIDIA GPUs.
+    Nvenc,
+    /// Intel and AMD GPUs on Linux.
+    Vaapi,
+}
+
+impl HwAccel {
+    pub const ALL: [HwAccel; 3] = [HwAccel::Software, HwAccel::Nvenc, HwAccel::Vaapi];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            HwAccel::Software => "Software (libx264)",
+            HwAccel::Nvenc => "NVIDIA NVENC",
+            HwAccel::Vaapi => "VAAPI (Intel/AMD)",
+        }
+    }
+
+    /// The ffmpeg encoder used for H.264.
+    pub fn encoder(self) -> &'static str {
+        match self {
+            HwAccel::Software => "libx264",
+            HwAccel::Nvenc => "h264_nvenc",
+            HwAccel::Vaapi => "h264_vaapi",
+        }
+    }
+}
+
+/// Subtitle sidecar written from the chapter markers when a recording stops.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum MarkerSubtitles {
+    #[default]
+    Off,
+    WebVtt,
+    Srt,
+}
+
+impl MarkerSubtitles {
+    pub const ALL: [MarkerSubtitles; 3] = [MarkerSubtitles::Off, MarkerSubtitles::WebVtt, MarkerSubtitles::Srt];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MarkerSubtitles::Off => "Off",
+            MarkerSubtitles::WebVtt => "WebVTT (.vtt)",
+            MarkerSubtitles::Srt => "SubRip (.srt)",
+        }
+    }
+
+    pub fn extension(self) -> Option<&'static str> {
+        match self {
+            MarkerSubtitles::Off => None,
+            MarkerSubtitles::WebVtt => Some("vtt"),
+            MarkerSubtitles::Srt => Some("srt"),
+        }
+    }
+}
+
+/// What to do with the files once a recording is saved.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum UploadMethod {
+    #[default]
+    Off,
+    /// Run `UploadSettings::command` once per file.
+    Command,
+    /// PUT each file to `UploadSettings::put_url`; works with S3-compatible
+    /// storage that accepts a bearer token or pre-signed bucket URL.
+    HttpPut,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct UploadSettings {
+    #[serde(default)]
+    pub method: UploadMethod,
+    /// Shell command; `{path}` is replaced by the quoted file path and
+    /// `{name}` by the file name.
+    #[serde(default)]
+    pub command: String,
+    /// Destination URL; `{name}` is replaced by the file name.
+    #[serde(default)]
+    pub put_url: String,
+    /// Sent as the `Authorization` header when not empty.
+    #[serde(default)]
+    pub authorization: String,
+}
+
+/// Fixed clips joined around each recording into `<name>.final.<ext>`; the
+/// recording itself is kept as it is.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct Bumpers {
+    #[serde(default)]
+    pub enabled: bool,
+    /// Played before the recording; empty for none.
+    #[serde(default)]
+    pub intro: String,
+    /// Played after the recording; empty for none.
+    #[serde(default)]
+    pub outro: String,
+}
+
+impl Bumpers {
+    pub fn intro_path(&self) -> Option<&Path> {
+        Some(Path::new(self.intro.trim())).filter(|p| !p.as_os_str().is_empty())
+    }
+
+    pub fn outro_path(&self) -> Option<&Path> {
+        Some(Path::new(self.outro.trim())).filter(|p| !p.as_os_str().is_empty())
+    }
+
+    /// Whether there is anything to join on stop.
+    pub fn is_active(&self) -> bool {
+        self.enabled && (self.intro_path().is_some() || self.outro_path().is_some())
+    }
+}
+
+/// A command run on each saved file after a recording, see
+/// `postprocess::expand_step` for the tokens it may use.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub struct PostStep {
+    #[serde(default)]
+    pub name: String,
+    pub command: String,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+/// Sample depth of FLAC output.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum FlacBitDepth {
+    Bits16,
+    Bits24,
+}
+
+/// Application log verbosity, adjustable at runtime from the settings.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum LogVerbosity {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogVerbosity {
+    pub const ALL: [LogVerbosity; 5] = [
+        LogVerbosity::Error,
+        LogVerbosity::Warn,
+        LogVerbosity::Info,
+        LogVerbosity::Debug,
+        LogVerbosity::Trace,
+    ];
+
+    pub fn level_filter(self) -> log::LevelFilter {
+        match self {
+            LogVerbosity::Error => log::LevelFilter::Error,
+            LogVerbosity::Warn => log::LevelFilter::Warn,
+            LogVerbosity::Info => log::LevelFilter::Info,
+            LogVerbosity::Debug => log::LevelFilter::Debug,
+            LogVerbosity::Trace => log::LevelFilter::Trace,
+        }
+    }
+
+    /// Changes the global log level. The logger itself lets everything through
+    /// (unless `RUST_LOG` narrowed it), so this is the effective filter.
+    pub fn apply(self) {
+        log::set_max_level(self.level_filter());
+    }
+}
+
+/// How the push-to-talk key controls the microphone.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum MicGateMode {
+    /// Microphone always open.
+    #[default]
+    Off,
+    /// Microphone muted unless the key is held.
+    PushToTalk,
+    /// Microphone open unless the key is held.
+    PushToMute,
+}
+
+impl MicGateMode {
+    pub fn mic_open(self, key_held: bool) -> bool {
+        match self {
+            MicGateMode::Off => true,
+            MicGateMode::PushToTalk => key_held,
+            MicGateMode::PushToMute => !key_held,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
 pub enum DiscordTheme {
+    /// Follow the OS light/dark preference.
+    #[default]
+    Auto,
     Dark,
     Light,
     AMOLED,
 }
 
+impl DiscordTheme {
+    pub const ALL: [DiscordTheme; 4] = [DiscordTheme::Auto, DiscordTheme::Dark, DiscordTheme::Light, DiscordTheme::AMOLED];
+
+    /// The concrete theme to draw with; `Auto` asks the OS, falling back to dark
+    /// when it has no preference.
+    pub fn resolve(self) -> DiscordTheme {
+        match self {
+            DiscordTheme::Auto => match dark_light::detect() {
+                dark_light::Mode::Light => DiscordTheme::Light,
+                dark_light::Mode::Dark | dark_light::Mode::Default => DiscordTheme::Dark,
+            },
+            theme => theme,
+        }
+    }
+}
+
 impl Default for Config {
     fn default() -> Self {
         Self {
-            output_directory: dirs::home_dir()
-                .unwrap_or_default()
-                .join("Videos")
-                .join("discord-recordings")
+            output_directory: portable_dir()
+                .map(|dir| dir.join("recordings"))
+                .unwrap_or_else(|| default_output_directory(dirs::video_dir(), dirs::home_dir()))
                 .to_string_lossy()
                 .to_string(),
             video_quality: VideoQuality::High,
@@ -62,44 +902,241 @@ impl Default for Config {
             record_audio: true,
             record_video: true,
             record_webcam: false,
-            discord_theme: DiscordTheme::Dark,
+            discord_theme: DiscordTheme::default(),
             separate_outputs: false,
             use_pipewire_on_wayland: false,
             enable_preview_overlay: false,
+            overlay_layout: OverlayLayout::default(),
+            scenes: default_scenes(),
+            overlay_border: OverlayBorder::default(),
+            click_highlight: ClickHighlight::default(),
+            chroma_key: ChromaKey::default(),
+            background_blur: BackgroundBlur::default(),
+            ffmpeg_path: default_ffmpeg_path(),
+            ffmpeg_quit_timeout_secs: default_ffmpeg_quit_timeout_secs(),
+            ffmpeg_terminate_timeout_secs: default_ffmpeg_terminate_timeout_secs(),
+            seamless_pause: true,
+            low_latency: false,
+            encoder_threads: None,
+            audio_balance: 0.0,
+            limiter_enabled: false,
+            limiter_threshold_db: default_limiter_threshold_db(),
+            limiter_makeup_db: 0.0,
+            mic_gate: MicGateMode::Off,
+            record_system_audio: false,
+            system_audio_device: None,
+            ducking_enabled: false,
+            ducking: DuckingSettings::default(),
+            audio_export_format: AudioExportFormat::default(),
+            output_container: OutputContainer::default(),
+            video_codec: VideoCodec::default(),
+            audio_codec: AudioCodec::default(),
+            hw_accel: HwAccel::default(),
+            log_clip_timestamps: false,
+            marker_subtitles: MarkerSubtitles::default(),
+            embed_marker_subtitles: false,
+            monitor_volume: default_monitor_volume(),
+            flac_bit_depth: None,
+            separate_webcam: false,
+            composite_webcam: true,
+            log_verbosity: LogVerbosity::default(),
+            frame_rate: default_frame_rate(),
+            preview_fps_cap: default_preview_fps_cap(),
+            frame_buffer_limit_mb: default_frame_buffer_limit_mb(),
+            capture_region: None,
+            cursor_zoom: None,
+            piped_screen_capture: false,
+            burn_in_annotations: false,
+            watch_config_file: false,
+            confirm_long_stop: false,
+            auto_stop: false,
+            auto_stop_after_secs: default_auto_stop_after_secs(),
+            confirm_stop_after_minutes: default_confirm_stop_after_minutes(),
+            hotkeys: StoredHotkeys::default(),
+            language: Language::default(),
+            show_preview_fps: false,
+            overlay_thirds_guides: false,
+            preview_split: default_preview_split(),
+            file_name_template: default_file_name_template(),
+            copy_path_on_stop: false,
+            show_stats_panel: false,
+            thumbnail_on_stop: false,
+            contact_sheet_on_stop: false,
+            stream_enabled: false,
+            srt: SrtSettings::default(),
+            upload: UploadSettings::default(),
+            bumpers: Bumpers::default(),
+            post_steps_enabled: false,
+            post_steps: Vec::new(),
         }
     }
 }
 
 impl Config {
-    pub fn load() -> Result<Self> {
-        let config_path = Self::get_config_path()?;
-        
-        if config_path.exists() {
-            let content = std::fs::read_to_string(config_path)?;
-            let config: Config = serde_json::from_str(&content)?;
-            Ok(config)
-        } else {
+    /// Loads the config at startup. A file that no longer parses is copied to
+    /// `config.json.bak` and replaced by the defaults, with a warning for the
+    /// user instead of their settings vanishing without a word.
+    pub fn load_or_recover() -> (Self, Option<String>) {
+        let config_path = match Self::get_config_path() {
+            Ok(path) => path,
+            Err(e) => return (Config::default(), Some(format!("Couldn't locate config.json: {:#}", e))),
+        };
+        match Self::load_from(&config_path) {
+            Ok(config) => (config, None),
+            Err(LoadError::Unreadable(e)) => {
+                (Config::default(), Some(format!("Couldn't read config.json; using default settings: {:#}", e)))
+            }
+            Err(LoadError::Corrupt(e)) => {
+                let backup = backup_path(&config_path);
+                let warning = match std::fs::copy(&config_path, &backup) {
+                    Ok(_) => format!(
+                        "config.json couldn't be parsed ({}); using default settings. The old file was saved as {}",
+                        e,
+                        backup.display()
+                    ),
+                    Err(copy_error) => format!(
+                        "config.json couldn't be parsed ({}) nor backed up ({}); using default settings",
+                        e, copy_error
+                    ),
+                };
+                (Config::default(), Some(warning))
+            }
+        }
+    }
+
+    /// Reads and validates the config at `path`, writing the defaults there
+    /// first when it doesn't exist.
+    pub fn load_from(path: &Path) -> std::result::Result<Self, LoadError> {
+        if !path.exists() {
             let config = Config::default();
-            config.save()?;
-            Ok(config)
+            config.save_to(path).map_err(LoadError::Unreadable)?;
+            return Ok(config);
         }
+        let content = read_locked(path).map_err(LoadError::Unreadable)?;
+        let mut config: Config = serde_json::from_str(&content).map_err(LoadError::Corrupt)?;
+        config.validate_and_fix();
+        Ok(config)
     }
 
-    pub fn save(&self) -> Result<()> {
-        let config_path = Self::get_config_path()?;
-        
+    /// Pulls values a hand edit may have broken back into the ranges the
+    /// settings window allows, so they can't crash the GUI or end up in an
+    /// ffmpeg command. Returns what was changed, which is also logged.
+    pub fn validate_and_fix(&mut self) -> Vec<String> {
+        let defaults = Config::default();
+        let mut fixes = Vec::new();
+
+        if self.output_directory.trim().is_empty() {
+            self.output_directory = defaults.output_directory.clone();
+            fixes.push(format!("output_directory was empty; using {}", self.output_directory));
+        }
+        if self.ffmpeg_path.trim().is_empty() {
+            self.ffmpeg_path = defaults.ffmpeg_path.clone();
+            fixes.push("ffmpeg_path was empty; using ffmpeg from PATH".to_string());
+        }
+        if self.file_name_template.trim().is_empty() {
+            self.file_name_template = defaults.file_name_template.clone();
+            fixes.push("file_name_template was empty; using the default".to_string());
+        }
+
+        clamp_u32(&mut fixes, "frame_rate", &mut self.frame_rate, 10..=120);
+        if let Some(cap) = self.preview_fps_cap.as_mut() {
+            clamp_u32(&mut fixes, "preview_fps_cap", cap, 5..=120);
+        }
+        clamp_u32(&mut fixes, "frame_buffer_limit_mb", &mut self.frame_buffer_limit_mb, 32..=2048);
+        if let Some(threads) = self.encoder_threads.as_mut() {
+            // 0 is the same as unset
+            clamp_u32(&mut fixes, "encoder_threads", threads, 0..=64);
+        }
+        clamp_u32(&mut fixes, "auto_stop_after_secs", &mut self.auto_stop_after_secs, 1..=AUTO_STOP_MAX_SECS);
+        clamp_u32(&mut fixes, "confirm_stop_after_minutes", &mut self.confirm_stop_after_minutes, 1..=600);
+        clamp_u32(&mut fixes, "click_highlight.size", &mut self.click_highlight.size, 16..=160);
+        clamp_u32(&mut fixes, "overlay_border.width", &mut self.overlay_border.width, 0..=20);
+        clamp_u32(&mut fixes, "background_blur.radius", &mut self.background_blur.radius, 2..=40);
+        clamp_u32(&mut fixes, "srt.latency_ms", &mut self.srt.latency_ms, 20..=8000);
+
+        clamp_f32(&mut fixes, "audio_balance", &mut self.audio_balance, -1.0..=1.0, defaults.audio_balance);
+        clamp_f32(&mut fixes, "limiter_threshold_db", &mut self.limiter_threshold_db, -24.0..=0.0, defaults.limiter_threshold_db);
+        clamp_f32(&mut fixes, "limiter_makeup_db", &mut self.limiter_makeup_db, 0.0..=12.0, defaults.limiter_makeup_db);
+        clamp_f32(&mut fixes, "preview_split", &mut self.preview_split, 0.2..=0.85, defaults.preview_split);
+        clamp_f32(&mut fixes, "monitor_volume", &mut self.monitor_volume, 0.0..=2.0, defaults.monitor_volume);
+        let ducking = defaults.ducking;
+        clamp_f32(&mut fixes, "ducking.threshold_db", &mut self.ducking.threshold_db, -60.0..=0.0, ducking.threshold_db);
+        clamp_f32(&mut fixes, "ducking.ratio", &mut self.ducking.ratio, 1.0..=20.0, ducking.ratio);
+        clamp_f32(&mut fixes, "ducking.attack_ms", &mut self.ducking.attack_ms, 1.0..=500.0, ducking.attack_ms);
+        clamp_f32(&mut fixes, "ducking.release_ms", &mut self.ducking.release_ms, 10.0..=3000.0, ducking.release_ms);
+        let key = defaults.chroma_key;
+        clamp_f32(&mut fixes, "chroma_key.similarity", &mut self.chroma_key.similarity, 0.01..=1.0, key.similarity);
+        clamp_f32(&mut fixes, "chroma_key.blend", &mut self.chroma_key.blend, 0.0..=1.0, key.blend);
+        let blur = defaults.background_blur;
+        clamp_f32(&mut fixes, "background_blur.subject_width", &mut self.background_blur.subject_width, 0.2..=1.0, blur.subject_width);
+        clamp_f32(&mut fixes, "background_blur.subject_height", &mut self.background_blur.subject_height, 0.2..=1.0, blur.subject_height);
+
+        if let Some(zoom) = self.cursor_zoom.as_mut() {
+            clamp_f32(&mut fixes, "cursor_zoom", zoom, 1.25..=4.0, 2.0);
+        }
+        if !self.output_container.allows(self.video_codec, self.audio_codec) {
+            let (video, audio) = self.output_container.default_codecs();
+            fixes.push(format!(
+                "{:?} can't hold {:?} + {:?}; using {:?} + {:?}",
+                self.output_container, self.video_codec, self.audio_codec, video, audio
+            ));
+            (self.video_codec, self.audio_codec) = (video, audio);
+        }
+        if let Some(region @ (_, _, width, height)) = self.capture_region {
+            if width == 0 || height == 0 {
+                self.capture_region = None;
+                fixes.push(format!("capture_region {:?} is empty; recording the whole screen", region));
+            }
+        }
+
+        for fix in &fixes {
+            log::warn!("Config: {}", fix);
+        }
+        fixes
+    }
+
+    /// Writes the config to `path` atomically, holding the config lock so a
+    /// second instance never reads or writes it halfway.
+    pub fn save_to(&self, path: &Path) -> Result<()> {
         // Create directory if it doesn't exist
-        if let Some(parent) = config_path.parent() {
+        if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
         
         let content = serde_json::to_string_pretty(self)?;
-        std::fs::write(config_path, content)?;
-        
-        Ok(())
+        let _lock = ConfigLock::acquire(path, true)?;
+        write_atomically(path, content.as_bytes())
+    }
+
+    /// The settings as they would be written to disk, for telling a real
+    /// external edit from the app's own save.
+    pub fn same_on_disk(&self, other: &Config) -> bool {
+        matches!((serde_json::to_value(self), serde_json::to_value(other)), (Ok(a), Ok(b)) if a == b)
+    }
+
+    /// `self` with the settings that differ between `base` and `edited` taken
+    /// from `edited`, compared per top-level field. Merges an edited file into
+    /// settings changed in the app since `base` was loaded.
+    pub fn merge_edits(&self, base: &Config, edited: &Config) -> Config {
+        let values = (serde_json::to_value(self), serde_json::to_value(base), serde_json::to_value(edited));
+        let (Ok(serde_json::Value::Object(mut ours)), Ok(serde_json::Value::Object(base)), Ok(serde_json::Value::Object(changes))) =
+            values
+        else {
+            return edited.clone();
+        };
+        for (key, value) in changes {
+            if base.get(&key) != Some(&value) {
+                ours.insert(key, value);
+            }
+        }
+        serde_json::from_value(serde_json::Value::Object(ours)).unwrap_or_else(|_| edited.clone())
     }
 
-    fn get_config_path() -> Result<PathBuf> {
+    pub fn get_config_path() -> Result<PathBuf> {
+        if let Some(dir) = portable_dir() {
+            return Ok(dir.join("config.json"));
+        }
+
         let config_dir = dirs::config_dir()
             .unwrap_or_default()
             .join("discord-recorder");
@@ -115,12 +1152,24 @@ impl Config {
         self.output_directory = path;
     }
 
+    /// The video and audio codec to record with, or the container's own if
+    /// it can't hold the chosen ones.
+    pub fn codecs(&self) -> (VideoCodec, AudioCodec) {
+        if self.output_container.allows(self.video_codec, self.audio_codec) {
+            (self.video_codec, self.audio_codec)
+        } else {
+            self.output_container.default_codecs()
+        }
+    }
+
     pub fn get_video_bitrate(&self) -> u32 {
         match self.video_quality {
             VideoQuality::Low => 1000,
             VideoQuality::Medium => 2500,
             VideoQuality::High => 5000,
             VideoQuality::Ultra => 10000,
+            // Not passed to ffmpeg; lossless output has no bitrate cap
+            VideoQuality::Lossless => 0,
         }
     }
 
@@ -133,6 +1182,13 @@ impl Config {
         }
     }
 
+    pub fn get_flac_bit_depth(&self) -> FlacBitDepth {
+        self.flac_bit_depth.unwrap_or(match self.audio_quality {
+            AudioQuality::Lossless => FlacBitDepth::Bits24,
+            _ => FlacBitDepth::Bits16,
+        })
+    }
+
     pub fn get_audio_bitrate(&self) -> u32 {
         match self.audio_quality {
             AudioQuality::Low => 64,
@@ -141,4 +1197,159 @@ impl Config {
             AudioQuality::Lossless => 320,
         }
     }
-}
\ No newline at end of file
+}
+
+/// `config.json` as it was last loaded or saved, next to the runtime config it
+/// was in step with then. The two differ by the command-line overrides, which
+/// saving leaves out of the file unless the setting was changed in the app.
+#[derive(Debug, Clone)]
+pub struct ConfigFile {
+    /// What the file holds.
+    pub on_disk: Config,
+    synced: Config,
+}
+
+impl ConfigFile {
+    pub fn new(on_disk: Config, runtime: &Config) -> Self {
+        Self { on_disk, synced: runtime.clone() }
+    }
+
+    /// Writes the settings of `runtime` changed since the last save or load.
+    pub fn save(&mut self, runtime: &Config) -> Result<()> {
+        self.save_to(&Config::get_config_path()?, runtime)
+    }
+
+    pub fn save_to(&mut self, path: &Path, runtime: &Config) -> Result<()> {
+        let config = self.on_disk.merge_edits(&self.synced, runtime);
+        config.save_to(path)?;
+        self.on_disk = config;
+        self.synced = runtime.clone();
+        Ok(())
+    }
+}
+
+fn clamp_u32(fixes: &mut Vec<String>, name: &str, value: &mut u32, range: RangeInclusive<u32>) {
+    let fixed = (*value).clamp(*range.start(), *range.end());
+    if fixed != *value {
+        fixes.push(format!("{} {} → {}", name, value, fixed));
+        *value = fixed;
+    }
+}
+
+/// Like `clamp_u32`, but NaN and infinities become `fallback`.
+fn clamp_f32(fixes: &mut Vec<String>, name: &str, value: &mut f32, range: RangeInclusive<f32>, fallback: f32) {
+    let fixed = if value.is_finite() { value.clamp(*range.start(), *range.end()) } else { fallback };
+    if fixed != *value {
+        fixes.push(format!("{} {} → {}", name, value, fixed));
+        *value = fixed;
+    }
+}
+
+/// Why a config file couldn't be loaded.
+#[derive(Debug)]
+pub enum LoadError {
+    Unreadable(anyhow::Error),
+    Corrupt(serde_json::Error),
+}
+
+/// Where a config that fails to parse is kept before the defaults replace it.
+pub fn backup_path(config_path: &Path) -> PathBuf {
+    sibling_path(config_path, ".bak")
+}
+
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Replaces `path` with `content` by writing a temporary file next to it and
+/// renaming it over the original, so a crash midway leaves either the old or
+/// the new file and never a truncated one.
+pub fn write_atomically(path: &Path, content: &[u8]) -> Result<()> {
+    let temp = sibling_path(path, &format!(".{}.tmp", std::process::id()));
+    let written = (|| -> std::io::Result<()> {
+        let mut file = File::create(&temp)?;
+        file.write_all(content)?;
+        file.sync_all()
+    })()
+    .and_then(|()| std::fs::rename(&temp, path));
+    if let Err(e) = written {
+        let _ = std::fs::remove_file(&temp);
+        return Err(anyhow!("Failed to write {}: {}", path.display(), e));
+    }
+    Ok(())
+}
+
+fn read_locked(path: &Path) -> Result<String> {
+    let _lock = ConfigLock::acquire(path, false)?;
+    Ok(std::fs::read_to_string(path)?)
+}
+
+/// Advisory lock on `config.json.lock`, held while the config is read (shared)
+/// or written (exclusive). A separate file is locked because the config
+/// itself is replaced on every save.
+struct ConfigLock(File);
+
+impl ConfigLock {
+    fn acquire(config_path: &Path, exclusive: bool) -> Result<Self> {
+        let lock_path = sibling_path(config_path, ".lock");
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&lock_path)
+            .map_err(|e| anyhow!("Failed to open {}: {}", lock_path.display(), e))?;
+        if exclusive {
+            FileExt::lock_exclusive(&file)?;
+        } else {
+            FileExt::lock_shared(&file)?;
+        }
+        Ok(Self(file))
+    }
+}
+
+impl Drop for ConfigLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.0);
+    }
+}
+
+/// Notices changes to `config.json`. The directory is watched rather than the
+/// file, since many editors save by writing a new file and renaming it over
+/// the old one. The app's own saves are reported too.
+pub struct ConfigWatcher {
+    _watcher: notify::RecommendedWatcher,
+    changed: Arc<AtomicBool>,
+}
+
+impl ConfigWatcher {
+    pub fn start() -> Result<Self> {
+        let path = Config::get_config_path()?;
+        let dir = path
+            .parent()
+            .ok_or_else(|| anyhow!("Config path {} has no directory", path.display()))?
+            .to_path_buf();
+        let name = path.file_name().map(|name| name.to_os_string());
+        let changed = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&changed);
+        let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            let Ok(event) = event else {
+                return;
+            };
+            if (event.kind.is_modify() || event.kind.is_create())
+                && event.paths.iter().any(|p| p.file_name() == name.as_deref())
+            {
+                flag.store(true, Ordering::Relaxed);
+            }
+        })?;
+        std::fs::create_dir_all(&dir)?;
+        watcher.watch(&dir, notify::RecursiveMode::NonRecursive)?;
+        Ok(Self { _watcher: watcher, changed })
+    }
+
+    /// Whether the file changed since the last call.
+    pub fn take_change(&self) -> bool {
+        self.changed.swap(false, Ordering::Relaxed)
+    }
+}
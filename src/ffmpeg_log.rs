@@ -0,0 +1,264 @@
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{error, info, warn};
+
+/// Lines kept for the log panel; older ones are dropped.
+pub const LOG_CAPACITY: usize = 400;
+/// Status-line samples kept for the stats graphs; ffmpeg prints two a second,
+/// so this covers the last ten minutes.
+pub const STATS_CAPACITY: usize = 1200;
+/// Samples looked back over when deciding whether frames are being dropped
+/// (about ten seconds).
+const DROP_WINDOW: usize = 20;
+/// Frames dropped within `DROP_WINDOW` before it counts as falling behind;
+/// a handful at startup is normal.
+const DROP_THRESHOLD: u64 = 15;
+/// Samples the current fps and bitrate are measured over (about two seconds).
+const RATE_WINDOW: usize = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub level: LogLevel,
+    pub text: String,
+    /// ffmpeg's `frame= ... fps= ...` status line, which replaces its predecessor.
+    pub progress: bool,
+}
+
+/// Values from one ffmpeg status line; fields ffmpeg reports as N/A are `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct EncoderStats {
+    /// Frames written so far.
+    pub frames: Option<u64>,
+    pub fps: Option<f32>,
+    /// Output written so far, in bytes.
+    pub size_bytes: Option<u64>,
+    pub bitrate_kbps: Option<f32>,
+    pub time: Option<Duration>,
+    pub speed: Option<f32>,
+    /// Input frames discarded because the encoder couldn't keep up.
+    pub dropped_frames: Option<u64>,
+    /// Frames repeated to fill gaps in the input.
+    pub duplicated_frames: Option<u64>,
+}
+
+/// Parses `frame=  120 fps= 60 q=23.0 size=    1024KiB time=00:00:02.00 bitrate=4194.3kbits/s speed=1x`.
+pub fn parse_stats(line: &str) -> Option<EncoderStats> {
+    if !is_progress(line) {
+        return None;
+    }
+    // ffmpeg pads values after the `=`, so glue them back to their keys
+    let mut compact = String::with_capacity(line.len());
+    let mut after_equals = false;
+    for c in line.chars() {
+        if after_equals && c == ' ' {
+            continue;
+        }
+        after_equals = c == '=';
+        compact.push(c);
+    }
+
+    let mut stats = EncoderStats::default();
+    for pair in compact.split_whitespace() {
+        let Some((key, value)) = pair.split_once('=') else {
+            continue;
+        };
+        match key {
+            "frame" => stats.frames = value.parse().ok(),
+            "fps" => stats.fps = value.parse().ok(),
+            "size" | "Lsize" => stats.size_bytes = parse_size(value),
+            "bitrate" => stats.bitrate_kbps = value.strip_suffix("kbits/s").and_then(|v| v.parse().ok()),
+            "time" => stats.time = parse_clock(value),
+            "speed" => stats.speed = value.strip_suffix('x').and_then(|v| v.parse().ok()),
+            "drop" => stats.dropped_frames = value.parse().ok(),
+            "dup" => stats.duplicated_frames = value.parse().ok(),
+            _ => {}
+        }
+    }
+    Some(stats)
+}
+
+/// Whether ffmpeg's drop counter has kept climbing over the most recent
+/// samples, meaning the machine can't sustain the chosen frame rate.
+pub fn is_dropping_frames(history: &[EncoderStats]) -> bool {
+    let latest = history.iter().rev().find_map(|s| s.dropped_frames);
+    let start = history.len().saturating_sub(DROP_WINDOW);
+    let earlier = history[start..].iter().find_map(|s| s.dropped_frames);
+    match (earlier, latest) {
+        (Some(earlier), Some(latest)) => latest.saturating_sub(earlier) >= DROP_THRESHOLD,
+        _ => false,
+    }
+}
+
+/// What the statistics panel shows, worked out from the status-line samples;
+/// `None` until ffmpeg has reported enough to tell.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct StatsSummary {
+    pub time: Option<Duration>,
+    /// Frame rate over the last few samples.
+    pub current_fps: Option<f32>,
+    /// Frame rate since the recording started.
+    pub average_fps: Option<f32>,
+    /// Bitrate over the last few samples; ffmpeg's own figure is the average.
+    pub bitrate_kbps: Option<f32>,
+    pub size_bytes: Option<u64>,
+    pub speed: Option<f32>,
+    pub dropped_frames: Option<u64>,
+    pub duplicated_frames: Option<u64>,
+}
+
+/// Sums up `history`, oldest first. Rates come from the change in frame count
+/// and size over ffmpeg's timestamps, which keep still while paused.
+pub fn summarize_stats(history: &[EncoderStats]) -> StatsSummary {
+    let latest = history.last().copied().unwrap_or_default();
+    let window = &history[history.len().saturating_sub(RATE_WINDOW + 1)..];
+    let rate = |value: fn(&EncoderStats) -> Option<f64>| {
+        let (first, last) = (window.first()?, window.last()?);
+        let seconds = (last.time? - first.time?).as_secs_f64();
+        let change = value(last)? - value(first)?;
+        (seconds > 0.0).then(|| (change / seconds) as f32)
+    };
+    let average_fps = match (latest.frames, latest.time) {
+        (Some(frames), Some(time)) if !time.is_zero() => Some((frames as f64 / time.as_secs_f64()) as f32),
+        _ => latest.fps,
+    };
+    StatsSummary {
+        time: latest.time,
+        current_fps: rate(|s| s.frames.map(|f| f as f64)).or(latest.fps),
+        average_fps,
+        bitrate_kbps: rate(|s| s.size_bytes.map(|b| b as f64 * 8.0 / 1000.0)).or(latest.bitrate_kbps),
+        size_bytes: latest.size_bytes,
+        speed: latest.speed,
+        dropped_frames: latest.dropped_frames,
+        duplicated_frames: latest.duplicated_frames,
+    }
+}
+
+fn parse_size(value: &str) -> Option<u64> {
+    let digits_end = value.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(value.len());
+    let number: f64 = value[..digits_end].parse().ok()?;
+    let scale = match &value[digits_end..] {
+        "B" | "" => 1.0,
+        "kB" | "KiB" => 1024.0,
+        "mB" | "MB" | "MiB" => 1024.0 * 1024.0,
+        "GiB" => 1024.0 * 1024.0 * 1024.0,
+        _ => return None,
+    };
+    Some((number * scale) as u64)
+}
+
+/// `HH:MM:SS.cc`; negative times (before the first frame) are `None`.
+fn parse_clock(value: &str) -> Option<Duration> {
+    let mut parts = value.splitn(3, ':');
+    let hours: u64 = parts.next()?.parse().ok()?;
+    let minutes: u64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    Some(Duration::from_secs(hours * 3600 + minutes * 60) + Duration::from_secs_f64(seconds))
+}
+
+/// Recent ffmpeg output shared between the encoder's drain threads and the GUI.
+#[derive(Debug, Clone, Default)]
+pub struct FfmpegLog {
+    lines: Arc<Mutex<VecDeque<LogLine>>>,
+    stats: Arc<Mutex<VecDeque<EncoderStats>>>,
+}
+
+impl FfmpegLog {
+    pub fn push(&self, level: LogLevel, text: impl Into<String>) {
+        self.push_line(LogLine { level, text: text.into(), progress: false });
+    }
+
+    fn push_line(&self, line: LogLine) {
+        let mut lines = self.lines.lock().unwrap();
+        if line.progress && lines.back().is_some_and(|last| last.progress) {
+            lines.pop_back();
+        }
+        if lines.len() >= LOG_CAPACITY {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    pub fn snapshot(&self) -> Vec<LogLine> {
+        self.lines.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn clear(&self) {
+        self.lines.lock().unwrap().clear();
+    }
+
+    /// Status-line samples of the current recording, oldest first.
+    pub fn stats_history(&self) -> Vec<EncoderStats> {
+        self.stats.lock().unwrap().iter().copied().collect()
+    }
+
+    pub fn clear_stats(&self) {
+        self.stats.lock().unwrap().clear();
+    }
+
+    fn push_stats(&self, sample: EncoderStats) {
+        let mut stats = self.stats.lock().unwrap();
+        if stats.len() >= STATS_CAPACITY {
+            stats.pop_front();
+        }
+        stats.push_back(sample);
+    }
+
+    /// Reads ffmpeg output until EOF, recording each line and mirroring it to the
+    /// application log. ffmpeg redraws its status line with `\r`, so both `\r`
+    /// and `\n` end a line.
+    pub fn drain<R: Read>(&self, reader: R) {
+        let reader = BufReader::new(reader);
+        for chunk in reader.split(b'\n').map_while(Result::ok) {
+            for part in chunk.split(|&b| b == b'\r') {
+                let text = String::from_utf8_lossy(part).trim_end().to_string();
+                if text.is_empty() {
+                    continue;
+                }
+                let progress = is_progress(&text);
+                if let Some(sample) = parse_stats(&text) {
+                    self.push_stats(sample);
+                }
+                let level = classify(&text);
+                match level {
+                    LogLevel::Error => error!("ffmpeg: {}", text),
+                    LogLevel::Warning => warn!("ffmpeg: {}", text),
+                    LogLevel::Info if !progress => info!("ffmpeg: {}", text),
+                    LogLevel::Info => {}
+                }
+                self.push_line(LogLine { level, text, progress });
+            }
+        }
+    }
+}
+
+fn is_progress(text: &str) -> bool {
+    text.starts_with("frame=") || text.starts_with("size=")
+}
+
+/// ffmpeg doesn't tag its messages by default, so go by wording.
+fn classify(text: &str) -> LogLevel {
+    let lower = text.to_ascii_lowercase();
+    if ["error", "failed", "invalid", "could not", "cannot", "no such", "permission denied", "conversion failed"]
+        .iter()
+        .any(|needle| lower.contains(needle))
+    {
+        LogLevel::Error
+    } else if ["warning", "deprecated", "past duration", "non-monotonous", "dropping", "buffer underflow", "queue"]
+        .iter()
+        .any(|needle| lower.contains(needle))
+    {
+        LogLevel::Warning
+    } else {
+        LogLevel::Info
+    }
+}
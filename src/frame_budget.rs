@@ -0,0 +1,108 @@
+use crossbeam::channel::{Receiver, Sender, TrySendError};
+use image::DynamicImage;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Frames a preview queue holds; the GUI only ever shows the newest.
+pub const PREVIEW_QUEUE_CAPACITY: usize = 2;
+
+/// Memory budget shared by all capture queues. Frames are reserved before they
+/// are queued and released when the consumer takes them; a frame that doesn't
+/// fit is dropped and counted by the capture that produced it.
+#[derive(Debug, Clone)]
+pub struct FrameBudget {
+    limit: Arc<AtomicUsize>,
+    used: Arc<AtomicUsize>,
+}
+
+impl FrameBudget {
+    pub fn new(limit_mb: u32) -> Self {
+        let budget = Self {
+            limit: Arc::new(AtomicUsize::new(0)),
+            used: Arc::new(AtomicUsize::new(0)),
+        };
+        budget.set_limit_mb(limit_mb);
+        budget
+    }
+
+    pub fn set_limit_mb(&self, limit_mb: u32) {
+        self.limit.store(limit_mb as usize * 1024 * 1024, Ordering::Relaxed);
+    }
+
+    /// Reserves `bytes` if they fit. With nothing queued a frame is always
+    /// admitted, so a budget smaller than one frame slows the preview instead
+    /// of stopping it.
+    pub fn try_reserve(&self, bytes: usize) -> bool {
+        let limit = self.limit.load(Ordering::Relaxed);
+        self.used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                (used == 0 || used + bytes <= limit).then_some(used + bytes)
+            })
+            .is_ok()
+    }
+
+    pub fn release(&self, bytes: usize) {
+        let _ = self
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| Some(used.saturating_sub(bytes)));
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.used.load(Ordering::Relaxed)
+    }
+}
+
+/// Queues `frame` for a preview that only shows the newest one. When the
+/// queue is full or the budget is used up, the oldest queued frames make
+/// room. Returns how many frames were dropped, counting `frame` itself when
+/// it still didn't fit.
+pub fn queue_newest(
+    sender: &Sender<DynamicImage>,
+    receiver: &Receiver<DynamicImage>,
+    budget: &FrameBudget,
+    frame: DynamicImage,
+) -> u64 {
+    let bytes = frame.as_bytes().len();
+    let mut dropped = 0;
+    let mut drop_oldest = || match receiver.try_recv() {
+        Ok(old) => {
+            budget.release(old.as_bytes().len());
+            dropped += 1;
+            true
+        }
+        Err(_) => false,
+    };
+    while !budget.try_reserve(bytes) {
+        // Another capture holds the memory; this frame gives way
+        if !drop_oldest() {
+            return dropped + 1;
+        }
+    }
+    let mut frame = frame;
+    loop {
+        match sender.try_send(frame) {
+            Ok(()) => break,
+            Err(TrySendError::Full(back)) => {
+                drop_oldest();
+                frame = back;
+            }
+            Err(TrySendError::Disconnected(_)) => {
+                budget.release(bytes);
+                dropped += 1;
+                break;
+            }
+        }
+    }
+    dropped
+}
+
+/// The newest queued frame; older ones are skipped and their memory returned
+/// to the budget.
+pub fn take_newest(receiver: &Receiver<DynamicImage>, budget: &FrameBudget) -> Option<DynamicImage> {
+    let mut newest = None;
+    while let Ok(frame) = receiver.try_recv() {
+        budget.release(frame.as_bytes().len());
+        newest = Some(frame);
+    }
+    newest
+}
@@ -1,20 +1,35 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use egui::{
     CentralPanel, TopBottomPanel, RichText, Color32, ColorImage, TextureHandle, TextureOptions,
-    Stroke, ProgressBar, DragValue, Slider, KeyboardShortcut, Modifiers, Key
+    Stroke, ProgressBar, DragValue, Slider, KeyboardShortcut, Modifiers, Key, WidgetType
 };
 use egui::vec2;
 use image::DynamicImage;
-use log::{info, error};
-use std::path::PathBuf;
+use log::{info, error, warn};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use crate::{
-    audio::{self, AudioProcessor, AudioRecorder},
-    config::{Config, VideoQuality, AudioQuality},
+    annotations::{self, Annotation, AnnotationTool, SharedAnnotations},
+    clicks::{click_ring_size, Click, ClickWatcher},
+    audio::{self, AudioMonitor, AudioProcessor, AudioRecorder, SampleRing, TestTone},
+    ffmpeg_log::{is_dropping_frames, summarize_stats, FfmpegLog, LogLevel},
+    pacing::{FpsCounter, FramePacing},
+    presets::{self, Preset},
+    frame_budget::FrameBudget,
+    i18n::{self, tr, Language},
+    config::{self, AudioCodec, Config, ConfigFile, ConfigWatcher, LoadError, LogVerbosity, VideoQuality, AudioQuality, MicGateMode, DuckingSettings, AudioExportFormat, OutputContainer, HwAccel, MarkerSubtitles, FlacBitDepth, StoredHotkey, StoredHotkeys, DiscordTheme, BackgroundBlur, ChromaKey, ClickHighlight, OverlayBorder, OverlayLayout, SrtSettings, UploadMethod, VideoCodec},
+    naming,
+    postprocess,
     screen::{self, ScreenCapture},
-    video::{RecorderOptions, VideoEncoder},
-    webcam::{self, WebcamCapture},
+    runtime::runtime_handle,
+    toast::{ToastKind, Toasts},
+    upload,
+    spectrum::{self, SpectrumAnalyzer},
+    video::{clamp_capture_region, OverlayPlacement, RecorderOptions, RecordingOutputs, VideoEncoder},
+    webcam::{self, WebcamCapture, WebcamStatus},
 };
 #[cfg(feature = "webcam")]
 use crate::webcam::WebcamOverlay;
@@ -23,21 +38,68 @@ use crate::webcam::WebcamOverlay;
 // pub use crate::config::VideoQuality;
 // pub use crate::config::AudioQuality;
 
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Hotkey {
+    pub shortcut: KeyboardShortcut,
+    /// A disabled hotkey keeps its binding but is ignored.
+    pub enabled: bool,
+}
+
+impl Hotkey {
+    fn new(modifiers: Modifiers, key: Key) -> Self {
+        Self { shortcut: KeyboardShortcut::new(modifiers, key), enabled: true }
+    }
+
+    fn from_stored(stored: &StoredHotkey) -> Option<Self> {
+        let key = Key::from_name(&stored.key)?;
+        let modifiers = Modifiers {
+            alt: stored.alt,
+            ctrl: stored.ctrl,
+            shift: stored.shift,
+            mac_cmd: stored.command && cfg!(target_os = "macos"),
+            command: stored.command,
+        };
+        Some(Self { shortcut: KeyboardShortcut::new(modifiers, key), enabled: stored.enabled })
+    }
+
+    fn to_stored(self) -> StoredHotkey {
+        let modifiers = self.shortcut.modifiers;
+        StoredHotkey {
+            key: self.shortcut.logical_key.name().to_string(),
+            ctrl: modifiers.ctrl,
+            alt: modifiers.alt,
+            shift: modifiers.shift,
+            command: modifiers.command,
+            enabled: self.enabled,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct HotkeyConfig {
-    pub start_stop: KeyboardShortcut,
-    pub pause_resume: KeyboardShortcut,
-    pub toggle_webcam: KeyboardShortcut,
+    pub start_stop: Hotkey,
+    pub pause_resume: Hotkey,
+    pub toggle_webcam: Hotkey,
+    pub add_marker: Hotkey,
+    pub webcam_snapshot: Hotkey,
+    pub next_scene: Hotkey,
+    /// Held rather than pressed; see `MicGateMode`.
+    pub push_to_talk: Hotkey,
 }
 
 impl Default for HotkeyConfig {
     fn default() -> Self {
         let mut ctrl = Modifiers::default();
         ctrl.ctrl = true;
+        let ctrl_shift = Modifiers { shift: true, ..ctrl };
         Self {
-            start_stop: KeyboardShortcut::new(ctrl, Key::R),
-            pause_resume: KeyboardShortcut::new(ctrl, Key::P),
-            toggle_webcam: KeyboardShortcut::new(ctrl, Key::W),
+            start_stop: Hotkey::new(ctrl, Key::R),
+            pause_resume: Hotkey::new(ctrl, Key::P),
+            toggle_webcam: Hotkey::new(ctrl, Key::W),
+            add_marker: Hotkey::new(ctrl, Key::M),
+            webcam_snapshot: Hotkey::new(ctrl_shift, Key::S),
+            next_scene: Hotkey::new(ctrl_shift, Key::N),
+            push_to_talk: Hotkey::new(Modifiers::NONE, Key::F8),
         }
     }
 }
@@ -47,11 +109,176 @@ enum HotkeyAction {
     StartStop,
     PauseResume,
     ToggleWebcam,
+    AddMarker,
+    WebcamSnapshot,
+    NextScene,
+    PushToTalk,
+}
+
+impl HotkeyAction {
+    const ALL: [HotkeyAction; 7] = [
+        HotkeyAction::StartStop,
+        HotkeyAction::PauseResume,
+        HotkeyAction::ToggleWebcam,
+        HotkeyAction::AddMarker,
+        HotkeyAction::WebcamSnapshot,
+        HotkeyAction::NextScene,
+        HotkeyAction::PushToTalk,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            HotkeyAction::StartStop => "Start/Stop Recording",
+            HotkeyAction::PauseResume => "Pause/Resume",
+            HotkeyAction::ToggleWebcam => "Toggle Webcam",
+            HotkeyAction::AddMarker => "Add Marker",
+            HotkeyAction::WebcamSnapshot => "Webcam Snapshot",
+            HotkeyAction::NextScene => "Next Scene",
+            HotkeyAction::PushToTalk => "Push-to-Talk",
+        }
+    }
+}
+
+impl HotkeyConfig {
+    /// Defaults with the saved bindings applied; unparsable entries keep the default.
+    pub fn from_stored(stored: &StoredHotkeys) -> Self {
+        let mut hotkeys = Self::default();
+        let mut stored = stored.clone();
+        for action in HotkeyAction::ALL {
+            if let Some(hotkey) = stored_slot(&mut stored, action).as_ref().and_then(Hotkey::from_stored) {
+                *hotkeys.hotkey_mut(action) = hotkey;
+            }
+        }
+        hotkeys
+    }
+
+    /// Bindings to save; hotkeys left at their default are omitted.
+    pub fn to_stored(&self) -> StoredHotkeys {
+        let defaults = Self::default();
+        let mut stored = StoredHotkeys::default();
+        for action in HotkeyAction::ALL {
+            let hotkey = *self.hotkey(action);
+            if hotkey != *defaults.hotkey(action) {
+                *stored_slot(&mut stored, action) = Some(hotkey.to_stored());
+            }
+        }
+        stored
+    }
+
+    fn hotkey(&self, action: HotkeyAction) -> &Hotkey {
+        match action {
+            HotkeyAction::StartStop => &self.start_stop,
+            HotkeyAction::PauseResume => &self.pause_resume,
+            HotkeyAction::ToggleWebcam => &self.toggle_webcam,
+            HotkeyAction::AddMarker => &self.add_marker,
+            HotkeyAction::WebcamSnapshot => &self.webcam_snapshot,
+            HotkeyAction::NextScene => &self.next_scene,
+            HotkeyAction::PushToTalk => &self.push_to_talk,
+        }
+    }
+
+    fn hotkey_mut(&mut self, action: HotkeyAction) -> &mut Hotkey {
+        match action {
+            HotkeyAction::StartStop => &mut self.start_stop,
+            HotkeyAction::PauseResume => &mut self.pause_resume,
+            HotkeyAction::ToggleWebcam => &mut self.toggle_webcam,
+            HotkeyAction::AddMarker => &mut self.add_marker,
+            HotkeyAction::WebcamSnapshot => &mut self.webcam_snapshot,
+            HotkeyAction::NextScene => &mut self.next_scene,
+            HotkeyAction::PushToTalk => &mut self.push_to_talk,
+        }
+    }
+
+    fn binding(&self, action: HotkeyAction) -> &KeyboardShortcut {
+        &self.hotkey(action).shortcut
+    }
+
+    /// The shortcut for `action` if it's enabled.
+    fn active(&self, action: HotkeyAction) -> Option<&KeyboardShortcut> {
+        let hotkey = self.hotkey(action);
+        hotkey.enabled.then_some(&hotkey.shortcut)
+    }
+
+    /// Another action already bound to `shortcut`, if any.
+    fn conflict(&self, action: HotkeyAction, shortcut: &KeyboardShortcut) -> Option<HotkeyAction> {
+        HotkeyAction::ALL
+            .into_iter()
+            .find(|&other| other != action && self.binding(other) == shortcut)
+    }
+
+    /// Pairs of actions sharing a binding, e.g. after resetting one to a default
+    /// that another was rebound to.
+    fn duplicates(&self) -> Vec<(HotkeyAction, HotkeyAction)> {
+        let mut pairs = Vec::new();
+        for (i, &first) in HotkeyAction::ALL.iter().enumerate() {
+            for &second in &HotkeyAction::ALL[i + 1..] {
+                if self.binding(first) == self.binding(second) {
+                    pairs.push((first, second));
+                }
+            }
+        }
+        pairs
+    }
+}
+
+fn stored_slot(stored: &mut StoredHotkeys, action: HotkeyAction) -> &mut Option<StoredHotkey> {
+    match action {
+        HotkeyAction::StartStop => &mut stored.start_stop,
+        HotkeyAction::PauseResume => &mut stored.pause_resume,
+        HotkeyAction::ToggleWebcam => &mut stored.toggle_webcam,
+        HotkeyAction::AddMarker => &mut stored.add_marker,
+        HotkeyAction::WebcamSnapshot => &mut stored.webcam_snapshot,
+        HotkeyAction::NextScene => &mut stored.next_scene,
+        HotkeyAction::PushToTalk => &mut stored.push_to_talk,
+    }
+}
+
+/// Why the OS or desktop is likely to take `shortcut` before the app sees it.
+pub fn reserved_shortcut(shortcut: &KeyboardShortcut) -> Option<&'static str> {
+    let m = shortcut.modifiers;
+    let ctrl_alt = m.ctrl && m.alt && !m.shift;
+    match shortcut.logical_key {
+        Key::Tab if m.alt || m.mac_cmd => Some("switches windows"),
+        Key::F4 if m.alt && !m.ctrl && !m.shift => Some("closes the window on Windows and most Linux desktops"),
+        Key::Delete if m.ctrl && m.alt => Some("is reserved by the OS"),
+        Key::Escape if m.ctrl && !m.alt => Some("opens the Start menu or Task Manager on Windows"),
+        Key::Q if m.mac_cmd => Some("quits the app on macOS"),
+        Key::W if m.mac_cmd => Some("closes the window on macOS"),
+        Key::H if m.mac_cmd => Some("hides the app on macOS"),
+        Key::Space if m.mac_cmd => Some("opens Spotlight on macOS"),
+        Key::T if ctrl_alt => Some("opens a terminal on many Linux desktops"),
+        Key::L if ctrl_alt => Some("locks the screen on many Linux desktops"),
+        key if m.is_none()
+            && (key.name().chars().count() == 1 || matches!(key, Key::Space | Key::Enter | Key::Backspace | Key::Tab)) =>
+        {
+            Some("has no modifier and fires while typing in other fields")
+        }
+        _ => None,
+    }
 }
 
+/// Opens the read-only shortcut list; checked after the configurable hotkeys so
+/// a binding on F1 still wins.
+const SHORTCUT_HELP_KEY: Key = Key::F1;
+
 pub struct AppState {
     pub is_recording: bool,
     pub is_paused: bool,
+    /// When the current recording started; `None` while idle.
+    pub recording_started: Option<Instant>,
+    /// When the current pause began.
+    pub paused_since: Option<Instant>,
+    /// Time spent in pauses that have ended.
+    pub paused_total: Duration,
+    /// Set while a pause or resume is finishing on a worker.
+    pub segment_switch: Option<SegmentSwitch>,
+    /// Set while a stopped recording's ffmpeg process is still flushing to disk.
+    pub is_finalizing: bool,
+    pub last_outputs: Option<RecordingOutputs>,
+    /// Recorded length of `last_outputs`, pauses excluded.
+    pub last_duration: Option<Duration>,
+    /// Thumbnail written for `last_outputs`.
+    pub last_thumbnail: Option<PathBuf>,
     pub record_audio: bool,
     pub record_video: bool,
     pub record_webcam: bool,
@@ -62,26 +289,99 @@ pub struct AppState {
     pub output_path: String,
     pub video_quality: VideoQuality,
     pub audio_quality: AudioQuality,
+    /// The settings in use, including command-line overrides.
     pub config: Config,
+    pub config_file: ConfigFile,
     pub show_settings: bool,
     pub audio_gain_db: f32,
+    pub audio_balance: f32,
+    pub limiter_enabled: bool,
+    pub limiter_threshold_db: f32,
+    pub limiter_makeup_db: f32,
     pub overlay_position: (u32, u32),
     pub overlay_size: (u32, u32),
     pub overlay_opacity: f32,
+    pub overlay_layout: OverlayLayout,
+    pub overlay_border: OverlayBorder,
+    pub chroma_key: ChromaKey,
+    pub background_blur: BackgroundBlur,
     pub hotkeys: HotkeyConfig,
     pub use_pipewire_on_wayland: bool,
     pub enable_preview_overlay: bool,
+    pub seamless_pause: bool,
+    pub low_latency: bool,
+    pub encoder_threads: Option<u32>,
     pub screen_zoom: f32,
     pub webcam_zoom: f32,
+    /// Show the screen preview at its fit-to-window size, ignoring `screen_zoom`.
+    pub screen_fit: bool,
+    /// Show the webcam preview at its fit-to-window size, ignoring `webcam_zoom`.
+    pub webcam_fit: bool,
+    /// Share of the preview height for the screen; set with the splitter.
+    pub preview_split: f32,
+    /// Show a scrolling waveform instead of the single level bar.
+    pub show_waveform: bool,
+    /// Show the FFT spectrum analyzer (costs some CPU while visible).
+    pub show_spectrum: bool,
+    pub mic_gate: MicGateMode,
+    pub record_system_audio: bool,
+    pub ducking_enabled: bool,
+    pub ducking: DuckingSettings,
+    pub audio_export_format: AudioExportFormat,
+    pub output_container: OutputContainer,
+    pub video_codec: VideoCodec,
+    pub audio_codec: AudioCodec,
+    pub hw_accel: HwAccel,
+    pub log_clip_timestamps: bool,
+    /// Play the input back on the default output while recording.
+    pub monitor_enabled: bool,
+    pub monitor_volume: f32,
+    pub monitor_muted: bool,
+    /// `None` picks the depth from the audio quality.
+    pub flac_bit_depth: Option<FlacBitDepth>,
+    pub separate_webcam: bool,
+    pub composite_webcam: bool,
+    pub log_verbosity: LogVerbosity,
+    pub language: Language,
+    pub show_preview_fps: bool,
+    pub copy_path_on_stop: bool,
+    pub show_stats_panel: bool,
+    pub file_name_template: String,
+    pub thumbnail_on_stop: bool,
+    pub contact_sheet_on_stop: bool,
+    /// Text the finalize task wants copied; the UI thread owns the clipboard.
+    pub pending_clipboard: Option<String>,
+    pub discord_theme: DiscordTheme,
+    pub frame_rate: u32,
+    pub preview_fps_cap: Option<u32>,
+    pub frame_buffer_limit_mb: u32,
+    /// (x, y, width, height) of the screen to record, drawn on the preview.
+    pub capture_region: Option<(u32, u32, u32, u32)>,
+    pub cursor_zoom: Option<f32>,
+    pub click_highlight: ClickHighlight,
+    pub piped_screen_capture: bool,
+    pub confirm_long_stop: bool,
+    pub confirm_stop_after_minutes: u32,
+    pub stream_enabled: bool,
+    pub srt: SrtSettings,
+    /// Problem with the config file found at startup, shown once as a toast.
+    pub config_warning: Option<String>,
 }
 
 impl AppState {
-    pub fn new() -> Self {
-        let config = Config::load().unwrap_or_default();
+    pub fn from_config(config: Config) -> Self {
         let enable_preview_overlay = config.enable_preview_overlay;
         Self {
             is_recording: false,
             is_paused: false,
+            recording_started: None,
+            paused_since: None,
+            paused_total: Duration::ZERO,
+            segment_switch: None,
+            is_finalizing: false,
+            last_outputs: None,
+            last_duration: None,
+            last_thumbnail: None,
             record_audio: config.record_audio,
             record_video: config.record_video,
             record_webcam: config.record_webcam,
@@ -94,17 +394,245 @@ impl AppState {
             audio_quality: config.audio_quality,
             show_settings: false,
             audio_gain_db: 0.0,
-            overlay_position: (40, 40),
-            overlay_size: (320, 180),
-            overlay_opacity: 0.9,
-            hotkeys: HotkeyConfig::default(),
+            audio_balance: config.audio_balance,
+            limiter_enabled: config.limiter_enabled,
+            limiter_threshold_db: config.limiter_threshold_db,
+            limiter_makeup_db: config.limiter_makeup_db,
+            overlay_position: OverlayPlacement::default().position,
+            overlay_size: OverlayPlacement::default().size,
+            overlay_opacity: OverlayPlacement::default().opacity,
+            overlay_layout: config.overlay_layout,
+            overlay_border: config.overlay_border,
+            chroma_key: config.chroma_key,
+            background_blur: config.background_blur,
+            hotkeys: HotkeyConfig::from_stored(&config.hotkeys),
             use_pipewire_on_wayland: false,
             enable_preview_overlay,
+            seamless_pause: config.seamless_pause,
+            low_latency: config.low_latency,
+            encoder_threads: config.encoder_threads,
             screen_zoom: 1.0,
             webcam_zoom: 1.0,
+            screen_fit: false,
+            webcam_fit: false,
+            preview_split: config.preview_split,
+            show_waveform: false,
+            show_spectrum: false,
+            mic_gate: config.mic_gate,
+            record_system_audio: config.record_system_audio,
+            ducking_enabled: config.ducking_enabled,
+            ducking: config.ducking,
+            audio_export_format: config.audio_export_format,
+            output_container: config.output_container,
+            video_codec: config.video_codec,
+            audio_codec: config.audio_codec,
+            hw_accel: config.hw_accel,
+            log_clip_timestamps: config.log_clip_timestamps,
+            monitor_enabled: false,
+            monitor_volume: config.monitor_volume,
+            monitor_muted: false,
+            flac_bit_depth: config.flac_bit_depth,
+            separate_webcam: config.separate_webcam,
+            composite_webcam: config.composite_webcam,
+            log_verbosity: config.log_verbosity,
+            language: config.language,
+            show_preview_fps: config.show_preview_fps,
+            copy_path_on_stop: config.copy_path_on_stop,
+            show_stats_panel: config.show_stats_panel,
+            file_name_template: config.file_name_template.clone(),
+            thumbnail_on_stop: config.thumbnail_on_stop,
+            contact_sheet_on_stop: config.contact_sheet_on_stop,
+            pending_clipboard: None,
+            discord_theme: config.discord_theme,
+            frame_rate: config.frame_rate,
+            preview_fps_cap: config.preview_fps_cap,
+            frame_buffer_limit_mb: config.frame_buffer_limit_mb,
+            capture_region: config.capture_region,
+            cursor_zoom: config.cursor_zoom,
+            click_highlight: config.click_highlight,
+            piped_screen_capture: config.piped_screen_capture,
+            confirm_long_stop: config.confirm_long_stop,
+            confirm_stop_after_minutes: config.confirm_stop_after_minutes,
+            stream_enabled: config.stream_enabled,
+            srt: config.srt.clone(),
+            config_warning: None,
+            config_file: ConfigFile::new(config.clone(), &config),
             config,
         }
     }
+
+    /// Takes the settings from a reloaded `config`, keeping session state
+    /// such as the recording status and preview zoom that the file doesn't hold.
+    /// Values edited in the app but not saved yet stay, unless `config`
+    /// changes the same setting, and so do command-line overrides.
+    pub fn reload_config(&mut self, config: Config) {
+        let on_disk = &self.config_file.on_disk;
+        let merged = self.config_snapshot().merge_edits(on_disk, &config);
+        let runtime = self.config.merge_edits(on_disk, &config);
+        self.replace_config(merged);
+        // The edits are still unsaved
+        self.config_file = ConfigFile::new(config, &runtime);
+        self.config = runtime;
+    }
+
+    /// Switches to the settings in `config`, keeping the session state.
+    pub fn replace_config(&mut self, config: Config) {
+        let session = std::mem::replace(self, AppState::from_config(config));
+        self.config_file = session.config_file;
+        self.config_warning = session.config_warning;
+        self.is_recording = session.is_recording;
+        self.is_paused = session.is_paused;
+        (self.recording_started, self.paused_since, self.paused_total) =
+            (session.recording_started, session.paused_since, session.paused_total);
+        self.segment_switch = session.segment_switch;
+        self.is_finalizing = session.is_finalizing;
+        self.last_outputs = session.last_outputs;
+        self.last_duration = session.last_duration;
+        self.last_thumbnail = session.last_thumbnail;
+        self.show_settings = session.show_settings;
+        self.audio_gain_db = session.audio_gain_db;
+        (self.overlay_position, self.overlay_size, self.overlay_opacity) =
+            (session.overlay_position, session.overlay_size, session.overlay_opacity);
+        self.use_pipewire_on_wayland = session.use_pipewire_on_wayland;
+        (self.screen_zoom, self.webcam_zoom) = (session.screen_zoom, session.webcam_zoom);
+        (self.screen_fit, self.webcam_fit) = (session.screen_fit, session.webcam_fit);
+        (self.show_waveform, self.show_spectrum) = (session.show_waveform, session.show_spectrum);
+        (self.monitor_enabled, self.monitor_muted) = (session.monitor_enabled, session.monitor_muted);
+        self.pending_clipboard = session.pending_clipboard;
+    }
+
+    /// Saves `config` to `config.json`, leaving out command-line overrides.
+    pub fn save_config(&mut self) -> Result<()> {
+        self.config_file.save(&self.config)
+    }
+
+    /// The stored config updated with the values edited in the main panel and
+    /// settings, which only reach `config` when a recording starts.
+    pub fn config_snapshot(&self) -> Config {
+        let mut config = self.config.clone();
+        config.set_output_directory(self.output_path.clone());
+        config.record_audio = self.record_audio;
+        config.record_video = self.record_video;
+        config.record_webcam = self.record_webcam;
+        config.separate_outputs = self.separate_outputs;
+        config.use_pipewire_on_wayland = self.use_pipewire_on_wayland;
+        config.enable_preview_overlay = self.enable_preview_overlay;
+        config.overlay_layout = self.overlay_layout;
+        config.overlay_border = self.overlay_border;
+        config.chroma_key = self.chroma_key;
+        config.background_blur = self.background_blur;
+        config.seamless_pause = self.seamless_pause;
+        config.low_latency = self.low_latency;
+        config.encoder_threads = self.encoder_threads;
+        config.audio_balance = self.audio_balance;
+        config.mic_gate = self.mic_gate;
+        config.audio_export_format = self.audio_export_format;
+        config.output_container = self.output_container;
+        (config.video_codec, config.audio_codec) = (self.video_codec, self.audio_codec);
+        config.hw_accel = self.hw_accel;
+        config.log_clip_timestamps = self.log_clip_timestamps;
+        config.monitor_volume = self.monitor_volume;
+        config.flac_bit_depth = self.flac_bit_depth;
+        (config.separate_webcam, config.composite_webcam) = (self.separate_webcam, self.composite_webcam);
+        (config.frame_rate, config.preview_fps_cap) = (self.frame_rate, self.preview_fps_cap);
+        config.frame_buffer_limit_mb = self.frame_buffer_limit_mb;
+        config.capture_region = self.capture_region;
+        config.cursor_zoom = self.cursor_zoom;
+        config.click_highlight = self.click_highlight;
+        config.piped_screen_capture = self.piped_screen_capture;
+        (config.confirm_long_stop, config.confirm_stop_after_minutes) =
+            (self.confirm_long_stop, self.confirm_stop_after_minutes);
+        config.hotkeys = self.hotkeys.to_stored();
+        config.show_preview_fps = self.show_preview_fps;
+        config.copy_path_on_stop = self.copy_path_on_stop;
+        config.show_stats_panel = self.show_stats_panel;
+        config.file_name_template = self.file_name_template.clone();
+        (config.thumbnail_on_stop, config.contact_sheet_on_stop) = (self.thumbnail_on_stop, self.contact_sheet_on_stop);
+        (config.stream_enabled, config.srt) = (self.stream_enabled, self.srt.clone());
+        config.record_system_audio = self.record_system_audio;
+        (config.ducking_enabled, config.ducking) = (self.ducking_enabled, self.ducking);
+        (config.limiter_enabled, config.limiter_threshold_db, config.limiter_makeup_db) =
+            (self.limiter_enabled, self.limiter_threshold_db, self.limiter_makeup_db);
+        config.video_quality = self.video_quality;
+        config.audio_quality = self.audio_quality;
+        config.default_screen = self.selected_screen;
+        config.default_audio_device = self.selected_audio_device.clone();
+        config.default_webcam = self.selected_webcam.clone();
+        config
+    }
+
+    /// Starts the recording timer from zero at `now`.
+    pub fn start_timer(&mut self, now: Instant) {
+        self.is_paused = false;
+        self.recording_started = Some(now);
+        self.paused_since = None;
+        self.paused_total = Duration::ZERO;
+    }
+
+    /// Clears the recording timer.
+    pub fn stop_timer(&mut self) {
+        self.is_paused = false;
+        self.recording_started = None;
+        self.paused_since = None;
+        self.paused_total = Duration::ZERO;
+    }
+
+    /// Sets the pause state, freezing the timer while paused.
+    pub fn set_paused(&mut self, paused: bool, now: Instant) {
+        match (self.paused_since, paused) {
+            (None, true) => self.paused_since = Some(now),
+            (Some(since), false) => {
+                self.paused_total += now.saturating_duration_since(since);
+                self.paused_since = None;
+            }
+            _ => {}
+        }
+        self.is_paused = paused;
+    }
+
+    /// Recorded time at `now`, pauses excluded; `None` while idle.
+    pub fn recording_elapsed(&self, now: Instant) -> Option<Duration> {
+        let started = self.recording_started?;
+        let end = self.paused_since.unwrap_or(now);
+        Some(end.saturating_duration_since(started).saturating_sub(self.paused_total))
+    }
+}
+
+/// In/out points for trimming the last recording.
+struct TrimDialog {
+    source: PathBuf,
+    duration: Duration,
+    start_secs: f64,
+    end_secs: f64,
+    /// Filled in by a background ffprobe.
+    keyframes: Arc<Mutex<Keyframes>>,
+}
+
+#[derive(Clone)]
+struct MergeFallback {
+    video: PathBuf,
+    audio: PathBuf,
+    error: String,
+}
+
+#[derive(Clone, PartialEq)]
+enum Keyframes {
+    Probing,
+    /// Audio only: any packet can start a cut.
+    NoVideo,
+    /// Empty if ffprobe couldn't list them, which forces a re-encode.
+    At(Vec<Duration>),
+}
+
+impl Keyframes {
+    /// In the form `postprocess::trim` takes; `None` both while probing and
+    /// for audio, so check for `Probing` first.
+    fn as_slice(&self) -> Option<&[Duration]> {
+        match self {
+            Keyframes::At(list) => Some(list),
+            Keyframes::Probing | Keyframes::NoVideo => None,
+        }
+    }
 }
 
 #[derive(Default)]
@@ -112,6 +640,25 @@ struct HotkeyTriggers {
     toggle_record: bool,
     toggle_pause: bool,
     toggle_webcam: bool,
+    add_marker: bool,
+    webcam_snapshot: bool,
+    next_scene: bool,
+}
+
+fn layout_label(layout: OverlayLayout) -> &'static str {
+    match layout {
+        OverlayLayout::WebcamOver => "Webcam over screen",
+        OverlayLayout::ScreenOver => "Screen over webcam",
+        OverlayLayout::SideBySide => "Side by side",
+    }
+}
+
+fn upload_method_label(method: UploadMethod) -> &'static str {
+    match method {
+        UploadMethod::Off => "Don't upload",
+        UploadMethod::Command => "Run a command",
+        UploadMethod::HttpPut => "HTTP PUT",
+    }
 }
 
 fn format_shortcut(shortcut: &KeyboardShortcut) -> String {
@@ -124,6 +671,51 @@ fn format_shortcut(shortcut: &KeyboardShortcut) -> String {
     parts.join(" + ")
 }
 
+fn theme_label(theme: DiscordTheme) -> &'static str {
+    match theme {
+        DiscordTheme::Auto => tr("theme.auto"),
+        DiscordTheme::Dark => tr("theme.dark"),
+        DiscordTheme::Light => tr("theme.light"),
+        DiscordTheme::AMOLED => tr("theme.amoled"),
+    }
+}
+
+fn audio_format_label(format: AudioExportFormat) -> &'static str {
+    match format {
+        AudioExportFormat::Flac => "FLAC (lossless)",
+        AudioExportFormat::Mp3 => "MP3",
+    }
+}
+
+fn flac_depth_label(depth: Option<FlacBitDepth>, quality: AudioQuality) -> String {
+    match depth {
+        Some(FlacBitDepth::Bits16) => "16-bit".to_string(),
+        Some(FlacBitDepth::Bits24) => "24-bit".to_string(),
+        None => {
+            let auto = if quality == AudioQuality::Lossless { 24 } else { 16 };
+            format!("Auto ({}-bit)", auto)
+        }
+    }
+}
+
+fn video_quality_label(quality: VideoQuality) -> &'static str {
+    match quality {
+        VideoQuality::Low => "Low",
+        VideoQuality::Medium => "Medium",
+        VideoQuality::High => "High",
+        VideoQuality::Ultra => "Ultra",
+        VideoQuality::Lossless => "Lossless (editing)",
+    }
+}
+
+fn mic_gate_label(mode: MicGateMode) -> &'static str {
+    match mode {
+        MicGateMode::Off => "Always on",
+        MicGateMode::PushToTalk => "Push-to-talk",
+        MicGateMode::PushToMute => "Push-to-mute",
+    }
+}
+
 fn capture_shortcut(ctx: &egui::Context) -> Option<KeyboardShortcut> {
     let mut captured = None;
     ctx.input(|input| {
@@ -139,10 +731,83 @@ fn capture_shortcut(ctx: &egui::Context) -> Option<KeyboardShortcut> {
     captured
 }
 
+/// Draws the ring as a min/max envelope, one vertical line per pixel column.
+fn draw_waveform(ui: &mut egui::Ui, waveform: &SampleRing) {
+    let size = vec2(ui.available_width().max(100.0), 60.0);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 4.0, Color32::from_rgb(32, 34, 37));
+    painter.line_segment(
+        [rect.left_center(), rect.right_center()],
+        Stroke::new(1.0, Color32::from_rgb(64, 68, 75)),
+    );
+
+    if waveform.is_empty() {
+        return;
+    }
+    let half_height = rect.height() / 2.0;
+    let stroke = Stroke::new(1.0, Color32::from_rgb(88, 101, 242));
+    waveform.envelope(rect.width() as usize, |column, min, max| {
+        let x = rect.left() + column as f32 + 0.5;
+        let top = rect.center().y - max.clamp(-1.0, 1.0) * half_height;
+        let bottom = rect.center().y - min.clamp(-1.0, 1.0) * half_height;
+        painter.line_segment([egui::pos2(x, top), egui::pos2(x, bottom.max(top + 1.0))], stroke);
+    });
+}
+
+/// Draws `values` as a line scaled to their maximum, oldest on the left.
+fn draw_sparkline(ui: &mut egui::Ui, values: &[f32], color: Color32) {
+    let size = vec2(ui.available_width().max(100.0), 50.0);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 4.0, Color32::from_rgb(32, 34, 37));
+    if values.len() < 2 {
+        return;
+    }
+    let max = values.iter().copied().fold(f32::EPSILON, f32::max);
+    let step = rect.width() / (values.len() - 1) as f32;
+    let points: Vec<egui::Pos2> = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| egui::pos2(rect.left() + i as f32 * step, rect.bottom() - v / max * (rect.height() - 4.0)))
+        .collect();
+    painter.add(egui::Shape::line(points, Stroke::new(1.5, color)));
+}
+
+/// Draws the analyzer's log-frequency bars, low frequencies on the left.
+fn draw_spectrum(ui: &mut egui::Ui, analyzer: &SpectrumAnalyzer) {
+    let size = vec2(ui.available_width().max(100.0), 80.0);
+    let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 4.0, Color32::from_rgb(32, 34, 37));
+
+    let bar_width = rect.width() / spectrum::BAR_COUNT as f32;
+    for (i, level) in analyzer.bars().iter().enumerate() {
+        let height = level * rect.height();
+        let left = rect.left() + i as f32 * bar_width;
+        let bar = egui::Rect::from_min_max(
+            egui::pos2(left + 1.0, rect.bottom() - height),
+            egui::pos2(left + bar_width - 1.0, rect.bottom()),
+        );
+        painter.rect_filled(bar, 1.0, Color32::from_rgb(87, 242, 135));
+    }
+}
+
 pub struct DiscordRecorderApp {
     state: Arc<Mutex<AppState>>,
     audio_recorder: Option<AudioRecorder>,
+    audio_monitor: Option<AudioMonitor>,
+    /// Recent ffmpeg output for the log panel; kept across recordings.
+    ffmpeg_log: FfmpegLog,
+    /// Frame rate shared with the preview capture threads.
+    pacing: FramePacing,
+    /// Memory cap shared by the preview frame queues.
+    frame_budget: FrameBudget,
     video_encoder: Option<VideoEncoder>,
+    /// Filled when the worker of `AppState::segment_switch` is done.
+    segment_switch_result: SegmentSwitchResult,
+    /// Stop was asked for while a segment switch held the encoder.
+    stop_after_segment_switch: bool,
     screen_capture: Option<ScreenCapture>,
     webcam_capture: Option<WebcamCapture>,
     available_screens: Vec<String>,
@@ -150,14 +815,160 @@ pub struct DiscordRecorderApp {
     available_webcams: Vec<String>,
     screen_preview_texture: Option<TextureHandle>,
     webcam_preview_texture: Option<TextureHandle>,
+    /// Newest webcam preview frame, kept for snapshots.
+    last_webcam_frame: Option<DynamicImage>,
     audio_level: f32,
+    waveform: SampleRing,
+    spectrum: SpectrumAnalyzer,
+    /// Whether the push-to-talk key is currently held down.
+    talk_key_held: bool,
+    /// Set when a metered sample reaches full scale; cleared only by the user.
+    clip_latched: bool,
+    /// Whether this recording has already warned about dropped frames.
+    drop_warning_shown: bool,
+    /// Mouse clicks to highlight while recording, when enabled.
+    click_watcher: Option<ClickWatcher>,
+    /// Newest click, whose ring is animating.
+    last_click: Option<Click>,
+    /// Geometry of the display being recorded, while it is watched.
+    watched_display: Option<(i32, i32, u32, u32)>,
+    watched_screen: Option<usize>,
+    display_checked_at: Instant,
+    /// Recording times at which clipping started, for the sidecar log.
+    clip_events: Vec<Duration>,
     awaiting_hotkey: Option<HotkeyAction>,
     active_screen_index: Option<usize>,
     active_webcam_name: Option<String>,
-    dragging_overlay: bool,
-    active_resize: Option<ResizeHandle>,
+    overlay_drag: RectDrag,
+    region_drag: RectDrag,
+    /// Next drag on the screen preview draws a new capture region.
+    selecting_region: bool,
+    /// Image-space corner where the region drag started.
+    region_anchor: Option<(f32, f32)>,
+    /// Marks drawn on the screen preview, in screen pixels.
+    annotations: SharedAnnotations,
+    /// Drags on the screen preview draw annotations.
+    annotating: bool,
+    annotation_tool: AnnotationTool,
+    annotation_color: [u8; 3],
+    status_message: Option<(String, Instant)>,
+    /// The stop confirmation for a long recording is showing.
+    confirm_stop_open: bool,
+    /// Presets found in the presets folder, with their files.
+    presets: Vec<(PathBuf, Preset)>,
+    selected_preset: Option<usize>,
+    /// Name for exporting the current settings as a preset.
+    preset_name: String,
+    /// Note being typed for the marker just added.
+    marker_note: Option<MarkerNote>,
+    /// The auto-stop duration being typed in the top panel.
+    auto_stop_text: String,
+    show_shortcuts: bool,
+    /// Result of the last rebinding, shown under the hotkey settings.
+    hotkey_warning: Option<String>,
+    toasts: Toasts,
+    /// A quick-share or trim export is running.
+    exporting: Arc<AtomicBool>,
+    trim_dialog: Option<TrimDialog>,
+    trim_probe: TrimProbeResult,
+    /// Texture of `AppState::last_thumbnail`, keyed by the path it was loaded
+    /// from so a file that fails to load is tried only once.
+    thumbnail_texture: Option<(PathBuf, Option<TextureHandle>)>,
+    /// A merge whose stream copy failed, waiting for the user to allow a re-encode.
+    merge_fallback: Arc<Mutex<Option<MergeFallback>>>,
+    /// Arrival rate of new preview frames.
+    screen_fps: FpsCounter,
+    webcam_fps: FpsCounter,
+    /// `discord_theme` with `Auto` replaced by the OS preference.
+    resolved_theme: DiscordTheme,
+    was_focused: bool,
+    /// Watches `config.json` while `Config::watch_config_file` is on.
+    config_watcher: Option<ConfigWatcher>,
+    /// The file changed and is reloaded once nothing depends on the current settings.
+    config_reload_pending: bool,
+    /// Device check started from the audio settings; never runs while recording.
+    audio_test: Option<AudioTest>,
+    audio_test_requested: bool,
+    /// Whether a device test also plays a tone on the monitor output.
+    audio_test_tone: bool,
+}
+
+/// A segment change the encoder is making on a worker. Ending or starting
+/// ffmpeg can take seconds, so the encoder is handed over for it and comes
+/// back with the result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentSwitch {
+    Pausing,
+    Resuming,
+    /// Restarting capture at a changed display size.
+    Restarting,
+}
+
+impl SegmentSwitch {
+    fn status(self) -> (&'static str, &'static str) {
+        match self {
+            SegmentSwitch::Pausing => ("status.pausing", "a11y.status.pausing"),
+            SegmentSwitch::Resuming => ("status.resuming", "a11y.status.resuming"),
+            SegmentSwitch::Restarting => ("status.restarting", "a11y.status.restarting"),
+        }
+    }
 }
 
+/// The encoder and its result, left by the worker of a `SegmentSwitch`.
+type SegmentSwitchResult = Arc<Mutex<Option<(VideoEncoder, Result<bool>)>>>;
+
+/// The file to trim, the recorder's own length for it and what ffprobe
+/// found, left by the worker `open_trim` starts.
+type TrimProbeResult = Arc<Mutex<Option<(PathBuf, Option<Duration>, Result<Duration>)>>>;
+
+/// A short-lived open of the selected input: the meter runs off
+/// `audio_recorder` as during a recording until `ends_at`.
+struct AudioTest {
+    device: String,
+    ends_at: Instant,
+    _tone: Option<TestTone>,
+}
+
+/// Mono samples kept for the waveform view (about two seconds at 48 kHz).
+const WAVEFORM_SAMPLES: usize = 96_000;
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(3);
+/// Clipping within this long of the last logged event counts as the same event.
+const CLIP_EVENT_GAP: Duration = Duration::from_secs(1);
+/// How often the recorded display's geometry is checked while recording.
+const DISPLAY_POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// How long "Test" keeps the audio device open.
+/// How long the webcam overlay takes to fade in or out of the preview.
+const OVERLAY_FADE: Duration = Duration::from_millis(300);
+/// How long "Test" keeps the audio device open.
+const AUDIO_TEST_DURATION: Duration = Duration::from_secs(5);
+const TEST_TONE_DURATION: Duration = Duration::from_secs(1);
+const PAUSE_UNSUPPORTED: &str = "Pause not supported for current inputs (enable Seamless pause in settings)";
+
+/// Drag state of one editable rectangle on the screen preview.
+#[derive(Default)]
+struct RectDrag {
+    moving: bool,
+    resize: Option<ResizeHandle>,
+    /// Where a move has taken the rectangle before snapping, so it can be
+    /// dragged off a guide again.
+    unsnapped: Option<(f32, f32)>,
+}
+
+/// Distance in points within which a dragged overlay snaps to a third line.
+const THIRDS_SNAP_DISTANCE: f32 = 8.0;
+
+/// Quick 16:9 sizes for the webcam overlay.
+pub const OVERLAY_SIZE_PRESETS: [(&str, (u32, u32)); 3] =
+    [("Small", (240, 135)), ("Medium", (320, 180)), ("Large", (480, 270))];
+
+/// How often the statistics panel updates while recording.
+const STATS_REFRESH_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Share of the central panel's height the two previews may fill together.
+const PREVIEW_HEIGHT_SHARE: f32 = 0.9;
+/// Limits of the splitter, so neither preview can be dragged away entirely.
+pub const PREVIEW_SPLIT_RANGE: std::ops::RangeInclusive<f32> = 0.2..=0.85;
+
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 enum ResizeHandle {
     N,
@@ -172,10 +983,25 @@ enum ResizeHandle {
 
 impl DiscordRecorderApp {
     pub fn new(_cc: &eframe::CreationContext<'_>, state: Arc<Mutex<AppState>>) -> Self {
+        let (pacing, frame_budget, resolved_theme, auto_stop_text) = {
+            let state = state.lock().unwrap();
+            (
+                FramePacing::new(state.frame_rate, state.preview_fps_cap),
+                FrameBudget::new(state.frame_buffer_limit_mb),
+                state.discord_theme.resolve(),
+                auto_stop_label(state.config.auto_stop_after_secs),
+            )
+        };
         let mut app = Self {
             state,
             audio_recorder: None,
+            audio_monitor: None,
+            ffmpeg_log: FfmpegLog::default(),
+            pacing,
+            frame_budget,
             video_encoder: None,
+            segment_switch_result: Arc::new(Mutex::new(None)),
+            stop_after_segment_switch: false,
             screen_capture: None,
             webcam_capture: None,
             available_screens: Vec::new(),
@@ -183,20 +1009,69 @@ impl DiscordRecorderApp {
             available_webcams: Vec::new(),
             screen_preview_texture: None,
             webcam_preview_texture: None,
+            last_webcam_frame: None,
             audio_level: 0.0,
+            waveform: SampleRing::new(WAVEFORM_SAMPLES),
+            spectrum: SpectrumAnalyzer::new(),
+            talk_key_held: false,
+            clip_latched: false,
+            drop_warning_shown: false,
+            click_watcher: None,
+            last_click: None,
+            watched_display: None,
+            watched_screen: None,
+            display_checked_at: Instant::now(),
+            clip_events: Vec::new(),
             awaiting_hotkey: None,
             active_screen_index: None,
             active_webcam_name: None,
-            dragging_overlay: false,
-            active_resize: None,
+            overlay_drag: RectDrag::default(),
+            region_drag: RectDrag::default(),
+            selecting_region: false,
+            region_anchor: None,
+            annotations: Arc::new(Mutex::new(Vec::new())),
+            annotating: false,
+            annotation_tool: AnnotationTool::default(),
+            annotation_color: [240, 71, 71],
+            status_message: None,
+            confirm_stop_open: false,
+            presets: find_presets(None).0,
+            selected_preset: None,
+            preset_name: String::new(),
+            marker_note: None,
+            auto_stop_text,
+            show_shortcuts: false,
+            hotkey_warning: None,
+            toasts: Toasts::default(),
+            exporting: Arc::new(AtomicBool::new(false)),
+            trim_dialog: None,
+            trim_probe: Arc::new(Mutex::new(None)),
+            thumbnail_texture: None,
+            merge_fallback: Arc::new(Mutex::new(None)),
+            screen_fps: FpsCounter::default(),
+            webcam_fps: FpsCounter::default(),
+            resolved_theme,
+            was_focused: true,
+            config_watcher: None,
+            config_reload_pending: false,
+            audio_test: None,
+            audio_test_requested: false,
+            audio_test_tone: false,
         };
 
         // Initialize available devices
         if let Err(e) = app.refresh_devices() {
             error!("Failed to refresh devices: {}", e);
+            app.toasts.error(format!("Couldn't list devices: {}", e));
+        }
+        if let Some(warning) = app.state.lock().unwrap().config_warning.take() {
+            app.toasts.error(warning);
         }
 
         app.initialize_previews();
+        // The test encodes take a moment; the encoder menu reads the results
+        let ffmpeg_path = app.state.lock().unwrap().config.ffmpeg_path.clone();
+        runtime_handle().spawn_blocking(move || crate::video::probe_hw_accels(&ffmpeg_path));
 
         app
     }
@@ -223,9 +1098,10 @@ impl DiscordRecorderApp {
         };
 
         if record_video && self.screen_capture.is_none() {
-            if let Ok(mut capture) = ScreenCapture::new(screen_index) {
+            if let Ok(mut capture) = ScreenCapture::new(screen_index, self.pacing.clone(), self.frame_budget.clone()) {
                 if let Err(e) = capture.start() {
                     error!("Failed to start screen preview: {}", e);
+                    self.toasts.error(format!("Screen preview unavailable: {}", e));
                 } else {
                     self.screen_capture = Some(capture);
                     self.active_screen_index = Some(screen_index);
@@ -234,9 +1110,10 @@ impl DiscordRecorderApp {
         }
 
         if record_webcam && self.webcam_capture.is_none() {
-            if let Ok(mut capture) = WebcamCapture::new(&webcam_name) {
+            if let Ok(mut capture) = WebcamCapture::new(&webcam_name, self.pacing.clone(), self.frame_budget.clone()) {
                 if let Err(e) = capture.start() {
                     error!("Failed to start webcam preview: {}", e);
+                    self.toasts.error(format!("Webcam preview unavailable: {}", e));
                 } else {
                     self.webcam_capture = Some(capture);
                     self.active_webcam_name = Some(webcam_name);
@@ -251,7 +1128,8 @@ impl DiscordRecorderApp {
             (
                 state.record_video,
                 state.selected_screen.unwrap_or(0),
-                state.record_webcam,
+                // ffmpeg keeps the v4l2 device open until it has finished writing
+                state.record_webcam && !state.is_finalizing,
                 state
                     .selected_webcam
                     .clone()
@@ -265,16 +1143,20 @@ impl DiscordRecorderApp {
                     let _ = capture.stop();
                 }
                 self.screen_capture = None;
-                match ScreenCapture::new(screen_index) {
+                match ScreenCapture::new(screen_index, self.pacing.clone(), self.frame_budget.clone()) {
                     Ok(mut capture) => {
                         if let Err(e) = capture.start() {
                             error!("Failed to start screen capture: {}", e);
+                            self.toasts.error(format!("Screen capture failed: {}", e));
                         } else {
                             self.screen_capture = Some(capture);
                             self.active_screen_index = Some(screen_index);
                         }
                     }
-                    Err(e) => error!("Failed to create screen capture: {}", e),
+                    Err(e) => {
+                        error!("Failed to create screen capture: {}", e);
+                        self.toasts.error(format!("Screen capture failed: {}", e));
+                    }
                 }
             }
         } else if self.screen_capture.is_some() {
@@ -291,16 +1173,20 @@ impl DiscordRecorderApp {
                     let _ = capture.stop();
                 }
                 self.webcam_capture = None;
-                match WebcamCapture::new(&webcam_name) {
+                match WebcamCapture::new(&webcam_name, self.pacing.clone(), self.frame_budget.clone()) {
                     Ok(mut capture) => {
                         if let Err(e) = capture.start() {
                             error!("Failed to start webcam capture: {}", e);
+                            self.toasts.error(format!("Webcam unavailable (device busy?): {}", e));
                         } else {
                             self.webcam_capture = Some(capture);
                             self.active_webcam_name = Some(webcam_name);
                         }
                     }
-                    Err(e) => error!("Failed to create webcam capture: {}", e),
+                    Err(e) => {
+                        error!("Failed to create webcam capture: {}", e);
+                        self.toasts.error(format!("Webcam unavailable: {}", e));
+                    }
                 }
             }
         } else if self.webcam_capture.is_some() {
@@ -312,12 +1198,44 @@ impl DiscordRecorderApp {
         }
     }
 
+    /// Rebinds `action`, refusing a shortcut another action already uses. OS
+    /// reserved combos are accepted with a warning, since some desktops let
+    /// them through.
     fn set_hotkey(&mut self, action: HotkeyAction, shortcut: KeyboardShortcut) {
         let mut state = self.state.lock().unwrap();
-        match action {
-            HotkeyAction::StartStop => state.hotkeys.start_stop = shortcut,
-            HotkeyAction::PauseResume => state.hotkeys.pause_resume = shortcut,
-            HotkeyAction::ToggleWebcam => state.hotkeys.toggle_webcam = shortcut,
+        if let Some(other) = state.hotkeys.conflict(action, &shortcut) {
+            self.hotkey_warning = Some(format!(
+                "{} is already bound to {}; {} was not changed",
+                format_shortcut(&shortcut),
+                other.label(),
+                action.label()
+            ));
+            return;
+        }
+        self.hotkey_warning = reserved_shortcut(&shortcut)
+            .map(|reason| format!("{} {} and may not reach the app", format_shortcut(&shortcut), reason));
+        state.hotkeys.hotkey_mut(action).shortcut = shortcut;
+    }
+
+    /// Tracks the push-to-talk key by its held state (not presses) and forwards
+    /// the resulting gate state to the encoder. A disabled key counts as never
+    /// held.
+    fn update_mic_gate(&mut self, ctx: &egui::Context) {
+        let (mode, shortcut) = {
+            let state = self.state.lock().unwrap();
+            (state.mic_gate, state.hotkeys.active(HotkeyAction::PushToTalk).copied())
+        };
+
+        self.talk_key_held = mode != MicGateMode::Off
+            && self.awaiting_hotkey.is_none()
+            && shortcut.is_some_and(|shortcut| {
+                ctx.input(|i| i.key_down(shortcut.logical_key) && i.modifiers.matches_logically(shortcut.modifiers))
+            });
+
+        if let Some(encoder) = self.video_encoder.as_mut() {
+            if let Err(e) = encoder.set_mic_open(mode.mic_open(self.talk_key_held)) {
+                error!("Failed to update push-to-talk state: {}", e);
+            }
         }
     }
 
@@ -338,100 +1256,1396 @@ impl DiscordRecorderApp {
             self.state.lock().unwrap().hotkeys.clone()
         };
  
-        if ctx.input_mut(|i| i.consume_shortcut(&hotkeys.start_stop)) {
-            triggers.toggle_record = true;
-        }
-        if ctx.input_mut(|i| i.consume_shortcut(&hotkeys.pause_resume)) {
-            triggers.toggle_pause = true;
-        }
-        if ctx.input_mut(|i| i.consume_shortcut(&hotkeys.toggle_webcam)) {
-            triggers.toggle_webcam = true;
+        // Disabled hotkeys aren't consumed, so the key press stays available to egui
+        let pressed = |action: HotkeyAction| {
+            hotkeys
+                .active(action)
+                .is_some_and(|shortcut| ctx.input_mut(|i| i.consume_shortcut(shortcut)))
+        };
+        triggers.toggle_record = pressed(HotkeyAction::StartStop);
+        triggers.toggle_pause = pressed(HotkeyAction::PauseResume);
+        triggers.toggle_webcam = pressed(HotkeyAction::ToggleWebcam);
+        triggers.add_marker = pressed(HotkeyAction::AddMarker);
+        triggers.webcam_snapshot = pressed(HotkeyAction::WebcamSnapshot);
+        triggers.next_scene = pressed(HotkeyAction::NextScene);
+        if ctx.input_mut(|i| i.consume_key(Modifiers::NONE, SHORTCUT_HELP_KEY)) {
+            self.show_shortcuts = !self.show_shortcuts;
         }
 
         triggers
     }
 
+    /// Stops the recording once it has run for the auto-stop duration, the
+    /// same way the stop button does but without asking first.
+    fn check_auto_stop(&mut self, ctx: &egui::Context) {
+        let after = {
+            let state = self.state.lock().unwrap();
+            (state.is_recording && state.config.auto_stop)
+                .then(|| Duration::from_secs(u64::from(state.config.auto_stop_after_secs)))
+        };
+        let (Some(after), Some(encoder)) = (after, self.video_encoder.as_ref()) else {
+            return;
+        };
+        let elapsed = encoder.elapsed();
+        if let Some(remaining) = after.checked_sub(elapsed).filter(|r| !r.is_zero()) {
+            ctx.request_repaint_after(remaining.min(Duration::from_secs(1)));
+            return;
+        }
+
+        info!("Auto-stop after {}", postprocess::format_timestamp(after));
+        self.confirm_stop_open = false;
+        match self.stop_recording() {
+            Ok(()) => self.toasts.info(format!("Stopped after {} as set", postprocess::format_timestamp(after))),
+            Err(e) => {
+                error!("Failed to auto-stop recording: {}", e);
+                self.toasts.error(format!("Couldn't stop recording: {}", e));
+            }
+        }
+    }
+
     fn toggle_recording(&mut self) {
-        let is_recording = { self.state.lock().unwrap().is_recording };
+        let (is_recording, confirm_after) = {
+            let state = self.state.lock().unwrap();
+            let minutes = Duration::from_secs(u64::from(state.confirm_stop_after_minutes) * 60);
+            (state.is_recording, state.confirm_long_stop.then_some(minutes))
+        };
         if is_recording {
+            let elapsed = self.video_encoder.as_ref().map_or(Duration::ZERO, |e| e.elapsed());
+            if confirm_after.is_some_and(|after| elapsed >= after) {
+                // A second press while the dialog is up must not stop either
+                self.confirm_stop_open = true;
+                return;
+            }
+            self.confirm_stop_open = false;
             if let Err(e) = self.stop_recording() {
                 error!("Failed to stop recording: {}", e);
+                self.toasts.error(format!("Couldn't stop recording: {}", e));
             }
         } else if let Err(e) = self.start_recording() {
             error!("Failed to start recording: {}", e);
+            self.toasts.error(format!("Couldn't start recording: {}", e));
         }
     }
 
-    fn toggle_pause(&mut self) {
-        let should_toggle = {
-            let state = self.state.lock().unwrap();
-            state.is_recording
-        };
+    /// Converts the audio recording `source` to MP3 in the background, with
+    /// progress in a toast.
+    fn start_mp3_conversion(&self, source: PathBuf, bitrate_kbps: u32) {
+        self.exporting.store(true, Ordering::Relaxed);
+        let exporting = Arc::clone(&self.exporting);
+        let toasts = self.toasts.clone();
+        let log = self.ffmpeg_log.clone();
+        let ffmpeg_path = self.state.lock().unwrap().config.ffmpeg_path.clone();
+        runtime_handle().spawn_blocking(move || {
+            let toast = toasts.progress("Converting to MP3…");
+            let mut last_percent = None;
+            let result = postprocess::convert_to_mp3(&ffmpeg_path, &source, bitrate_kbps, Some(&log), |fraction| {
+                let percent = (fraction * 100.0) as u32;
+                if last_percent != Some(percent) {
+                    last_percent = Some(percent);
+                    toasts.update(toast, ToastKind::Info, format!("Converting to MP3: {}%", percent));
+                }
+            });
+            let (kind, text) = match result {
+                Ok(path) => (ToastKind::Info, format!("MP3 saved: {}", path.display())),
+                Err(e) => {
+                    error!("MP3 conversion failed: {}", e);
+                    (ToastKind::Error, format!("MP3 conversion failed: {}", e))
+                }
+            };
+            toasts.finish(toast, kind, text);
+            exporting.store(false, Ordering::Relaxed);
+        });
+    }
 
-        if !should_toggle {
+    /// Re-encodes `source` for sharing in the background, with progress in a toast.
+    fn start_quick_share(&self, source: PathBuf, duration: Duration) {
+        self.exporting.store(true, Ordering::Relaxed);
+        let exporting = Arc::clone(&self.exporting);
+        let toasts = self.toasts.clone();
+        let log = self.ffmpeg_log.clone();
+        let ffmpeg_path = self.state.lock().unwrap().config.ffmpeg_path.clone();
+        runtime_handle().spawn_blocking(move || {
+            let toast = toasts.progress("Making share copy…");
+            let mut last_percent = None;
+            let result = postprocess::quick_share(&ffmpeg_path, &source, duration, &log, |fraction| {
+                let percent = (fraction * 100.0) as u32;
+                if last_percent != Some(percent) {
+                    last_percent = Some(percent);
+                    toasts.update(toast, ToastKind::Info, format!("Making share copy: {}%", percent));
+                }
+            });
+            let (kind, text) = match result {
+                Ok(path) => (ToastKind::Info, format!("Share copy saved: {}", path.display())),
+                Err(e) => {
+                    error!("Quick share failed: {}", e);
+                    (ToastKind::Error, format!("Quick share failed: {}", e))
+                }
+            };
+            toasts.finish(toast, kind, text);
+            exporting.store(false, Ordering::Relaxed);
+        });
+    }
+
+    /// Opens the trim window for `source` once ffprobe has read its length
+    /// on a worker; `poll_trim_probe` picks the result up.
+    fn open_trim(&self, source: PathBuf, recorded: Option<Duration>) {
+        let done = Arc::clone(&self.trim_probe);
+        runtime_handle().spawn_blocking(move || {
+            let duration = postprocess::probe_duration(&source);
+            *done.lock().unwrap() = Some((source, recorded, duration));
+        });
+    }
+
+    /// Shows the trim window for a probed file. The length comes from
+    /// ffprobe, or from the recorder's clock if ffprobe isn't available.
+    fn poll_trim_probe(&mut self) {
+        let Some((source, recorded, probed)) = self.trim_probe.lock().unwrap().take() else {
             return;
-        }
+        };
+        let duration = match probed {
+            Ok(duration) => duration,
+            Err(e) => match recorded {
+                Some(duration) => {
+                    warn!("Using the recorded length for trimming: {}", e);
+                    duration
+                }
+                None => {
+                    self.toasts.error(format!("Can't trim: {}", e));
+                    return;
+                }
+            },
+        };
 
-        if let Some(encoder) = self.video_encoder.as_mut() {
-            if let Err(e) = encoder.toggle_pause() {
-                error!("Failed to toggle pause: {}", e);
-                return;
-            }
-        }
+        // Listing keyframes reads the whole file, so it happens in the background
+        let keyframes = Arc::new(Mutex::new(Keyframes::Probing));
+        let probe_source = source.clone();
+        let probed = Arc::clone(&keyframes);
+        runtime_handle().spawn_blocking(move || {
+            let result = match postprocess::probe_keyframes(&probe_source) {
+                Ok(list) if list.is_empty() => Keyframes::NoVideo,
+                Ok(list) => Keyframes::At(list),
+                Err(e) => {
+                    warn!("Couldn't list keyframes, trims will be re-encoded: {}", e);
+                    Keyframes::At(Vec::new())
+                }
+            };
+            *probed.lock().unwrap() = result;
+        });
 
-        let mut state = self.state.lock().unwrap();
-        state.is_paused = !state.is_paused;
+        self.trim_dialog = Some(TrimDialog {
+            source,
+            duration,
+            start_secs: 0.0,
+            end_secs: duration.as_secs_f64(),
+            keyframes,
+        });
     }
 
-    fn toggle_webcam_capture(&mut self) {
-        let mut state = self.state.lock().unwrap();
-        state.record_webcam = !state.record_webcam;
-        let desired = state.record_webcam;
-        let webcam_name = state
-            .selected_webcam
-            .clone()
-            .unwrap_or_else(|| "Default Webcam".to_string());
-        drop(state);
+    fn draw_trim(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = self.trim_dialog.as_mut() else {
+            return;
+        };
+        let total = dialog.duration.as_secs_f64();
+        let keyframes = dialog.keyframes.lock().unwrap().clone();
+        let mut open = true;
+        let mut save = false;
+        egui::Window::new("Trim recording")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .show(ctx, |ui| {
+                let name = dialog.source.file_name().unwrap_or_default().to_string_lossy();
+                ui.label(format!("{} ({})", name, postprocess::format_timestamp(dialog.duration)));
+                egui::Grid::new("trim_points").num_columns(2).show(ui, |ui| {
+                    ui.label("In");
+                    ui.add(egui::Slider::new(&mut dialog.start_secs, 0.0..=total).suffix(" s").max_decimals(2));
+                    ui.end_row();
+                    ui.label("Out");
+                    ui.add(egui::Slider::new(&mut dialog.end_secs, 0.0..=total).suffix(" s").max_decimals(2));
+                    ui.end_row();
+                });
+                dialog.end_secs = dialog.end_secs.max(dialog.start_secs);
 
-        if desired {
-            if let Some(capture) = self.webcam_capture.as_mut() {
-                let _ = capture.stop();
-            }
-            self.webcam_capture = None;
-            match WebcamCapture::new(&webcam_name) {
-                Ok(mut capture) => {
-                    if let Err(e) = capture.start() {
-                        error!("Failed to start webcam capture: {}", e);
-                    } else {
-                        self.webcam_capture = Some(capture);
-                        self.active_webcam_name = Some(webcam_name);
+                if let Keyframes::At(list) = &keyframes {
+                    let start = Duration::from_secs_f64(dialog.start_secs);
+                    let previous = list.iter().rfind(|k| **k <= start);
+                    if let Some(k) = previous.filter(|_| ui.small_button("Snap in point to previous keyframe").clicked()) {
+                        dialog.start_secs = k.as_secs_f64();
                     }
                 }
-                Err(e) => error!("Failed to create webcam capture: {}", e),
-            }
-        } else {
-            if let Some(capture) = self.webcam_capture.as_mut() {
-                let _ = capture.stop();
-            }
-            self.webcam_capture = None;
-            self.active_webcam_name = None;
+                let start = Duration::from_secs_f64(dialog.start_secs);
+                ui.label(match keyframes.as_slice() {
+                    None if keyframes == Keyframes::Probing => "Checking keyframes…",
+                    list if postprocess::trim_can_copy(start, list) => "Fast copy, no quality loss",
+                    _ => "The cut starts between keyframes: the copy is re-encoded",
+                });
+                ui.label(RichText::new(format!("Saves {}", postprocess::trimmed_path(&dialog.source).display())).size(11.0));
+                let ready = keyframes != Keyframes::Probing && dialog.end_secs > dialog.start_secs;
+                save = ui.add_enabled(ready, egui::Button::new("Save trimmed copy")).clicked();
+            });
+
+        if save {
+            let dialog = self.trim_dialog.take().expect("trim dialog is open");
+            self.start_trim(dialog, keyframes);
+        } else if !open {
+            self.trim_dialog = None;
         }
     }
 
-    fn draw_settings_contents(&mut self, ui: &mut egui::Ui) -> bool {
-        let mut state = self.state.lock().unwrap();
-        let mut refresh_requested = false;
+    fn start_trim(&self, dialog: TrimDialog, keyframes: Keyframes) {
+        self.exporting.store(true, Ordering::Relaxed);
+        let exporting = Arc::clone(&self.exporting);
+        let toasts = self.toasts.clone();
+        let config = self.state.lock().unwrap().config_snapshot();
+        runtime_handle().spawn_blocking(move || {
+            let toast = toasts.progress("Trimming…");
+            let start = Duration::from_secs_f64(dialog.start_secs);
+            let end = Duration::from_secs_f64(dialog.end_secs);
+            match postprocess::trim(&config.ffmpeg_path, &dialog.source, start, end, keyframes.as_slice(), &config) {
+                Ok((path, copied)) => {
+                    let how = if copied { "copied" } else { "re-encoded" };
+                    toasts.finish(toast, ToastKind::Info, format!("Trimmed copy saved ({}): {}", how, path.display()));
+                }
+                Err(e) => {
+                    error!("Trim failed: {}", e);
+                    toasts.finish(toast, ToastKind::Error, format!("Trim failed: {}", e));
+                }
+            }
+            exporting.store(false, Ordering::Relaxed);
+        });
+    }
 
-        ui.heading("Capture Options");
-        ui.checkbox(&mut state.record_audio, "Record system audio");
-        ui.checkbox(&mut state.record_video, "Record screen");
-        ui.checkbox(&mut state.record_webcam, "Enable webcam overlay");
-        ui.checkbox(&mut state.separate_outputs, "Save audio and video separately");
+    /// Muxes a separate-outputs recording in the background. A failed stream
+    /// copy asks whether to retry with the audio re-encoded.
+    fn start_merge(&self, video: PathBuf, audio: PathBuf, reencode_audio: bool) {
+        self.exporting.store(true, Ordering::Relaxed);
+        let exporting = Arc::clone(&self.exporting);
+        let fallback = Arc::clone(&self.merge_fallback);
+        let toasts = self.toasts.clone();
+        let ffmpeg_path = self.state.lock().unwrap().config.ffmpeg_path.clone();
+        runtime_handle().spawn_blocking(move || {
+            let toast = toasts.progress("Merging video and audio…");
+            match postprocess::merge(&ffmpeg_path, &video, &audio, reencode_audio) {
+                Ok(path) => toasts.finish(toast, ToastKind::Info, format!("Merged file saved: {}", path.display())),
+                Err(e) if !reencode_audio => {
+                    warn!("Stream-copy merge failed: {}", e);
+                    toasts.finish(toast, ToastKind::Error, "The streams can't be merged as they are");
+                    *fallback.lock().unwrap() = Some(MergeFallback { video, audio, error: e.to_string() });
+                }
+                Err(e) => {
+                    error!("Merge failed: {}", e);
+                    toasts.finish(toast, ToastKind::Error, format!("Merge failed: {}", e));
+                }
+            }
+            exporting.store(false, Ordering::Relaxed);
+        });
+    }
 
-        ui.separator();
-        ui.heading("Audio");
-        ui.label("Input Gain (dB)");
-        ui.add(Slider::new(&mut state.audio_gain_db, -30.0..=12.0).suffix(" dB"));
+    fn draw_merge_fallback(&mut self, ctx: &egui::Context) {
+        let Some(pending) = self.merge_fallback.lock().unwrap().clone() else {
+            return;
+        };
+        let mut reencode = false;
+        let mut cancel = false;
+        let modal = egui::Modal::new(egui::Id::new("merge_fallback")).show(ctx, |ui| {
+            ui.heading("Merge failed");
+            ui.label("ffmpeg couldn't copy the streams into one file:");
+            ui.label(RichText::new(&pending.error).monospace().size(11.0));
+            ui.label("Re-encoding the audio to AAC (Opus for WebM) usually fixes this. The video is still copied as is.");
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                reencode = ui.button("Re-encode audio").clicked();
+                cancel = ui.button("Cancel").clicked();
+            });
+        });
+        if reencode || cancel || modal.should_close() {
+            *self.merge_fallback.lock().unwrap() = None;
+        }
+        if reencode {
+            self.start_merge(pending.video, pending.audio, true);
+        }
+    }
+
+    fn toggle_pause(&mut self) {
+        let should_toggle = {
+            let state = self.state.lock().unwrap();
+            state.is_recording && state.segment_switch.is_none()
+        };
+
+        if !should_toggle {
+            return;
+        }
+
+        let Some(encoder) = self.video_encoder.as_ref() else {
+            return;
+        };
+
+        if !encoder.can_pause() {
+            warn!("Pause not supported for current inputs; enable seamless pause to pause recordings");
+            self.status_message = Some((PAUSE_UNSUPPORTED.to_string(), Instant::now()));
+            return;
+        }
+
+        if encoder.is_paused() {
+            self.switch_segment(SegmentSwitch::Resuming);
+        } else {
+            // The timer stops when asked to, not once ffmpeg has exited
+            self.state.lock().unwrap().set_paused(true, Instant::now());
+            self.switch_segment(SegmentSwitch::Pausing);
+        }
+    }
+
+    /// Hands the encoder to a worker for `switch`; `poll_segment_switch`
+    /// takes it back.
+    fn switch_segment(&mut self, switch: SegmentSwitch) {
+        let Some(mut encoder) = self.video_encoder.take() else {
+            return;
+        };
+        self.state.lock().unwrap().segment_switch = Some(switch);
+        let done = Arc::clone(&self.segment_switch_result);
+        runtime_handle().spawn_blocking(move || {
+            let result = match switch {
+                SegmentSwitch::Pausing | SegmentSwitch::Resuming => encoder.toggle_pause(),
+                SegmentSwitch::Restarting => encoder.display_changed(),
+            };
+            *done.lock().unwrap() = Some((encoder, result));
+        });
+    }
+
+    /// Takes the encoder back from a finished segment switch and reports how
+    /// it went, then carries out a stop asked for in the meantime.
+    fn poll_segment_switch(&mut self) {
+        let Some((encoder, result)) = self.segment_switch_result.lock().unwrap().take() else {
+            return;
+        };
+        let is_paused = encoder.is_paused();
+        self.video_encoder = Some(encoder);
+        let switch = {
+            let mut state = self.state.lock().unwrap();
+            // Mirror what the encoder actually did rather than flipping blindly
+            state.set_paused(is_paused, Instant::now());
+            state.segment_switch.take()
+        };
+
+        match (switch, result) {
+            (Some(SegmentSwitch::Restarting), Ok(true)) => {
+                self.toasts.info("Display changed — continuing in a new segment at the new size")
+            }
+            (Some(SegmentSwitch::Restarting), Ok(false)) => self.toasts.error(
+                "Display changed — recording may be affected. Enable seamless pause to let capture restart at the new size",
+            ),
+            (Some(SegmentSwitch::Restarting), Err(e)) => {
+                error!("Failed to restart capture after a display change: {:#}", e);
+                self.toasts.error(format!("Display changed and capture couldn't restart; recording stopped: {:#}", e));
+                self.stop_after_segment_switch = true;
+            }
+            (_, Err(e)) => {
+                error!("Failed to toggle pause: {}", e);
+                self.toasts.error(format!("Pause failed: {}", e));
+            }
+            (_, Ok(_)) => {}
+        }
+
+        if std::mem::take(&mut self.stop_after_segment_switch) {
+            if let Err(e) = self.stop_recording() {
+                error!("Failed to stop recording: {}", e);
+            }
+        }
+    }
+
+    fn draw_stats_panel(&mut self, ui: &mut egui::Ui) {
+        let history = self.ffmpeg_log.stats_history();
+        let summary = summarize_stats(&history);
+        let elapsed = self.video_encoder.as_ref().map(|e| e.elapsed()).or(summary.time);
+        let show = |value: Option<String>| value.unwrap_or_else(|| "—".to_string());
+        let fps = |fps: Option<f32>| show(fps.map(|f| format!("{:.1}", f)));
+
+        let open = egui::CollapsingHeader::new(tr("heading.statistics"))
+            .id_salt("stats_panel")
+            .default_open(true)
+            .show(ui, |ui| {
+                egui::Grid::new("stats_grid").num_columns(4).spacing([24.0, 4.0]).striped(true).show(ui, |ui| {
+                    ui.label("Elapsed");
+                    ui.monospace(show(elapsed.map(postprocess::format_timestamp)));
+                    ui.label("File size");
+                    ui.monospace(show(summary.size_bytes.map(|b| format!("{:.1} MB", b as f64 / 1_000_000.0))));
+                    ui.end_row();
+
+                    ui.label("FPS (current)");
+                    ui.monospace(fps(summary.current_fps));
+                    ui.label("FPS (average)");
+                    ui.monospace(fps(summary.average_fps));
+                    ui.end_row();
+
+                    ui.label("Bitrate");
+                    ui.monospace(show(summary.bitrate_kbps.map(|k| format!("{:.1} Mb/s", k / 1000.0))));
+                    ui.label("Speed");
+                    ui.monospace(show(summary.speed.map(|s| format!("{:.2}x", s))));
+                    ui.end_row();
+
+                    let dropping = is_dropping_frames(&history);
+                    ui.label("Dropped frames");
+                    let dropped = RichText::new(show(summary.dropped_frames.map(|d| d.to_string()))).monospace();
+                    if dropping {
+                        ui.label(dropped.color(Color32::from_rgb(255, 180, 0)))
+                            .on_hover_text("ffmpeg is dropping frames; try a lower frame rate or preview cap");
+                    } else {
+                        ui.label(dropped);
+                    }
+                    ui.label("Duplicated frames");
+                    ui.monospace(show(summary.duplicated_frames.map(|d| d.to_string())));
+                    ui.end_row();
+                });
+            })
+            .fully_open();
+        if !open {
+            return;
+        }
+
+        let bitrate: Vec<f32> = history.iter().map(|s| s.bitrate_kbps.unwrap_or(0.0)).collect();
+        let size: Vec<f32> = history.iter().map(|s| s.size_bytes.unwrap_or(0) as f32).collect();
+        ui.columns(2, |columns| {
+            columns[0].label(RichText::new("Bitrate").size(11.0));
+            draw_sparkline(&mut columns[0], &bitrate, Color32::from_rgb(88, 101, 242));
+            columns[1].label(RichText::new("File size").size(11.0));
+            draw_sparkline(&mut columns[1], &size, Color32::from_rgb(87, 242, 135));
+        });
+        if self.video_encoder.is_some() {
+            ui.ctx().request_repaint_after(STATS_REFRESH_INTERVAL);
+        }
+    }
+
+    /// Moves the click ring along: the newest click's ring grows for
+    /// `CLICK_ANIMATION`, then is hidden again.
+    fn update_click_highlight(&mut self, ctx: &egui::Context) {
+        let Some(watcher) = self.click_watcher.as_ref() else {
+            return;
+        };
+        if let Some(click) = watcher.poll().pop() {
+            self.last_click = Some(click);
+        }
+        let size = self.state.lock().unwrap().config.click_highlight.size;
+        let ring = self
+            .last_click
+            .and_then(|click| click_ring_size(click.at.elapsed(), size).map(|side| (click.x, click.y, side)));
+        if ring.is_none() {
+            self.last_click = None;
+        }
+        if let Some(encoder) = self.video_encoder.as_mut() {
+            if let Err(e) = encoder.set_click_ring(ring) {
+                warn!("Failed to update click highlight: {}", e);
+            }
+        }
+        // Clicks arrive from another thread, so keep checking for them
+        ctx.request_repaint_after(Duration::from_millis(if ring.is_some() { 16 } else { 50 }));
+    }
+
+    /// Suggests easing the load once per recording when ffmpeg starts dropping frames.
+    fn check_dropped_frames(&mut self) {
+        if self.drop_warning_shown || self.video_encoder.is_none() {
+            return;
+        }
+        if is_dropping_frames(&self.ffmpeg_log.stats_history()) {
+            self.drop_warning_shown = true;
+            warn!("ffmpeg is dropping frames");
+            self.toasts.info(
+                "Frames are being dropped. A lower frame rate or preview FPS cap may help.",
+            );
+        }
+    }
+
+    /// Polls the recorded display and reacts when its size changes or it goes
+    /// away: a new segment at the new size where the encoder can do that, a
+    /// warning where it can't, and a clean stop if the new segment fails.
+    fn check_display(&mut self) {
+        // While a segment switch holds the encoder, look again once it's back
+        let Some(watched) = self.watched_display.filter(|_| self.video_encoder.is_some()) else {
+            return;
+        };
+        if self.display_checked_at.elapsed() < DISPLAY_POLL_INTERVAL {
+            return;
+        }
+        self.display_checked_at = Instant::now();
+        let current = crate::video::display_geometry(self.watched_screen);
+        if current == Some(watched) {
+            return;
+        }
+        warn!("Display changed from {:?} to {:?} while recording", watched, current);
+        self.watched_display = current;
+        // Restarting ffmpeg takes a while, so it runs on a worker like a pause
+        self.switch_segment(SegmentSwitch::Restarting);
+    }
+
+    /// Opens the selected audio device for a few seconds so its level shows
+    /// on the meter, optionally with a tone on the monitor output.
+    fn start_audio_test(&mut self) {
+        let (device, volume) = {
+            let state = self.state.lock().unwrap();
+            if state.is_recording || state.is_finalizing {
+                return;
+            }
+            let device = state.selected_audio_device.clone().filter(|name| !name.is_empty());
+            (device.unwrap_or_else(|| "default".to_string()), state.monitor_volume)
+        };
+        self.stop_audio_test();
+
+        let opened = AudioRecorder::new(&device).and_then(|mut recorder| recorder.start().map(|()| recorder));
+        let recorder = match opened {
+            Ok(recorder) => recorder,
+            Err(e) => {
+                error!("Audio test of {} failed: {:#}", device, e);
+                self.toasts.error(format!("Couldn't open audio device \"{}\": {:#}", device, e));
+                return;
+            }
+        };
+        self.audio_recorder = Some(recorder);
+        self.audio_level = 0.0;
+
+        let tone = self
+            .audio_test_tone
+            .then(|| TestTone::play(TEST_TONE_DURATION, volume))
+            .and_then(|tone| {
+                tone.map_err(|e| {
+                    warn!("Test tone unavailable: {:#}", e);
+                    self.toasts.error(format!("Couldn't play the test tone: {:#}", e));
+                })
+                .ok()
+            });
+        info!("Testing audio device {}", device);
+        self.audio_test = Some(AudioTest { device, ends_at: Instant::now() + AUDIO_TEST_DURATION, _tone: tone });
+    }
+
+    /// Ends a device test and closes the device; a no-op without one.
+    fn stop_audio_test(&mut self) {
+        let Some(test) = self.audio_test.take() else {
+            return;
+        };
+        // The monitor reads from the recorder's tap, so it goes first
+        self.audio_monitor = None;
+        if let Some(recorder) = &mut self.audio_recorder {
+            let _ = recorder.stop();
+        }
+        self.audio_recorder = None;
+        info!("Audio test of {} finished", test.device);
+    }
+
+    fn update_audio_test(&mut self) {
+        if std::mem::take(&mut self.audio_test_requested) {
+            self.start_audio_test();
+        }
+        if self.audio_test.as_ref().is_some_and(|test| Instant::now() >= test.ends_at) {
+            self.stop_audio_test();
+        }
+    }
+
+    /// Copies a dropped preset file into the presets folder and selects it.
+    fn import_preset(&mut self, path: &Path) {
+        let imported = presets::presets_dir().and_then(|dir| Ok((presets::import_preset(path, &dir)?, dir)));
+        match imported {
+            Ok((preset, dir)) => {
+                (self.presets, self.selected_preset) = find_presets(Some(&dir.join(presets::preset_file_name(&preset.name))));
+                self.toasts.info(format!("Imported preset \"{}\"; apply it under Settings → Presets", preset.name));
+            }
+            Err(e) => {
+                warn!("Failed to import preset {}: {:#}", path.display(), e);
+                self.toasts.error(format!("Couldn't import {}: {:#}", path.display(), e));
+            }
+        }
+    }
+
+    /// Makes a folder dropped onto the window, or the folder of a dropped
+    /// file, the output directory. A recording in progress keeps its own.
+    fn handle_dropped_files(&mut self, ctx: &egui::Context) {
+        let dropped = ctx.input(|i| i.raw.dropped_files.iter().find_map(|file| file.path.clone()));
+        let Some(dropped) = dropped else {
+            return;
+        };
+        if dropped.extension().is_some_and(|ext| ext == "json") {
+            self.import_preset(&dropped);
+            return;
+        }
+        let Some(directory) = dropped_output_directory(&dropped) else {
+            self.toasts.error(format!("Can't tell which folder {} is in", dropped.display()));
+            return;
+        };
+        if let Err(e) = check_writable_directory(&directory) {
+            warn!("Dropped output directory rejected: {:#}", e);
+            self.toasts.error(format!("Can't save recordings there: {:#}", e));
+            return;
+        }
+
+        let path = directory.display().to_string();
+        let mut state = self.state.lock().unwrap();
+        state.output_path = path.clone();
+        state.config.set_output_directory(path.clone());
+        if let Err(e) = state.save_config() {
+            error!("Failed to save output directory: {}", e);
+        }
+        info!("Output directory set to {}", path);
+        let note = if state.is_recording { " from the next recording" } else { "" };
+        self.toasts.info(format!("Recordings are saved to {}{}", path, note));
+    }
+
+    /// Starts or stops watching `config.json` to match the setting, and
+    /// reloads it after an external edit. The reload waits while the settings
+    /// window is open or a recording runs, so it never overwrites settings
+    /// being edited or in use; the app's own saves are recognized and skipped.
+    fn check_config_file(&mut self) {
+        let wanted = self.state.lock().unwrap().config.watch_config_file;
+        if wanted != self.config_watcher.is_some() {
+            self.config_watcher = None;
+            if wanted {
+                match ConfigWatcher::start() {
+                    Ok(watcher) => self.config_watcher = Some(watcher),
+                    Err(e) => {
+                        warn!("Can't watch the config file: {:#}", e);
+                        self.toasts.error(format!("Can't watch config.json for changes: {:#}", e));
+                        // Not saved, so the next start tries again
+                        self.state.lock().unwrap().config.watch_config_file = false;
+                    }
+                }
+            }
+        }
+        if self.config_watcher.as_ref().is_some_and(|watcher| watcher.take_change()) {
+            self.config_reload_pending = true;
+        }
+        if !self.config_reload_pending {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if state.show_settings || state.is_recording || state.is_finalizing {
+            return;
+        }
+        self.config_reload_pending = false;
+        let path = match Config::get_config_path() {
+            Ok(path) => path,
+            Err(e) => {
+                warn!("Can't locate the edited config file: {:#}", e);
+                return;
+            }
+        };
+        // Deleted or mid-rename: wait for the next change instead of
+        // writing the defaults over it
+        if !path.exists() {
+            return;
+        }
+        let config = match Config::load_from(&path) {
+            Ok(config) => config,
+            Err(LoadError::Unreadable(e)) => {
+                warn!("Ignoring edited config file: {:#}", e);
+                self.toasts.error(format!("config.json couldn't be read; keeping the current settings: {:#}", e));
+                return;
+            }
+            Err(LoadError::Corrupt(e)) => {
+                warn!("Ignoring edited config file: {}", e);
+                self.toasts.error(format!("config.json couldn't be parsed ({}); keeping the current settings", e));
+                return;
+            }
+        };
+        if config.same_on_disk(&state.config_file.on_disk) {
+            return;
+        }
+        info!("Reloading settings from the edited config file");
+        state.reload_config(config);
+        i18n::set_language(state.language);
+        state.log_verbosity.apply();
+        self.resolved_theme = state.discord_theme.resolve();
+        self.auto_stop_text = auto_stop_label(state.config.auto_stop_after_secs);
+        drop(state);
+        self.toasts.info("Settings reloaded from config.json");
+    }
+
+    fn draw_log_panel(&mut self, ui: &mut egui::Ui) {
+        let lines = self.ffmpeg_log.snapshot();
+        let problems = lines.iter().filter(|l| l.level != LogLevel::Info).count();
+        let title = if problems > 0 {
+            format!("Log ({} warnings/errors)", problems)
+        } else {
+            "Log".to_string()
+        };
+        egui::CollapsingHeader::new(title)
+            .id_salt("ffmpeg_log")
+            .default_open(false)
+            .show(ui, |ui| {
+                if ui.small_button("Clear").clicked() {
+                    self.ffmpeg_log.clear();
+                }
+                egui::ScrollArea::vertical()
+                    .max_height(180.0)
+                    .stick_to_bottom(true)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        for line in &lines {
+                            let color = match line.level {
+                                LogLevel::Error => Color32::from_rgb(240, 71, 71),
+                                LogLevel::Warning => Color32::from_rgb(255, 180, 0),
+                                LogLevel::Info => ui.visuals().weak_text_color(),
+                            };
+                            ui.label(RichText::new(&line.text).monospace().size(11.0).color(color));
+                        }
+                    });
+            });
+    }
+
+    /// Starts or stops input monitoring to match the settings, and keeps its volume in sync.
+    fn update_monitor(&mut self) {
+        let (enabled, volume, muted) = {
+            let state = self.state.lock().unwrap();
+            (state.monitor_enabled, state.monitor_volume, state.monitor_muted)
+        };
+        let recorder = self.audio_recorder.as_ref().filter(|r| r.is_recording());
+
+        match (enabled, recorder, self.audio_monitor.as_ref()) {
+            (true, Some(recorder), None) => match AudioMonitor::start(recorder) {
+                Ok(monitor) => self.audio_monitor = Some(monitor),
+                Err(e) => {
+                    error!("Failed to start audio monitoring: {}", e);
+                    self.status_message = Some((format!("Monitoring unavailable: {}", e), Instant::now()));
+                    self.state.lock().unwrap().monitor_enabled = false;
+                }
+            },
+            (false, _, Some(_)) | (_, None, Some(_)) => self.audio_monitor = None,
+            _ => {}
+        }
+
+        if let Some(monitor) = self.audio_monitor.as_ref() {
+            monitor.set_volume(volume, muted);
+        }
+    }
+
+    /// Current hotkey bindings, read fresh each frame so rebinding in settings
+    /// shows up immediately.
+    fn draw_shortcuts(&mut self, ctx: &egui::Context) {
+        let (hotkeys, mic_gate) = {
+            let state = self.state.lock().unwrap();
+            (state.hotkeys.clone(), state.mic_gate)
+        };
+        egui::Window::new("Keyboard Shortcuts")
+            .open(&mut self.show_shortcuts)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("shortcut_grid").num_columns(2).spacing([24.0, 6.0]).show(ui, |ui| {
+                    for action in HotkeyAction::ALL {
+                        ui.label(action.label());
+                        ui.label(RichText::new(format_shortcut(hotkeys.binding(action))).monospace());
+                        ui.end_row();
+                    }
+                    ui.label("Show this list");
+                    ui.label(RichText::new(SHORTCUT_HELP_KEY.name()).monospace());
+                    ui.end_row();
+                });
+                if mic_gate == MicGateMode::Off {
+                    ui.label(RichText::new("Push-to-Talk is held, and only active when a mic gate mode is set").size(11.0));
+                } else {
+                    ui.label(RichText::new("Push-to-Talk is held rather than pressed").size(11.0));
+                }
+                ui.label(RichText::new("Change bindings under Settings → Hotkeys").size(11.0));
+            });
+    }
+
+    /// Asks whether to end a long recording; opened by `toggle_recording`.
+    fn draw_confirm_stop(&mut self, ctx: &egui::Context) {
+        if !self.state.lock().unwrap().is_recording {
+            self.confirm_stop_open = false;
+            return;
+        }
+        let elapsed = self.video_encoder.as_ref().map_or(Duration::ZERO, |e| e.elapsed());
+        let mut stop = false;
+        let mut keep = false;
+        let modal = egui::Modal::new(egui::Id::new("confirm_stop")).show(ctx, |ui| {
+            ui.heading(tr("confirm_stop.title"));
+            ui.label(format!("This recording has been running for {}.", postprocess::format_timestamp(elapsed)));
+            ui.add_space(8.0);
+            ui.horizontal(|ui| {
+                stop = ui.button(tr("confirm_stop.stop")).clicked();
+                keep = ui.button(tr("confirm_stop.keep")).clicked();
+            });
+        });
+        if keep || modal.should_close() {
+            self.confirm_stop_open = false;
+        } else if stop {
+            self.confirm_stop_open = false;
+            if let Err(e) = self.stop_recording() {
+                error!("Failed to stop recording: {}", e);
+                self.toasts.error(format!("Couldn't stop recording: {}", e));
+            }
+        }
+    }
+
+    fn note_clipping(&mut self) {
+        self.clip_latched = true;
+        let Some(encoder) = self.video_encoder.as_ref() else {
+            return;
+        };
+        let at = encoder.elapsed();
+        if self.clip_events.last().is_none_or(|last| at >= *last + CLIP_EVENT_GAP) {
+            self.clip_events.push(at);
+        }
+    }
+
+    fn add_marker(&mut self) {
+        if !self.state.lock().unwrap().is_recording {
+            return;
+        }
+        self.save_marker_note();
+        if let Some(encoder) = self.video_encoder.as_mut() {
+            let at = encoder.add_marker();
+            self.marker_note = Some(MarkerNote { index: encoder.markers().len() - 1, text: String::new(), focus: true });
+            self.status_message = Some((
+                format!("Marker added at {}", postprocess::format_timestamp(at)),
+                Instant::now(),
+            ));
+        }
+    }
+
+    /// Attaches the note being typed to its marker and closes the field.
+    fn save_marker_note(&mut self) {
+        if let (Some(note), Some(encoder)) = (self.marker_note.take(), self.video_encoder.as_mut()) {
+            encoder.set_marker_note(note.index, &note.text);
+        }
+    }
+
+    /// Index of the scene matching the current sources and layout, if any.
+    fn current_scene(&self) -> Option<usize> {
+        let state = self.state.lock().unwrap();
+        state
+            .config
+            .scenes
+            .iter()
+            .position(|scene| scene.matches(state.record_video, state.record_webcam, state.overlay_layout))
+    }
+
+    /// Switches sources and layout to the scene at `index` and saves it as the
+    /// scene to start in. Changing them would need a new ffmpeg process, so a
+    /// running recording keeps its scene.
+    fn apply_scene(&mut self, index: usize) {
+        let (scene, toggle_webcam) = {
+            let mut state = self.state.lock().unwrap();
+            let Some(scene) = state.config.scenes.get(index).cloned() else {
+                return;
+            };
+            if state.is_recording || state.is_finalizing {
+                drop(state);
+                self.toasts.info("Scenes can be switched between recordings");
+                return;
+            }
+            state.record_video = scene.record_video;
+            state.overlay_layout = scene.layout;
+            if scene.record_video && scene.record_webcam {
+                state.enable_preview_overlay = true;
+            }
+            let toggle_webcam = state.record_webcam != scene.record_webcam;
+            scene.remember_in(&mut state.config);
+            if let Err(e) = state.save_config() {
+                error!("Failed to save scene: {}", e);
+            }
+            (scene, toggle_webcam)
+        };
+        if toggle_webcam {
+            self.toggle_webcam_capture();
+        }
+        info!("Switched to scene \"{}\"", scene.name);
+        self.status_message = Some((format!("Scene: {}", scene.name), Instant::now()));
+    }
+
+    fn next_scene(&mut self) {
+        let count = self.state.lock().unwrap().config.scenes.len();
+        if count > 0 {
+            let next = self.current_scene().map_or(0, |i| (i + 1) % count);
+            self.apply_scene(next);
+        }
+    }
+
+    /// Saves a webcam still to the output directory: the newest preview frame
+    /// when the preview is running, otherwise one from briefly opening the camera.
+    fn take_webcam_snapshot(&mut self) {
+        let (output_path, webcam_name) = {
+            let state = self.state.lock().unwrap();
+            let name = state.selected_webcam.clone().unwrap_or_else(|| "Default Webcam".to_string());
+            (PathBuf::from(&state.output_path), name)
+        };
+        let preview_frame = if self.webcam_capture.is_some() { self.last_webcam_frame.clone() } else { None };
+        let (pacing, budget) = (self.pacing.clone(), self.frame_budget.clone());
+        let toasts = self.toasts.clone();
+        runtime_handle().spawn_blocking(move || {
+            let result = match preview_frame {
+                Some(frame) => Ok(frame),
+                None => webcam::grab_frame(&webcam_name, pacing, budget),
+            }
+            .and_then(|frame| webcam::save_snapshot(&frame, &output_path));
+            match result {
+                Ok(path) => {
+                    info!("Saved webcam snapshot to {}", path.display());
+                    toasts.info(format!("Snapshot saved to {}", path.display()));
+                }
+                Err(e) => {
+                    error!("Webcam snapshot failed: {:#}", e);
+                    toasts.error(format!("Couldn't take a snapshot: {:#}", e));
+                }
+            }
+        });
+    }
+
+    fn toggle_webcam_capture(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.record_webcam = !state.record_webcam;
+        let desired = state.record_webcam;
+        let webcam_name = state
+            .selected_webcam
+            .clone()
+            .unwrap_or_else(|| "Default Webcam".to_string());
+        drop(state);
+
+        if desired {
+            if let Some(capture) = self.webcam_capture.as_mut() {
+                let _ = capture.stop();
+            }
+            self.webcam_capture = None;
+            match WebcamCapture::new(&webcam_name, self.pacing.clone(), self.frame_budget.clone()) {
+                Ok(mut capture) => {
+                    if let Err(e) = capture.start() {
+                        error!("Failed to start webcam capture: {}", e);
+                        self.toasts.error(format!("Webcam unavailable (device busy?): {}", e));
+                    } else {
+                        self.webcam_capture = Some(capture);
+                        self.active_webcam_name = Some(webcam_name);
+                    }
+                }
+                Err(e) => {
+                    error!("Failed to create webcam capture: {}", e);
+                    self.toasts.error(format!("Webcam unavailable: {}", e));
+                }
+            }
+        } else {
+            if let Some(capture) = self.webcam_capture.as_mut() {
+                let _ = capture.stop();
+            }
+            self.webcam_capture = None;
+            self.active_webcam_name = None;
+        }
+    }
+
+    fn draw_settings_contents(&mut self, ui: &mut egui::Ui) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let mut refresh_requested = false;
+
+        ui.heading(tr("heading.language"));
+        let previous_language = state.language;
+        egui::ComboBox::from_id_salt("settings_language")
+            .selected_text(state.language.native_name())
+            .show_ui(ui, |ui| {
+                for language in Language::ALL {
+                    ui.selectable_value(&mut state.language, language, language.native_name());
+                }
+            });
+        if state.language != previous_language {
+            let language = state.language;
+            i18n::set_language(language);
+            state.config.language = language;
+            if let Err(e) = state.save_config() {
+                error!("Failed to save language: {}", e);
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.label(tr("settings.theme"));
+            let previous_theme = state.discord_theme;
+            egui::ComboBox::from_id_salt("settings_theme")
+                .selected_text(theme_label(state.discord_theme))
+                .show_ui(ui, |ui| {
+                    for theme in DiscordTheme::ALL {
+                        ui.selectable_value(&mut state.discord_theme, theme, theme_label(theme));
+                    }
+                });
+            if state.discord_theme != previous_theme {
+                let theme = state.discord_theme;
+                self.resolved_theme = theme.resolve();
+                state.config.discord_theme = theme;
+                if let Err(e) = state.save_config() {
+                    error!("Failed to save theme: {}", e);
+                }
+            }
+        });
+
+        ui.separator();
+        ui.heading(tr("heading.capture"));
+        ui.checkbox(&mut state.record_audio, "Record system audio");
+        ui.checkbox(&mut state.record_video, "Record screen");
+        ui.add_enabled_ui(state.record_video && !state.is_recording, |ui| {
+            ui.indent("cursor_zoom", |ui| {
+                ui.horizontal(|ui| {
+                    let mut zoomed = state.cursor_zoom.is_some();
+                    if ui.checkbox(&mut zoomed, "Zoom around the cursor").changed() {
+                        state.cursor_zoom = zoomed.then_some(2.0);
+                    }
+                    if let Some(zoom) = state.cursor_zoom.as_mut() {
+                        ui.add(Slider::new(zoom, 1.25..=4.0).step_by(0.25).suffix("×"));
+                    }
+                })
+                .response
+                .on_hover_text("Records a magnified window that follows the mouse, for tutorials. X11 only and not combined with a capture region; the preview keeps showing the whole screen");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut state.click_highlight.enabled, "Highlight mouse clicks")
+                        .on_hover_text("Draws a growing ring around each click. Needs X11 with xinput and xdotool; not combined with the cursor zoom");
+                    ui.add_enabled_ui(state.click_highlight.enabled, |ui| {
+                        ui.color_edit_button_srgb(&mut state.click_highlight.color);
+                        ui.add(Slider::new(&mut state.click_highlight.size, 16..=160).suffix(" px"));
+                    });
+                });
+                ui.checkbox(&mut state.piped_screen_capture, "Pipe screen frames through the app")
+                    .on_hover_text("Captures the screen itself and feeds raw frames to ffmpeg, so effects like the click ring are drawn per frame. Costs more CPU than letting ffmpeg grab the screen; not combined with push-to-talk or the cursor zoom");
+                let piped = state.piped_screen_capture;
+                if ui
+                    .add_enabled(piped, egui::Checkbox::new(&mut state.config.burn_in_annotations, "Burn annotations into the recording"))
+                    .on_disabled_hover_text("Needs piped screen capture")
+                    .changed()
+                {
+                    if let Err(e) = state.save_config() {
+                        error!("Failed to save annotation setting: {}", e);
+                    }
+                }
+            });
+        });
+        ui.checkbox(&mut state.record_webcam, "Enable webcam overlay");
+        ui.add_enabled_ui(state.record_webcam && state.record_video && !state.is_recording, |ui| {
+            ui.indent("webcam_outputs", |ui| {
+                ui.checkbox(&mut state.separate_webcam, "Save webcam separately")
+                    .on_hover_text("Also writes the raw webcam to a .webcam.mkv file for re-compositing later");
+                ui.add_enabled(
+                    state.separate_webcam,
+                    egui::Checkbox::new(&mut state.composite_webcam, "Keep overlay in screen recording"),
+                )
+                .on_hover_text("Off records the screen without the webcam; the webcam is then only in its own file");
+            });
+        });
+        ui.checkbox(&mut state.separate_outputs, "Save audio and video separately")
+            .on_hover_text("With desktop audio mixed in, the microphone and desktop audio get a file each too");
+        ui.add_enabled(!state.is_recording, egui::Checkbox::new(&mut state.seamless_pause, "Seamless pause"))
+            .on_hover_text("Pause ends the current segment and resume starts a new one, so there is no frozen gap; the segments are joined into one file on stop. On by default. Resuming takes a moment while ffmpeg restarts.");
+        ui.add_enabled(!state.is_recording, egui::Checkbox::new(&mut state.low_latency, "Low latency"))
+            .on_hover_text("Unbuffered capture and zerolatency encoding. Cuts delay for live streaming at the cost of some compression; file recordings gain little.");
+        ui.add_enabled_ui(!state.is_recording, |ui| {
+            ui.horizontal(|ui| {
+                let mut capped = state.encoder_threads.is_some();
+                if ui.checkbox(&mut capped, "Limit encoder threads to").changed() {
+                    state.encoder_threads = capped.then_some(4);
+                }
+                if let Some(threads) = state.encoder_threads.as_mut() {
+                    ui.add(DragValue::new(threads).range(1..=64));
+                }
+            })
+            .response
+            .on_hover_text("Keeps ffmpeg from taking every core, e.g. while gaming. Off lets ffmpeg decide");
+        });
+        ui.checkbox(&mut state.copy_path_on_stop, "Copy the file path when a recording is saved")
+            .on_hover_text("With several output files the folder is copied instead");
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut state.thumbnail_on_stop, "Save a thumbnail")
+                .on_hover_text("Writes <name>.jpg from a few seconds into each recording");
+            ui.add_enabled(
+                state.thumbnail_on_stop,
+                egui::Checkbox::new(&mut state.contact_sheet_on_stop, "and a contact sheet"),
+            )
+            .on_hover_text("Writes <name>.sheet.jpg, a grid of frames from across the recording");
+        });
+        ui.horizontal(|ui| {
+            ui.label("Marker subtitles");
+            let previous = (state.config.marker_subtitles, state.config.embed_marker_subtitles);
+            egui::ComboBox::from_id_salt("settings_marker_subtitles")
+                .selected_text(state.config.marker_subtitles.label())
+                .show_ui(ui, |ui| {
+                    for format in MarkerSubtitles::ALL {
+                        ui.selectable_value(&mut state.config.marker_subtitles, format, format.label());
+                    }
+                })
+                .response
+                .on_hover_text("Writes a subtitle file next to the recording with each marker's note, shown for a few seconds");
+            ui.add_enabled(
+                state.config.marker_subtitles != MarkerSubtitles::Off,
+                egui::Checkbox::new(&mut state.config.embed_marker_subtitles, "and add them to the MKV"),
+            );
+            if (state.config.marker_subtitles, state.config.embed_marker_subtitles) != previous {
+                if let Err(e) = state.save_config() {
+                    error!("Failed to save marker subtitles: {}", e);
+                }
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut state.confirm_long_stop, "Confirm before stopping recordings longer than")
+                .on_hover_text("Applies to the Stop button and the hotkey alike");
+            ui.add_enabled(
+                state.confirm_long_stop,
+                DragValue::new(&mut state.confirm_stop_after_minutes).range(1..=600).suffix(" min"),
+            );
+        });
+        ui.horizontal(|ui| {
+            ui.label("Video quality");
+            let recording = state.is_recording;
+            ui.add_enabled_ui(!recording, |ui| {
+                egui::ComboBox::from_id_salt("settings_video_quality")
+                    .selected_text(video_quality_label(state.video_quality))
+                    .show_ui(ui, |ui| {
+                        for quality in [
+                            VideoQuality::Low,
+                            VideoQuality::Medium,
+                            VideoQuality::High,
+                            VideoQuality::Ultra,
+                            VideoQuality::Lossless,
+                        ] {
+                            ui.selectable_value(&mut state.video_quality, quality, video_quality_label(quality));
+                        }
+                    });
+            });
+        });
+        ui.horizontal(|ui| {
+            ui.label("Frame rate");
+            ui.add_enabled(!state.is_recording, Slider::new(&mut state.frame_rate, 10..=120).suffix(" fps"))
+                .on_disabled_hover_text("Applies to the next recording");
+        });
+        ui.horizontal(|ui| {
+            let mut capped = state.preview_fps_cap.is_some();
+            if ui.checkbox(&mut capped, "Limit preview to").changed() {
+                state.preview_fps_cap = capped.then_some(30);
+            }
+            let frame_rate = state.frame_rate;
+            if let Some(cap) = state.preview_fps_cap.as_mut() {
+                ui.add(Slider::new(cap, 5..=frame_rate.max(5)).suffix(" fps"));
+            }
+        })
+        .response
+        .on_hover_text("A lower preview rate saves CPU; the recording still uses the full frame rate");
+        ui.checkbox(&mut state.show_stats_panel, "Show statistics")
+            .on_hover_text("Elapsed time, fps, bitrate, file size and dropped frames, with graphs, while recording");
+        ui.checkbox(&mut state.show_preview_fps, "Show preview fps")
+            .on_hover_text("Overlays how many new frames per second reach each preview");
+        ui.horizontal(|ui| {
+            ui.label("Frame buffer memory");
+            ui.add(Slider::new(&mut state.frame_buffer_limit_mb, 32..=2048).logarithmic(true).suffix(" MiB"));
+        })
+        .response
+        .on_hover_text("Upper bound for preview frames waiting to be shown. Frames that don't fit are dropped and counted below the preview");
+        if state.video_quality == VideoQuality::Lossless {
+            ui.colored_label(
+                Color32::from_rgb(255, 180, 0),
+                "⚠ Lossless files are very large (often several GB per minute at 1080p) and meant for editing, not sharing.",
+            );
+        }
+
+        ui.separator();
+        ui.heading(tr("heading.audio"));
+        ui.label("Input Gain (dB)");
+        ui.add(Slider::new(&mut state.audio_gain_db, -30.0..=12.0).suffix(" dB"));
+        ui.label("Balance (L / R)");
+        ui.horizontal(|ui| {
+            ui.add(Slider::new(&mut state.audio_balance, -1.0..=1.0).fixed_decimals(2));
+            if ui.small_button("Center").clicked() {
+                state.audio_balance = 0.0;
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Microphone mode");
+            let recording = state.is_recording;
+            ui.add_enabled_ui(!recording, |ui| {
+                egui::ComboBox::from_id_salt("settings_mic_gate")
+                    .selected_text(mic_gate_label(state.mic_gate))
+                    .show_ui(ui, |ui| {
+                        for mode in [MicGateMode::Off, MicGateMode::PushToTalk, MicGateMode::PushToMute] {
+                            ui.selectable_value(&mut state.mic_gate, mode, mic_gate_label(mode));
+                        }
+                    });
+            })
+            .response
+            .on_disabled_hover_text("Stop recording to change the microphone mode");
+        });
+        let ffmpeg_path_edited = ui
+            .horizontal(|ui| {
+                ui.label("FFmpeg binary");
+                ui.add(egui::TextEdit::singleline(&mut state.config.ffmpeg_path).hint_text("ffmpeg"))
+                    .on_hover_text("Used for recording and all post-processing; a bare name is looked up on PATH")
+                    .lost_focus()
+            })
+            .inner;
+        if ffmpeg_path_edited {
+            if state.config.ffmpeg_path.trim().is_empty() {
+                state.config.ffmpeg_path = "ffmpeg".to_string();
+            }
+            let ffmpeg_path = state.config.ffmpeg_path.clone();
+            runtime_handle().spawn_blocking(move || crate::video::probe_hw_accels(&ffmpeg_path));
+            if let Err(e) = state.save_config() {
+                error!("Failed to save ffmpeg path: {}", e);
+            }
+        }
+        let ffmpeg_path = state.config.ffmpeg_path.clone();
+        ui.horizontal(|ui| {
+            ui.label("Video container");
+            egui::ComboBox::from_id_salt("settings_output_container")
+                .selected_text(state.output_container.label())
+                .show_ui(ui, |ui| {
+                    for container in OutputContainer::ALL {
+                        let available = container != OutputContainer::WebM
+                            || (crate::video::ffmpeg_has_encoder(&ffmpeg_path, "libvpx-vp9")
+                                && crate::video::ffmpeg_has_encoder(&ffmpeg_path, "libopus"));
+                        ui.add_enabled_ui(available, |ui| {
+                            ui.selectable_value(&mut state.output_container, container, container.label());
+                        })
+                        .response
+                        .on_disabled_hover_text("This ffmpeg build has no libvpx-vp9 or libopus encoder");
+                    }
+                });
+        })
+        .response
+        .on_hover_text("MP4 plays inline in Discord and on phones without remuxing; MKV survives a crash mid-recording");
+        if !state.output_container.allows(state.video_codec, state.audio_codec) {
+            (state.video_codec, state.audio_codec) = state.output_container.default_codecs();
+        }
+        ui.add_enabled_ui(state.output_container == OutputContainer::Mkv, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Codecs");
+                egui::ComboBox::from_id_salt("settings_video_codec")
+                    .selected_text(state.video_codec.label())
+                    .show_ui(ui, |ui| {
+                        for codec in VideoCodec::ALL {
+                            let available = codec != VideoCodec::Vp9 || crate::video::ffmpeg_has_encoder(&ffmpeg_path, "libvpx-vp9");
+                            ui.add_enabled_ui(available, |ui| {
+                                ui.selectable_value(&mut state.video_codec, codec, codec.label());
+                            })
+                            .response
+                            .on_disabled_hover_text("This ffmpeg build has no libvpx-vp9 encoder");
+                        }
+                    });
+                egui::ComboBox::from_id_salt("settings_audio_codec")
+                    .selected_text(state.audio_codec.label())
+                    .show_ui(ui, |ui| {
+                        for codec in AudioCodec::ALL {
+                            let available = codec != AudioCodec::Opus || crate::video::ffmpeg_has_encoder(&ffmpeg_path, "libopus");
+                            ui.add_enabled_ui(available, |ui| {
+                                ui.selectable_value(&mut state.audio_codec, codec, codec.label());
+                            })
+                            .response
+                            .on_disabled_hover_text("This ffmpeg build has no libopus encoder");
+                        }
+                    });
+            });
+        })
+        .response
+        .on_hover_text("MKV takes H.264 or VP9 with AAC or Opus")
+        .on_disabled_hover_text("MP4 records H.264 + AAC and WebM VP9 + Opus; pick MKV to choose the codecs");
+        ui.horizontal(|ui| {
+            ui.label("Video encoder");
+            egui::ComboBox::from_id_salt("settings_hw_accel")
+                .selected_text(state.hw_accel.label())
+                .show_ui(ui, |ui| {
+                    for accel in HwAccel::ALL {
+                        let support = crate::video::cached_hw_accel_support(&ffmpeg_path, accel);
+                        ui.add_enabled_ui(support == Some(true), |ui| {
+                            ui.selectable_value(&mut state.hw_accel, accel, accel.label());
+                        })
+                        .response
+                        .on_disabled_hover_text(match support {
+                            None => format!("Still checking whether ffmpeg can encode with {}", accel.encoder()),
+                            Some(_) => format!("ffmpeg can't encode with {} on this machine", accel.encoder()),
+                        });
+                    }
+                });
+        })
+        .response
+        .on_hover_text("A GPU encoder keeps high resolutions and frame rates from maxing out the CPU. VP9 is always encoded in software");
+        ui.horizontal(|ui| {
+            ui.label("Audio file format");
+            egui::ComboBox::from_id_salt("settings_audio_format")
+                .selected_text(audio_format_label(state.audio_export_format))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut state.audio_export_format, AudioExportFormat::Flac, audio_format_label(AudioExportFormat::Flac));
+                    let mp3_available = crate::video::ffmpeg_has_encoder(&ffmpeg_path, "libmp3lame");
+                    ui.add_enabled_ui(mp3_available, |ui| {
+                        ui.selectable_value(&mut state.audio_export_format, AudioExportFormat::Mp3, audio_format_label(AudioExportFormat::Mp3));
+                    })
+                    .response
+                    .on_disabled_hover_text("This ffmpeg build has no libmp3lame encoder");
+                });
+        })
+        .response
+        .on_hover_text("Used for audio-only recordings and the separate audio file");
+        if state.audio_export_format == AudioExportFormat::Flac {
+            ui.horizontal(|ui| {
+                ui.label("FLAC bit depth");
+                let selected = flac_depth_label(state.flac_bit_depth, state.audio_quality);
+                egui::ComboBox::from_id_salt("settings_flac_depth")
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        let quality = state.audio_quality;
+                        for depth in [None, Some(FlacBitDepth::Bits16), Some(FlacBitDepth::Bits24)] {
+                            ui.selectable_value(&mut state.flac_bit_depth, depth, flac_depth_label(depth, quality));
+                        }
+                    });
+            });
+        }
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut state.monitor_enabled, "Monitor input")
+                .on_hover_text("Plays the input on the default output device while recording. Use headphones: through speakers the microphone picks the playback up again and feeds back");
+            ui.add_enabled_ui(state.monitor_enabled, |ui| {
+                ui.add(Slider::new(&mut state.monitor_volume, 0.0..=2.0).fixed_decimals(2));
+                let mute_label = if state.monitor_muted { "🔇 Unmute" } else { "🔊 Mute" };
+                if ui.small_button(mute_label).clicked() {
+                    state.monitor_muted = !state.monitor_muted;
+                }
+            });
+        });
+        let loopback_selected = state
+            .selected_audio_device
+            .as_deref()
+            .is_some_and(|name| name.ends_with(audio::LOOPBACK_SUFFIX));
+        if state.monitor_enabled && (loopback_selected || state.record_system_audio) {
+            ui.colored_label(
+                Color32::from_rgb(255, 180, 0),
+                "⚠ Desktop audio is being captured; the monitor playback will be recorded too (and loopback inputs can't be monitored).",
+            );
+        }
+        ui.checkbox(&mut state.log_clip_timestamps, "Log clipping times")
+            .on_hover_text("Writes a .clips.txt file next to the recording listing when the input hit 0 dBFS");
+        ui.checkbox(&mut state.limiter_enabled, "Limiter")
+            .on_hover_text("Catches peaks after gain and balance so loud moments don't clip");
+        ui.add_enabled_ui(state.limiter_enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Threshold");
+                ui.add(Slider::new(&mut state.limiter_threshold_db, -24.0..=0.0).suffix(" dB"));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Makeup gain");
+                ui.add(Slider::new(&mut state.limiter_makeup_db, 0.0..=12.0).suffix(" dB"));
+            });
+        });
+        ui.add_enabled(
+            !state.is_recording,
+            egui::Checkbox::new(&mut state.record_system_audio, "Mix in desktop audio"),
+        )
+        .on_hover_text("Records what is playing on this computer alongside the selected input device");
+        let ducking_available = state.record_system_audio;
+        ui.add_enabled_ui(ducking_available, |ui| {
+            ui.checkbox(&mut state.ducking_enabled, "Duck desktop audio while talking")
+                .on_hover_text("Lowers desktop audio whenever the microphone is above the threshold");
+            ui.add_enabled_ui(state.ducking_enabled, |ui| {
+                let ducking = &mut state.ducking;
+                ui.horizontal(|ui| {
+                    ui.label("Threshold");
+                    ui.add(Slider::new(&mut ducking.threshold_db, -60.0..=0.0).suffix(" dB"));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Ratio");
+                    ui.add(Slider::new(&mut ducking.ratio, 1.0..=20.0).fixed_decimals(1).suffix(":1"));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Attack");
+                    ui.add(Slider::new(&mut ducking.attack_ms, 1.0..=500.0).suffix(" ms"));
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Release");
+                    ui.add(Slider::new(&mut ducking.release_ms, 10.0..=3000.0).suffix(" ms"));
+                });
+            });
+        });
 
         if state.record_video {
             ui.separator();
@@ -464,6 +2678,24 @@ impl DiscordRecorderApp {
                         ui.selectable_value(&mut state.selected_audio_device, Some(device.clone()), device);
                     }
                 });
+            ui.horizontal(|ui| {
+                let idle = !state.is_recording && !state.is_finalizing;
+                let label = if self.audio_test.is_some() { "Testing…" } else { "Test" };
+                if ui
+                    .add_enabled(idle && self.audio_test.is_none(), egui::Button::new(label))
+                    .on_hover_text("Opens the device for a few seconds and shows its level, without recording")
+                    .on_disabled_hover_text("The meter already shows the device while recording")
+                    .clicked()
+                {
+                    self.audio_test_requested = true;
+                }
+                ui.checkbox(&mut self.audio_test_tone, "Play a tone")
+                    .on_hover_text("Also plays a short tone on the default output device, where the monitor plays");
+                if self.audio_test.is_some() {
+                    ui.add(ProgressBar::new(self.audio_level.clamp(0.0, 1.0)).desired_width(120.0));
+                    ui.ctx().request_repaint();
+                }
+            });
         }
 
         if state.record_webcam {
@@ -480,10 +2712,45 @@ impl DiscordRecorderApp {
                         ui.selectable_value(&mut state.selected_webcam, Some(webcam.clone()), webcam);
                     }
                 });
+            // Only what the open camera exposes; nothing while the webcam is off
+            let capture = self.webcam_capture.as_ref();
+            let controls = capture.map(|capture| capture.controls()).unwrap_or_default();
+            if let (Some(capture), false) = (capture, controls.is_empty()) {
+                egui::CollapsingHeader::new("Camera controls").id_salt("camera_controls").show(ui, |ui| {
+                    for control in controls {
+                        ui.horizontal(|ui| {
+                            ui.label(control.setting.label());
+                            let mut value = control.value;
+                            let slider = Slider::new(&mut value, control.min..=control.max).step_by(control.step.max(1) as f64);
+                            if ui.add(slider).changed() {
+                                capture.set_control(control.setting, value);
+                            }
+                            if ui.small_button("Reset").clicked() {
+                                capture.set_control(control.setting, control.default);
+                            }
+                        });
+                    }
+                });
+            }
         }
 
         ui.separator();
-        ui.heading("Webcam Overlay");
+        ui.heading(tr("heading.overlay"));
+        ui.horizontal(|ui| {
+            ui.label("Layout");
+            for layout in OverlayLayout::ALL {
+                ui.radio_value(&mut state.overlay_layout, layout, layout_label(layout));
+            }
+        });
+        match state.overlay_layout {
+            OverlayLayout::ScreenOver => {
+                ui.label(RichText::new("Position and size below apply to the small screen").size(11.0));
+            }
+            OverlayLayout::SideBySide => {
+                ui.label(RichText::new("Position and size below don't apply side by side").size(11.0));
+            }
+            OverlayLayout::WebcamOver => {}
+        }
         let mut overlay_x = state.overlay_position.0 as i32;
         let mut overlay_y = state.overlay_position.1 as i32;
         let mut overlay_width = state.overlay_size.0 as i32;
@@ -511,17 +2778,177 @@ impl DiscordRecorderApp {
             }
         });
 
+        let frame_size = self.screen_preview_texture.as_ref().map(|t| (t.size()[0] as u32, t.size()[1] as u32));
+        ui.horizontal(|ui| {
+            ui.label("Size");
+            for (label, preset) in OVERLAY_SIZE_PRESETS {
+                let text = format!("{} {}×{}", label, preset.0, preset.1);
+                if ui.selectable_label(state.overlay_size == preset, text).clicked() {
+                    (state.overlay_position, state.overlay_size) =
+                        overlay_size_preset(state.overlay_position, state.overlay_size, preset, frame_size);
+                }
+            }
+        });
+
         ui.horizontal(|ui| {
             ui.label("Opacity");
             ui.add(Slider::new(&mut state.overlay_opacity, 0.0..=1.0));
         });
+        if ui
+            .checkbox(&mut state.config.overlay_thirds_guides, "Rule-of-thirds guides")
+            .on_hover_text("Shows third lines on the preview and snaps the overlay's edges and center to them while dragging")
+            .changed()
+        {
+            if let Err(e) = state.save_config() {
+                error!("Failed to save thirds guides: {}", e);
+            }
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Border");
+            ui.color_edit_button_srgb(&mut state.overlay_border.color);
+            ui.add(Slider::new(&mut state.overlay_border.width, 0..=20).suffix(" px"))
+                .on_hover_text("0 turns the border off");
+        });
+
+        ui.checkbox(&mut state.chroma_key.enabled, "Remove green screen")
+            .on_hover_text("Makes the key color transparent in the preview overlay and the recording");
+        ui.add_enabled_ui(state.chroma_key.enabled, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Key color");
+                ui.color_edit_button_srgb(&mut state.chroma_key.color);
+            });
+            ui.horizontal(|ui| {
+                ui.label("Similarity");
+                ui.add(Slider::new(&mut state.chroma_key.similarity, 0.01..=1.0));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Blend");
+                ui.add(Slider::new(&mut state.chroma_key.blend, 0.0..=1.0));
+            });
+        });
+
+        ui.checkbox(&mut state.background_blur.enabled, "Blur background (experimental)");
+        if state.background_blur.enabled {
+            ui.label(
+                RichText::new("Blurs everything outside a centered box, so stay inside it. Costs noticeable CPU in the preview and while recording.")
+                    .size(11.0)
+                    .color(Color32::from_rgb(250, 166, 26)),
+            );
+            ui.horizontal(|ui| {
+                ui.label("Strength");
+                ui.add(Slider::new(&mut state.background_blur.radius, 2..=40).suffix(" px"));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Subject box");
+                ui.add(Slider::new(&mut state.background_blur.subject_width, 0.2..=1.0).text("width"));
+                ui.add(Slider::new(&mut state.background_blur.subject_height, 0.2..=1.0).text("height"));
+            });
+        }
+
+        ui.separator();
+        ui.heading(tr("heading.presets"));
+        ui.horizontal(|ui| {
+            let selected = self.selected_preset.and_then(|i| self.presets.get(i));
+            egui::ComboBox::from_id_salt("settings_preset")
+                .selected_text(selected.map_or("Choose a preset", |(_, preset)| preset.name.as_str()))
+                .show_ui(ui, |ui| {
+                    for (index, (_, preset)) in self.presets.iter().enumerate() {
+                        ui.selectable_value(&mut self.selected_preset, Some(index), &preset.name);
+                    }
+                });
+            let apply = ui
+                .add_enabled(selected.is_some() && !state.is_recording, egui::Button::new("Apply"))
+                .on_hover_text("Sets quality, frame rate, sources, overlay and hotkeys from the preset")
+                .on_disabled_hover_text("Pick a preset; presets can't be applied while recording");
+            if let (true, Some((_, preset))) = (apply.clicked(), selected) {
+                // On top of what the panel shows, so edits the preset doesn't cover survive
+                let mut config = state.config_snapshot();
+                let notes = preset.apply_to(&mut config, &self.available_audio_devices, &self.available_webcams);
+                state.replace_config(config);
+                if let Err(e) = state.save_config() {
+                    error!("Failed to save preset settings: {}", e);
+                }
+                info!("Applied preset \"{}\"", preset.name);
+                for note in &notes {
+                    warn!("Preset \"{}\": {}", preset.name, note);
+                    self.toasts.error(note.clone());
+                }
+                self.toasts.info(format!("Applied preset \"{}\"", preset.name));
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.preset_name).desired_width(160.0).hint_text("Preset name"));
+            if ui
+                .add_enabled(!self.preset_name.trim().is_empty(), egui::Button::new("Save current settings"))
+                .on_hover_text("Writes a preset file you can send to someone; they drop it onto their window to import it")
+                .clicked()
+            {
+                let preset = Preset::from_config(&self.preset_name, &state.config_snapshot());
+                match presets::presets_dir().and_then(|dir| presets::save_preset(&dir, &preset)) {
+                    Ok(path) => {
+                        self.toasts.info(format!("Saved preset to {}", path.display()));
+                        self.preset_name.clear();
+                        (self.presets, self.selected_preset) = find_presets(Some(&path));
+                    }
+                    Err(e) => {
+                        error!("Failed to save preset: {:#}", e);
+                        self.toasts.error(format!("Couldn't save preset: {:#}", e));
+                    }
+                }
+            }
+        });
+        ui.label(RichText::new("Drop a preset file onto the window to import it").size(11.0));
+
+        ui.separator();
+        ui.heading(tr("heading.scenes"));
+        let mut scenes_changed = false;
+        let mut remove_scene = None;
+        for (index, scene) in state.config.scenes.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                scenes_changed |= ui.add(egui::TextEdit::singleline(&mut scene.name).desired_width(120.0)).changed();
+                scenes_changed |= ui.checkbox(&mut scene.record_video, "Screen").changed();
+                scenes_changed |= ui.checkbox(&mut scene.record_webcam, "Webcam").changed();
+                ui.add_enabled_ui(scene.record_video && scene.record_webcam, |ui| {
+                    egui::ComboBox::from_id_salt(("scene_layout", index))
+                        .selected_text(layout_label(scene.layout))
+                        .show_ui(ui, |ui| {
+                            for layout in OverlayLayout::ALL {
+                                scenes_changed |= ui.selectable_value(&mut scene.layout, layout, layout_label(layout)).changed();
+                            }
+                        });
+                });
+                if ui.small_button("🗑").on_hover_text("Remove scene").clicked() {
+                    remove_scene = Some(index);
+                }
+            });
+        }
+        if let Some(index) = remove_scene {
+            state.config.scenes.remove(index);
+            scenes_changed = true;
+        }
+        if ui.button("➕ Add current as scene").clicked() {
+            let scene = config::Scene {
+                name: format!("Scene {}", state.config.scenes.len() + 1),
+                record_video: state.record_video,
+                record_webcam: state.record_webcam,
+                layout: state.overlay_layout,
+            };
+            state.config.scenes.push(scene);
+            scenes_changed = true;
+        }
+        if scenes_changed {
+            if let Err(e) = state.save_config() {
+                error!("Failed to save scenes: {}", e);
+            }
+        }
 
         ui.separator();
-        ui.heading("Hotkeys");
+        ui.heading(tr("heading.hotkeys"));
 
         ui.separator();
-        ui.heading("Wayland");
-        let ffmpeg_has_pipewire = crate::video::ffmpeg_supports_pipewire("ffmpeg");
+        ui.heading(tr("heading.wayland"));
+        let ffmpeg_has_pipewire = crate::video::ffmpeg_supports_pipewire(&state.config.ffmpeg_path);
         ui.add_enabled(
             ffmpeg_has_pipewire,
             egui::Checkbox::without_text(&mut state.use_pipewire_on_wayland)
@@ -531,52 +2958,249 @@ impl DiscordRecorderApp {
         state.config.enable_preview_overlay = state.enable_preview_overlay;
  
         let default_hotkeys = HotkeyConfig::default();
+        let warning_color = Color32::from_rgb(255, 180, 0);
+        if let Some(warning) = self.hotkey_warning.as_ref() {
+            ui.colored_label(warning_color, format!("⚠ {}", warning));
+        }
+        for (first, second) in state.hotkeys.duplicates() {
+            ui.colored_label(
+                warning_color,
+                format!("⚠ {} and {} share {}", first.label(), second.label(), format_shortcut(state.hotkeys.binding(first))),
+            );
+        }
+
+        for action in HotkeyAction::ALL {
+            ui.horizontal(|ui| {
+                let hotkey = state.hotkeys.hotkey_mut(action);
+                ui.checkbox(&mut hotkey.enabled, "")
+                    .on_hover_text("Unchecked hotkeys keep their binding but do nothing");
+                let label = if action == HotkeyAction::PushToTalk { "Push-to-Talk (hold)" } else { action.label() };
+                ui.add_enabled(hotkey.enabled, egui::Label::new(label));
+                let button_label = if self.awaiting_hotkey == Some(action) {
+                    "Press keys...".to_string()
+                } else {
+                    format_shortcut(&hotkey.shortcut)
+                };
+                if ui.button(button_label).clicked() {
+                    self.awaiting_hotkey = Some(action);
+                }
+                if ui.small_button("Reset").clicked() {
+                    hotkey.shortcut = *default_hotkeys.binding(action);
+                }
+            });
+        }
+ 
+        ui.separator();
+        ui.heading(tr("heading.streaming"));
+        ui.add_enabled_ui(!state.is_recording, |ui| {
+            ui.checkbox(&mut state.stream_enabled, "Stream over SRT while recording")
+                .on_hover_text("Sends the recording to a remote SRT listener as well as saving it");
+            ui.horizontal(|ui| {
+                ui.label("URL");
+                ui.add(egui::TextEdit::singleline(&mut state.srt.url).hint_text("srt://host:9000"));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Latency");
+                ui.add(egui::DragValue::new(&mut state.srt.latency_ms).range(20..=8000).suffix(" ms"))
+                    .on_hover_text("Receiver buffer; raise it on lossy or distant links");
+            });
+            ui.horizontal(|ui| {
+                ui.label("Passphrase");
+                ui.add(egui::TextEdit::singleline(&mut state.srt.passphrase).password(true).hint_text("optional"))
+                    .on_hover_text("Encrypts the stream; 10 to 79 characters");
+            });
+        });
+        if state.stream_enabled {
+            if let Err(e) = crate::streaming::srt_output_url(&state.srt) {
+                ui.colored_label(Color32::from_rgb(255, 180, 0), format!("⚠ {}", e));
+            }
+        }
 
+        ui.separator();
+        ui.heading(tr("heading.upload"));
+        let upload = &mut state.config.upload;
+        let mut upload_changed = false;
         ui.horizontal(|ui| {
-            ui.label("Start/Stop Recording");
-            let button_label = if self.awaiting_hotkey == Some(HotkeyAction::StartStop) {
-                "Press keys...".to_string()
-            } else {
-                format_shortcut(&state.hotkeys.start_stop)
-            };
-            if ui.button(button_label).clicked() {
-                self.awaiting_hotkey = Some(HotkeyAction::StartStop);
+            ui.label("After saving");
+            egui::ComboBox::from_id_salt("settings_upload_method")
+                .selected_text(upload_method_label(upload.method))
+                .show_ui(ui, |ui| {
+                    for method in [UploadMethod::Off, UploadMethod::Command, UploadMethod::HttpPut] {
+                        upload_changed |= ui
+                            .selectable_value(&mut upload.method, method, upload_method_label(method))
+                            .changed();
+                    }
+                });
+        });
+        match upload.method {
+            UploadMethod::Off => {}
+            UploadMethod::Command => {
+                ui.horizontal(|ui| {
+                    ui.label("Command");
+                    upload_changed |= ui
+                        .add(egui::TextEdit::singleline(&mut upload.command).hint_text("rclone copy {path} remote:recordings"))
+                        .on_hover_text("Runs once per file; {path} is the quoted file path, {name} the file name")
+                        .lost_focus();
+                });
+            }
+            UploadMethod::HttpPut => {
+                ui.horizontal(|ui| {
+                    ui.label("URL");
+                    upload_changed |= ui
+                        .add(egui::TextEdit::singleline(&mut upload.put_url).hint_text("https://bucket.example.com/{name}"))
+                        .on_hover_text("{name} is replaced by the file name")
+                        .lost_focus();
+                });
+                ui.horizontal(|ui| {
+                    ui.label("Authorization");
+                    upload_changed |= ui
+                        .add(egui::TextEdit::singleline(&mut upload.authorization).password(true).hint_text("optional"))
+                        .on_hover_text("Sent as the Authorization header, e.g. \"Bearer <token>\"")
+                        .lost_focus();
+                });
             }
-            if ui.small_button("Reset").clicked() {
-                state.hotkeys.start_stop = default_hotkeys.start_stop;
+        }
+        if upload_changed {
+            if let Err(e) = state.save_config() {
+                error!("Failed to save upload settings: {}", e);
+            }
+        }
+
+        ui.separator();
+        ui.heading(tr("heading.bumpers"));
+        let bumpers = &mut state.config.bumpers;
+        let mut bumpers_changed = ui
+            .checkbox(&mut bumpers.enabled, "Join an intro and outro around each recording")
+            .on_hover_text("Writes <name>.final.<ext> in the recording's format and keeps the recording as it is. Clips in another size or frame rate are scaled to match, which re-encodes the whole video")
+            .changed();
+        ui.add_enabled_ui(bumpers.enabled, |ui| {
+            for (label, path) in [("Intro", &mut bumpers.intro), ("Outro", &mut bumpers.outro)] {
+                ui.horizontal(|ui| {
+                    ui.label(label);
+                    bumpers_changed |= ui
+                        .add(egui::TextEdit::singleline(path).hint_text("path to a video, or empty"))
+                        .lost_focus();
+                });
             }
         });
+        if bumpers_changed {
+            if let Err(e) = state.save_config() {
+                error!("Failed to save intro/outro settings: {}", e);
+            }
+        }
 
-        ui.horizontal(|ui| {
-            ui.label("Pause/Resume");
-            let button_label = if self.awaiting_hotkey == Some(HotkeyAction::PauseResume) {
-                "Press keys...".to_string()
-            } else {
-                format_shortcut(&state.hotkeys.pause_resume)
-            };
-            if ui.button(button_label).clicked() {
-                self.awaiting_hotkey = Some(HotkeyAction::PauseResume);
+        ui.separator();
+        ui.heading(tr("heading.post_steps"));
+        let mut steps_changed = false;
+        steps_changed |= ui
+            .checkbox(&mut state.config.post_steps_enabled, "Run these commands on each saved file")
+            .changed();
+        ui.colored_label(
+            Color32::from_rgb(255, 180, 0),
+            "⚠ Steps run through the shell with your permissions. Only add commands you trust.",
+        );
+        ui.label(RichText::new("Tokens: {input} file, {output_dir} its folder, {basename} name without extension").size(11.0));
+        let mut remove = None;
+        let mut move_up = None;
+        let steps_enabled = state.config.post_steps_enabled;
+        let step_count = state.config.post_steps.len();
+        ui.add_enabled_ui(steps_enabled, |ui| {
+            for (index, step) in state.config.post_steps.iter_mut().enumerate() {
+                ui.horizontal(|ui| {
+                    steps_changed |= ui.checkbox(&mut step.enabled, "").changed();
+                    steps_changed |= ui
+                        .add(egui::TextEdit::singleline(&mut step.name).hint_text("Name").desired_width(90.0))
+                        .lost_focus();
+                    steps_changed |= ui
+                        .add(
+                            egui::TextEdit::singleline(&mut step.command)
+                                .hint_text("ffmpeg -i {input} -frames:v 1 {output_dir}/{basename}.jpg")
+                                .desired_width(320.0),
+                        )
+                        .lost_focus();
+                    if ui.add_enabled(index > 0, egui::Button::new("⏶").small()).on_hover_text("Run earlier").clicked() {
+                        move_up = Some(index);
+                    }
+                    if ui.small_button("🗑").on_hover_text("Remove step").clicked() {
+                        remove = Some(index);
+                    }
+                });
             }
-            if ui.small_button("Reset").clicked() {
-                state.hotkeys.pause_resume = default_hotkeys.pause_resume;
+            if ui.button("➕ Add step").clicked() {
+                state.config.post_steps.push(config::PostStep { enabled: true, ..Default::default() });
+                steps_changed = true;
             }
         });
+        if let Some(index) = move_up.filter(|&i| i > 0 && i < step_count) {
+            state.config.post_steps.swap(index - 1, index);
+            steps_changed = true;
+        }
+        if let Some(index) = remove {
+            state.config.post_steps.remove(index);
+            steps_changed = true;
+        }
+        if steps_changed {
+            if let Err(e) = state.save_config() {
+                error!("Failed to save post-processing steps: {}", e);
+            }
+        }
 
+        ui.separator();
+        ui.heading(tr("heading.storage"));
         ui.horizontal(|ui| {
-            ui.label("Toggle Webcam");
-            let button_label = if self.awaiting_hotkey == Some(HotkeyAction::ToggleWebcam) {
-                "Press keys...".to_string()
-            } else {
-                format_shortcut(&state.hotkeys.toggle_webcam)
-            };
-            if ui.button(button_label).clicked() {
-                self.awaiting_hotkey = Some(HotkeyAction::ToggleWebcam);
+            ui.label("File name");
+            ui.add(egui::TextEdit::singleline(&mut state.file_name_template).desired_width(220.0))
+                .on_hover_text("{date}, {time} and {window} (the focused window's title when recording starts). A name that's already taken gets -1, -2, … added");
+        });
+        let example = naming::expand_file_name(&state.file_name_template, chrono::Local::now(), Some("Window Title"));
+        ui.label(RichText::new(format!("e.g. {}.mkv", example)).size(11.0));
+        ui.label(format!("Output folder: {}", state.output_path))
+            .on_hover_text("Drop a folder, or a file inside one, onto the window to change it");
+        match Config::get_config_path() {
+            Ok(path) => {
+                let mode = if config::portable_dir().is_some() { " (portable)" } else { "" };
+                ui.label(format!("Config file{}: {}", mode, path.display()));
+            }
+            Err(e) => {
+                ui.label(format!("Config file: unavailable ({})", e));
+            }
+        }
+        if ui
+            .checkbox(&mut state.config.watch_config_file, "Reload when the file is edited outside the app")
+            .on_hover_text("Changes are picked up once this window is closed and no recording is running")
+            .changed()
+        {
+            if let Err(e) = state.save_config() {
+                error!("Failed to save config watch setting: {}", e);
             }
-            if ui.small_button("Reset").clicked() {
-                state.hotkeys.toggle_webcam = default_hotkeys.toggle_webcam;
+        }
+
+        ui.separator();
+        ui.heading(tr("heading.diagnostics"));
+        ui.horizontal(|ui| {
+            ui.label("Log level");
+            let previous = state.log_verbosity;
+            egui::ComboBox::from_id_salt("settings_log_level")
+                .selected_text(format!("{:?}", state.log_verbosity))
+                .show_ui(ui, |ui| {
+                    for level in LogVerbosity::ALL {
+                        ui.selectable_value(&mut state.log_verbosity, level, format!("{:?}", level));
+                    }
+                });
+            if state.log_verbosity != previous {
+                let level = state.log_verbosity;
+                level.apply();
+                info!("Log level set to {:?}", level);
+                state.config.log_verbosity = level;
+                if let Err(e) = state.save_config() {
+                    error!("Failed to save log level: {}", e);
+                }
             }
         });
- 
+        if std::env::var_os("RUST_LOG").is_some() {
+            ui.label(RichText::new("RUST_LOG is set and limits what can be logged").size(11.0));
+        }
+
         ui.separator();
         if ui.button("Refresh device list").clicked() {
             refresh_requested = true;
@@ -586,64 +3210,41 @@ impl DiscordRecorderApp {
     }
 
     fn start_recording(&mut self) -> Result<()> {
-        let (
-            output_path,
-            include_audio,
-            include_video,
-            include_webcam,
-            separate_outputs,
-            selected_screen,
-            audio_device_opt,
-            webcam_device_opt,
-            audio_gain_db,
-            use_pipewire_on_wayland,
-            enable_preview_overlay,
-            video_quality,
-            audio_quality,
-            mut config_snapshot,
-        ) = {
+        // The recording opens the device itself
+        self.stop_audio_test();
+        let (output_path, audio_gain_db, webcam_overlay, mut config_snapshot) = {
             let state = self.state.lock().unwrap();
             if state.is_recording {
                 return Ok(());
             }
+            if state.is_finalizing {
+                return Err(anyhow!("Previous recording is still being finalized"));
+            }
 
             (
                 state.output_path.clone(),
-                state.record_audio,
-                state.record_video,
-                state.record_webcam,
-                state.separate_outputs,
-                state.selected_screen,
-                state.selected_audio_device.clone(),
-                state.selected_webcam.clone(),
                 state.audio_gain_db,
-                state.use_pipewire_on_wayland,
-                state.enable_preview_overlay,
-                state.video_quality,
-                state.audio_quality,
-                state.config.clone(),
+                OverlayPlacement {
+                    position: state.overlay_position,
+                    size: state.overlay_size,
+                    opacity: state.overlay_opacity,
+                },
+                state.config_snapshot(),
             )
         };
+        let (include_audio, include_video, include_webcam) =
+            (config_snapshot.record_audio, config_snapshot.record_video, config_snapshot.record_webcam);
+        let selected_screen = config_snapshot.default_screen;
+        let audio_device_opt = config_snapshot.default_audio_device.clone();
+        let use_pipewire_on_wayland = config_snapshot.use_pipewire_on_wayland;
 
         info!("Starting recording");
 
         // Ensure output directory exists
         std::fs::create_dir_all(&output_path)?;
+        config_snapshot.set_output_directory(output_path);
 
-        config_snapshot.set_output_directory(output_path.clone());
-        config_snapshot.record_audio = include_audio;
-        config_snapshot.record_video = include_video;
-        config_snapshot.record_webcam = include_webcam;
-        config_snapshot.separate_outputs = separate_outputs;
-        config_snapshot.use_pipewire_on_wayland = use_pipewire_on_wayland;
-        config_snapshot.enable_preview_overlay = enable_preview_overlay;
-        config_snapshot.video_quality = video_quality;
-        config_snapshot.audio_quality = audio_quality;
-        config_snapshot.default_screen = selected_screen;
-        config_snapshot.default_audio_device = audio_device_opt.clone();
-        config_snapshot.default_webcam = webcam_device_opt.clone();
-
-        config_snapshot.save()?;
+        self.state.lock().unwrap().config_file.save(&config_snapshot)?;
 
         // Reflect Wayland PipeWire preference via environment for the encoder
         if use_pipewire_on_wayland {
@@ -653,27 +3254,15 @@ impl DiscordRecorderApp {
         }
 
         let options = RecorderOptions {
-            output_directory: PathBuf::from(config_snapshot.get_output_directory()),
-            video_quality: config_snapshot.video_quality,
-            video_bitrate_kbps: config_snapshot.get_video_bitrate(),
-            audio_bitrate_kbps: config_snapshot.get_audio_bitrate(),
-            audio_sample_rate: config_snapshot.get_audio_sample_rate(),
-            frame_rate: 60,
-            include_audio,
-            include_video,
-            include_webcam,
-            separate_outputs,
-            selected_screen,
-            audio_device: audio_device_opt.clone(),
-            webcam_device: webcam_device_opt.clone(),
-            ffmpeg_path: "ffmpeg".to_string(),
             audio_gain_db,
+            webcam_overlay,
+            ..RecorderOptions::from_config(&config_snapshot)
         };
 
         if include_video {
             let screen_index = selected_screen.unwrap_or(0);
             if self.screen_capture.is_none() {
-                match ScreenCapture::new(screen_index) {
+                match ScreenCapture::new(screen_index, self.pacing.clone(), self.frame_budget.clone()) {
                     Ok(mut capture) => {
                         if let Err(e) = capture.start() {
                             error!("Failed to start screen capture (screen_index={}): {}", screen_index, e);
@@ -694,14 +3283,38 @@ impl DiscordRecorderApp {
             self.webcam_capture = None;
         }
 
+        self.clip_events.clear();
+        self.ffmpeg_log.clear_stats();
+        self.drop_warning_shown = false;
+        self.watched_screen = selected_screen;
+        self.watched_display = include_video.then(|| crate::video::display_geometry(selected_screen)).flatten();
+        let watch_clicks = options.click_highlight.is_some();
+        let zoomed = options.cursor_zoom.is_some() && !options.piped_screen;
+        let burn_in_origin = (options.piped_screen && config_snapshot.burn_in_annotations)
+            .then(|| options.capture_region.map_or((0, 0), |(x, y, _, _)| (x, y)));
         self.video_encoder = Some(VideoEncoder::new(options)?);
         if let Some(encoder) = self.video_encoder.as_mut() {
+            encoder.set_log(self.ffmpeg_log.clone());
+            if let Some(origin) = burn_in_origin {
+                encoder.add_frame_effect(annotations::burn_in_effect(Arc::clone(&self.annotations), origin));
+            }
             if let Err(e) = encoder.start() {
                 error!("Failed to start encoder: {}", e);
                 self.video_encoder = None;
                 return Err(e);
             }
         }
+        if watch_clicks && zoomed {
+            self.toasts.info("Click highlighting is off while zooming around the cursor");
+        } else if watch_clicks {
+            match ClickWatcher::start() {
+                Ok(watcher) => self.click_watcher = Some(watcher),
+                Err(e) => {
+                    warn!("Click highlighting unavailable: {:#}", e);
+                    self.toasts.error(format!("Can't highlight clicks: {:#}", e));
+                }
+            }
+        }
 
         if include_audio {
             let device_name = audio_device_opt
@@ -713,12 +3326,14 @@ impl DiscordRecorderApp {
                 Ok(mut recorder) => {
                     if let Err(err) = recorder.start() {
                         error!("Failed to start audio monitor: {}", err);
+                        self.toasts.error(format!("Audio level meter unavailable: {}", err));
                     } else {
                         self.audio_recorder = Some(recorder);
                     }
                 }
                 Err(err) => {
                     error!("Failed to initialize audio monitor: {}", err);
+                    self.toasts.error(format!("Audio level meter unavailable: {}", err));
                 }
             }
         }
@@ -726,7 +3341,7 @@ impl DiscordRecorderApp {
         {
             let mut state = self.state.lock().unwrap();
             state.is_recording = true;
-            state.is_paused = false;
+            state.start_timer(Instant::now());
             state.config = config_snapshot;
         }
 
@@ -734,20 +3349,150 @@ impl DiscordRecorderApp {
     }
 
     fn stop_recording(&mut self) -> Result<()> {
-        let mut state = self.state.lock().unwrap();
-
-        if !state.is_recording {
+        if self.state.lock().unwrap().segment_switch.is_some() {
+            // The worker has the encoder; stop once it hands it back
+            self.stop_after_segment_switch = true;
             return Ok(());
         }
+        let clip_events = std::mem::take(&mut self.clip_events);
+        self.save_marker_note();
+        let (log_clips, upload, post_steps, thumbnails, bumpers, subtitles) = {
+            let mut state = self.state.lock().unwrap();
+
+            if !state.is_recording {
+                return Ok(());
+            }
+
+            info!("Stopping recording");
+            state.is_recording = false;
+            state.stop_timer();
+            state.is_finalizing = self.video_encoder.is_some();
+            state.last_outputs = None;
+            let post_steps = if state.config.post_steps_enabled { state.config.post_steps.clone() } else { Vec::new() };
+            let thumbnails = (state.thumbnail_on_stop, state.contact_sheet_on_stop);
+            state.last_thumbnail = None;
+            // Same codec settings as the recording in case the clips need re-encoding
+            let bumpers = state.config.bumpers.is_active().then(|| state.config.clone());
+            let subtitles = (state.config.marker_subtitles, state.config.embed_marker_subtitles);
+            (state.config.log_clip_timestamps, state.config.upload.clone(), post_steps, thumbnails, bumpers, subtitles)
+        };
 
-        info!("Stopping recording");
-        state.is_recording = false;
-        state.is_paused = false;
+        // Waiting on ffmpeg can take several seconds, so finalize off the UI thread
+        if let Some(mut encoder) = self.video_encoder.take() {
+            let state = Arc::clone(&self.state);
+            let log = self.ffmpeg_log.clone();
+            let toasts = self.toasts.clone();
+            runtime_handle().spawn_blocking(move || {
+                let result = encoder.stop();
+                let outputs = encoder.outputs().cloned();
+                let duration = encoder.elapsed();
+                for path in outputs.iter().flat_map(|o| o.paths()) {
+                    match std::fs::metadata(path) {
+                        Ok(meta) if meta.len() > 0 => {}
+                        Ok(_) => log.push(LogLevel::Error, format!("{} is empty; see the ffmpeg messages above", path.display())),
+                        Err(_) => log.push(LogLevel::Error, format!("{} was not written", path.display())),
+                    }
+                }
+                if let (Ok(()), Some(outputs)) = (result.as_ref(), outputs.as_ref()) {
+                    if let Err(e) = postprocess::write_chapters(
+                        encoder.ffmpeg_path(),
+                        outputs,
+                        encoder.markers(),
+                        encoder.elapsed(),
+                    ) {
+                        error!("Failed to write chapter markers: {}", e);
+                        toasts.error(format!("Couldn't write chapter markers: {}", e));
+                    }
+                    let (subtitle_format, embed_subtitles) = subtitles;
+                    if let Err(e) = postprocess::write_marker_subtitles(
+                        encoder.ffmpeg_path(),
+                        outputs,
+                        encoder.markers(),
+                        encoder.elapsed(),
+                        subtitle_format,
+                        embed_subtitles,
+                    ) {
+                        error!("Failed to write marker subtitles: {}", e);
+                        toasts.error(format!("Couldn't write marker subtitles: {}", e));
+                    }
+                    if log_clips {
+                        if let Err(e) = postprocess::write_clip_log(outputs, &clip_events) {
+                            error!("Failed to write clipping log: {}", e);
+                            toasts.error(format!("Couldn't write clipping log: {}", e));
+                        }
+                    }
+                }
+                let ffmpeg_path = encoder.ffmpeg_path().to_string();
+                drop(encoder);
+
+                let saved = {
+                    let mut state = state.lock().unwrap();
+                    state.is_finalizing = false;
+                    match result {
+                        Ok(()) => {
+                            info!("Recording finalized: {:?}", outputs);
+                            toasts.info("Recording saved");
+                            if state.copy_path_on_stop {
+                                state.pending_clipboard = outputs.as_ref().and_then(|o| o.clipboard_text());
+                            }
+                            state.last_outputs = outputs.clone();
+                            state.last_duration = Some(duration);
+                            outputs
+                        }
+                        Err(e) => {
+                            error!("Failed to finalize recording: {}", e);
+                            toasts.error(format!("Recording may be incomplete: {}", e));
+                            None
+                        }
+                    }
+                };
 
-        if let Some(encoder) = &mut self.video_encoder {
-            encoder.stop()?;
+                // After the state is released: steps may transcode for a while
+                // and shouldn't hold up the next recording
+                if let Some(outputs) = saved {
+                    let video = [&outputs.combined, &outputs.video_only, &outputs.webcam]
+                        .into_iter()
+                        .flatten()
+                        .find(|path| OutputContainer::from_path(path).is_some());
+                    if let (true, Some(video)) = (thumbnails.0, video) {
+                        match postprocess::write_thumbnails(&ffmpeg_path, video, duration, thumbnails.1) {
+                            Ok(thumbnail) => state.lock().unwrap().last_thumbnail = Some(thumbnail),
+                            Err(e) => {
+                                error!("Failed to write thumbnails: {:#}", e);
+                                toasts.error(format!("Couldn't write the thumbnail: {:#}", e));
+                            }
+                        }
+                    }
+                    if let (Some(config), Some(video)) = (bumpers.as_ref(), video) {
+                        let toast = toasts.progress("Adding intro and outro…");
+                        match postprocess::add_bumpers(
+                            &ffmpeg_path,
+                            video,
+                            config.bumpers.intro_path(),
+                            config.bumpers.outro_path(),
+                            config,
+                        ) {
+                            Ok(path) => toasts.finish(toast, ToastKind::Info, format!("Saved {}", path.display())),
+                            Err(e) => {
+                                error!("Failed to add intro/outro: {:#}", e);
+                                toasts.finish(toast, ToastKind::Error, format!("Couldn't add the intro/outro: {:#}", e));
+                            }
+                        }
+                    }
+                    for failure in postprocess::run_post_steps(&post_steps, &outputs, &log) {
+                        toasts.error(failure);
+                    }
+                    upload::spawn_uploads(&upload, outputs.paths().into_iter().cloned().collect(), toasts.clone());
+                }
+            });
         }
 
+        self.click_watcher = None;
+        self.watched_display = None;
+        self.last_click = None;
+
+        // The monitor reads from the recorder's tap, so it goes first
+        self.audio_monitor = None;
         if let Some(recorder) = &mut self.audio_recorder {
             let _ = recorder.stop();
         }
@@ -762,112 +3507,397 @@ impl DiscordRecorderApp {
             let _ = capture.stop();
         }
         self.webcam_capture = None;
-        self.video_encoder = None;
 
-        // Restore previews to prior state
-        {
-            let state = self.state.lock().unwrap();
-            if state.record_video && self.screen_capture.is_none() {
-                if let Some(idx) = state.selected_screen {
-                    if let Ok(mut cap) = ScreenCapture::new(idx) { let _ = cap.start(); self.screen_capture = Some(cap); }
-                }
-            }
-            if state.record_webcam && self.webcam_capture.is_none() {
-                let name = state.selected_webcam.clone().unwrap_or_else(|| "Default Webcam".to_string());
-                if let Ok(mut cap) = WebcamCapture::new(&name) { let _ = cap.start(); self.webcam_capture = Some(cap); }
-            }
-        }
+        // Previews are restored by ensure_capture_state on the next frame
+        self.active_screen_index = None;
+        self.active_webcam_name = None;
 
         Ok(())
     }
 }
 
-impl eframe::App for DiscordRecorderApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        ctx.set_style(discord_style());
+impl eframe::App for DiscordRecorderApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // The OS theme may have changed while another window had focus
+        let focused = ctx.input(|i| i.focused);
+        if focused && !self.was_focused {
+            self.resolved_theme = self.state.lock().unwrap().discord_theme.resolve();
+        }
+        self.was_focused = focused;
+        ctx.set_style(discord_style(self.resolved_theme));
+
+        let hotkey_triggers = self.handle_hotkeys(ctx);
+        self.update_mic_gate(ctx);
+        self.update_audio_test();
+        self.update_monitor();
+        self.update_click_highlight(ctx);
+        self.check_dropped_frames();
+        self.poll_segment_switch();
+        self.poll_trim_probe();
+        self.check_display();
+        self.check_config_file();
+        self.handle_dropped_files(ctx);
+        {
+            let state = self.state.lock().unwrap();
+            self.pacing.set(state.frame_rate, state.preview_fps_cap);
+            self.frame_budget.set_limit_mb(state.frame_buffer_limit_mb);
+        }
+        self.ensure_capture_state();
+        self.check_auto_stop(ctx);
+
+        if let Some((_, shown_at)) = self.status_message.as_ref() {
+            match STATUS_MESSAGE_DURATION.checked_sub(shown_at.elapsed()) {
+                Some(remaining) => ctx.request_repaint_after(remaining),
+                None => self.status_message = None,
+            }
+        }
+
+        if self.state.lock().unwrap().recording_started.is_some() {
+            // Tick the recording timer
+            ctx.request_repaint_after(Duration::from_millis(500));
+        }
 
-        let hotkey_triggers = self.handle_hotkeys(ctx);
-        self.ensure_capture_state();
+        if self.state.lock().unwrap().is_finalizing {
+            // Keep polling so the UI notices when the background finalize completes
+            ctx.request_repaint_after(Duration::from_millis(200));
+        }
 
-        let audio_gain_db = { self.state.lock().unwrap().audio_gain_db };
-        let audio_gain_linear = 10f32.powf(audio_gain_db / 20.0);
+        let (audio_gain_db, audio_balance, limiter, show_spectrum, mic_open) = {
+            let state = self.state.lock().unwrap();
+            let mic_open = state.mic_gate.mic_open(self.talk_key_held);
+            let limiter = state
+                .limiter_enabled
+                .then_some((state.limiter_threshold_db, state.limiter_makeup_db));
+            (state.audio_gain_db, state.audio_balance, limiter, state.show_spectrum, mic_open)
+        };
+        // A closed push-to-talk gate records silence, so meter it as such
+        let audio_gain_linear = if mic_open { 10f32.powf(audio_gain_db / 20.0) } else { 0.0 };
 
         let mut toggle_record_click = false;
         let mut toggle_pause_click = false;
         let mut toggle_webcam_click = false;
+        let mut add_marker_click = false;
+        let mut marker_note_done = false;
+        let mut snapshot_click = false;
+        let mut scene_request = None;
+        let current_scene = self.current_scene();
+        let mut quick_share_request = None;
+        let mut mp3_request = None;
+        let mut trim_request = None;
+        let mut merge_request = None;
 
         TopBottomPanel::top("controls_panel").show(ctx, |ui| {
             let mut state = self.state.lock().unwrap();
 
             ui.horizontal_centered(|ui| {
                 let record_label = if state.is_recording {
-                    "⏹ Stop Recording"
+                    tr("record.stop")
                 } else {
-                    "⏺ Start Recording"
+                    tr("record.start")
                 };
                 let record_color = if state.is_recording {
                     Color32::from_rgb(240, 71, 71)
                 } else {
                     Color32::from_rgb(35, 165, 90)
                 };
-                if ui
-                    .add_sized(
-                        [150.0, 44.0],
-                        egui::Button::new(RichText::new(record_label).size(16.0))
-                            .fill(record_color)
-                            .corner_radius(10),
-                    )
-                    .clicked()
-                {
+                let record_button = egui::Button::new(RichText::new(record_label).size(16.0))
+                    .fill(record_color)
+                    .corner_radius(10)
+                    .min_size(vec2(150.0, 44.0));
+                let record_response = ui.add_enabled(!state.is_finalizing, record_button);
+                let record_name = if state.is_recording { "a11y.record.stop" } else { "a11y.record.start" };
+                accessible(&record_response, WidgetType::Button, record_name, Some(state.is_recording));
+                if record_response.clicked() {
                     toggle_record_click = true;
                 }
 
-                let pause_enabled = state.is_recording;
-                let pause_label = if state.is_paused { "▶ Resume" } else { "⏸ Pause" };
+                let can_pause = self
+                    .video_encoder
+                    .as_ref()
+                    .is_some_and(|encoder| encoder.can_pause());
+                let switching = state.segment_switch.is_some();
+                let pause_enabled = state.is_recording && can_pause && !switching;
+                let pause_label = if state.is_paused { tr("record.resume") } else { tr("record.pause") };
                 let pause_button = egui::Button::new(pause_label).min_size(vec2(120.0, 40.0));
-                if ui.add_enabled(pause_enabled, pause_button).clicked() && pause_enabled
+                let pause_response = ui.add_enabled(pause_enabled, pause_button);
+                let pause_name = if state.is_paused { "a11y.record.resume" } else { "a11y.record.pause" };
+                accessible(&pause_response, WidgetType::Button, pause_name, Some(state.is_paused));
+                if state.is_recording && !can_pause && !switching {
+                    pause_response.clone().on_disabled_hover_text(PAUSE_UNSUPPORTED);
+                }
+                if pause_response.clicked() && pause_enabled
                 {
                     toggle_pause_click = true;
                 }
 
+                let marker_button = egui::Button::new(tr("record.marker")).min_size(vec2(100.0, 40.0));
+                let marker_response = ui.add_enabled(state.is_recording, marker_button);
+                accessible(&marker_response, WidgetType::Button, "a11y.record.marker", None);
+                if marker_response.clicked() {
+                    add_marker_click = true;
+                }
+                if let Some(note) = self.marker_note.as_mut() {
+                    let note_edit = ui.add(
+                        egui::TextEdit::singleline(&mut note.text)
+                            .desired_width(180.0)
+                            .hint_text("Note for this marker (Enter)"),
+                    );
+                    accessible(&note_edit, WidgetType::TextEdit, "a11y.marker_note", None);
+                    if std::mem::take(&mut note.focus) {
+                        note_edit.request_focus();
+                    }
+                    if note_edit.lost_focus() {
+                        marker_note_done = true;
+                    }
+                }
+
                 let webcam_label = if state.record_webcam {
-                    "📷 Webcam On"
+                    tr("webcam.on")
                 } else {
-                    "📷 Webcam Off"
+                    tr("webcam.off")
                 };
-                if ui
-                    .add_sized([140.0, 40.0], egui::Button::new(webcam_label))
-                    .clicked()
-                {
+                let webcam_response = ui.add_sized([140.0, 40.0], egui::Button::new(webcam_label));
+                accessible(&webcam_response, WidgetType::Button, "a11y.webcam", Some(state.record_webcam));
+                if webcam_response.clicked() {
                     toggle_webcam_click = true;
                 }
 
-                if ui
-                    .add_sized([120.0, 40.0], egui::Button::new("⚙ Settings"))
-                    .clicked()
-                {
+                let scene_text = current_scene
+                    .and_then(|i| state.config.scenes.get(i))
+                    .map_or(tr("scene.custom"), |scene| scene.name.as_str());
+                ui.add_enabled_ui(!state.is_recording && !state.is_finalizing, |ui| {
+                    let scene_response = egui::ComboBox::from_id_salt("scene_select")
+                        .selected_text(scene_text)
+                        .width(130.0)
+                        .show_ui(ui, |ui| {
+                            for (index, scene) in state.config.scenes.iter().enumerate() {
+                                if ui.selectable_label(current_scene == Some(index), &scene.name).clicked() {
+                                    scene_request = Some(index);
+                                }
+                            }
+                        })
+                        .response
+                        .on_hover_text(tr("scene.tooltip"));
+                    let scene_name = format!("{}: {}", tr("a11y.scene"), scene_text);
+                    scene_response.widget_info(|| egui::WidgetInfo::labeled(WidgetType::ComboBox, true, &scene_name));
+                });
+
+                let snapshot_response = ui
+                    .add_sized([40.0, 40.0], egui::Button::new("📸"))
+                    .on_hover_text(tr("webcam.snapshot"));
+                accessible(&snapshot_response, WidgetType::Button, "webcam.snapshot", None);
+                if snapshot_response.clicked() {
+                    snapshot_click = true;
+                }
+
+                let settings_response = ui.add_sized([120.0, 40.0], egui::Button::new(tr("settings.button")));
+                accessible(&settings_response, WidgetType::Button, "a11y.settings", None);
+                if settings_response.clicked() {
                     state.show_settings = true;
                 }
 
-                let status_text = if state.is_recording {
+                let shortcuts_response = ui
+                    .add_sized([40.0, 40.0], egui::Button::new("?"))
+                    .on_hover_text(format!("Keyboard shortcuts ({})", SHORTCUT_HELP_KEY.name()));
+                accessible(&shortcuts_response, WidgetType::Button, "a11y.shortcuts", Some(self.show_shortcuts));
+                if shortcuts_response.clicked() {
+                    self.show_shortcuts = !self.show_shortcuts;
+                }
+
+                let switch_status = state.segment_switch.map(SegmentSwitch::status);
+                let status_text = if let Some((text, _)) = switch_status {
+                    tr(text)
+                } else if state.is_recording {
                     if state.is_paused {
-                        "⏸ Paused"
+                        tr("status.paused")
                     } else {
-                        "● REC"
+                        tr("status.recording")
                     }
+                } else if state.is_finalizing {
+                    tr("status.finalizing")
                 } else {
-                    "● IDLE"
+                    tr("status.idle")
                 };
-                let status_color = if state.is_recording {
+                let status_color = if switch_status.is_some() {
+                    Color32::from_rgb(255, 180, 0)
+                } else if state.is_recording {
                     if state.is_paused {
                         Color32::from_rgb(255, 180, 0)
                     } else {
                         Color32::from_rgb(240, 71, 71)
                     }
+                } else if state.is_finalizing {
+                    Color32::from_rgb(255, 180, 0)
                 } else {
                     Color32::from_rgb(116, 127, 141)
                 };
-                ui.colored_label(status_color, RichText::new(status_text).size(14.0));
+                let status_name = if let Some((_, name)) = switch_status {
+                    name
+                } else if state.is_recording {
+                    if state.is_paused { "a11y.status.paused" } else { "a11y.status.recording" }
+                } else if state.is_finalizing {
+                    "a11y.status.finalizing"
+                } else {
+                    "a11y.status.idle"
+                };
+                let status_response = ui.colored_label(status_color, RichText::new(status_text).size(14.0));
+                accessible(&status_response, WidgetType::Label, status_name, None);
+                if let Some(elapsed) = state.recording_elapsed(Instant::now()) {
+                    ui.colored_label(status_color, RichText::new(recording_timer_label(elapsed)).monospace().size(14.0))
+                        .on_hover_text("Recorded time, pauses excluded");
+                }
+
+                ui.separator();
+                let auto_stop_response = ui
+                    .checkbox(&mut state.config.auto_stop, "⏱ Stop after")
+                    .on_hover_text("Stops the recording by itself after this long; can be changed while recording");
+                accessible(&auto_stop_response, WidgetType::Checkbox, "a11y.auto_stop", Some(state.config.auto_stop));
+                if auto_stop_response.changed() {
+                    if let Err(e) = state.save_config() {
+                        error!("Failed to save auto-stop: {}", e);
+                    }
+                }
+                let duration_edit = ui.add_enabled(
+                    state.config.auto_stop,
+                    egui::TextEdit::singleline(&mut self.auto_stop_text).desired_width(64.0).hint_text("HH:MM:SS"),
+                );
+                accessible(&duration_edit, WidgetType::TextEdit, "a11y.auto_stop.duration", None);
+                if duration_edit.lost_focus() {
+                    let secs = postprocess::parse_timestamp(&self.auto_stop_text)
+                        .and_then(|d| u32::try_from(d.as_secs()).ok())
+                        .filter(|secs| (1..=config::AUTO_STOP_MAX_SECS).contains(secs));
+                    if let Some(secs) = secs.filter(|&secs| secs != state.config.auto_stop_after_secs) {
+                        state.config.auto_stop_after_secs = secs;
+                        if let Err(e) = state.save_config() {
+                            error!("Failed to save auto-stop duration: {}", e);
+                        }
+                    }
+                    self.auto_stop_text = auto_stop_label(state.config.auto_stop_after_secs);
+                }
+                let countdown = if !state.config.auto_stop {
+                    "Off".to_string()
+                } else if state.is_recording {
+                    let after = Duration::from_secs(u64::from(state.config.auto_stop_after_secs));
+                    let elapsed = self.video_encoder.as_ref().map_or(Duration::ZERO, |e| e.elapsed());
+                    format!("{} left", postprocess::format_timestamp(after.saturating_sub(elapsed)))
+                } else {
+                    String::new()
+                };
+                ui.label(RichText::new(countdown).color(Color32::from_rgb(116, 127, 141)));
+
+                if let Some(outputs) = state.last_outputs.as_ref() {
+                    match state.last_thumbnail.as_ref() {
+                        Some(path) => {
+                            if self.thumbnail_texture.as_ref().is_none_or(|(loaded, _)| loaded != path) {
+                                self.thumbnail_texture = Some((path.clone(), load_thumbnail(ui.ctx(), path)));
+                            }
+                            if let Some((_, Some(texture))) = &self.thumbnail_texture {
+                                let size = texture.size_vec2();
+                                let height = 40.0;
+                                ui.image((texture.id(), egui::vec2(size.x * height / size.y.max(1.0), height)));
+                            }
+                        }
+                        None => self.thumbnail_texture = None,
+                    }
+                    for path in outputs.paths() {
+                        ui.label(RichText::new(format!("Saved: {}", path.display())).size(12.0));
+                    }
+                    let mut copied = None;
+                    match outputs.paths().as_slice() {
+                        [] => {}
+                        [single] => {
+                            if ui.small_button("📋 Copy path").clicked() {
+                                copied = Some(single.display().to_string());
+                            }
+                        }
+                        several => {
+                            ui.menu_button("📋 Copy path", |ui| {
+                                if let Some(folder) = outputs.clipboard_text() {
+                                    if ui.button("Folder").clicked() {
+                                        copied = Some(folder);
+                                    }
+                                }
+                                for path in several {
+                                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                                    if ui.button(name).clicked() {
+                                        copied = Some(path.display().to_string());
+                                    }
+                                }
+                            });
+                        }
+                    }
+                    if let Some(text) = copied {
+                        ui.ctx().copy_text(text);
+                        self.toasts.info("Copied!");
+                    }
+
+                    let share_source = outputs.combined.as_ref().or(outputs.video_only.as_ref());
+                    let exporting = self.exporting.load(Ordering::Relaxed);
+                    let share_button = ui
+                        .add_enabled(share_source.is_some() && !exporting, egui::Button::new("📤 Quick share").small())
+                        .on_hover_text("Save a copy under 24 MB as <name>.share.mp4")
+                        .on_disabled_hover_text(if exporting { "Another copy is being made" } else { "No video in the last recording" });
+                    if let (true, Some(source), Some(duration)) = (share_button.clicked(), share_source, state.last_duration) {
+                        quick_share_request = Some((source.clone(), duration));
+                    }
+                    let trim_source = share_source.or(outputs.audio_only.as_ref());
+                    if ui
+                        .add_enabled(trim_source.is_some() && !exporting, egui::Button::new("✂ Trim").small())
+                        .on_hover_text("Cut the start or end off a copy of the recording")
+                        .clicked()
+                    {
+                        trim_request = trim_source.map(|source| (source.clone(), state.last_duration));
+                    }
+                    if let (Some(video), Some(audio)) = (outputs.video_only.as_ref(), outputs.audio_only.as_ref()) {
+                        if ui
+                            .add_enabled(!exporting, egui::Button::new("🔗 Merge").small())
+                            .on_hover_text("Mux the separate video and audio into one file; the originals are kept")
+                            .clicked()
+                        {
+                            merge_request = Some((video.clone(), audio.clone()));
+                        }
+                    }
+                    let flac = outputs.audio_only.as_ref().filter(|path| path.extension().is_some_and(|ext| ext == "flac"));
+                    if let Some(flac) = flac {
+                        ui.add_enabled_ui(!exporting, |ui| {
+                            ui.menu_button("🎵 MP3", |ui| {
+                                for bitrate in postprocess::MP3_BITRATES {
+                                    if ui.button(format!("{} kbps", bitrate)).clicked() {
+                                        mp3_request = Some((flac.clone(), bitrate));
+                                        ui.close();
+                                    }
+                                }
+                            })
+                            .response
+                            .on_hover_text("Save an MP3 copy of the FLAC audio; the FLAC is kept");
+                        });
+                    }
+                }
+
+                if self.talk_key_held {
+                    match state.mic_gate {
+                        MicGateMode::PushToTalk => {
+                            ui.colored_label(Color32::from_rgb(87, 242, 135), RichText::new("🎙 TALK").size(14.0).strong());
+                        }
+                        MicGateMode::PushToMute => {
+                            ui.colored_label(Color32::from_rgb(240, 71, 71), RichText::new("🔇 MUTED").size(14.0).strong());
+                        }
+                        MicGateMode::Off => {}
+                    }
+                }
+
+                if self.clip_latched
+                    && ui
+                        .button(RichText::new("⚠ CLIP").color(Color32::from_rgb(240, 71, 71)).size(14.0).strong())
+                        .on_hover_text("The input reached 0 dBFS. Click to acknowledge")
+                        .clicked()
+                {
+                    self.clip_latched = false;
+                }
+
+                if let Some((message, _)) = self.status_message.as_ref() {
+                    ui.label(RichText::new(message).size(12.0));
+                }
             });
         });
 
@@ -875,26 +3905,66 @@ impl eframe::App for DiscordRecorderApp {
             .screen_capture
             .as_ref()
             .and_then(|capture| capture.get_latest_frame());
+        let background_blur = self.state.lock().unwrap().background_blur;
         let webcam_frame_opt = self
             .webcam_capture
             .as_ref()
-            .and_then(|capture| capture.get_latest_frame());
+            .and_then(|capture| capture.get_latest_frame())
+            .map(|frame| if background_blur.enabled { webcam::blur_background(&frame, &background_blur) } else { frame });
+        let now = Instant::now();
+        if screen_frame_opt.is_some() {
+            self.screen_fps.tick(now);
+        }
+        if webcam_frame_opt.is_some() {
+            self.webcam_fps.tick(now);
+        }
 
         // Update textures; optionally composite webcam over screen for preview when enabled
         let enable_overlay = { self.state.lock().unwrap().enable_preview_overlay };
+        // Eases the overlay in and out rather than popping it on and off
+        let overlay_fade = ctx.animate_bool_with_time(
+            egui::Id::new("webcam_overlay_fade"),
+            enable_overlay && self.webcam_capture.is_some(),
+            OVERLAY_FADE.as_secs_f32(),
+        );
+        if overlay_fade == 0.0 && self.webcam_capture.is_none() {
+            self.last_webcam_frame = None;
+        }
 
         #[cfg(feature = "webcam")]
         {
-            if enable_overlay {
-                if let (Some(screen_frame), Some(webcam_frame)) = (screen_frame_opt.as_ref(), webcam_frame_opt.as_ref()) {
-                    let (pos, size, opacity) = {
+            if overlay_fade > 0.0 {
+                // Webcams deliver fewer frames than the screen; reuse the last
+                // one in between, and while fading out after the camera stopped
+                let webcam_frame = webcam_frame_opt.as_ref().or(self.last_webcam_frame.as_ref());
+                if let (Some(screen_frame), Some(webcam_frame)) = (screen_frame_opt.as_ref(), webcam_frame) {
+                    let (pos, size, opacity, border, chroma_key, layout) = {
                         let st = self.state.lock().unwrap();
-                        (st.overlay_position, st.overlay_size, st.overlay_opacity)
+                        (st.overlay_position, st.overlay_size, st.overlay_opacity, st.overlay_border, st.chroma_key, st.overlay_layout)
                     };
-                    let mut composed = screen_frame.clone();
                     let mut overlay = WebcamOverlay::new(pos.0, pos.1, size.0, size.1);
-                    overlay.set_opacity(opacity);
-                    overlay.overlay_onto(webcam_frame, &mut composed);
+                    overlay.set_opacity(overlay_fade_opacity(opacity, overlay_fade));
+                    let [r, g, b] = border.color;
+                    overlay.set_border_color([r, g, b, 255]);
+                    overlay.set_border_width(border.width);
+                    let composed = match layout {
+                        OverlayLayout::WebcamOver => {
+                            let mut composed = screen_frame.clone();
+                            overlay.set_chroma_key(Some(chroma_key));
+                            overlay.overlay_onto(webcam_frame, &mut composed);
+                            composed
+                        }
+                        OverlayLayout::SideBySide => webcam::side_by_side(screen_frame, webcam_frame),
+                        OverlayLayout::ScreenOver => {
+                            let mut composed = webcam_frame.resize_to_fill(
+                                screen_frame.width(),
+                                screen_frame.height(),
+                                image::imageops::FilterType::Triangle,
+                            );
+                            overlay.overlay_onto(screen_frame, &mut composed);
+                            composed
+                        }
+                    };
                     update_texture(ctx, &mut self.screen_preview_texture, &composed, "screen_preview");
                 } else if let Some(screen_frame) = screen_frame_opt.as_ref() {
                     update_texture(ctx, &mut self.screen_preview_texture, screen_frame, "screen_preview");
@@ -912,42 +3982,62 @@ impl eframe::App for DiscordRecorderApp {
             }
         }
 
-        if let Some(webcam_frame) = webcam_frame_opt.as_ref() {
-            update_texture(ctx, &mut self.webcam_preview_texture, webcam_frame, "webcam_preview");
+        if let Some(webcam_frame) = webcam_frame_opt {
+            update_texture(ctx, &mut self.webcam_preview_texture, &webcam_frame, "webcam_preview");
+            self.last_webcam_frame = Some(webcam_frame);
         }
 
+        let mut clipped = false;
         if let Some(recorder) = self.audio_recorder.as_ref() {
-            let data = recorder.get_audio_data();
+            let mut data = recorder.get_audio_data();
             if recorder.is_recording() && !data.is_empty() {
                 let processor = AudioProcessor::new(recorder.get_sample_rate(), recorder.get_channels());
+                // Meter what will be recorded: balance, then the overall gain
+                let gains: Vec<f32> = if recorder.get_channels() >= 2 {
+                    audio::balance_gains(audio_balance).iter().map(|g| g * audio_gain_linear).collect()
+                } else {
+                    vec![audio_gain_linear]
+                };
+                processor.apply_channel_gains(&mut data, &gains);
+                if let Some((threshold_db, makeup_db)) = limiter {
+                    processor.apply_limiter(&mut data, threshold_db, audio::LIMITER_RELEASE_MS);
+                    let makeup = 10f32.powf(makeup_db / 20.0);
+                    data.iter_mut().for_each(|s| *s *= makeup);
+                }
+                // Checked per channel, since the mono mix can hide a clipped side
+                let channel_peak = data.iter().fold(0.0f32, |acc, s| acc.max(s.abs()));
+                clipped = channel_peak >= 1.0;
                 let mono = processor.mix_to_mono(&data);
+                self.waveform.extend_from_slice(&mono);
+                if show_spectrum {
+                    self.spectrum.update(&self.waveform, recorder.get_sample_rate());
+                }
                 let peak = mono
                     .iter()
                     .copied()
                     .map(f32::abs)
-                    .fold(0.0, f32::max)
-                    .clamp(0.0, 1.0)
-                    * audio_gain_linear;
+                    .fold(0.0, f32::max);
                 self.audio_level = self.audio_level * 0.8 + peak.clamp(0.0, 1.0) * 0.2;
             } else {
                 self.audio_level *= 0.95;
             }
         }
 
+        if clipped {
+            self.note_clipping();
+        }
+
         let mut refresh_requested = false;
         let show_settings = { self.state.lock().unwrap().show_settings };
         if show_settings {
             let mut open_flag = show_settings;
-            egui::Window::new("Settings")
+            egui::Window::new(tr("settings.title"))
+                .id(egui::Id::new("settings_window"))
                 .open(&mut open_flag)
                 .resizable(true)
                 .show(ctx, |ui| {
                     if let Some(action) = self.awaiting_hotkey {
-                        let action_name = match action {
-                            HotkeyAction::StartStop => "Start/Stop Recording",
-                            HotkeyAction::PauseResume => "Pause/Resume",
-                            HotkeyAction::ToggleWebcam => "Toggle Webcam",
-                        };
+                        let action_name = action.label();
                         ui.colored_label(Color32::from_rgb(255, 180, 0), format!(
                             "Waiting for new shortcut for {action_name}. Press desired keys or Esc to cancel."
                         ));
@@ -963,30 +4053,99 @@ impl eframe::App for DiscordRecorderApp {
             }
         }
 
+        if self.show_shortcuts {
+            self.draw_shortcuts(ctx);
+        }
+
+        if self.confirm_stop_open {
+            self.draw_confirm_stop(ctx);
+        }
+
+        if self.trim_dialog.is_some() {
+            self.draw_trim(ctx);
+        }
+
+        self.draw_merge_fallback(ctx);
+
+        let pending_clipboard = self.state.lock().unwrap().pending_clipboard.take();
+        if let Some(text) = pending_clipboard {
+            ctx.copy_text(text);
+            self.toasts.info("Copied path to the clipboard");
+        }
+
+        self.toasts.show(ctx);
+
         if refresh_requested {
             if let Err(e) = self.refresh_devices() {
                 error!("Failed to refresh devices: {}", e);
+                self.toasts.error(format!("Couldn't list devices: {}", e));
             } else {
                 self.ensure_capture_state();
             }
         }
 
+        TopBottomPanel::bottom("log_panel").resizable(true).show(ctx, |ui| {
+            self.draw_log_panel(ui);
+        });
+
+        if self.state.lock().unwrap().show_stats_panel {
+            TopBottomPanel::bottom("stats_panel").show(ctx, |ui| {
+                self.draw_stats_panel(ui);
+            });
+        }
+
+        let show_fps = { self.state.lock().unwrap().show_preview_fps };
+        if show_fps {
+            // Keep the counter falling to 0 when frames stop arriving
+            ctx.request_repaint_after(Duration::from_millis(250));
+        }
         CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Preview");
+            ui.horizontal(|ui| {
+                ui.heading(tr("heading.preview"));
+                let screen_dropped = self.screen_capture.as_ref().map_or(0, |c| c.dropped_frames());
+                let webcam_dropped = self.webcam_capture.as_ref().map_or(0, |c| c.dropped_frames());
+                let color = if screen_dropped + webcam_dropped > 0 {
+                    Color32::from_rgb(255, 180, 0)
+                } else {
+                    ui.visuals().weak_text_color()
+                };
+                ui.label(
+                    RichText::new(format!(
+                        "Dropped frames: screen {}, webcam {} · buffers {:.1} MiB",
+                        screen_dropped,
+                        webcam_dropped,
+                        self.frame_budget.used_bytes() as f64 / (1024.0 * 1024.0)
+                    ))
+                    .size(12.0)
+                    .color(color),
+                )
+                .on_hover_text("Preview frames discarded because the queue was full or the frame buffer memory limit was reached. A rising count means the machine or the UI can't keep up; lower the frame rate or preview cap");
+            });
             ui.separator();
 
+            if let Some(failure) = self.screen_capture.as_ref().and_then(|c| c.failure()) {
+                ui.colored_label(Color32::from_rgb(255, 180, 0), format!("⚠ {}", failure));
+            }
+            let previews_height = ui.available_height() * PREVIEW_HEIGHT_SHARE;
+            let split = { self.state.lock().unwrap().preview_split };
             if let Some(texture) = &self.screen_preview_texture {
                 let size = texture.size();
                 let tex_w = size[0] as f32;
                 let tex_h = size[1] as f32;
                 let avail = ui.available_size();
                 let max_w = avail.x.max(100.0);
-                let max_h = (avail.y * 0.6).max(100.0);
-                let zoom = { self.state.lock().unwrap().screen_zoom }.clamp(0.25, 4.0);
-                let fit_scale = (max_w / tex_w).min(max_h / tex_h).min(1.0);
-                let scale = (fit_scale * zoom).max(0.1);
-                let disp = vec2(tex_w * scale, tex_h * scale);
+                let max_h = (previews_height * split).max(100.0);
+                let zoom = {
+                    let state = self.state.lock().unwrap();
+                    effective_zoom(state.screen_zoom, state.screen_fit)
+                };
+                let sar = self.screen_capture.as_ref().map_or(1.0, |c| c.sample_aspect_ratio());
+                let scale = preview_scale(tex_w, tex_h, sar, max_w, max_h, zoom);
+                let disp = vec2(tex_w * scale.x, tex_h * scale.y);
                 let response = ui.image((texture.id(), disp));
+                if show_fps {
+                    draw_fps_badge(ui, response.rect, self.screen_fps.fps(now));
+                }
 
                 // Draw draggable/resizable overlay guides when enabled
                 let enable_overlay = { self.state.lock().unwrap().enable_preview_overlay };
@@ -994,11 +4153,67 @@ impl eframe::App for DiscordRecorderApp {
                     let rect = response.rect;
                     self.handle_overlay_interactions(ui, rect, scale, tex_w, tex_h);
                 }
+                // Registered after the overlay so region drags take priority
+                let (is_recording, region) = {
+                    let state = self.state.lock().unwrap();
+                    (state.is_recording, state.capture_region)
+                };
+                if !is_recording && !self.annotating {
+                    self.handle_region_interactions(ui, response.rect, scale, tex_w, tex_h);
+                }
+                // Registered last so drawing wins over the guides underneath
+                if self.annotating {
+                    self.handle_annotation_interactions(ui, response.rect, scale, tex_w, tex_h);
+                }
+                paint_annotations(ui, response.rect, scale, &self.annotations.lock().unwrap());
+
+                ui.horizontal(|ui| {
+                    ui.add_enabled_ui(!is_recording, |ui| {
+                        let label = if self.selecting_region { "Drag on the preview…" } else { "Select region" };
+                        if ui
+                            .selectable_label(self.selecting_region, label)
+                            .on_hover_text("Draw the part of the screen to record on the preview")
+                            .clicked()
+                        {
+                            self.selecting_region = !self.selecting_region;
+                            self.annotating = false;
+                            self.region_anchor = None;
+                        }
+                        if ui.add_enabled(region.is_some(), egui::Button::new("Reset to full screen")).clicked() {
+                            self.state.lock().unwrap().capture_region = None;
+                            self.selecting_region = false;
+                        }
+                    });
+                    match region {
+                        Some((x, y, w, h)) => ui.label(format!("Region: {}x{} at {},{}", w, h, x, y)),
+                        None => ui.label("Full screen"),
+                    };
+                });
+                ui.horizontal(|ui| {
+                    if ui
+                        .selectable_label(self.annotating, "🖊 Annotate")
+                        .on_hover_text("Draw on the preview, e.g. for a demo shared live. Burned into the recording only with piped screen capture")
+                        .clicked()
+                    {
+                        self.annotating = !self.annotating;
+                        self.selecting_region = false;
+                    }
+                    if self.annotating {
+                        for tool in AnnotationTool::ALL {
+                            ui.selectable_value(&mut self.annotation_tool, tool, tool.label());
+                        }
+                        ui.color_edit_button_srgb(&mut self.annotation_color);
+                    }
+                    let has_annotations = !self.annotations.lock().unwrap().is_empty();
+                    if ui.add_enabled(has_annotations, egui::Button::new("Clear")).clicked() {
+                        self.annotations.lock().unwrap().clear();
+                    }
+                });
             } else {
                 ui.label("No screen preview available");
             }
 
-            ui.separator();
+            self.draw_preview_splitter(ui, previews_height);
 
             if let Some(texture) = &self.webcam_preview_texture {
                 let size = texture.size();
@@ -1006,33 +4221,66 @@ impl eframe::App for DiscordRecorderApp {
                 let tex_h = size[1] as f32;
                 let avail = ui.available_size();
                 let max_w = avail.x.max(100.0);
-                let max_h = (avail.y * 0.3).max(80.0);
-                let zoom = { self.state.lock().unwrap().webcam_zoom }.clamp(0.25, 4.0);
-                let fit_scale = (max_w / tex_w).min(max_h / tex_h).min(1.0);
-                let scale = (fit_scale * zoom).max(0.1);
-                let disp = vec2(tex_w * scale, tex_h * scale);
-                ui.image((texture.id(), disp));
+                let max_h = (previews_height * (1.0 - split)).max(80.0);
+                let zoom = {
+                    let state = self.state.lock().unwrap();
+                    effective_zoom(state.webcam_zoom, state.webcam_fit)
+                };
+                let sar = self.webcam_capture.as_ref().map_or(1.0, |c| c.sample_aspect_ratio());
+                let scale = preview_scale(tex_w, tex_h, sar, max_w, max_h, zoom);
+                let disp = vec2(tex_w * scale.x, tex_h * scale.y);
+                let response = ui.image((texture.id(), disp));
+                if show_fps {
+                    draw_fps_badge(ui, response.rect, self.webcam_fps.fps(now));
+                }
             } else {
                 ui.label("No webcam preview available");
             }
+            match self.webcam_capture.as_ref().map(|c| c.status()) {
+                Some(WebcamStatus::Reconnecting { attempt }) => {
+                    ui.colored_label(
+                        Color32::from_rgb(255, 180, 0),
+                        format!("⟳ Webcam reconnecting… (attempt {} of {})", attempt, webcam::MAX_RECONNECT_ATTEMPTS),
+                    );
+                    ui.ctx().request_repaint_after(Duration::from_millis(500));
+                }
+                Some(WebcamStatus::Failed(message)) => {
+                    ui.colored_label(Color32::from_rgb(237, 66, 69), format!("⚠ {}", message));
+                }
+                _ => {}
+            }
 
             ui.separator();
-            ui.label("Audio Level");
-            ui.add(ProgressBar::new(self.audio_level.clamp(0.0, 1.0)).desired_width(200.0));
+            let (mut show_waveform, mut show_spectrum) = {
+                let state = self.state.lock().unwrap();
+                (state.show_waveform, state.show_spectrum)
+            };
+            ui.horizontal(|ui| {
+                ui.label("Audio Level");
+                if ui.checkbox(&mut show_waveform, "Waveform").changed() {
+                    self.state.lock().unwrap().show_waveform = show_waveform;
+                }
+                if ui.checkbox(&mut show_spectrum, "Spectrum").changed() {
+                    self.state.lock().unwrap().show_spectrum = show_spectrum;
+                }
+            });
+            if show_waveform {
+                draw_waveform(ui, &self.waveform);
+            } else {
+                ui.add(ProgressBar::new(self.audio_level.clamp(0.0, 1.0)).desired_width(200.0));
+            }
+            if show_spectrum {
+                draw_spectrum(ui, &self.spectrum);
+            }
 
             // Zoom controls
-            let mut screen_zoom = { self.state.lock().unwrap().screen_zoom };
-            let mut webcam_zoom = { self.state.lock().unwrap().webcam_zoom };
             ui.separator();
             ui.horizontal(|ui| {
-                ui.label("Screen zoom");
-                if ui.add(Slider::new(&mut screen_zoom, 0.25..=4.0)).changed() {
-                    self.state.lock().unwrap().screen_zoom = screen_zoom;
-                }
-                ui.label("Webcam zoom");
-                if ui.add(Slider::new(&mut webcam_zoom, 0.25..=4.0)).changed() {
-                    self.state.lock().unwrap().webcam_zoom = webcam_zoom;
-                }
+                let mut state = self.state.lock().unwrap();
+                let state = &mut *state;
+                zoom_controls(ui, "Screen zoom", &mut state.screen_zoom, &mut state.screen_fit);
+                ui.separator();
+                zoom_controls(ui, "Webcam zoom", &mut state.webcam_zoom, &mut state.webcam_fit);
             });
         });
 
@@ -1045,30 +4293,80 @@ impl eframe::App for DiscordRecorderApp {
         if toggle_webcam_click || hotkey_triggers.toggle_webcam {
             self.toggle_webcam_capture();
         }
+        if let Some((source, duration)) = quick_share_request {
+            self.start_quick_share(source, duration);
+        }
+        if let Some((source, bitrate)) = mp3_request {
+            self.start_mp3_conversion(source, bitrate);
+        }
+        if let Some((source, recorded)) = trim_request {
+            self.open_trim(source, recorded);
+        }
+        if let Some((video, audio)) = merge_request {
+            self.start_merge(video, audio, false);
+        }
+
+        if marker_note_done {
+            self.save_marker_note();
+        }
+        if add_marker_click || hotkey_triggers.add_marker {
+            self.add_marker();
+        }
+        if snapshot_click || hotkey_triggers.webcam_snapshot {
+            self.take_webcam_snapshot();
+        }
+        if let Some(index) = scene_request {
+            self.apply_scene(index);
+        } else if hotkey_triggers.next_scene {
+            self.next_scene();
+        }
     }
 }
 
-fn discord_style() -> egui::Style {
+/// Style for a resolved theme; `Auto` must be resolved by the caller.
+pub fn discord_style(theme: DiscordTheme) -> egui::Style {
     let mut style = egui::Style::default();
+    if theme == DiscordTheme::Light {
+        style.visuals = egui::Visuals::light();
+    }
 
     // Discord color palette
     style.visuals.widgets.inactive.bg_fill = Color32::from_rgb(88, 101, 242);
     style.visuals.widgets.hovered.bg_fill = Color32::from_rgb(71, 82, 196);
     style.visuals.widgets.active.bg_fill = Color32::from_rgb(58, 67, 159);
-
-    // Background colors
-    style.visuals.panel_fill = Color32::from_rgb(54, 57, 63);
-    style.visuals.extreme_bg_color = Color32::from_rgb(47, 49, 54);
-    style.visuals.code_bg_color = Color32::from_rgb(40, 42, 46);
-
-    // Text colors
     style.visuals.widgets.inactive.fg_stroke.color = Color32::from_rgb(255, 255, 255);
-    // Fixed: Added .widgets. to the path
-    style.visuals.widgets.noninteractive.fg_stroke.color = Color32::from_rgb(185, 187, 190);
 
-    // Window styling
-    style.visuals.window_fill = Color32::from_rgb(54, 57, 63);
-    style.visuals.window_stroke = Stroke::new(1.0, Color32::from_rgb(32, 34, 37));
+    match theme {
+        DiscordTheme::Light => {
+            style.visuals.panel_fill = Color32::from_rgb(255, 255, 255);
+            style.visuals.extreme_bg_color = Color32::from_rgb(242, 243, 245);
+            style.visuals.code_bg_color = Color32::from_rgb(235, 237, 239);
+            style.visuals.widgets.noninteractive.fg_stroke.color = Color32::from_rgb(46, 51, 56);
+            style.visuals.window_fill = Color32::from_rgb(255, 255, 255);
+            style.visuals.window_stroke = Stroke::new(1.0, Color32::from_rgb(227, 229, 232));
+        }
+        DiscordTheme::AMOLED => {
+            // Pure black lets OLED panels switch the pixels off; text and
+            // outlines are brighter than in the dark theme to stay readable
+            style.visuals.panel_fill = Color32::BLACK;
+            style.visuals.extreme_bg_color = Color32::BLACK;
+            style.visuals.faint_bg_color = Color32::from_rgb(14, 14, 16);
+            style.visuals.code_bg_color = Color32::from_rgb(16, 16, 18);
+            style.visuals.widgets.noninteractive.bg_fill = Color32::BLACK;
+            style.visuals.widgets.noninteractive.bg_stroke = Stroke::new(1.0, Color32::from_rgb(60, 62, 68));
+            style.visuals.widgets.noninteractive.fg_stroke.color = Color32::from_rgb(242, 243, 245);
+            style.visuals.window_fill = Color32::BLACK;
+            style.visuals.window_stroke = Stroke::new(1.0, Color32::from_rgb(60, 62, 68));
+        }
+        DiscordTheme::Dark | DiscordTheme::Auto => {
+            style.visuals.panel_fill = Color32::from_rgb(54, 57, 63);
+            style.visuals.extreme_bg_color = Color32::from_rgb(47, 49, 54);
+            style.visuals.code_bg_color = Color32::from_rgb(40, 42, 46);
+            style.visuals.widgets.noninteractive.fg_stroke.color = Color32::from_rgb(185, 187, 190);
+            style.visuals.window_fill = Color32::from_rgb(54, 57, 63);
+            style.visuals.window_stroke = Stroke::new(1.0, Color32::from_rgb(32, 34, 37));
+        }
+    }
 
     // Spacing
     style.spacing.item_spacing = egui::Vec2::new(8.0, 8.0);
@@ -1077,6 +4375,69 @@ fn discord_style() -> egui::Style {
     style
 }
 
+/// Preview opacity of the webcam overlay `fade` of the way (0 to 1) into
+/// fading in, eased at both ends and ending at the configured `target`.
+#[cfg_attr(not(feature = "webcam"), allow(dead_code))]
+pub fn overlay_fade_opacity(target: f32, fade: f32) -> f32 {
+    let t = fade.clamp(0.0, 1.0);
+    target.clamp(0.0, 1.0) * t * t * (3.0 - 2.0 * t)
+}
+
+/// Per-axis scale from texture pixels to preview points. `sar` is the source's
+/// sample (pixel) aspect ratio, width over height; it stretches the horizontal
+/// axis so non-square pixels display at their true shape. 1.0 is square.
+pub fn preview_scale(tex_w: f32, tex_h: f32, sar: f32, max_w: f32, max_h: f32, zoom: f32) -> egui::Vec2 {
+    let sar = if sar.is_finite() && sar > 0.0 { sar } else { 1.0 };
+    let fit_scale = (max_w / (tex_w * sar)).min(max_h / tex_h).min(1.0);
+    let scale = (fit_scale * zoom).max(0.1);
+    vec2(scale * sar, scale)
+}
+
+/// Zoom passed to `preview_scale`: 1.0 when fitting the window, otherwise
+/// the slider value within its range.
+pub fn effective_zoom(zoom: f32, fit: bool) -> f32 {
+    if fit { 1.0 } else { zoom.clamp(0.25, 4.0) }
+}
+
+/// Zoom slider with a reset button and a fit-to-window toggle that
+/// overrides it.
+fn zoom_controls(ui: &mut egui::Ui, label: &str, zoom: &mut f32, fit: &mut bool) {
+    ui.label(label);
+    ui.add_enabled(!*fit, Slider::new(zoom, 0.25..=4.0));
+    if ui
+        .add_enabled(*zoom != 1.0 && !*fit, egui::Button::new("Reset zoom").small())
+        .on_hover_text("Back to 100%")
+        .clicked()
+    {
+        *zoom = 1.0;
+    }
+    ui.toggle_value(fit, "Fit").on_hover_text("Always fit the preview to the window, ignoring the zoom");
+}
+
+/// Measured preview rate in the top-left corner of an image.
+fn draw_fps_badge(ui: &egui::Ui, image_rect: egui::Rect, fps: f32) {
+    let painter = ui.painter_at(image_rect);
+    let text = format!("{:.0} fps", fps);
+    let galley = painter.layout_no_wrap(text, egui::FontId::monospace(12.0), Color32::WHITE);
+    let badge = egui::Rect::from_min_size(image_rect.min + vec2(6.0, 6.0), galley.size() + vec2(8.0, 4.0));
+    painter.rect_filled(badge, 3.0, Color32::from_black_alpha(160));
+    painter.galley(badge.min + vec2(4.0, 2.0), galley, Color32::WHITE);
+}
+
+fn load_thumbnail(ctx: &egui::Context, path: &std::path::Path) -> Option<TextureHandle> {
+    match image::open(path) {
+        Ok(image) => {
+            let mut texture = None;
+            update_texture(ctx, &mut texture, &image, "last_thumbnail");
+            texture
+        }
+        Err(e) => {
+            warn!("Failed to load thumbnail {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
 fn update_texture(
     ctx: &egui::Context,
     texture: &mut Option<TextureHandle>,
@@ -1095,135 +4456,413 @@ fn update_texture(
 }
 
 impl DiscordRecorderApp {
-    fn handle_overlay_interactions(&mut self, ui: &mut egui::Ui, image_rect: egui::Rect, scale: f32, tex_w: f32, tex_h: f32) {
-        let (pos, size, opacity) = {
-            let st = self.state.lock().unwrap();
-            (st.overlay_position, st.overlay_size, st.overlay_opacity)
+    fn handle_overlay_interactions(&mut self, ui: &mut egui::Ui, image_rect: egui::Rect, scale: egui::Vec2, tex_w: f32, tex_h: f32) {
+        let mut st = self.state.lock().unwrap();
+        let (mut pos, mut size) = (st.overlay_position, st.overlay_size);
+        let stroke = Stroke::new(2.0, Color32::from_rgb(88, 101, 242));
+        let thirds = st.config.overlay_thirds_guides;
+        if thirds {
+            let guide = Stroke::new(1.0, Color32::from_white_alpha(90));
+            for i in 1..=2 {
+                let x = image_rect.left() + image_rect.width() * i as f32 / 3.0;
+                let y = image_rect.top() + image_rect.height() * i as f32 / 3.0;
+                ui.painter().line_segment([egui::pos2(x, image_rect.top()), egui::pos2(x, image_rect.bottom())], guide);
+                ui.painter().line_segment([egui::pos2(image_rect.left(), y), egui::pos2(image_rect.right(), y)], guide);
+            }
+        }
+        let view = PreviewView { ui, image_rect, scale, tex_w, tex_h };
+        if edit_preview_rect(view, "overlay", stroke, thirds, &mut self.overlay_drag, &mut pos, &mut size) {
+            (st.overlay_position, st.overlay_size) = (pos, size);
+        }
+    }
+
+    /// Separator between the screen and webcam previews that can be dragged
+    /// to share `previews_height` differently; the ratio is saved on release.
+    fn draw_preview_splitter(&mut self, ui: &mut egui::Ui, previews_height: f32) {
+        let (rect, response) = ui.allocate_exact_size(vec2(ui.available_width(), 10.0), egui::Sense::drag());
+        let response = response.on_hover_cursor(egui::CursorIcon::ResizeVertical);
+        let color = if response.hovered() || response.dragged() {
+            Color32::from_rgb(88, 101, 242)
+        } else {
+            ui.visuals().widgets.noninteractive.bg_stroke.color
         };
+        ui.painter().hline(rect.x_range(), rect.center().y, Stroke::new(2.0, color));
+
+        let mut state = self.state.lock().unwrap();
+        if response.dragged() {
+            state.preview_split = drag_split(state.preview_split, response.drag_delta().y, previews_height);
+        }
+        if response.drag_stopped() {
+            state.config.preview_split = state.preview_split;
+            if let Err(e) = state.save_config() {
+                error!("Failed to save preview split: {}", e);
+            }
+        }
+    }
 
-        let top_left = image_rect.min + egui::vec2(pos.0 as f32 * scale, pos.1 as f32 * scale);
-        let overlay_size = egui::vec2(size.0 as f32 * scale, size.1 as f32 * scale);
-        let overlay_rect = egui::Rect::from_min_size(top_left, overlay_size);
+    /// Starts an annotation where a drag on the screen preview begins and
+    /// extends it while the drag lasts.
+    fn handle_annotation_interactions(&mut self, ui: &mut egui::Ui, image_rect: egui::Rect, scale: egui::Vec2, tex_w: f32, tex_h: f32) {
+        let resp = ui.interact(image_rect, ui.make_persistent_id("annotate"), egui::Sense::drag());
+        let Some(pointer) = ui.input(|i| i.pointer.interact_pos()) else {
+            return;
+        };
+        let at = (
+            ((pointer.x - image_rect.min.x) / scale.x).clamp(0.0, tex_w),
+            ((pointer.y - image_rect.min.y) / scale.y).clamp(0.0, tex_h),
+        );
+        let mut annotations = self.annotations.lock().unwrap();
+        if resp.drag_started() {
+            annotations.push(Annotation::new(self.annotation_tool, at, self.annotation_color));
+        } else if resp.dragged() {
+            if let Some(annotation) = annotations.last_mut() {
+                annotation.drag_to(at);
+                ui.ctx().request_repaint();
+            }
+        }
+    }
 
-        // Draw border for visual feedback
-        let stroke = Stroke::new(2.0, Color32::from_rgb(88, 101, 242));
-        let tl = overlay_rect.left_top();
-        let tr = overlay_rect.right_top();
-        let bl = overlay_rect.left_bottom();
-        let br = overlay_rect.right_bottom();
-        ui.painter().line_segment([tl, tr], stroke);
-        ui.painter().line_segment([tr, br], stroke);
-        ui.painter().line_segment([br, bl], stroke);
-        ui.painter().line_segment([bl, tl], stroke);
-
-        // Draw resize handles
-        let handle_s = 10.0;
-        let corners = [
-            (ResizeHandle::NW, overlay_rect.left_top()),
-            (ResizeHandle::NE, overlay_rect.right_top()),
-            (ResizeHandle::SW, overlay_rect.left_bottom()),
-            (ResizeHandle::SE, overlay_rect.right_bottom()),
-        ];
-        for (handle, center) in corners {
-            let hr = egui::Rect::from_center_size(center, egui::vec2(handle_s, handle_s));
-            ui.painter().rect_filled(hr, 2.0, Color32::from_rgb(71, 82, 196));
-            let resp = ui.interact(hr, ui.make_persistent_id(format!("overlay_handle_{:?}", handle)), egui::Sense::click_and_drag());
-            if resp.drag_started() {
-                self.active_resize = Some(handle);
-            }
-        }
-
-        // Edge handles
-        let edges = [
-            (ResizeHandle::N, egui::pos2(overlay_rect.center().x, overlay_rect.top())),
-            (ResizeHandle::S, egui::pos2(overlay_rect.center().x, overlay_rect.bottom())),
-            (ResizeHandle::W, egui::pos2(overlay_rect.left(), overlay_rect.center().y)),
-            (ResizeHandle::E, egui::pos2(overlay_rect.right(), overlay_rect.center().y)),
-        ];
-        for (handle, center) in edges {
-            let size_vec = if matches!(handle, ResizeHandle::N | ResizeHandle::S) { egui::vec2(handle_s, handle_s * 0.6) } else { egui::vec2(handle_s * 0.6, handle_s) };
-            let hr = egui::Rect::from_center_size(center, size_vec);
-            ui.painter().rect_filled(hr, 1.0, Color32::from_rgb(71, 82, 196));
-            let resp = ui.interact(hr, ui.make_persistent_id(format!("overlay_edge_{:?}", handle)), egui::Sense::click_and_drag());
+    /// Draws the capture region on the screen preview. While selecting, a drag
+    /// anywhere on the image draws a new region; otherwise the current one can
+    /// be moved and resized like the overlay guide.
+    fn handle_region_interactions(&mut self, ui: &mut egui::Ui, image_rect: egui::Rect, scale: egui::Vec2, tex_w: f32, tex_h: f32) {
+        let mut st = self.state.lock().unwrap();
+        let stroke = Stroke::new(2.0, Color32::from_rgb(250, 166, 26));
+        let to_image = |p: egui::Pos2| {
+            (
+                ((p.x - image_rect.min.x) / scale.x).clamp(0.0, tex_w),
+                ((p.y - image_rect.min.y) / scale.y).clamp(0.0, tex_h),
+            )
+        };
+
+        if self.selecting_region {
+            let resp = ui.interact(image_rect, ui.make_persistent_id("region_select"), egui::Sense::drag());
+            let pointer = ui.input(|i| i.pointer.interact_pos());
             if resp.drag_started() {
-                self.active_resize = Some(handle);
+                self.region_anchor = pointer.map(to_image);
+            }
+            if let (Some((ax, ay)), Some(pp)) = (self.region_anchor, pointer) {
+                if resp.dragged() {
+                    let (px, py) = to_image(pp);
+                    let (x, y) = (ax.min(px), ay.min(py));
+                    let (w, h) = ((ax - px).abs(), (ay - py).abs());
+                    st.capture_region = Some((x.round() as u32, y.round() as u32, w.round() as u32, h.round() as u32));
+                    ui.ctx().request_repaint();
+                }
+            }
+            if resp.drag_stopped() {
+                self.region_anchor = None;
+                self.selecting_region = false;
+                // A click without a drag leaves nothing worth recording
+                st.capture_region = st
+                    .capture_region
+                    .and_then(|region| clamp_capture_region(region, tex_w as u32, tex_h as u32))
+                    .filter(|&(_, _, w, h)| w >= 16 && h >= 16);
             }
+            if let Some((x, y, w, h)) = st.capture_region {
+                let rect = egui::Rect::from_min_size(
+                    image_rect.min + egui::vec2(x as f32 * scale.x, y as f32 * scale.y),
+                    egui::vec2(w as f32 * scale.x, h as f32 * scale.y),
+                );
+                ui.painter().rect_stroke(rect, 0.0, stroke, egui::StrokeKind::Middle);
+            }
+            return;
         }
 
-        // Drag body
-        let body_resp = ui.interact(overlay_rect, ui.make_persistent_id("overlay_body"), egui::Sense::click_and_drag());
-        if body_resp.drag_started() {
-            self.dragging_overlay = true;
-            self.active_resize = None;
+        let Some((x, y, w, h)) = st.capture_region else {
+            return;
+        };
+        let (mut pos, mut size) = ((x, y), (w, h));
+        let view = PreviewView { ui, image_rect, scale, tex_w, tex_h };
+        if edit_preview_rect(view, "region", stroke, false, &mut self.region_drag, &mut pos, &mut size) {
+            st.capture_region = clamp_capture_region((pos.0, pos.1, size.0, size.1), tex_w as u32, tex_h as u32);
         }
+    }
+}
 
-        let mut st = self.state.lock().unwrap();
-        let min_w = 32.0f32.max(4.0);
-        let min_h = 32.0f32.max(4.0);
-        let _img_w = tex_w * scale;
-        let _img_h = tex_h * scale;
-
-        // Apply dragging
-        if self.dragging_overlay && body_resp.dragged() {
-            let delta = body_resp.drag_delta();
-            let mut new_x = st.overlay_position.0 as f32 + (delta.x / scale);
-            let mut new_y = st.overlay_position.1 as f32 + (delta.y / scale);
-            new_x = new_x.clamp(0.0, (tex_w - st.overlay_size.0 as f32).max(0.0));
-            new_y = new_y.clamp(0.0, (tex_h - st.overlay_size.1 as f32).max(0.0));
-            st.overlay_position.0 = new_x.round() as u32;
-            st.overlay_position.1 = new_y.round() as u32;
-            ui.ctx().request_repaint();
+/// Position and size of an overlay at `pos` of `size` resized to `preset`.
+/// With the screen's `frame` size known the preset shrinks, still 16:9, to
+/// fit it, and an overlay sitting in the right or bottom half keeps that
+/// edge, so one in a corner stays there.
+pub fn overlay_size_preset(
+    pos: (u32, u32),
+    size: (u32, u32),
+    preset: (u32, u32),
+    frame: Option<(u32, u32)>,
+) -> ((u32, u32), (u32, u32)) {
+    let Some((frame_w, frame_h)) = frame.filter(|&(w, h)| w > 0 && h > 0) else {
+        return (pos, preset);
+    };
+    let fit = (frame_w as f32 / preset.0 as f32).min(frame_h as f32 / preset.1 as f32).min(1.0);
+    let new_size = (
+        ((preset.0 as f32 * fit) as u32).max(1),
+        ((preset.1 as f32 * fit) as u32).max(1),
+    );
+    let place = |start: u32, old: u32, new: u32, frame: u32| {
+        let start = if start * 2 + old > frame { (start + old).saturating_sub(new) } else { start };
+        start.min(frame - new)
+    };
+    let new_pos = (
+        place(pos.0, size.0, new_size.0, frame_w),
+        place(pos.1, size.1, new_size.1, frame_h),
+    );
+    (new_pos, new_size)
+}
+
+/// The presets in the presets folder and the position of the one saved at
+/// `select`.
+fn find_presets(select: Option<&Path>) -> (Vec<(PathBuf, Preset)>, Option<usize>) {
+    let presets = presets::presets_dir().map(|dir| presets::list_presets(&dir)).unwrap_or_default();
+    let selected = select.and_then(|select| presets.iter().position(|(path, _)| path == select));
+    (presets, selected)
+}
+
+/// The note field shown after a marker is added.
+struct MarkerNote {
+    /// Position of the marker in `VideoEncoder::markers`.
+    index: usize,
+    text: String,
+    /// Focus the field on the next frame so typing goes straight into it.
+    focus: bool,
+}
+
+/// Names `response` for screen readers with the translation of `key`,
+/// without the emoji its caption shows, and reports whether a toggle is `on`.
+fn accessible(response: &egui::Response, typ: WidgetType, key: &'static str, on: Option<bool>) {
+    let enabled = response.enabled();
+    response.widget_info(|| {
+        let mut info = egui::WidgetInfo::labeled(typ, enabled, tr(key));
+        info.selected = on;
+        info
+    });
+}
+
+/// The auto-stop duration as shown in its field, always with hours.
+fn auto_stop_label(secs: u32) -> String {
+    recording_timer_label(Duration::from_secs(u64::from(secs)))
+}
+
+/// The recording timer next to the status, always with hours.
+pub fn recording_timer_label(elapsed: Duration) -> String {
+    let secs = elapsed.as_secs();
+    format!("{:02}:{:02}:{:02}", secs / 3600, (secs / 60) % 60, secs % 60)
+}
+
+/// The output directory for a path dropped onto the window: a folder itself,
+/// or the folder a file is in.
+pub fn dropped_output_directory(path: &Path) -> Option<PathBuf> {
+    if path.is_dir() {
+        Some(path.to_path_buf())
+    } else {
+        path.parent().filter(|parent| !parent.as_os_str().is_empty()).map(Path::to_path_buf)
+    }
+}
+
+/// Fails unless a file can be created in `directory`, which is checked by
+/// writing and removing an empty probe file.
+pub fn check_writable_directory(directory: &Path) -> Result<()> {
+    if !directory.is_dir() {
+        return Err(anyhow!("{} is not a folder", directory.display()));
+    }
+    let probe = directory.join(format!(".discord-recorder-{}.probe", std::process::id()));
+    std::fs::File::create(&probe).map_err(|e| anyhow!("{} is not writable: {}", directory.display(), e))?;
+    let _ = std::fs::remove_file(&probe);
+    Ok(())
+}
+
+/// The preview split after dragging the splitter `delta` points down in
+/// previews `height` points tall, kept within `PREVIEW_SPLIT_RANGE`.
+pub fn drag_split(split: f32, delta: f32, height: f32) -> f32 {
+    let moved = if height > 0.0 { split + delta / height } else { split };
+    moved.clamp(*PREVIEW_SPLIT_RANGE.start(), *PREVIEW_SPLIT_RANGE.end())
+}
+
+/// Moves a rectangle at `pos` of `size` so that, on each axis, whichever of
+/// its near edge, center or far edge is closest to one of the frame's third
+/// lines sits on it, if that is within `distance` pixels.
+pub fn snap_rect_to_thirds(pos: (f32, f32), size: (f32, f32), frame: (f32, f32), distance: f32) -> (f32, f32) {
+    let snap_axis = |start: f32, length: f32, extent: f32| {
+        let lines = [extent / 3.0, extent * 2.0 / 3.0];
+        let offsets = [0.0, length / 2.0, length];
+        lines
+            .iter()
+            .flat_map(|line| offsets.iter().map(move |offset| line - offset))
+            .map(|snapped| (snapped, (snapped - start).abs()))
+            .filter(|&(_, off_by)| off_by <= distance)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(start, |(snapped, _)| snapped)
+    };
+    (snap_axis(pos.0, size.0, frame.0), snap_axis(pos.1, size.1, frame.1))
+}
+
+/// Draws the annotations over the screen preview.
+fn paint_annotations(ui: &egui::Ui, image_rect: egui::Rect, scale: egui::Vec2, annotations: &[Annotation]) {
+    let painter = ui.painter_at(image_rect);
+    let to_screen = |(x, y): (f32, f32)| image_rect.min + egui::vec2(x * scale.x, y * scale.y);
+    for annotation in annotations {
+        let [r, g, b] = annotation.color;
+        let stroke = Stroke::new((annotations::STROKE_WIDTH * scale.x).max(1.5), Color32::from_rgb(r, g, b));
+        for (from, to) in annotation.segments() {
+            painter.line_segment([to_screen(from), to_screen(to)], stroke);
         }
+    }
+}
 
-        if ui.input(|i| !i.pointer.button_down(egui::PointerButton::Primary)) {
-            self.dragging_overlay = false;
-        }
-
-        // Apply resizing
-        if let Some(handle) = self.active_resize {
-            let pointer_pos = ui.input(|i| i.pointer.interact_pos());
-            if let Some(pp) = pointer_pos {
-                // convert to image space
-                let img_origin = image_rect.min;
-                let px = ((pp.x - img_origin.x) / scale).clamp(0.0, tex_w);
-                let py = ((pp.y - img_origin.y) / scale).clamp(0.0, tex_h);
-
-                let mut x = st.overlay_position.0 as f32;
-                let mut y = st.overlay_position.1 as f32;
-                let mut w = st.overlay_size.0 as f32;
-                let mut h = st.overlay_size.1 as f32;
-
-                match handle {
-                    ResizeHandle::NW => { w += x - px; h += y - py; x = px; y = py; }
-                    ResizeHandle::NE => { w = (px - x).max(min_w / scale); h += y - py; y = py; }
-                    ResizeHandle::SW => { w += x - px; x = px; h = (py - y).max(min_h / scale); }
-                    ResizeHandle::SE => { w = (px - x).max(min_w / scale); h = (py - y).max(min_h / scale); }
-                    ResizeHandle::N => { h += y - py; y = py; }
-                    ResizeHandle::S => { h = (py - y).max(min_h / scale); }
-                    ResizeHandle::W => { w += x - px; x = px; }
-                    ResizeHandle::E => { w = (px - x).max(min_w / scale); }
-                }
-
-                // Clamp within texture bounds
-                if x < 0.0 { w += x; x = 0.0; }
-                if y < 0.0 { h += y; y = 0.0; }
-                if x + w > tex_w { w = tex_w - x; }
-                if y + h > tex_h { h = tex_h - y; }
-
-                w = w.max(min_w / scale);
-                h = h.max(min_h / scale);
-
-                st.overlay_position = (x.round() as u32, y.round() as u32);
-                st.overlay_size = (w.round() as u32, h.round() as u32);
-                ui.ctx().request_repaint();
-            }
+/// Where a texture is shown on screen: `scale` maps texture pixels to points.
+struct PreviewView<'a> {
+    ui: &'a mut egui::Ui,
+    image_rect: egui::Rect,
+    scale: egui::Vec2,
+    tex_w: f32,
+    tex_h: f32,
+}
+
+/// Draws a rectangle in texture coordinates with a border and resize handles,
+/// and applies body drags and handle drags to `pos`/`size`. `id` keeps the
+/// widgets of different rectangles apart. Returns whether anything changed.
+fn edit_preview_rect(
+    view: PreviewView<'_>,
+    id: &str,
+    stroke: Stroke,
+    snap_to_thirds: bool,
+    drag: &mut RectDrag,
+    pos: &mut (u32, u32),
+    size: &mut (u32, u32),
+) -> bool {
+    let PreviewView { ui, image_rect, scale, tex_w, tex_h } = view;
+    let top_left = image_rect.min + egui::vec2(pos.0 as f32 * scale.x, pos.1 as f32 * scale.y);
+    let rect_size = egui::vec2(size.0 as f32 * scale.x, size.1 as f32 * scale.y);
+    let guide_rect = egui::Rect::from_min_size(top_left, rect_size);
+    let handle_color = stroke.color.gamma_multiply(0.8);
+
+    // Draw border for visual feedback
+    let tl = guide_rect.left_top();
+    let tr = guide_rect.right_top();
+    let bl = guide_rect.left_bottom();
+    let br = guide_rect.right_bottom();
+    ui.painter().line_segment([tl, tr], stroke);
+    ui.painter().line_segment([tr, br], stroke);
+    ui.painter().line_segment([br, bl], stroke);
+    ui.painter().line_segment([bl, tl], stroke);
+
+    // Draw resize handles
+    let handle_s = 10.0;
+    let corners = [
+        (ResizeHandle::NW, guide_rect.left_top()),
+        (ResizeHandle::NE, guide_rect.right_top()),
+        (ResizeHandle::SW, guide_rect.left_bottom()),
+        (ResizeHandle::SE, guide_rect.right_bottom()),
+    ];
+    for (handle, center) in corners {
+        let hr = egui::Rect::from_center_size(center, egui::vec2(handle_s, handle_s));
+        ui.painter().rect_filled(hr, 2.0, handle_color);
+        let resp = ui.interact(hr, ui.make_persistent_id(format!("{}_handle_{:?}", id, handle)), egui::Sense::click_and_drag());
+        if resp.drag_started() {
+            drag.resize = Some(handle);
+        }
+    }
+
+    // Edge handles
+    let edges = [
+        (ResizeHandle::N, egui::pos2(guide_rect.center().x, guide_rect.top())),
+        (ResizeHandle::S, egui::pos2(guide_rect.center().x, guide_rect.bottom())),
+        (ResizeHandle::W, egui::pos2(guide_rect.left(), guide_rect.center().y)),
+        (ResizeHandle::E, egui::pos2(guide_rect.right(), guide_rect.center().y)),
+    ];
+    for (handle, center) in edges {
+        let size_vec = if matches!(handle, ResizeHandle::N | ResizeHandle::S) { egui::vec2(handle_s, handle_s * 0.6) } else { egui::vec2(handle_s * 0.6, handle_s) };
+        let hr = egui::Rect::from_center_size(center, size_vec);
+        ui.painter().rect_filled(hr, 1.0, handle_color);
+        let resp = ui.interact(hr, ui.make_persistent_id(format!("{}_edge_{:?}", id, handle)), egui::Sense::click_and_drag());
+        if resp.drag_started() {
+            drag.resize = Some(handle);
+        }
+    }
 
-            // Release on mouse up
-            if ui.input(|i| !i.pointer.button_down(egui::PointerButton::Primary)) {
-                self.active_resize = None;
+    // Drag body
+    let body_resp = ui.interact(guide_rect, ui.make_persistent_id(format!("{}_body", id)), egui::Sense::click_and_drag());
+    if body_resp.drag_started() {
+        drag.moving = true;
+        drag.resize = None;
+    }
+
+    let mut changed = false;
+    let min_w = 32.0f32.max(4.0);
+    let min_h = 32.0f32.max(4.0);
+
+    // Apply dragging
+    if drag.moving && body_resp.dragged() {
+        let delta = body_resp.drag_delta();
+        let (free_x, free_y) = drag.unsnapped.unwrap_or((pos.0 as f32, pos.1 as f32));
+        let max = ((tex_w - size.0 as f32).max(0.0), (tex_h - size.1 as f32).max(0.0));
+        let free = (
+            (free_x + delta.x / scale.x).clamp(0.0, max.0),
+            (free_y + delta.y / scale.y).clamp(0.0, max.1),
+        );
+        drag.unsnapped = Some(free);
+        let (new_x, new_y) = if snap_to_thirds {
+            let size = (size.0 as f32, size.1 as f32);
+            let snapped = snap_rect_to_thirds(free, size, (tex_w, tex_h), THIRDS_SNAP_DISTANCE / scale.x);
+            (snapped.0.clamp(0.0, max.0), snapped.1.clamp(0.0, max.1))
+        } else {
+            free
+        };
+        pos.0 = new_x.round() as u32;
+        pos.1 = new_y.round() as u32;
+        changed = true;
+        ui.ctx().request_repaint();
+    }
+
+    if ui.input(|i| !i.pointer.button_down(egui::PointerButton::Primary)) {
+        drag.moving = false;
+        drag.unsnapped = None;
+    }
+
+    // Apply resizing
+    if let Some(handle) = drag.resize {
+        let pointer_pos = ui.input(|i| i.pointer.interact_pos());
+        if let Some(pp) = pointer_pos {
+            // convert to image space
+            let img_origin = image_rect.min;
+            let px = ((pp.x - img_origin.x) / scale.x).clamp(0.0, tex_w);
+            let py = ((pp.y - img_origin.y) / scale.y).clamp(0.0, tex_h);
+
+            let mut x = pos.0 as f32;
+            let mut y = pos.1 as f32;
+            let mut w = size.0 as f32;
+            let mut h = size.1 as f32;
+
+            match handle {
+                ResizeHandle::NW => { w += x - px; h += y - py; x = px; y = py; }
+                ResizeHandle::NE => { w = (px - x).max(min_w / scale.x); h += y - py; y = py; }
+                ResizeHandle::SW => { w += x - px; x = px; h = (py - y).max(min_h / scale.y); }
+                ResizeHandle::SE => { w = (px - x).max(min_w / scale.x); h = (py - y).max(min_h / scale.y); }
+                ResizeHandle::N => { h += y - py; y = py; }
+                ResizeHandle::S => { h = (py - y).max(min_h / scale.y); }
+                ResizeHandle::W => { w += x - px; x = px; }
+                ResizeHandle::E => { w = (px - x).max(min_w / scale.x); }
             }
+
+            // Clamp within texture bounds
+            if x < 0.0 { w += x; x = 0.0; }
+            if y < 0.0 { h += y; y = 0.0; }
+            if x + w > tex_w { w = tex_w - x; }
+            if y + h > tex_h { h = tex_h - y; }
+
+            w = w.max(min_w / scale.x);
+            h = h.max(min_h / scale.y);
+
+            *pos = (x.round() as u32, y.round() as u32);
+            *size = (w.round() as u32, h.round() as u32);
+            changed = true;
+            ui.ctx().request_repaint();
         }
 
-        let _ = opacity; // kept in case we later reflect in guides
+        // Release on mouse up
+        if ui.input(|i| !i.pointer.button_down(egui::PointerButton::Primary)) {
+            drag.resize = None;
+        }
     }
+
+    changed
 }
@@ -0,0 +1,189 @@
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// UI language. Strings missing from a translation fall back to English.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum Language {
+    #[default]
+    English,
+    German,
+}
+
+impl Language {
+    pub const ALL: [Language; 2] = [Language::English, Language::German];
+
+    /// Name of the language in that language, for the selector.
+    pub fn native_name(self) -> &'static str {
+        match self {
+            Language::English => "English",
+            Language::German => "Deutsch",
+        }
+    }
+
+    fn table(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Language::English => ENGLISH,
+            Language::German => GERMAN,
+        }
+    }
+
+    fn from_index(index: u8) -> Self {
+        Self::ALL.get(index as usize).copied().unwrap_or_default()
+    }
+}
+
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+/// Switches the language for every later `tr` call; the UI picks it up on the
+/// next frame.
+pub fn set_language(language: Language) {
+    CURRENT.store(language as u8, Ordering::Relaxed);
+}
+
+pub fn language() -> Language {
+    Language::from_index(CURRENT.load(Ordering::Relaxed))
+}
+
+/// The string for `key` in the current language.
+pub fn tr(key: &'static str) -> &'static str {
+    translate(language(), key)
+}
+
+/// The string for `key` in `language`, falling back to English and then to the
+/// key itself so a missing entry shows up as such rather than as a blank.
+pub fn translate(language: Language, key: &'static str) -> &'static str {
+    let find = |table: &'static [(&'static str, &'static str)]| {
+        table.iter().find(|(k, _)| *k == key).map(|(_, text)| *text)
+    };
+    find(language.table()).or_else(|| find(ENGLISH)).unwrap_or(key)
+}
+
+const ENGLISH: &[(&str, &str)] = &[
+    ("record.start", "⏺ Start Recording"),
+    ("record.stop", "⏹ Stop Recording"),
+    ("record.pause", "⏸ Pause"),
+    ("record.resume", "▶ Resume"),
+    ("record.marker", "🔖 Marker"),
+    ("webcam.on", "📷 Webcam On"),
+    ("webcam.off", "📷 Webcam Off"),
+    ("webcam.snapshot", "Webcam snapshot"),
+    ("scene.custom", "Custom"),
+    ("scene.tooltip", "Scene: sources and layout for the next recording"),
+    ("settings.button", "⚙ Settings"),
+    ("settings.title", "Settings"),
+    ("status.recording", "● REC"),
+    ("status.paused", "⏸ Paused"),
+    ("status.pausing", "⏳ Pausing…"),
+    ("status.resuming", "⏳ Resuming…"),
+    ("status.restarting", "⏳ Restarting capture…"),
+    ("status.finalizing", "⏳ Finalizing…"),
+    ("status.idle", "● IDLE"),
+    ("heading.preview", "Preview"),
+    ("heading.statistics", "Statistics"),
+    ("heading.language", "Language"),
+    ("settings.theme", "Theme"),
+    ("theme.auto", "Auto (follow system)"),
+    ("theme.dark", "Dark"),
+    ("theme.light", "Light"),
+    ("theme.amoled", "AMOLED black"),
+    ("heading.capture", "Capture Options"),
+    ("heading.audio", "Audio"),
+    ("heading.overlay", "Webcam Overlay"),
+    ("heading.presets", "Presets"),
+    ("heading.scenes", "Scenes"),
+    ("heading.hotkeys", "Hotkeys"),
+    ("heading.wayland", "Wayland"),
+    ("heading.streaming", "Streaming"),
+    ("heading.upload", "Upload"),
+    ("heading.bumpers", "Intro & Outro"),
+    ("heading.post_steps", "Post-processing"),
+    ("heading.storage", "Storage"),
+    ("heading.diagnostics", "Diagnostics"),
+    ("confirm_stop.title", "Stop recording?"),
+    ("confirm_stop.stop", "Stop"),
+    ("confirm_stop.keep", "Keep recording"),
+    ("a11y.record.start", "Start recording"),
+    ("a11y.record.stop", "Stop recording"),
+    ("a11y.record.pause", "Pause recording"),
+    ("a11y.record.resume", "Resume recording"),
+    ("a11y.record.marker", "Add chapter marker"),
+    ("a11y.webcam", "Record webcam"),
+    ("a11y.marker_note", "Note for the marker just added"),
+    ("a11y.scene", "Scene"),
+    ("a11y.settings", "Open settings"),
+    ("a11y.shortcuts", "Keyboard shortcuts"),
+    ("a11y.status.recording", "Status: recording"),
+    ("a11y.status.paused", "Status: paused"),
+    ("a11y.status.pausing", "Status: pausing"),
+    ("a11y.status.resuming", "Status: resuming"),
+    ("a11y.status.restarting", "Status: restarting capture"),
+    ("a11y.status.finalizing", "Status: finishing the recording"),
+    ("a11y.status.idle", "Status: not recording"),
+    ("a11y.auto_stop", "Stop recording automatically"),
+    ("a11y.auto_stop.duration", "Auto-stop after hours, minutes and seconds"),
+];
+
+const GERMAN: &[(&str, &str)] = &[
+    ("record.start", "⏺ Aufnahme starten"),
+    ("record.stop", "⏹ Aufnahme beenden"),
+    ("record.pause", "⏸ Pause"),
+    ("record.resume", "▶ Fortsetzen"),
+    ("record.marker", "🔖 Marker"),
+    ("webcam.on", "📷 Webcam an"),
+    ("webcam.off", "📷 Webcam aus"),
+    ("webcam.snapshot", "Webcam-Schnappschuss"),
+    ("scene.custom", "Benutzerdefiniert"),
+    ("scene.tooltip", "Szene: Quellen und Anordnung für die nächste Aufnahme"),
+    ("settings.button", "⚙ Einstellungen"),
+    ("settings.title", "Einstellungen"),
+    ("status.recording", "● AUFN"),
+    ("status.paused", "⏸ Pausiert"),
+    ("status.pausing", "⏳ Wird pausiert…"),
+    ("status.resuming", "⏳ Wird fortgesetzt…"),
+    ("status.restarting", "⏳ Aufnahme wird neu gestartet…"),
+    ("status.finalizing", "⏳ Wird abgeschlossen…"),
+    ("status.idle", "● BEREIT"),
+    ("heading.preview", "Vorschau"),
+    ("heading.statistics", "Statistik"),
+    ("heading.language", "Sprache"),
+    ("settings.theme", "Design"),
+    ("theme.auto", "Automatisch (System)"),
+    ("theme.dark", "Dunkel"),
+    ("theme.light", "Hell"),
+    ("theme.amoled", "AMOLED-Schwarz"),
+    ("heading.capture", "Aufnahmeoptionen"),
+    ("heading.audio", "Audio"),
+    ("heading.overlay", "Webcam-Overlay"),
+    ("heading.presets", "Voreinstellungen"),
+    ("heading.scenes", "Szenen"),
+    ("heading.hotkeys", "Tastenkürzel"),
+    ("heading.wayland", "Wayland"),
+    ("heading.streaming", "Streaming"),
+    ("heading.upload", "Hochladen"),
+    ("heading.bumpers", "Intro & Outro"),
+    ("heading.post_steps", "Nachbearbeitung"),
+    ("heading.storage", "Speicher"),
+    ("heading.diagnostics", "Diagnose"),
+    ("confirm_stop.title", "Aufnahme beenden?"),
+    ("confirm_stop.stop", "Beenden"),
+    ("confirm_stop.keep", "Weiter aufnehmen"),
+    ("a11y.record.start", "Aufnahme starten"),
+    ("a11y.record.stop", "Aufnahme beenden"),
+    ("a11y.record.pause", "Aufnahme pausieren"),
+    ("a11y.record.resume", "Aufnahme fortsetzen"),
+    ("a11y.record.marker", "Kapitelmarke setzen"),
+    ("a11y.webcam", "Webcam aufnehmen"),
+    ("a11y.marker_note", "Notiz zur eben gesetzten Marke"),
+    ("a11y.scene", "Szene"),
+    ("a11y.settings", "Einstellungen öffnen"),
+    ("a11y.shortcuts", "Tastenkürzel"),
+    ("a11y.status.recording", "Status: Aufnahme läuft"),
+    ("a11y.status.paused", "Status: pausiert"),
+    ("a11y.status.pausing", "Status: wird pausiert"),
+    ("a11y.status.resuming", "Status: wird fortgesetzt"),
+    ("a11y.status.restarting", "Status: Aufnahme wird neu gestartet"),
+    ("a11y.status.finalizing", "Status: Aufnahme wird abgeschlossen"),
+    ("a11y.status.idle", "Status: keine Aufnahme"),
+    ("a11y.auto_stop", "Aufnahme automatisch beenden"),
+    ("a11y.auto_stop.duration", "Automatisch beenden nach Stunden, Minuten und Sekunden"),
+];
@@ -1,11 +1,31 @@
+pub mod annotations;
 pub mod audio;
+pub mod batch;
+pub mod benchmark;
+pub mod cli;
+pub mod clicks;
 pub mod config;
+pub mod ffmpeg_log;
+pub mod frame_budget;
 pub mod gui;
+pub mod i18n;
+pub mod naming;
+pub mod pacing;
+pub mod postprocess;
+pub mod presets;
 pub mod screen;
+pub mod screen_feed;
+pub mod spectrum;
+pub mod streaming;
+pub mod toast;
+pub mod upload;
 pub mod video;
 pub mod webcam;
 pub mod runtime;
 
+#[cfg(test)]
+mod test_fixes;
+
 // Re-export main types
 pub use gui::DiscordRecorderApp;
 pub use config::Config;
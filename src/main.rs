@@ -1,12 +1,29 @@
 use anyhow::Result;
-use log::{error, info};
+use log::{error, info, warn};
 use std::sync::{Arc, Mutex};
 
+mod annotations;
 mod audio;
+mod batch;
+mod benchmark;
+mod cli;
+mod clicks;
 mod video;
+mod ffmpeg_log;
+mod frame_budget;
 mod screen;
+mod screen_feed;
+mod spectrum;
+mod streaming;
+mod toast;
+mod upload;
 mod webcam;
 mod gui;
+mod i18n;
+mod naming;
+mod pacing;
+mod postprocess;
+mod presets;
 mod config;
 mod runtime;
 
@@ -14,19 +31,101 @@ use gui::DiscordRecorderApp;
 use eframe::egui;
 
 fn main() -> Result<()> {
-    // Initialize logging (default to info if RUST_LOG is not set)
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    // RUST_LOG wins when set; otherwise the logger passes everything and the
+    // level from the config (adjustable in settings) does the filtering
+    let rust_log_set = std::env::var_os("RUST_LOG").is_some();
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("trace")).init();
+    if !rust_log_set {
+        config::LogVerbosity::default().apply();
+    }
+    let cli = match cli::CliArgs::parse(std::env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if cli.show_help {
+        println!("{}", cli::USAGE);
+        return Ok(());
+    }
+    if cli.show_version {
+        println!("{}", cli::version_string());
+        return Ok(());
+    }
+    if cli.list_devices {
+        return cli::print_devices(cli.json);
+    }
+
     info!("Starting Discord Recorder");
 
+    if cli.portable {
+        config::enable_portable_mode();
+    }
+
+    // Command-line flags override the loaded config for this session; the
+    // file keeps its own values
+    let (disk_config, config_warning) = config::Config::load_or_recover();
+    if let Some(warning) = &config_warning {
+        warn!("{}", warning);
+    }
+    let mut config = disk_config.clone();
+    cli.apply_to(&mut config);
+    if !rust_log_set {
+        config.log_verbosity.apply();
+    }
+    i18n::set_language(config.language);
+
+    if let Some(seconds) = cli.benchmark {
+        let report = benchmark::run(&config, std::time::Duration::from_secs(seconds))?;
+        println!("{}", report);
+        return Ok(());
+    }
+
+    if cli.batch {
+        let summary = batch::run(&config, &cli.batch_files, cli.batch_container, cli.realtime)?;
+        println!("{}", summary);
+        if summary.items.iter().any(|item| item.result.is_err()) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if cli.convert_mp3 {
+        let summary = batch::convert_mp3(&config, &cli.convert_files, cli.mp3_bitrate)?;
+        println!("{}", summary);
+        if summary.items.iter().any(|item| item.result.is_err()) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if cli.dry_run {
+        if config.use_pipewire_on_wayland {
+            std::env::set_var("OCTOCORD_USE_PIPEWIRE", "1");
+        }
+        let options = video::RecorderOptions {
+            output_target: cli.output_target.clone().unwrap_or_default(),
+            ..video::RecorderOptions::from_config(&config)
+        };
+        println!("{}", video::dry_run_command(&options)?);
+        return Ok(());
+    }
+
     // Create application state
-    let app_state = Arc::new(Mutex::new(gui::AppState::new()));
+    let app_state = Arc::new(Mutex::new(gui::AppState {
+        config_warning,
+        config_file: config::ConfigFile::new(disk_config, &config),
+        ..gui::AppState::from_config(config)
+    }));
 
     // Configure eframe
     let native_options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1200.0, 800.0])
             .with_min_inner_size([800.0, 600.0])
-            .with_title("Discord Recorder"),
+            .with_title("Discord Recorder")
+            .with_drag_and_drop(true),
         ..Default::default()
     };
 
@@ -0,0 +1,155 @@
+use chrono::{DateTime, Local};
+use log::{debug, info};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// The naming scheme recordings have always used.
+pub const DEFAULT_FILE_NAME_TEMPLATE: &str = "recording_{date}_{time}";
+
+/// Stands in for `{window}` when the active window can't be determined.
+const UNKNOWN_WINDOW: &str = "recording";
+
+/// Longest part of a file name a window title may take up.
+const MAX_WINDOW_CHARS: usize = 60;
+
+/// Builds a recording's base name from `template`. Tokens are `{date}`
+/// (YYYYMMDD), `{time}` (HHMMSS) and `{window}`, the title of the window that
+/// had focus when the recording started. The active window is only queried
+/// when the template uses it.
+pub fn recording_base_name(template: &str, now: DateTime<Local>) -> String {
+    let window = template.contains("{window}").then(active_window_title).flatten();
+    expand_file_name(template, now, window.as_deref())
+}
+
+/// `recording_base_name` with the window title already known.
+pub fn expand_file_name(template: &str, now: DateTime<Local>, window: Option<&str>) -> String {
+    let template = if template.trim().is_empty() { DEFAULT_FILE_NAME_TEMPLATE } else { template };
+    let window = window
+        .map(|w| sanitize_file_name(&w.chars().take(MAX_WINDOW_CHARS).collect::<String>()))
+        .filter(|w| !w.is_empty());
+    let name = template
+        .replace("{date}", &now.format("%Y%m%d").to_string())
+        .replace("{time}", &now.format("%H%M%S").to_string())
+        .replace("{window}", window.as_deref().unwrap_or(UNKNOWN_WINDOW));
+    let name = sanitize_file_name(&name);
+    if name.is_empty() {
+        expand_file_name(DEFAULT_FILE_NAME_TEMPLATE, now, None)
+    } else {
+        name
+    }
+}
+
+/// `base`, or `base-1`, `base-2`, … for the first that no file in `dir` is
+/// named after. ffmpeg overwrites its outputs, so without this a template
+/// lacking `{date}` and `{time}` would replace the previous recording.
+pub fn unique_base_name(dir: &Path, base: &str) -> String {
+    let names: Vec<String> = fs::read_dir(dir)
+        .map(|entries| entries.flatten().map(|e| e.file_name().to_string_lossy().into_owned()).collect())
+        .unwrap_or_default();
+    let unique = unique_among(&names, base);
+    if unique != base {
+        info!("{} is taken in {}; recording as {}", base, dir.display(), unique);
+    }
+    unique
+}
+
+/// `unique_base_name` against a list of file names. A name is taken by
+/// `<name>` itself or any `<name>.<suffix>`, which covers every output,
+/// segment and sidecar of a recording.
+pub fn unique_among(names: &[String], base: &str) -> String {
+    let taken = |candidate: &str| {
+        names.iter().any(|name| {
+            name.strip_prefix(candidate).is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+    };
+    if !taken(base) {
+        return base.to_string();
+    }
+    (1..).map(|n| format!("{}-{}", base, n)).find(|candidate| !taken(candidate)).unwrap_or_default()
+}
+
+/// Keeps letters, digits, `-`, `_` and `.`, turning runs of anything else into
+/// one `_`. Dots and underscores are trimmed from both ends so the result is
+/// never hidden, `..`, or a name Windows refuses.
+pub fn sanitize_file_name(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') {
+            out.push(c);
+        } else if !out.ends_with('_') {
+            out.push('_');
+        }
+    }
+    out.trim_matches(['.', '_']).to_string()
+}
+
+/// Title of the focused window, or `None` when it can't be queried. Uses
+/// `xprop` on Linux (X11 and XWayland windows only), AppleScript on macOS
+/// and PowerShell on Windows.
+pub fn active_window_title() -> Option<String> {
+    let title = query_active_window().filter(|t| !t.trim().is_empty());
+    if title.is_none() {
+        debug!("Active window title unavailable");
+    }
+    title
+}
+
+#[cfg(target_os = "linux")]
+fn query_active_window() -> Option<String> {
+    let root = command_output("xprop", &["-root", "_NET_ACTIVE_WINDOW"])?;
+    // _NET_ACTIVE_WINDOW(WINDOW): window id # 0x3a00007
+    let id = root.rsplit(' ').next()?.trim();
+    if !id.starts_with("0x") || id == "0x0" {
+        return None;
+    }
+    let name = command_output("xprop", &["-id", id, "_NET_WM_NAME", "WM_NAME"])?;
+    // _NET_WM_NAME(UTF8_STRING) = "Title"
+    name.lines()
+        .filter_map(|line| line.split_once(" = "))
+        .map(|(_, value)| value.trim().trim_matches('"').replace("\\\"", "\""))
+        .find(|title| !title.is_empty())
+}
+
+#[cfg(target_os = "macos")]
+fn query_active_window() -> Option<String> {
+    let script = r#"tell application "System Events"
+set frontApp to first application process whose frontmost is true
+try
+return name of front window of frontApp
+on error
+return name of frontApp
+end try
+end tell"#;
+    command_output("osascript", &["-e", script]).map(|s| s.trim().to_string())
+}
+
+#[cfg(windows)]
+fn query_active_window() -> Option<String> {
+    let script = r#"Add-Type @"
+using System;
+using System.Text;
+using System.Runtime.InteropServices;
+public static class Fg {
+  [DllImport("user32.dll")] public static extern IntPtr GetForegroundWindow();
+  [DllImport("user32.dll", CharSet = CharSet.Unicode)] public static extern int GetWindowText(IntPtr h, StringBuilder s, int n);
+}
+"@
+$b = New-Object System.Text.StringBuilder 512
+[void][Fg]::GetWindowText([Fg]::GetForegroundWindow(), $b, $b.Capacity)
+$b.ToString()"#;
+    command_output("powershell", &["-NoProfile", "-NonInteractive", "-Command", script]).map(|s| s.trim().to_string())
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos", windows)))]
+fn query_active_window() -> Option<String> {
+    None
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,83 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Fallback when the config asks for 0 fps.
+const MIN_FPS: u32 = 1;
+
+/// Target frame rate shared by the encoder settings and the preview capture
+/// threads. The threads read it every frame, so changes apply without a restart.
+#[derive(Debug, Clone)]
+pub struct FramePacing {
+    fps: Arc<AtomicU32>,
+    /// 0 means the preview runs at the full frame rate.
+    preview_cap: Arc<AtomicU32>,
+}
+
+impl FramePacing {
+    pub fn new(fps: u32, preview_cap: Option<u32>) -> Self {
+        let pacing = Self {
+            fps: Arc::new(AtomicU32::new(MIN_FPS)),
+            preview_cap: Arc::new(AtomicU32::new(0)),
+        };
+        pacing.set(fps, preview_cap);
+        pacing
+    }
+
+    pub fn set(&self, fps: u32, preview_cap: Option<u32>) {
+        self.fps.store(fps.max(MIN_FPS), Ordering::Relaxed);
+        self.preview_cap.store(preview_cap.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    pub fn fps(&self) -> u32 {
+        self.fps.load(Ordering::Relaxed)
+    }
+
+    /// Preview rate: the recording rate, limited by the cap if one is set.
+    pub fn preview_fps(&self) -> u32 {
+        let fps = self.fps();
+        match self.preview_cap.load(Ordering::Relaxed) {
+            0 => fps,
+            cap => fps.min(cap.max(MIN_FPS)),
+        }
+    }
+
+    pub fn preview_interval(&self) -> Duration {
+        Duration::from_secs(1) / self.preview_fps()
+    }
+}
+
+/// Frame rate measured from frame arrival times, averaged over the last
+/// `FPS_WINDOW` so the number doesn't flicker.
+#[derive(Debug, Clone, Default)]
+pub struct FpsCounter {
+    arrivals: VecDeque<Instant>,
+}
+
+const FPS_WINDOW: Duration = Duration::from_secs(1);
+
+impl FpsCounter {
+    pub fn tick(&mut self, now: Instant) {
+        self.arrivals.push_back(now);
+        self.expire(now);
+    }
+
+    /// Frames per second over the window ending at `now`; 0 once frames stop.
+    pub fn fps(&mut self, now: Instant) -> f32 {
+        self.expire(now);
+        match (self.arrivals.front(), self.arrivals.back()) {
+            (Some(first), Some(last)) if self.arrivals.len() > 1 => {
+                let span = last.duration_since(*first).as_secs_f32().max(f32::EPSILON);
+                (self.arrivals.len() - 1) as f32 / span
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn expire(&mut self, now: Instant) {
+        while self.arrivals.front().is_some_and(|t| now.duration_since(*t) > FPS_WINDOW) {
+            self.arrivals.pop_front();
+        }
+    }
+}
@@ -0,0 +1,1175 @@
+use anyhow::{anyhow, Context, Result};
+use log::{info, warn};
+use std::fs;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+use crate::config::{AudioCodec, Config, MarkerSubtitles, OutputContainer, PostStep};
+use crate::ffmpeg_log::{FfmpegLog, LogLevel};
+use crate::video::{file_video_args, usable_hw_accel, Marker, RecordingOutputs};
+
+/// Format a duration as `MM:SS`, growing to `HH:MM:SS` past the hour mark.
+pub fn format_timestamp(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, (secs / 60) % 60, secs % 60);
+    if hours > 0 {
+        format!("{:02}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{:02}:{:02}", minutes, seconds)
+    }
+}
+
+/// Parses `HH:MM:SS`, `MM:SS` or plain seconds, as `format_timestamp`
+/// writes them. Only the leading field may reach 60.
+pub fn parse_timestamp(text: &str) -> Option<Duration> {
+    let fields: Vec<u64> = text.trim().split(':').map(|f| f.trim().parse().ok()).collect::<Option<_>>()?;
+    if fields.len() > 3 || fields[1..].iter().any(|&f| f >= 60) {
+        return None;
+    }
+    Some(Duration::from_secs(fields.iter().fold(0, |total, field| total * 60 + field)))
+}
+
+/// Longest a marker's subtitle stays on screen.
+const SUBTITLE_CUE_DURATION: Duration = Duration::from_secs(4);
+
+/// Markers within the recording, in time order with one per instant, each
+/// with its title: the note, or `Marker N (MM:SS)` without one.
+fn titled_markers(markers: &[Marker], total: Duration) -> Vec<(Duration, String)> {
+    let mut sorted: Vec<&Marker> = markers.iter().filter(|m| m.at < total).collect();
+    sorted.sort_by_key(|m| m.at);
+    sorted.dedup_by_key(|m| m.at);
+    sorted
+        .iter()
+        .enumerate()
+        .map(|(i, marker)| {
+            let title = if marker.note.is_empty() {
+                format!("Marker {} ({})", i + 1, format_timestamp(marker.at))
+            } else {
+                marker.note.clone()
+            };
+            (marker.at, title)
+        })
+        .collect()
+}
+
+/// Escapes the characters ffmetadata treats specially in a value.
+fn escape_metadata(value: &str) -> String {
+    value.chars().fold(String::with_capacity(value.len()), |mut escaped, c| {
+        if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        escaped
+    })
+}
+
+/// Build an ffmetadata document with one chapter per marker. A leading
+/// chapter covers the span before the first marker.
+fn chapters_metadata(markers: &[Marker], total: Duration) -> String {
+    let titled = titled_markers(markers, total);
+    let mut chapters: Vec<(Duration, String)> = Vec::new();
+    if titled.first().is_none_or(|(start, _)| !start.is_zero()) {
+        chapters.push((Duration::ZERO, "Start".to_string()));
+    }
+    chapters.extend(titled.into_iter().map(|(start, title)| (start, escape_metadata(&title))));
+
+    let mut doc = String::from(";FFMETADATA1\n");
+    for (i, (start, title)) in chapters.iter().enumerate() {
+        let end = chapters.get(i + 1).map(|(next, _)| *next).unwrap_or(total);
+        doc.push_str("[CHAPTER]\nTIMEBASE=1/1000\n");
+        doc.push_str(&format!("START={}\nEND={}\ntitle={}\n", start.as_millis(), end.as_millis(), title));
+    }
+    doc
+}
+
+/// Embed markers as chapters by stream-copying each video output with an
+/// ffmetadata file. The original file is replaced only once the remux succeeds.
+pub fn write_chapters(
+    ffmpeg_path: &str,
+    outputs: &RecordingOutputs,
+    markers: &[Marker],
+    total: Duration,
+) -> Result<()> {
+    if markers.is_empty() {
+        return Ok(());
+    }
+
+    let targets = [&outputs.combined, &outputs.video_only, &outputs.webcam]
+        .into_iter()
+        .flatten()
+        .filter_map(|path| Some((path, OutputContainer::from_path(path)?)));
+
+    for (target, container) in targets {
+        let metadata_path = target.with_extension("chapters.txt");
+        fs::write(&metadata_path, chapters_metadata(markers, total))
+            .with_context(|| format!("Failed to write {}", metadata_path.display()))?;
+
+        let remuxed = target.with_extension(format!("chapters.{}", container.extension()));
+        let result = remux_with_metadata(ffmpeg_path, target, &metadata_path, &remuxed, container);
+        let _ = fs::remove_file(&metadata_path);
+
+        match result {
+            Ok(()) => {
+                fs::rename(&remuxed, target)
+                    .with_context(|| format!("Failed to replace {}", target.display()))?;
+                info!("Wrote {} chapter markers to {}", markers.len(), target.display());
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&remuxed);
+                warn!("Failed to add chapters to {}: {}", target.display(), e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// One subtitle cue per marker: `(start, end, text)`. A cue lasts
+/// `SUBTITLE_CUE_DURATION`, or until the next marker or the end if sooner.
+pub fn subtitle_cues(markers: &[Marker], total: Duration) -> Vec<(Duration, Duration, String)> {
+    let titled = titled_markers(markers, total);
+    titled
+        .iter()
+        .enumerate()
+        .map(|(i, (start, title))| {
+            let next = titled.get(i + 1).map_or(total, |(next, _)| *next);
+            (*start, (*start + SUBTITLE_CUE_DURATION).min(next), title.clone())
+        })
+        .collect()
+}
+
+/// The cues as a WebVTT or SRT document; the two differ in the header, the
+/// cue numbers and the decimal separator.
+pub fn subtitles_document(format: MarkerSubtitles, cues: &[(Duration, Duration, String)]) -> String {
+    let srt = format == MarkerSubtitles::Srt;
+    let clock = |at: Duration| {
+        let ms = at.as_millis();
+        let separator = if srt { ',' } else { '.' };
+        format!("{:02}:{:02}:{:02}{}{:03}", ms / 3_600_000, ms / 60_000 % 60, ms / 1000 % 60, separator, ms % 1000)
+    };
+    let mut doc = if srt { String::new() } else { String::from("WEBVTT\n\n") };
+    for (i, (start, end, text)) in cues.iter().enumerate() {
+        if srt {
+            doc.push_str(&format!("{}\n", i + 1));
+        }
+        // A blank line would end the cue early
+        let text = text.lines().filter(|line| !line.trim().is_empty()).collect::<Vec<_>>().join("\n");
+        doc.push_str(&format!("{} --> {}\n{}\n\n", clock(*start), clock(*end), text));
+    }
+    doc
+}
+
+/// Writes the markers as `<recording>.vtt` or `.srt` beside the first output
+/// and, with `embed`, also muxes them as a subtitle track into each video file.
+/// Returns the sidecar's path, or `None` when there is nothing to write.
+pub fn write_marker_subtitles(
+    ffmpeg_path: &str,
+    outputs: &RecordingOutputs,
+    markers: &[Marker],
+    total: Duration,
+    format: MarkerSubtitles,
+    embed: bool,
+) -> Result<Option<PathBuf>> {
+    let cues = subtitle_cues(markers, total);
+    let first = outputs.combined.clone().or_else(|| outputs.paths().into_iter().next().cloned());
+    let (Some(extension), Some(first), false) = (format.extension(), first, cues.is_empty()) else {
+        return Ok(None);
+    };
+
+    let sidecar = first.with_extension(extension);
+    fs::write(&sidecar, subtitles_document(format, &cues))
+        .with_context(|| format!("Failed to write {}", sidecar.display()))?;
+    info!("Wrote {} marker subtitles to {}", cues.len(), sidecar.display());
+    if !embed {
+        return Ok(Some(sidecar));
+    }
+
+    let targets = [&outputs.combined, &outputs.video_only, &outputs.webcam]
+        .into_iter()
+        .flatten()
+        .filter_map(|path| Some((path, OutputContainer::from_path(path)?)));
+    for (target, container) in targets {
+        let muxed = target.with_extension(format!("subtitles.{}", container.extension()));
+        match mux_subtitles(ffmpeg_path, target, &sidecar, &muxed, container) {
+            Ok(()) => {
+                fs::rename(&muxed, target).with_context(|| format!("Failed to replace {}", target.display()))?;
+                info!("Added marker subtitles to {}", target.display());
+            }
+            Err(e) => {
+                let _ = fs::remove_file(&muxed);
+                warn!("Failed to add marker subtitles to {}: {}", target.display(), e);
+            }
+        }
+    }
+    Ok(Some(sidecar))
+}
+
+/// Write the moments the input clipped to `<recording>.clips.txt`, one
+/// timestamp per line, beside the first output file.
+pub fn write_clip_log(outputs: &RecordingOutputs, clips: &[Duration]) -> Result<()> {
+    let Some(first) = outputs.paths().into_iter().next() else {
+        return Ok(());
+    };
+    if clips.is_empty() {
+        return Ok(());
+    }
+
+    let path = first.with_extension("clips.txt");
+    let body: String = clips
+        .iter()
+        .map(|at| format!("{}\n", format_timestamp(*at)))
+        .collect();
+    fs::write(&path, body).with_context(|| format!("Failed to write {}", path.display()))?;
+    info!("Logged {} clipping events to {}", clips.len(), path.display());
+    Ok(())
+}
+
+/// Muxer options for a file rewritten into `container`. MP4 keeps its index
+/// at the front, as the live recording has it.
+pub fn container_flags(container: Option<OutputContainer>) -> Vec<String> {
+    match container {
+        Some(OutputContainer::Mp4) => ["-movflags", "+faststart"].map(String::from).to_vec(),
+        _ => Vec::new(),
+    }
+}
+
+fn remux_with_metadata(
+    ffmpeg_path: &str,
+    input: &Path,
+    metadata: &Path,
+    output: &PathBuf,
+    container: OutputContainer,
+) -> Result<()> {
+    let status = Command::new(ffmpeg_path)
+        .arg("-y")
+        .arg("-hide_banner")
+        .arg("-loglevel").arg("error")
+        .arg("-i").arg(input)
+        .arg("-i").arg(metadata)
+        .arg("-map").arg("0")
+        .arg("-map_metadata").arg("0")
+        .arg("-map_chapters").arg("1")
+        .arg("-c").arg("copy")
+        .args(container_flags(Some(container)))
+        .arg(output)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Failed to launch ffmpeg for chapter remux")?;
+
+    status
+        .success()
+        .then_some(())
+        .ok_or_else(|| anyhow!("ffmpeg chapter remux exited with {}", status))
+}
+
+/// Subtitle codec `container` takes: MP4 only stores `mov_text` and WebM
+/// only WebVTT, while Matroska keeps the sidecar's format as it is.
+pub fn subtitle_codec(container: OutputContainer) -> &'static str {
+    match container {
+        OutputContainer::Mkv => "copy",
+        OutputContainer::Mp4 => "mov_text",
+        OutputContainer::WebM => "webvtt",
+    }
+}
+
+fn mux_subtitles(ffmpeg_path: &str, input: &Path, subtitles: &Path, output: &Path, container: OutputContainer) -> Result<()> {
+    let status = Command::new(ffmpeg_path)
+        .arg("-y")
+        .arg("-hide_banner")
+        .arg("-loglevel").arg("error")
+        .arg("-i").arg(input)
+        .arg("-i").arg(subtitles)
+        .arg("-map").arg("0")
+        .arg("-map").arg("1")
+        .arg("-c").arg("copy")
+        .arg("-c:s").arg(subtitle_codec(container))
+        .arg("-metadata:s:s:0").arg("title=Markers")
+        .args(container_flags(Some(container)))
+        .arg(output)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Failed to launch ffmpeg for the subtitle remux")?;
+
+    status
+        .success()
+        .then_some(())
+        .ok_or_else(|| anyhow!("ffmpeg subtitle remux exited with {}", status))
+}
+
+/// Stitch the per-segment files of a seamless-paused recording back into the
+/// final outputs with the concat demuxer. Segments are removed only after
+/// their slot was joined successfully.
+pub fn join_segments(ffmpeg_path: &str, segments: &[RecordingOutputs], outputs: &RecordingOutputs) -> Result<()> {
+    for (slot, target) in outputs.slots().into_iter().enumerate() {
+        let Some(target) = target else {
+            continue;
+        };
+        let parts: Vec<&PathBuf> = segments
+            .iter()
+            .filter_map(|segment| segment.slots()[slot].as_ref())
+            .filter(|path| path.exists())
+            .collect();
+
+        match parts.as_slice() {
+            [] => warn!("No segments were written for {}", target.display()),
+            [only] => fs::rename(only, target)
+                .with_context(|| format!("Failed to move {} to {}", only.display(), target.display()))?,
+            _ => {
+                concat_files(ffmpeg_path, &parts, target, OutputContainer::from_path(target))?;
+                for part in parts {
+                    let _ = fs::remove_file(part);
+                }
+                info!("Joined segments into {}", target.display());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Concat demuxer list joining `parts` in the given order, which is the
+/// order they were recorded in.
+pub fn concat_list(parts: &[&PathBuf]) -> String {
+    parts
+        .iter()
+        .map(|part| format!("file '{}'\n", part.display().to_string().replace('\'', "'\\''")))
+        .collect()
+}
+
+/// ffmpeg arguments copying the parts listed in `list_path` into `output`,
+/// a file in `container` (`None` for standalone audio).
+/// The concat demuxer offsets each part by the length of the ones before
+/// it, so audio and video keep running on one continuous clock.
+/// `-avoid_negative_ts make_zero` then shifts the whole output once so it
+/// starts at zero, e.g. when audio priming gives the first part a negative
+/// start.
+pub fn concat_args(list_path: &Path, output: &Path, container: Option<OutputContainer>) -> Vec<String> {
+    let mut args: Vec<String> = [
+        "-y", "-hide_banner",
+        "-loglevel", "error",
+        "-f", "concat",
+        "-safe", "0",
+        "-i",
+    ]
+    .map(String::from)
+    .to_vec();
+    args.push(list_path.display().to_string());
+    args.extend(["-map", "0", "-c", "copy", "-avoid_negative_ts", "make_zero"].map(String::from));
+    args.extend(container_flags(container));
+    args.push(output.display().to_string());
+    args
+}
+
+fn concat_files(ffmpeg_path: &str, parts: &[&PathBuf], output: &Path, container: Option<OutputContainer>) -> Result<()> {
+    let list_path = output.with_extension("concat.txt");
+    fs::write(&list_path, concat_list(parts)).with_context(|| format!("Failed to write {}", list_path.display()))?;
+
+    let status = Command::new(ffmpeg_path)
+        .args(concat_args(&list_path, output, container))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .context("Failed to launch ffmpeg for segment concat");
+    let _ = fs::remove_file(&list_path);
+
+    let status = status?;
+    status
+        .success()
+        .then_some(())
+        .ok_or_else(|| anyhow!("ffmpeg concat exited with {}", status))
+}
+
+/// Largest start or end mismatch between a joined file's audio and video
+/// before it is reported as out of sync.
+pub const AV_SYNC_TOLERANCE: Duration = Duration::from_millis(150);
+
+/// First and last-plus-duration packet times in seconds from ffprobe's
+/// `pts_time,duration_time` csv. `None` when no packet has a timestamp.
+pub fn packet_span(csv: &str) -> Option<(f64, f64)> {
+    csv.lines()
+        .filter_map(|line| {
+            let mut fields = line.split(',');
+            let pts: f64 = fields.next()?.trim().parse().ok()?;
+            let duration: f64 = fields.next().and_then(|d| d.trim().parse().ok()).unwrap_or(0.0);
+            Some((pts, pts + duration))
+        })
+        .fold(None, |span, (start, end)| match span {
+            None => Some((start, end)),
+            Some((first, last)) => Some((f64::min(first, start), f64::max(last, end))),
+        })
+}
+
+/// Span of the packets of `stream` (e.g. `v:0`) in `path`, read from packet
+/// headers so nothing is decoded.
+pub fn probe_stream_span(path: &Path, stream: &str) -> Result<Option<(f64, f64)>> {
+    let output = Command::new("ffprobe")
+        .arg("-v").arg("error")
+        .arg("-select_streams").arg(stream)
+        .arg("-show_entries").arg("packet=pts_time,duration_time")
+        .arg("-of").arg("csv=p=0")
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to run ffprobe")?;
+    if !output.status.success() {
+        return Err(anyhow!("ffprobe couldn't read {}", path.display()));
+    }
+    Ok(packet_span(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// How far apart the `video` and `audio` spans start or end, whichever is worse.
+pub fn av_offset(video: (f64, f64), audio: (f64, f64)) -> Duration {
+    Duration::from_secs_f64((video.0 - audio.0).abs().max((video.1 - audio.1).abs()))
+}
+
+/// `av_offset` of a file's first video and audio streams; `None` unless it
+/// has both.
+pub fn probe_av_offset(path: &Path) -> Result<Option<Duration>> {
+    let (Some(video), Some(audio)) = (probe_stream_span(path, "v:0")?, probe_stream_span(path, "a:0")?) else {
+        return Ok(None);
+    };
+    Ok(Some(av_offset(video, audio)))
+}
+
+/// A path quoted for the shell `shell_command` runs.
+pub(crate) fn quote_path(path: &Path) -> String {
+    let text = path.to_string_lossy();
+    if cfg!(windows) {
+        format!("\"{}\"", text)
+    } else {
+        crate::video::shell_quote(&text)
+    }
+}
+
+/// `command` run through `sh -c` (`cmd /C` on Windows).
+pub(crate) fn shell_command(command: &str) -> Command {
+    #[cfg(windows)]
+    let (shell, flag) = ("cmd", "/C");
+    #[cfg(not(windows))]
+    let (shell, flag) = ("sh", "-c");
+    let mut cmd = Command::new(shell);
+    cmd.arg(flag).arg(command).stdin(Stdio::null());
+    cmd
+}
+
+/// Fills in a step's tokens for one recorded file: `{input}` is the quoted
+/// file, `{output_dir}` its quoted folder and `{basename}` the file name
+/// without extension, e.g. `{output_dir}/{basename}.jpg`.
+pub fn expand_step(template: &str, input: &Path) -> String {
+    let dir = input.parent().unwrap_or(Path::new("."));
+    let basename = input
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    template
+        .replace("{input}", &quote_path(input))
+        .replace("{output_dir}", &quote_path(dir))
+        .replace("{basename}", &basename)
+}
+
+/// Runs the enabled steps in order on every output file, copying their output
+/// into `log`. A failing step skips the remaining steps for that file, since
+/// they usually build on it. Returns one message per failure.
+pub fn run_post_steps(steps: &[PostStep], outputs: &RecordingOutputs, log: &FfmpegLog) -> Vec<String> {
+    let mut failures = Vec::new();
+    for input in outputs.paths() {
+        for step in steps.iter().filter(|s| s.enabled && !s.command.trim().is_empty()) {
+            let label = if step.name.is_empty() { step.command.as_str() } else { step.name.as_str() };
+            let command = expand_step(&step.command, input);
+            info!("Post step '{}': {}", label, command);
+            log.push(LogLevel::Info, format!("[{}] {}", label, command));
+
+            match shell_command(&command).output() {
+                Ok(output) => {
+                    for line in String::from_utf8_lossy(&output.stdout).lines().filter(|l| !l.trim().is_empty()) {
+                        log.push(LogLevel::Info, format!("[{}] {}", label, line));
+                    }
+                    for line in String::from_utf8_lossy(&output.stderr).lines().filter(|l| !l.trim().is_empty()) {
+                        log.push(LogLevel::Warning, format!("[{}] {}", label, line));
+                    }
+                    if !output.status.success() {
+                        let message = format!("Post step '{}' failed on {} ({})", label, input.display(), output.status);
+                        log.push(LogLevel::Error, message.clone());
+                        failures.push(message);
+                        break;
+                    }
+                }
+                Err(e) => {
+                    let message = format!("Post step '{}' could not start: {}", label, e);
+                    log.push(LogLevel::Error, message.clone());
+                    failures.push(message);
+                    break;
+                }
+            }
+        }
+    }
+    failures
+}
+
+/// Size the quick-share copy aims for; Discord's upload limit without Nitro
+/// is just above it.
+pub const SHARE_TARGET_BYTES: u64 = 24_000_000;
+
+/// Video and audio bitrates in kbps that fit `duration` into `target_bytes`,
+/// leaving a few percent for the container. Short clips are capped so they
+/// don't get a pointlessly high bitrate.
+pub fn share_bitrates(duration: Duration, target_bytes: u64) -> (u32, u32) {
+    let secs = duration.as_secs_f64().max(1.0);
+    let total_kbps = (target_bytes as f64 * 8.0 / 1000.0 / secs * 0.95) as u32;
+    let audio_kbps = if total_kbps >= 1000 { 128 } else { 64 };
+    let video_kbps = total_kbps.saturating_sub(audio_kbps).clamp(50, 8000);
+    (video_kbps, audio_kbps)
+}
+
+/// `<name>.share.mp4` next to `input`.
+pub fn share_path(input: &Path) -> PathBuf {
+    let stem = input.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    input.with_file_name(format!("{}.share.mp4", stem))
+}
+
+/// Bitrates offered for MP3 conversion, in kbps; MP3 tops out at 320.
+pub const MP3_BITRATES: [u32; 4] = [128, 192, 256, 320];
+
+/// `<name>.mp3` beside `input`, or `<name>.converted.mp3` when `input` is
+/// already that file.
+pub fn mp3_path(input: &Path) -> PathBuf {
+    let target = input.with_extension("mp3");
+    if target == input {
+        let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+        input.with_file_name(format!("{}.converted.mp3", stem))
+    } else {
+        target
+    }
+}
+
+/// ffmpeg arguments transcoding the first audio stream of `input` to MP3,
+/// keeping its tags. ffmpeg picks the nearest sample rate MP3 supports.
+pub fn mp3_args(input: &Path, output: &Path, bitrate_kbps: u32) -> Vec<String> {
+    let mut args: Vec<String> = ["-y", "-hide_banner", "-loglevel", "error"].map(String::from).to_vec();
+    args.extend(["-i".to_string(), input.to_string_lossy().into_owned()]);
+    args.extend(["-map", "0:a:0", "-map_metadata", "0", "-c:a", "libmp3lame"].map(String::from));
+    args.extend(["-b:a".to_string(), format!("{}k", bitrate_kbps.min(320))]);
+    args.push(output.to_string_lossy().into_owned());
+    args
+}
+
+/// Transcodes an audio recording (e.g. a FLAC) to MP3 beside it, leaving the
+/// original alone. `on_progress` gets the fraction done as ffmpeg reports it.
+pub fn convert_to_mp3(
+    ffmpeg_path: &str,
+    input: &Path,
+    bitrate_kbps: u32,
+    log: Option<&FfmpegLog>,
+    mut on_progress: impl FnMut(f32),
+) -> Result<PathBuf> {
+    if !crate::video::ffmpeg_has_encoder(ffmpeg_path, "libmp3lame") {
+        return Err(anyhow!("MP3 conversion needs an ffmpeg built with libmp3lame"));
+    }
+    let output = mp3_path(input);
+    let duration = probe_duration(input).ok();
+    if let Err(e) = run_with_progress(ffmpeg_path, &mp3_args(input, &output, bitrate_kbps), duration, log, &mut on_progress) {
+        let _ = fs::remove_file(&output);
+        return Err(e);
+    }
+    info!("Converted {} to {}", input.display(), output.display());
+    Ok(output)
+}
+
+/// Position reported by an `-progress` line (`out_time_us=...`).
+pub fn parse_progress_time(line: &str) -> Option<Duration> {
+    let micros = line.strip_prefix("out_time_us=")?.trim().parse::<u64>().ok()?;
+    Some(Duration::from_micros(micros))
+}
+
+/// Runs ffmpeg with `args` (which must not set `-progress`), reporting the
+/// fraction of `duration` encoded so far. Errors are copied into `log` when
+/// given; the last one ends up in the returned error either way.
+pub fn run_with_progress(
+    ffmpeg_path: &str,
+    args: &[String],
+    duration: Option<Duration>,
+    log: Option<&FfmpegLog>,
+    on_progress: &mut dyn FnMut(f32),
+) -> Result<()> {
+    let mut child = Command::new(ffmpeg_path)
+        .arg("-nostats")
+        .arg("-progress").arg("pipe:1")
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to launch ffmpeg")?;
+
+    let stderr = child.stderr.take().map(|stderr| {
+        let log = log.cloned();
+        std::thread::spawn(move || {
+            let mut last_error = String::new();
+            for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if let Some(log) = &log {
+                    log.push(LogLevel::Error, line.clone());
+                }
+                last_error = line;
+            }
+            last_error
+        })
+    });
+    if let Some(stdout) = child.stdout.take() {
+        let total = duration.map(|d| d.as_secs_f32().max(f32::EPSILON));
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if let (Some(done), Some(total)) = (parse_progress_time(&line), total) {
+                on_progress((done.as_secs_f32() / total).clamp(0.0, 1.0));
+            }
+        }
+    }
+    let status = child.wait().context("Failed to wait for ffmpeg")?;
+    let last_error = stderr.and_then(|thread| thread.join().ok()).unwrap_or_default();
+
+    status
+        .success()
+        .then_some(())
+        .ok_or_else(|| anyhow!("ffmpeg exited with {} {}", status, last_error.trim()))
+}
+
+/// Re-encodes `input` into a size-capped MP4 for sharing, leaving the original
+/// alone. `on_progress` gets the fraction done as ffmpeg reports it.
+pub fn quick_share(
+    ffmpeg_path: &str,
+    input: &Path,
+    duration: Duration,
+    log: &FfmpegLog,
+    mut on_progress: impl FnMut(f32),
+) -> Result<PathBuf> {
+    let output = share_path(input);
+    let (video_kbps, audio_kbps) = share_bitrates(duration, SHARE_TARGET_BYTES);
+    let mut args: Vec<String> = ["-y", "-hide_banner", "-loglevel", "error"].map(String::from).to_vec();
+    args.extend(["-i".to_string(), input.to_string_lossy().into_owned()]);
+    args.extend(
+        [
+            "-map", "0:v:0", "-map", "0:a:0?",
+            "-c:v", "libx264", "-preset", "veryfast", "-pix_fmt", "yuv420p",
+        ]
+        .map(String::from),
+    );
+    args.extend([
+        "-b:v".to_string(), format!("{}k", video_kbps),
+        "-maxrate".to_string(), format!("{}k", video_kbps),
+        "-bufsize".to_string(), format!("{}k", video_kbps * 2),
+        "-c:a".to_string(), "aac".to_string(),
+        "-b:a".to_string(), format!("{}k", audio_kbps),
+        "-movflags".to_string(), "+faststart".to_string(),
+    ]);
+    args.push(output.to_string_lossy().into_owned());
+
+    if let Err(e) = run_with_progress(ffmpeg_path, &args, Some(duration), Some(log), &mut on_progress) {
+        let _ = fs::remove_file(&output);
+        return Err(e);
+    }
+    info!("Wrote share copy {}", output.display());
+    Ok(output)
+}
+
+/// How far a cut may sit from a keyframe and still be stream-copied.
+const KEYFRAME_TOLERANCE: Duration = Duration::from_millis(50);
+
+/// `<name>.trimmed.<ext>` next to `input`.
+pub fn trimmed_path(input: &Path) -> PathBuf {
+    let stem = input.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let ext = input.extension().map(|e| e.to_string_lossy().into_owned()).unwrap_or_else(|| "mkv".to_string());
+    input.with_file_name(format!("{}.trimmed.{}", stem, ext))
+}
+
+/// Length of a media file according to ffprobe.
+pub fn probe_duration(path: &Path) -> Result<Duration> {
+    let output = Command::new("ffprobe")
+        .arg("-v").arg("error")
+        .arg("-show_entries").arg("format=duration")
+        .arg("-of").arg("default=noprint_wrappers=1:nokey=1")
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to run ffprobe")?;
+    let text = String::from_utf8_lossy(&output.stdout);
+    let secs: f64 = text
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("ffprobe reported no duration for {}", path.display()))?;
+    Ok(Duration::from_secs_f64(secs.max(0.0)))
+}
+
+/// Times of the video keyframes in `path`, read from the packet flags so
+/// nothing has to be decoded. Empty for files without video.
+pub fn probe_keyframes(path: &Path) -> Result<Vec<Duration>> {
+    let output = Command::new("ffprobe")
+        .arg("-v").arg("error")
+        .arg("-select_streams").arg("v:0")
+        .arg("-show_entries").arg("packet=pts_time,flags")
+        .arg("-of").arg("csv=p=0")
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to run ffprobe")?;
+    Ok(parse_keyframes(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses `pts_time,flags` lines, keeping those flagged `K`.
+pub fn parse_keyframes(csv: &str) -> Vec<Duration> {
+    csv.lines()
+        .filter_map(|line| {
+            let (time, flags) = line.split_once(',')?;
+            let secs: f64 = time.trim().parse().ok()?;
+            flags.starts_with('K').then(|| Duration::from_secs_f64(secs.max(0.0)))
+        })
+        .collect()
+}
+
+/// Whether a cut starting at `start` can be stream-copied without losing the
+/// frames before the next keyframe. `None` means the file has no video, where
+/// every audio packet can start a cut.
+pub fn trim_can_copy(start: Duration, keyframes: Option<&[Duration]>) -> bool {
+    match keyframes {
+        None => true,
+        Some(_) if start.is_zero() => true,
+        Some(keyframes) => keyframes.iter().any(|k| k.abs_diff(start) <= KEYFRAME_TOLERANCE),
+    }
+}
+
+/// Arguments cutting `start..end` out of `input`. Seeking before `-i` resets
+/// the timestamps, so the end is given as a length. A re-encode keeps the
+/// input's container, with the video quality and encoder from `config`.
+pub fn trim_args(input: &Path, output: &Path, start: Duration, end: Duration, copy: bool, config: &Config) -> Vec<String> {
+    let container = OutputContainer::from_path(input);
+    let (device, video) = file_video_args(
+        container.unwrap_or_default(),
+        config.hw_accel,
+        config.video_quality,
+        config.get_video_bitrate(),
+    );
+    let mut args: Vec<String> = ["-y", "-hide_banner", "-loglevel", "error"].map(String::from).to_vec();
+    if !copy {
+        args.extend(device);
+    }
+    args.extend(["-ss".to_string(), format!("{:.3}", start.as_secs_f64())]);
+    args.extend(["-i".to_string(), input.to_string_lossy().into_owned()]);
+    args.extend(["-t".to_string(), format!("{:.3}", end.saturating_sub(start).as_secs_f64())]);
+    args.extend(["-map", "0"].map(String::from));
+    if copy {
+        args.extend(["-c", "copy"].map(String::from));
+    } else {
+        args.extend(video);
+        args.extend(["-c:a", "copy"].map(String::from));
+    }
+    args.extend(container_flags(container));
+    args.push(output.to_string_lossy().into_owned());
+    args
+}
+
+/// Writes `start..end` of `input` to `trimmed_path(input)`, stream-copying when
+/// the cut allows it and re-encoding per `config` otherwise. Returns the new
+/// file and whether it was copied.
+pub fn trim(
+    ffmpeg_path: &str,
+    input: &Path,
+    start: Duration,
+    end: Duration,
+    keyframes: Option<&[Duration]>,
+    config: &Config,
+) -> Result<(PathBuf, bool)> {
+    if end <= start {
+        return Err(anyhow!("The out point must come after the in point"));
+    }
+    let output = trimmed_path(input);
+    let copy = trim_can_copy(start, keyframes);
+    let config = Config { hw_accel: usable_hw_accel(ffmpeg_path, config.hw_accel), ..config.clone() };
+    if let Err(e) = run_quiet(ffmpeg_path, &trim_args(input, &output, start, end, copy, &config)) {
+        let _ = fs::remove_file(&output);
+        return Err(e);
+    }
+    info!("Trimmed {} to {} ({})", input.display(), output.display(), if copy { "copied" } else { "re-encoded" });
+    Ok((output, copy))
+}
+
+/// `<name>.merged.<ext>` for the `<name>.video.<ext>` of a separate-outputs
+/// recording, in the video's container (MKV when the extension isn't one).
+pub fn merged_path(video: &Path) -> PathBuf {
+    let stem = video.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+    let base = stem.strip_suffix(".video").unwrap_or(&stem);
+    let container = OutputContainer::from_path(video).unwrap_or_default();
+    video.with_file_name(format!("{}.merged.{}", base, container.extension()))
+}
+
+/// First timestamp of a media file in seconds, per ffprobe.
+pub fn probe_start_time(path: &Path) -> Result<f64> {
+    let output = Command::new("ffprobe")
+        .arg("-v").arg("error")
+        .arg("-show_entries").arg("format=start_time")
+        .arg("-of").arg("default=noprint_wrappers=1:nokey=1")
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to run ffprobe")?;
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("ffprobe reported no start time for {}", path.display()))
+}
+
+/// Arguments muxing `video` and `audio` into `output`. ffmpeg rebases every
+/// input to its own start, so `audio_offset` (audio start minus video start,
+/// in seconds) is put back with `-itsoffset`. `reencode_audio` converts the
+/// audio to the container's codec (AAC, or Opus for WebM) for when stream
+/// copy is refused.
+pub fn merge_args(video: &Path, audio: &Path, output: &Path, audio_offset: f64, reencode_audio: bool) -> Vec<String> {
+    let mut args: Vec<String> = ["-y", "-hide_banner", "-loglevel", "error"].map(String::from).to_vec();
+    args.extend(["-i".to_string(), video.to_string_lossy().into_owned()]);
+    if audio_offset.abs() >= 0.001 {
+        args.extend(["-itsoffset".to_string(), format!("{:.3}", audio_offset)]);
+    }
+    args.extend(["-i".to_string(), audio.to_string_lossy().into_owned()]);
+    args.extend(["-map", "0:v", "-map", "1:a"].map(String::from));
+    let container = OutputContainer::from_path(output);
+    if reencode_audio {
+        let encoder = match container.unwrap_or_default().default_codecs().1 {
+            AudioCodec::Aac => "aac",
+            AudioCodec::Opus => "libopus",
+        };
+        args.extend(["-c:v", "copy", "-c:a", encoder, "-b:a", "192k"].map(String::from));
+    } else {
+        args.extend(["-c", "copy"].map(String::from));
+    }
+    args.extend(container_flags(container));
+    args.push(output.to_string_lossy().into_owned());
+    args
+}
+
+/// Muxes the separate video and audio files of a recording into
+/// `merged_path(video)`, leaving both originals in place.
+pub fn merge(ffmpeg_path: &str, video: &Path, audio: &Path, reencode_audio: bool) -> Result<PathBuf> {
+    let output = merged_path(video);
+    let audio_offset = match (probe_start_time(video), probe_start_time(audio)) {
+        (Ok(video_start), Ok(audio_start)) => audio_start - video_start,
+        (Err(e), _) | (_, Err(e)) => {
+            warn!("Merging without offset correction: {}", e);
+            0.0
+        }
+    };
+    if let Err(e) = run_quiet(ffmpeg_path, &merge_args(video, audio, &output, audio_offset, reencode_audio)) {
+        let _ = fs::remove_file(&output);
+        return Err(e);
+    }
+    info!("Merged {} and {} into {}", video.display(), audio.display(), output.display());
+    Ok(output)
+}
+
+/// Columns and rows of a contact sheet.
+pub const SHEET_GRID: (u32, u32) = (4, 4);
+/// Width of each frame in the contact sheet.
+const SHEET_TILE_WIDTH: u32 = 320;
+
+/// `<name>.jpg` next to `input`.
+pub fn thumbnail_path(input: &Path) -> PathBuf {
+    input.with_extension("jpg")
+}
+
+/// `<name>.sheet.jpg` next to `input`.
+pub fn contact_sheet_path(input: &Path) -> PathBuf {
+    input.with_extension("sheet.jpg")
+}
+
+/// Where the thumbnail is grabbed: a few seconds in, past any fade-in or
+/// black first frame, but inside short clips.
+pub fn thumbnail_time(duration: Duration) -> Duration {
+    Duration::from_secs(3).min(duration / 2)
+}
+
+pub fn thumbnail_args(input: &Path, output: &Path, at: Duration) -> Vec<String> {
+    let mut args: Vec<String> = ["-y", "-hide_banner", "-loglevel", "error"].map(String::from).to_vec();
+    args.extend(["-ss".to_string(), format!("{:.3}", at.as_secs_f64())]);
+    args.extend(["-i".to_string(), input.to_string_lossy().into_owned()]);
+    args.extend(["-frames:v", "1", "-q:v", "3"].map(String::from));
+    args.push(output.to_string_lossy().into_owned());
+    args
+}
+
+/// One frame per tile, spread evenly over `duration`, scaled and tiled into
+/// a single image.
+pub fn contact_sheet_args(input: &Path, output: &Path, duration: Duration) -> Vec<String> {
+    let (cols, rows) = SHEET_GRID;
+    let tiles = cols * rows;
+    let secs = duration.as_secs_f64().max(1.0);
+    let filter = format!(
+        "fps={tiles}/{secs:.3},scale={width}:-2,tile={cols}x{rows}",
+        tiles = tiles,
+        secs = secs,
+        width = SHEET_TILE_WIDTH,
+        cols = cols,
+        rows = rows
+    );
+    let mut args: Vec<String> = ["-y", "-hide_banner", "-loglevel", "error"].map(String::from).to_vec();
+    args.extend(["-i".to_string(), input.to_string_lossy().into_owned()]);
+    args.extend(["-vf".to_string(), filter]);
+    args.extend(["-frames:v", "1", "-q:v", "3"].map(String::from));
+    args.push(output.to_string_lossy().into_owned());
+    args
+}
+
+/// Writes the thumbnail and, when `sheet` is set, the contact sheet for
+/// `input`. Returns the thumbnail path.
+pub fn write_thumbnails(ffmpeg_path: &str, input: &Path, duration: Duration, sheet: bool) -> Result<PathBuf> {
+    let thumbnail = thumbnail_path(input);
+    run_quiet(ffmpeg_path, &thumbnail_args(input, &thumbnail, thumbnail_time(duration)))
+        .context("Failed to grab the thumbnail")?;
+    if sheet {
+        let sheet_path = contact_sheet_path(input);
+        run_quiet(ffmpeg_path, &contact_sheet_args(input, &sheet_path, duration))
+            .context("Failed to make the contact sheet")?;
+    }
+    Ok(thumbnail)
+}
+
+/// Runs ffmpeg to completion; a failure carries its last error line.
+fn run_quiet(ffmpeg_path: &str, args: &[String]) -> Result<()> {
+    let output = Command::new(ffmpeg_path)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to launch ffmpeg")?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let detail = stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("").trim().to_string();
+    Err(anyhow!("ffmpeg exited with {} {}", output.status, detail))
+}
+
+/// Codec and shape of a file's first video stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VideoFormat {
+    pub codec: String,
+    pub width: u32,
+    pub height: u32,
+    /// As ffprobe reports it, e.g. `60/1` or `30000/1001`.
+    pub frame_rate: String,
+}
+
+/// Codec and layout of a file's first audio stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioFormat {
+    pub codec: String,
+    pub sample_rate: u32,
+    pub channels: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MediaFormat {
+    pub video: Option<VideoFormat>,
+    pub audio: Option<AudioFormat>,
+}
+
+/// Parses ffprobe's `-of compact=p=0` stream lines, e.g.
+/// `codec_name=h264|codec_type=video|width=1920|height=1080|r_frame_rate=60/1`.
+pub fn parse_media_format(text: &str) -> MediaFormat {
+    let mut format = MediaFormat::default();
+    for line in text.lines() {
+        let field = |key: &str| {
+            line.split('|')
+                .filter_map(|pair| pair.split_once('='))
+                .find(|(k, _)| *k == key)
+                .map(|(_, v)| v.trim().to_string())
+        };
+        let number = |key: &str| field(key).and_then(|v| v.parse().ok());
+        match field("codec_type").as_deref() {
+            Some("video") if format.video.is_none() => {
+                format.video = Some(VideoFormat {
+                    codec: field("codec_name").unwrap_or_default(),
+                    width: number("width").unwrap_or(0),
+                    height: number("height").unwrap_or(0),
+                    frame_rate: field("r_frame_rate").unwrap_or_default(),
+                });
+            }
+            Some("audio") if format.audio.is_none() => {
+                format.audio = Some(AudioFormat {
+                    codec: field("codec_name").unwrap_or_default(),
+                    sample_rate: number("sample_rate").unwrap_or(0),
+                    channels: number("channels").unwrap_or(0),
+                });
+            }
+            _ => {}
+        }
+    }
+    format
+}
+
+/// Stream formats of a media file according to ffprobe.
+pub fn probe_media_format(path: &Path) -> Result<MediaFormat> {
+    let output = Command::new("ffprobe")
+        .arg("-v").arg("error")
+        .arg("-show_entries").arg("stream=codec_type,codec_name,width,height,r_frame_rate,sample_rate,channels")
+        .arg("-of").arg("compact=p=0")
+        .arg(path)
+        .stdin(Stdio::null())
+        .output()
+        .context("Failed to run ffprobe")?;
+    if !output.status.success() {
+        return Err(anyhow!("ffprobe couldn't read {}", path.display()));
+    }
+    Ok(parse_media_format(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// `<name>.final.<ext>` next to `recording`, in the recording's container
+/// (MKV when the extension isn't one).
+pub fn final_path(recording: &Path) -> PathBuf {
+    let stem = recording.file_stem().unwrap_or_default().to_string_lossy();
+    let container = OutputContainer::from_path(recording).unwrap_or_default();
+    recording.with_file_name(format!("{}.final.{}", stem, container.extension()))
+}
+
+/// One clip of a bumpered video, in playback order.
+#[derive(Debug, Clone)]
+pub struct BumperPart {
+    pub path: PathBuf,
+    pub format: MediaFormat,
+    pub duration: Duration,
+}
+
+/// Whether `parts` can be joined by the concat demuxer without re-encoding:
+/// every clip has to share the recording's codecs, size and frame rate.
+pub fn bumpers_can_copy(parts: &[BumperPart]) -> bool {
+    parts.windows(2).all(|pair| pair[0].format == pair[1].format)
+}
+
+/// Arguments joining `parts` with the concat filter after scaling, padding and
+/// retiming every clip to `target` (the recording's format), encoded per
+/// `config` in the container of `output`. Clips without audio get silence
+/// when the recording has sound.
+pub fn bumper_concat_args(parts: &[BumperPart], target: &MediaFormat, output: &Path, config: &Config) -> Result<Vec<String>> {
+    let video = target.video.as_ref().ok_or_else(|| anyhow!("The recording has no video"))?;
+    let (w, h) = (video.width, video.height);
+    let container = OutputContainer::from_path(output);
+    let (device, mut video_codec) = file_video_args(
+        container.unwrap_or_default(),
+        config.hw_accel,
+        config.video_quality,
+        config.get_video_bitrate(),
+    );
+    // A filter graph output can't take -vf as well, so the hardware upload
+    // becomes the graph's last step
+    let upload = video_codec.iter().position(|a| a == "-vf").map(|i| {
+        let filter = video_codec.remove(i + 1);
+        video_codec.remove(i);
+        filter
+    });
+    let mut args: Vec<String> = ["-y", "-hide_banner", "-loglevel", "error"].map(String::from).to_vec();
+    args.extend(device);
+    for part in parts {
+        if part.format.video.is_none() {
+            return Err(anyhow!("{} has no video", part.path.display()));
+        }
+        args.extend(["-i".to_string(), part.path.to_string_lossy().into_owned()]);
+    }
+
+    let audio = target.audio.as_ref().map(|a| {
+        let layout = if a.channels == 1 { "mono" } else { "stereo" };
+        (a.sample_rate.max(8000), layout)
+    });
+    let mut graph = Vec::new();
+    let mut concat_inputs = String::new();
+    // Generated silence is added after the clips
+    let mut next_input = parts.len();
+    for (index, part) in parts.iter().enumerate() {
+        graph.push(format!(
+            "[{i}:v]scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1,fps={fps},format=yuv420p[v{i}]",
+            i = index,
+            w = w,
+            h = h,
+            fps = video.frame_rate
+        ));
+        concat_inputs.push_str(&format!("[v{}]", index));
+        if let Some((rate, layout)) = audio {
+            let source = if part.format.audio.is_some() {
+                format!("{}:a", index)
+            } else {
+                args.extend(["-f", "lavfi", "-t"].map(String::from));
+                args.push(format!("{:.3}", part.duration.as_secs_f64()));
+                args.extend(["-i".to_string(), format!("anullsrc=channel_layout={}:sample_rate={}", layout, rate)]);
+                next_input += 1;
+                format!("{}:a", next_input - 1)
+            };
+            graph.push(format!(
+                "[{}]aresample={rate},aformat=sample_rates={rate}:channel_layouts={layout}[a{i}]",
+                source,
+                rate = rate,
+                layout = layout,
+                i = index
+            ));
+            concat_inputs.push_str(&format!("[a{}]", index));
+        }
+    }
+    let (audio_streams, audio_label) = if audio.is_some() { (1, "[a]") } else { (0, "") };
+    match &upload {
+        Some(filter) => {
+            graph.push(format!("{}concat=n={}:v=1:a={}[joined]{}", concat_inputs, parts.len(), audio_streams, audio_label));
+            graph.push(format!("[joined]{}[v]", filter));
+        }
+        None => graph.push(format!("{}concat=n={}:v=1:a={}[v]{}", concat_inputs, parts.len(), audio_streams, audio_label)),
+    }
+
+    args.extend(["-filter_complex".to_string(), graph.join(";")]);
+    args.extend(["-map", "[v]"].map(String::from));
+    args.extend(video_codec);
+    if audio.is_some() {
+        let (encoder, bitrate) = match container.unwrap_or_default().default_codecs().1 {
+            AudioCodec::Aac => ("aac", config.get_audio_bitrate()),
+            AudioCodec::Opus => ("libopus", config.get_audio_bitrate().min(512)),
+        };
+        args.extend(["-map", "[a]", "-c:a", encoder].map(String::from));
+        args.extend(["-b:a".to_string(), format!("{}k", bitrate)]);
+    }
+    args.extend(container_flags(container));
+    args.push(output.to_string_lossy().into_owned());
+    Ok(args)
+}
+
+/// Joins `intro`, `recording` and `outro` into `final_path(recording)`,
+/// stream-copying when the formats match and re-encoding per `config`
+/// otherwise. The recording itself is left alone.
+pub fn add_bumpers(
+    ffmpeg_path: &str,
+    recording: &Path,
+    intro: Option<&Path>,
+    outro: Option<&Path>,
+    config: &Config,
+) -> Result<PathBuf> {
+    let mut parts = Vec::new();
+    for path in [intro, Some(recording), outro].into_iter().flatten() {
+        if !path.is_file() {
+            return Err(anyhow!("{} does not exist", path.display()));
+        }
+        parts.push(BumperPart {
+            path: path.to_path_buf(),
+            format: probe_media_format(path)?,
+            duration: probe_duration(path)?,
+        });
+    }
+    let output = final_path(recording);
+    if bumpers_can_copy(&parts) {
+        let paths: Vec<&PathBuf> = parts.iter().map(|p| &p.path).collect();
+        concat_files(ffmpeg_path, &paths, &output, OutputContainer::from_path(&output))?;
+    } else {
+        let target = parts.iter().find(|p| p.path == recording).map(|p| p.format.clone()).unwrap_or_default();
+        let config = Config { hw_accel: usable_hw_accel(ffmpeg_path, config.hw_accel), ..config.clone() };
+        let args = bumper_concat_args(&parts, &target, &output, &config)?;
+        run_quiet(ffmpeg_path, &args).context("Failed to join the intro and outro")?;
+    }
+    info!("Wrote {} with intro/outro", output.display());
+    Ok(output)
+}
@@ -0,0 +1,234 @@
+use anyhow::{anyhow, Context, Result};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{
+    self, AudioExportFormat, AudioQuality, ChromaKey, Config, HwAccel, OutputContainer, OverlayBorder, OverlayLayout,
+    StoredHotkeys, VideoQuality,
+};
+
+/// Tag in every preset file, so a stray JSON file isn't taken for one.
+pub const PRESET_FORMAT: &str = "discord-recorder-preset";
+
+/// Version written into new presets. Bump it when a field changes meaning;
+/// added fields don't need it, as files without them still load.
+pub const PRESET_VERSION: u32 = 1;
+
+/// A named bundle of recording settings that can be shared as one file.
+/// Every setting is optional, so a hand-trimmed preset only changes what it
+/// lists.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    pub format: String,
+    pub version: u32,
+    pub name: String,
+    #[serde(default)]
+    pub settings: PresetSettings,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PresetSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub video_quality: Option<VideoQuality>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_quality: Option<AudioQuality>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_export_format: Option<AudioExportFormat>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_container: Option<OutputContainer>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hw_accel: Option<HwAccel>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frame_rate: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record_audio: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record_video: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record_webcam: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub separate_outputs: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overlay_layout: Option<OverlayLayout>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub overlay_border: Option<OverlayBorder>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chroma_key: Option<ChromaKey>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hotkeys: Option<StoredHotkeys>,
+    /// Device names; only used when the importing machine has them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_device: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webcam: Option<String>,
+}
+
+impl Preset {
+    /// Captures the shareable part of `config` under `name`.
+    pub fn from_config(name: &str, config: &Config) -> Self {
+        Self {
+            format: PRESET_FORMAT.to_string(),
+            version: PRESET_VERSION,
+            name: name.trim().to_string(),
+            settings: PresetSettings {
+                video_quality: Some(config.video_quality),
+                audio_quality: Some(config.audio_quality),
+                audio_export_format: Some(config.audio_export_format),
+                output_container: Some(config.output_container),
+                hw_accel: Some(config.hw_accel),
+                frame_rate: Some(config.frame_rate),
+                record_audio: Some(config.record_audio),
+                record_video: Some(config.record_video),
+                record_webcam: Some(config.record_webcam),
+                separate_outputs: Some(config.separate_outputs),
+                overlay_layout: Some(config.overlay_layout),
+                overlay_border: Some(config.overlay_border),
+                chroma_key: Some(config.chroma_key),
+                hotkeys: Some(config.hotkeys.clone()),
+                audio_device: config.default_audio_device.clone(),
+                webcam: config.default_webcam.clone(),
+            },
+        }
+    }
+
+    /// Parses a preset file, refusing other JSON and presets from a newer
+    /// version of the app.
+    pub fn parse(json: &str) -> Result<Self> {
+        let preset: Preset = serde_json::from_str(json).context("Not a valid preset file")?;
+        if preset.format != PRESET_FORMAT {
+            return Err(anyhow!("Not a Discord Recorder preset"));
+        }
+        if preset.version > PRESET_VERSION {
+            return Err(anyhow!(
+                "Preset version {} needs a newer Discord Recorder (this one reads up to {})",
+                preset.version,
+                PRESET_VERSION
+            ));
+        }
+        if preset.name.trim().is_empty() {
+            return Err(anyhow!("The preset has no name"));
+        }
+        Ok(preset)
+    }
+
+    /// Writes the preset's settings into `config`. Devices missing from
+    /// `audio_devices` or `webcams` are left as they were; each such
+    /// fallback is returned as a message.
+    pub fn apply_to(&self, config: &mut Config, audio_devices: &[String], webcams: &[String]) -> Vec<String> {
+        let s = &self.settings;
+        let mut notes = Vec::new();
+        if let Some(value) = s.video_quality {
+            config.video_quality = value;
+        }
+        if let Some(value) = s.audio_quality {
+            config.audio_quality = value;
+        }
+        if let Some(value) = s.audio_export_format {
+            config.audio_export_format = value;
+        }
+        if let Some(value) = s.output_container {
+            config.output_container = value;
+            if !value.allows(config.video_codec, config.audio_codec) {
+                (config.video_codec, config.audio_codec) = value.default_codecs();
+            }
+        }
+        if let Some(value) = s.hw_accel {
+            config.hw_accel = value;
+        }
+        if let Some(value) = s.frame_rate {
+            config.frame_rate = value;
+        }
+        if let Some(value) = s.record_audio {
+            config.record_audio = value;
+        }
+        if let Some(value) = s.record_video {
+            config.record_video = value;
+        }
+        if let Some(value) = s.record_webcam {
+            config.record_webcam = value;
+        }
+        if let Some(value) = s.separate_outputs {
+            config.separate_outputs = value;
+        }
+        if let Some(value) = s.overlay_layout {
+            config.overlay_layout = value;
+        }
+        if let Some(value) = s.overlay_border {
+            config.overlay_border = value;
+        }
+        if let Some(value) = s.chroma_key {
+            config.chroma_key = value;
+        }
+        if let Some(hotkeys) = &s.hotkeys {
+            config.hotkeys = hotkeys.clone();
+        }
+        match &s.audio_device {
+            Some(device) if audio_devices.contains(device) => config.default_audio_device = Some(device.clone()),
+            Some(device) => notes.push(format!("Audio device \"{}\" isn't connected; keeping the current one", device)),
+            None => {}
+        }
+        match &s.webcam {
+            Some(webcam) if webcams.contains(webcam) => config.default_webcam = Some(webcam.clone()),
+            Some(webcam) => notes.push(format!("Webcam \"{}\" isn't connected; keeping the current one", webcam)),
+            None => {}
+        }
+        notes.extend(config.validate_and_fix());
+        notes
+    }
+}
+
+/// Folder holding the presets, next to `config.json`.
+pub fn presets_dir() -> Result<PathBuf> {
+    Ok(Config::get_config_path()?.with_file_name("presets"))
+}
+
+/// File name for a preset called `name`, without characters file systems
+/// reject.
+pub fn preset_file_name(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, ' ' | '-' | '_') { c } else { '_' })
+        .collect();
+    format!("{}.json", if stem.trim().is_empty() { "preset" } else { stem.trim() })
+}
+
+/// The presets in `dir` sorted by name; unreadable files are skipped.
+pub fn list_presets(dir: &Path) -> Vec<(PathBuf, Preset)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut presets: Vec<(PathBuf, Preset)> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .filter_map(|path| {
+            let preset = fs::read_to_string(&path).ok().and_then(|json| Preset::parse(&json).ok())?;
+            Some((path, preset))
+        })
+        .collect();
+    presets.sort_by_key(|(_, preset)| preset.name.to_lowercase());
+    presets
+}
+
+/// Saves `preset` into `dir`, replacing one with the same file name.
+pub fn save_preset(dir: &Path, preset: &Preset) -> Result<PathBuf> {
+    fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = dir.join(preset_file_name(&preset.name));
+    let json = serde_json::to_string_pretty(preset)?;
+    config::write_atomically(&path, json.as_bytes())?;
+    info!("Saved preset \"{}\" to {}", preset.name, path.display());
+    Ok(path)
+}
+
+/// Reads the preset at `path` and saves it into `dir` so it shows up in the
+/// picker.
+pub fn import_preset(path: &Path, dir: &Path) -> Result<Preset> {
+    let json = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let preset = Preset::parse(&json)?;
+    save_preset(dir, &preset)?;
+    Ok(preset)
+}
@@ -2,24 +2,45 @@ use anyhow::Result;
 use image::{DynamicImage, ImageBuffer};
 use log::{info, error};
 use screenshots::Screen;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 use crossbeam::channel::{Sender, Receiver, bounded};
 use std::panic::{catch_unwind, AssertUnwindSafe};
 
+use crate::frame_budget::{queue_newest, take_newest, FrameBudget, PREVIEW_QUEUE_CAPACITY};
+use crate::pacing::FramePacing;
+
+/// Shown when the capture backend panics, which is how `screenshots` fails on
+/// Wayland compositors without the wlr screencopy protocol.
+pub const CAPTURE_UNSUPPORTED: &str = "Screen capture is unsupported on this compositor (no screencopy support)";
+
+/// Runs a call into the capture backend, turning a panic into
+/// `CAPTURE_UNSUPPORTED` and an error into its message.
+pub fn capture_guarded<T, E: std::fmt::Display>(call: impl FnOnce() -> Result<T, E>) -> Result<T, String> {
+    match catch_unwind(AssertUnwindSafe(call)) {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(_) => Err(CAPTURE_UNSUPPORTED.to_string()),
+    }
+}
+
 pub struct ScreenCapture {
     screen_index: usize,
     is_capturing: Arc<Mutex<bool>>,
     frame_sender: Sender<DynamicImage>,
     frame_receiver: Receiver<DynamicImage>,
     capture_thread: Option<thread::JoinHandle<()>>,
-    capture_rate: Duration,
+    pacing: FramePacing,
+    budget: FrameBudget,
+    dropped_frames: Arc<AtomicU64>,
+    /// Why the capture thread gave up, if it did.
+    failure: Arc<Mutex<Option<String>>>,
 }
 
 impl ScreenCapture {
-    pub fn new(screen_index: usize) -> Result<Self> {
-        let (sender, receiver) = bounded(2);
+    pub fn new(screen_index: usize, pacing: FramePacing, budget: FrameBudget) -> Result<Self> {
+        let (sender, receiver) = bounded(PREVIEW_QUEUE_CAPACITY);
 
         Ok(Self {
             screen_index,
@@ -27,32 +48,44 @@ impl ScreenCapture {
             frame_sender: sender,
             frame_receiver: receiver,
             capture_thread: None,
-            capture_rate: Duration::from_millis(33),
+            pacing,
+            budget,
+            dropped_frames: Arc::new(AtomicU64::new(0)),
+            failure: Arc::new(Mutex::new(None)),
         })
     }
 
     pub fn start(&mut self) -> Result<()> {
         let is_capturing = Arc::clone(&self.is_capturing);
         let sender = self.frame_sender.clone();
+        let receiver = self.frame_receiver.clone();
         let screen_index = self.screen_index;
-        let capture_rate = self.capture_rate;
+        let pacing = self.pacing.clone();
+        let budget = self.budget.clone();
+        let dropped_frames = Arc::clone(&self.dropped_frames);
+        let failure = Arc::clone(&self.failure);
+        let fail = move |message: String| {
+            error!("Screen capture stopped: {}", message);
+            *failure.lock().unwrap() = Some(message);
+        };
 
         *is_capturing.lock().unwrap() = true;
+        *self.failure.lock().unwrap() = None;
 
         self.capture_thread = Some(thread::spawn(move || {
-            let _ = catch_unwind(AssertUnwindSafe(|| {
+            let result = catch_unwind(AssertUnwindSafe(|| {
                 info!("Screen capture thread started for screen {}", screen_index);
 
-                let screens = match Screen::all() {
+                let screens = match capture_guarded(Screen::all) {
                     Ok(screens) => screens,
                     Err(e) => {
-                        error!("Failed to get screens: {}", e);
+                        fail(format!("Failed to get screens: {}", e));
                         return;
                     }
                 };
 
                 if screen_index >= screens.len() {
-                    error!("Invalid screen index: {}", screen_index);
+                    fail(format!("Invalid screen index: {}", screen_index));
                     return;
                 }
 
@@ -62,8 +95,8 @@ impl ScreenCapture {
                 while *is_capturing.lock().unwrap() {
                     let now = Instant::now();
 
-                    if now.duration_since(last_capture) >= capture_rate {
-                        match screen.capture() {
+                    if now.duration_since(last_capture) >= pacing.preview_interval() {
+                        match capture_guarded(|| screen.capture()) {
                             Ok(image) => {
                                 let (width, height) = image.dimensions();
                                 let raw_pixels = image.as_raw();
@@ -72,15 +105,20 @@ impl ScreenCapture {
                                     height,
                                     raw_pixels.to_vec(),
                                 ) {
-                                let dynamic_image = DynamicImage::ImageRgba8(rgba_image);
-                                if let Err(e) = sender.try_send(dynamic_image) {
-                                    log::debug!("Dropping screen frame ({}): channel full", e);
-                                    // Drop frame if channel is full
+                                    let dynamic_image = DynamicImage::ImageRgba8(rgba_image);
+                                    let dropped = queue_newest(&sender, &receiver, &budget, dynamic_image);
+                                    if dropped > 0 {
+                                        log::debug!("Dropped {} screen frame(s): preview fell behind", dropped);
+                                        dropped_frames.fetch_add(dropped, Ordering::Relaxed);
                                     }
                                 } else {
                                     error!("Failed to create image buffer");
                                 }
                             }
+                            Err(e) if e == CAPTURE_UNSUPPORTED => {
+                                fail(e);
+                                return;
+                            }
                             Err(e) => {
                                 error!("Failed to capture screen: {}", e);
                                 thread::sleep(Duration::from_millis(100));
@@ -94,6 +132,9 @@ impl ScreenCapture {
 
                 info!("Screen capture thread stopped");
             }));
+            if result.is_err() {
+                fail("Screen capture thread crashed".to_string());
+            }
         }));
 
         Ok(())
@@ -105,13 +146,40 @@ impl ScreenCapture {
         if let Some(thread) = self.capture_thread.take() {
             thread.join().ok();
         }
+        // Hand back the memory of frames nobody will read
+        let _ = self.get_latest_frame();
 
         info!("Screen recording stopped");
         Ok(())
     }
 
+    /// The newest captured frame, skipping any older ones still queued.
     pub fn get_latest_frame(&self) -> Option<DynamicImage> {
-        self.frame_receiver.try_recv().ok()
+        take_newest(&self.frame_receiver, &self.budget)
+    }
+
+    /// Frames dropped because the preview fell behind or the memory budget
+    /// was used up.
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames.load(Ordering::Relaxed)
+    }
+
+    /// Why the capture thread stopped on its own, e.g. `CAPTURE_UNSUPPORTED`.
+    pub fn failure(&self) -> Option<String> {
+        self.failure.lock().unwrap().clone()
+    }
+
+    /// Width-over-height shape of one captured pixel. Screen grabs are in
+    /// physical pixels, which are square.
+    pub fn sample_aspect_ratio(&self) -> f32 {
+        1.0
+    }
+}
+
+impl Drop for ScreenCapture {
+    fn drop(&mut self) {
+        // Stops the thread and returns queued frames to the shared budget
+        let _ = self.stop();
     }
 }
 
@@ -121,14 +189,7 @@ pub fn get_available_screens() -> Result<Vec<String>> {
 
     let mut screen_names = Vec::new();
     for (i, screen) in screens.iter().enumerate() {
-        let dimensions = match screen.capture() {
-            Ok(img) => {
-                let (w, h) = img.dimensions();
-                format!("{}x{}", w, h)
-            }
-            Err(_) => "unknown".to_string(),
-        };
-        screen_names.push(format!("Screen {} ({})", i, dimensions));
+        screen_names.push(format!("Screen {} ({})", i, screen_dimensions(screen)));
     }
 
     if screen_names.is_empty() {
@@ -138,6 +199,30 @@ pub fn get_available_screens() -> Result<Vec<String>> {
     Ok(screen_names)
 }
 
+/// On X11 the monitor geometry reported by the OS can disagree with what x11grab
+/// sees, so a probe capture is used. Windows and macOS report it reliably, and a
+/// capture there would trigger the macOS screen-recording permission prompt.
+#[cfg(all(feature = "screenshots", not(any(target_os = "windows", target_os = "macos"))))]
+fn screen_dimensions(screen: &Screen) -> String {
+    match screen.capture() {
+        Ok(img) => {
+            let (w, h) = img.dimensions();
+            format!("{}x{}", w, h)
+        }
+        Err(_) => "unknown".to_string(),
+    }
+}
+
+#[cfg(all(feature = "screenshots", any(target_os = "windows", target_os = "macos")))]
+fn screen_dimensions(screen: &Screen) -> String {
+    let info = screen.display_info;
+    if info.is_primary {
+        format!("{}x{}, primary", info.width, info.height)
+    } else {
+        format!("{}x{}", info.width, info.height)
+    }
+}
+
 #[cfg(not(feature = "screenshots"))]
 pub fn get_available_screens() -> Result<Vec<String>> {
     Ok(vec!["Primary Screen (1920x1080)".to_string()])
@@ -0,0 +1,266 @@
+use anyhow::{anyhow, Context, Result};
+use image::{imageops, Rgba, RgbaImage};
+use log::{error, info, warn};
+use screenshots::Screen;
+use std::io::Write;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TrySendError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Edits a screen frame in place before it is sent to the encoder. Gets the
+/// time since the feed started.
+pub type FrameEffect = Box<dyn FnMut(&mut RgbaImage, Duration) + Send>;
+
+/// Effects shared between the encoder and the feed of its current segment.
+pub type FrameEffects = Arc<Mutex<Vec<FrameEffect>>>;
+
+/// What the feed captures: a screen, optionally cut down to a region, at a
+/// size fixed for the whole recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeedSource {
+    pub screen_index: usize,
+    /// (x, y, width, height) within the screen; `None` for all of it.
+    pub region: Option<(u32, u32, u32, u32)>,
+    /// Size of every frame written; rawvideo can't change it midway.
+    pub size: (u32, u32),
+}
+
+/// Raw frames ffmpeg may hold before it stops reading the pipe. A 1080p RGBA
+/// frame is 8 MB, so the usual 512 could buffer gigabytes behind a slow
+/// encoder; the feed drops frames instead.
+pub const PIPE_QUEUE_FRAMES: usize = 8;
+
+/// Captured frames waiting for the pipe before new ones are dropped.
+const WRITER_QUEUE_FRAMES: usize = 2;
+
+/// Input arguments reading `rgba` frames of `size` from ffmpeg's stdin.
+/// Frames are stamped on arrival, so a capture slower than `fps` stays in
+/// sync with the audio instead of playing back fast.
+pub fn rawvideo_input_args((width, height): (u32, u32), fps: u32) -> Vec<String> {
+    let size = format!("{}x{}", width, height);
+    let queue = PIPE_QUEUE_FRAMES.to_string();
+    [
+        "-thread_queue_size", &queue,
+        "-f", "rawvideo",
+        "-pix_fmt", "rgba",
+        "-video_size", &size,
+        "-framerate", &fps.max(1).to_string(),
+        "-use_wallclock_as_timestamps", "1",
+        "-i", "pipe:0",
+    ]
+    .map(String::from)
+    .to_vec()
+}
+
+/// Cuts `region` out of a captured frame, or returns it unchanged.
+pub fn crop_to_region(frame: RgbaImage, region: Option<(u32, u32, u32, u32)>) -> RgbaImage {
+    match region {
+        Some((x, y, w, h)) => imageops::crop_imm(&frame, x, y, w, h).to_image(),
+        None => frame,
+    }
+}
+
+/// Draws a ring of outer diameter `side` centered on `(cx, cy)`, clipped to
+/// the frame.
+pub fn draw_ring(frame: &mut RgbaImage, (cx, cy): (i32, i32), side: u32, color: [u8; 3]) {
+    let radius = side as f32 / 2.0;
+    let thickness = (side as f32 / 12.0).max(2.0);
+    let (width, height) = frame.dimensions();
+    let r = radius.ceil() as i32;
+    for y in (cy - r).max(0)..(cy + r).min(height as i32) {
+        for x in (cx - r).max(0)..(cx + r).min(width as i32) {
+            let distance = (((x - cx) as f32).powi(2) + ((y - cy) as f32).powi(2)).sqrt();
+            if distance <= radius && distance >= radius - thickness {
+                frame.put_pixel(x as u32, y as u32, Rgba([color[0], color[1], color[2], 255]));
+            }
+        }
+    }
+}
+
+/// Totals for one run of the feed.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FeedStats {
+    pub frames: u64,
+    /// Frames dropped because the pipe was still busy with earlier ones.
+    pub dropped: u64,
+    /// Time spent in frame effects.
+    pub effect_time: Duration,
+    /// Time spent writing frames into the pipe, including waiting on ffmpeg.
+    pub write_time: Duration,
+}
+
+impl FeedStats {
+    pub fn average_write_time(&self) -> Duration {
+        self.write_time.checked_div(self.frames.max(1) as u32).unwrap_or_default()
+    }
+}
+
+/// Captures the screen at the recording frame rate on its own thread,
+/// applies the frame effects and writes the frames to `sink` as raw RGBA
+/// from a second thread. A frame captured while that one is still behind is
+/// dropped, so a slow encoder costs frames rather than memory. Stopping
+/// drops the sink, which is ffmpeg's cue that the input ended.
+pub struct ScreenFeed {
+    running: Arc<AtomicBool>,
+    thread: Option<thread::JoinHandle<FeedStats>>,
+}
+
+impl ScreenFeed {
+    pub fn start<W: Write + Send + 'static>(source: FeedSource, fps: u32, sink: W, effects: FrameEffects) -> Self {
+        let running = Arc::new(AtomicBool::new(true));
+        let flag = Arc::clone(&running);
+        let thread = thread::spawn(move || {
+            catch_unwind(AssertUnwindSafe(|| feed_frames(source, fps, sink, &effects, &flag))).unwrap_or_else(|_| {
+                error!("Screen feed crashed");
+                FeedStats::default()
+            })
+        });
+        Self { running, thread: Some(thread) }
+    }
+
+    /// Stops capturing and closes the pipe.
+    pub fn stop(&mut self) -> FeedStats {
+        self.running.store(false, Ordering::Relaxed);
+        let stats = self.thread.take().and_then(|t| t.join().ok()).unwrap_or_default();
+        info!(
+            "Screen feed wrote {} frames ({} dropped), {:.1} ms per frame into the pipe",
+            stats.frames,
+            stats.dropped,
+            stats.average_write_time().as_secs_f64() * 1000.0
+        );
+        stats
+    }
+}
+
+impl Drop for ScreenFeed {
+    fn drop(&mut self) {
+        if self.thread.is_some() {
+            self.stop();
+        }
+    }
+}
+
+fn feed_frames<W: Write + Send>(
+    source: FeedSource,
+    fps: u32,
+    sink: W,
+    effects: &FrameEffects,
+    running: &AtomicBool,
+) -> FeedStats {
+    let (frames, queue) = mpsc::sync_channel(WRITER_QUEUE_FRAMES);
+    thread::scope(|scope| {
+        let writer = scope.spawn(move || write_frames(queue, sink));
+        let mut stats = capture_frames(source, fps, frames, effects, running);
+        let (written, write_time) = writer.join().unwrap_or_default();
+        stats.frames = written;
+        stats.write_time = write_time;
+        stats
+    })
+}
+
+/// Writes queued frames into the pipe until the queue closes or ffmpeg
+/// stops reading. Returns the frames written and the time it took.
+fn write_frames<W: Write>(queue: Receiver<RgbaImage>, mut sink: W) -> (u64, Duration) {
+    let mut written = 0;
+    let mut write_time = Duration::ZERO;
+    for frame in queue {
+        let write_started = Instant::now();
+        if let Err(e) = sink.write_all(frame.as_raw()) {
+            // ffmpeg exited or closed its input
+            warn!("Screen feed stopped: {}", e);
+            break;
+        }
+        write_time += write_started.elapsed();
+        written += 1;
+    }
+    let _ = sink.flush();
+    (written, write_time)
+}
+
+fn capture_frames(
+    source: FeedSource,
+    fps: u32,
+    frames: mpsc::SyncSender<RgbaImage>,
+    effects: &FrameEffects,
+    running: &AtomicBool,
+) -> FeedStats {
+    let mut stats = FeedStats::default();
+    let screen = match Screen::all().ok().and_then(|screens| screens.into_iter().nth(source.screen_index)) {
+        Some(screen) => screen,
+        None => {
+            error!("Screen {} not found for the screen feed", source.screen_index);
+            return stats;
+        }
+    };
+    let interval = Duration::from_secs_f64(1.0 / fps.max(1) as f64);
+    let started = Instant::now();
+    let mut next_frame = started;
+    while running.load(Ordering::Relaxed) {
+        let now = Instant::now();
+        if now < next_frame {
+            thread::sleep((next_frame - now).min(Duration::from_millis(5)));
+            continue;
+        }
+        // Skip ahead rather than bursting after a slow frame
+        next_frame = (next_frame + interval).max(now);
+
+        let frame = match screen.capture() {
+            Ok(image) => match RgbaImage::from_raw(image.width(), image.height(), image.as_raw().to_vec()) {
+                Some(frame) => crop_to_region(frame, source.region),
+                None => continue,
+            },
+            Err(e) => {
+                warn!("Screen feed capture failed: {}", e);
+                thread::sleep(Duration::from_millis(100));
+                continue;
+            }
+        };
+        if frame.dimensions() != source.size {
+            warn!("Screen size changed to {:?}; skipping frame", frame.dimensions());
+            continue;
+        }
+
+        let mut frame = frame;
+        let effect_started = Instant::now();
+        for effect in effects.lock().unwrap().iter_mut() {
+            effect(&mut frame, started.elapsed());
+        }
+        stats.effect_time += effect_started.elapsed();
+
+        match frames.try_send(frame) {
+            Ok(()) => {}
+            Err(TrySendError::Full(_)) => stats.dropped += 1,
+            // The writer gave up on the pipe
+            Err(TrySendError::Disconnected(_)) => break,
+        }
+    }
+    stats
+}
+
+/// Resolves the feed for `screen_index`: grabs one frame for the size and
+/// clamps `region` into it.
+pub fn resolve_feed_source(screen_index: usize, region: Option<(u32, u32, u32, u32)>) -> Result<FeedSource> {
+    let screen = catch_unwind(AssertUnwindSafe(Screen::all))
+        .map_err(|_| anyhow!("Screen capture backend crashed"))?
+        .context("Failed to enumerate screens")?
+        .into_iter()
+        .nth(screen_index)
+        .ok_or_else(|| anyhow!("Invalid screen index {}", screen_index))?;
+    let image = catch_unwind(AssertUnwindSafe(|| screen.capture()))
+        .map_err(|_| anyhow!("Screen capture unsupported by compositor"))?
+        .context("Failed to capture screen to determine resolution")?;
+    let (width, height) = (image.width() & !1, image.height() & !1);
+    let region = match region {
+        Some(region) => Some(
+            crate::video::clamp_capture_region(region, width, height)
+                .ok_or_else(|| anyhow!("Capture region {:?} lies outside the {}x{} screen", region, width, height))?,
+        ),
+        None if (width, height) != image.dimensions() => Some((0, 0, width, height)),
+        None => None,
+    };
+    let size = region.map_or((width, height), |(_, _, w, h)| (w, h));
+    Ok(FeedSource { screen_index, region, size })
+}
@@ -0,0 +1,100 @@
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::audio::SampleRing;
+
+/// Samples per FFT; about 43 ms at 48 kHz, enough resolution for the low bands.
+const FFT_SIZE: usize = 2048;
+pub const BAR_COUNT: usize = 48;
+const MIN_FREQ: f32 = 20.0;
+const MAX_FREQ: f32 = 20_000.0;
+/// Bars map -90..0 dBFS onto 0..1.
+const FLOOR_DB: f32 = -90.0;
+/// Recomputing faster than this only burns CPU; bars decay smoothly in between.
+const UPDATE_INTERVAL: Duration = Duration::from_millis(50);
+/// Fraction of the previous bar height kept when the new value is lower.
+const DECAY: f32 = 0.85;
+
+/// Log-frequency spectrum of the most recent mono samples. All buffers are
+/// allocated once so updates don't allocate.
+pub struct SpectrumAnalyzer {
+    fft: Arc<dyn Fft<f32>>,
+    window: Vec<f32>,
+    buffer: Vec<Complex<f32>>,
+    scratch: Vec<Complex<f32>>,
+    bars: [f32; BAR_COUNT],
+    last_update: Option<Instant>,
+}
+
+impl SpectrumAnalyzer {
+    pub fn new() -> Self {
+        let fft = FftPlanner::new().plan_fft_forward(FFT_SIZE);
+        let scratch = vec![Complex::default(); fft.get_inplace_scratch_len()];
+        // Hann window to keep leakage from smearing neighbouring bands
+        let window = (0..FFT_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / (FFT_SIZE - 1) as f32).cos())
+            .collect();
+        Self {
+            fft,
+            window,
+            buffer: vec![Complex::default(); FFT_SIZE],
+            scratch,
+            bars: [0.0; BAR_COUNT],
+            last_update: None,
+        }
+    }
+
+    /// Bar heights in `0.0..=1.0`, lowest frequency first.
+    pub fn bars(&self) -> &[f32; BAR_COUNT] {
+        &self.bars
+    }
+
+    /// Recomputes the bars from the newest samples in `ring`, at most every `UPDATE_INTERVAL`.
+    pub fn update(&mut self, ring: &SampleRing, sample_rate: u32) {
+        let now = Instant::now();
+        if self.last_update.is_some_and(|last| now.duration_since(last) < UPDATE_INTERVAL) {
+            return;
+        }
+        self.last_update = Some(now);
+
+        if ring.len() < FFT_SIZE || sample_rate == 0 {
+            self.bars.iter_mut().for_each(|bar| *bar *= DECAY);
+            return;
+        }
+
+        let start = ring.len() - FFT_SIZE;
+        for (i, slot) in self.buffer.iter_mut().enumerate() {
+            let sample = ring.get(start + i).unwrap_or(0.0);
+            *slot = Complex::new(sample * self.window[i], 0.0);
+        }
+        self.fft.process_with_scratch(&mut self.buffer, &mut self.scratch);
+
+        let bin_hz = sample_rate as f32 / FFT_SIZE as f32;
+        let max_freq = MAX_FREQ.min(sample_rate as f32 / 2.0);
+        let ratio = (max_freq / MIN_FREQ).powf(1.0 / BAR_COUNT as f32);
+        // Hann window halves the coherent gain; scale so a full-scale sine reads ~0 dB
+        let norm = 4.0 / FFT_SIZE as f32;
+
+        for (band, bar) in self.bars.iter_mut().enumerate() {
+            let low = MIN_FREQ * ratio.powi(band as i32);
+            let high = low * ratio;
+            let first_bin = ((low / bin_hz) as usize).max(1);
+            let last_bin = ((high / bin_hz) as usize).clamp(first_bin, FFT_SIZE / 2 - 1);
+            let magnitude = self.buffer[first_bin..=last_bin]
+                .iter()
+                .map(|c| c.norm() * norm)
+                .fold(0.0f32, f32::max);
+            let db = 20.0 * magnitude.max(1e-9).log10();
+            let level = ((db - FLOOR_DB) / -FLOOR_DB).clamp(0.0, 1.0);
+            *bar = level.max(*bar * DECAY);
+        }
+    }
+}
+
+impl Default for SpectrumAnalyzer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,93 @@
+use anyhow::{anyhow, Result};
+
+use crate::config::SrtSettings;
+
+/// A network destination ffmpeg sends the recording to next to the files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamTarget {
+    /// ffmpeg protocol the URL needs, checked against `ffmpeg -protocols`.
+    pub protocol: &'static str,
+    /// Muxer for the stream.
+    pub format: &'static str,
+    pub url: String,
+}
+
+impl StreamTarget {
+    pub fn srt(settings: &SrtSettings) -> Result<Self> {
+        Ok(Self {
+            protocol: "srt",
+            format: "mpegts",
+            url: srt_output_url(settings)?,
+        })
+    }
+
+    /// Output for ffmpeg's `tee` muxer. A failed connect or dropped link
+    /// would otherwise end the whole ffmpeg process, files included; with
+    /// `onfail=ignore` the stream leg is dropped instead. The tee still gives
+    /// up once every leg has failed, so a `null` leg keeps it going.
+    pub fn tee_output(&self) -> String {
+        format!("[f={}:onfail=ignore]{}|[f=null]-", self.format, tee_escape(&self.url))
+    }
+}
+
+/// Escapes the characters the tee muxer's leg list treats specially.
+fn tee_escape(url: &str) -> String {
+    url.chars().fold(String::with_capacity(url.len()), |mut escaped, c| {
+        if matches!(c, '\\' | '\'' | '|' | '[') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+        escaped
+    })
+}
+
+/// Checks that `settings.url` looks like `srt://host:port` and adds the latency
+/// and passphrase options. Options already written into the URL take precedence.
+pub fn srt_output_url(settings: &SrtSettings) -> Result<String> {
+    let url = settings.url.trim();
+    let rest = url
+        .strip_prefix("srt://")
+        .ok_or_else(|| anyhow!("SRT URL must start with srt://, e.g. srt://example.com:9000"))?;
+    let (authority, query) = match rest.split_once('?') {
+        Some((authority, query)) => (authority, query),
+        None => (rest, ""),
+    };
+    let authority = authority.trim_end_matches('/');
+    let (host, port) = authority
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("SRT URL needs a port, e.g. srt://{}:9000", authority))?;
+    if host.is_empty() || host.contains(['/', ' ']) {
+        return Err(anyhow!("SRT URL has no valid host: {}", url));
+    }
+    if !matches!(port.parse::<u16>(), Ok(p) if p != 0) {
+        return Err(anyhow!("SRT URL has an invalid port \"{}\"", port));
+    }
+
+    let mut options: Vec<String> = query.split('&').filter(|o| !o.is_empty()).map(str::to_string).collect();
+    let has_option = |name: &str| {
+        query.split('&').any(|o| o.split('=').next() == Some(name))
+    };
+    if !has_option("latency") {
+        // ffmpeg's srt protocol takes the latency in microseconds
+        options.push(format!("latency={}", u64::from(settings.latency_ms) * 1000));
+    }
+    if !settings.passphrase.is_empty() && !has_option("passphrase") {
+        let len = settings.passphrase.chars().count();
+        if !(10..=79).contains(&len) {
+            return Err(anyhow!("SRT passphrase must be 10 to 79 characters long (it has {})", len));
+        }
+        options.push(format!("passphrase={}", percent_encode(&settings.passphrase)));
+    }
+
+    Ok(format!("srt://{}:{}?{}", host, port, options.join("&")))
+}
+
+pub(crate) fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
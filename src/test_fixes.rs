@@ -3,10 +3,33 @@ use anyhow::Result;
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::config::{Config, VideoQuality};
-    use crate::video::{RecorderOptions, VideoEncoder};
+    use crate::annotations::{arrow_head, burn_in, Annotation, AnnotationTool};
+    use crate::audio::{balance_gains, tone_samples, AudioProcessor, TEST_TONE_HZ};
+    use crate::batch::{batch_args, batch_output_path, BatchContainer};
+    use crate::cli::CliArgs;
+    use crate::clicks::{click_ring_size, parse_mouse_location, XinputParser, CLICK_ANIMATION};
+    use crate::frame_budget::{queue_newest, take_newest, FrameBudget, PREVIEW_QUEUE_CAPACITY};
+    use crate::i18n::{translate, Language};
+    use crate::naming::{expand_file_name, unique_among, unique_base_name, DEFAULT_FILE_NAME_TEMPLATE};
+    use crate::pacing::FpsCounter;
+    use crate::screen::{capture_guarded, CAPTURE_UNSUPPORTED};
+    use crate::screen_feed::{crop_to_region, draw_ring, rawvideo_input_args, FeedSource, PIPE_QUEUE_FRAMES};
+    use crate::toast::{ToastKind, Toasts, TOAST_DURATION};
+    use crate::gui::{check_writable_directory, effective_zoom, overlay_size_preset, OVERLAY_SIZE_PRESETS, discord_style, dropped_output_directory, drag_split, PREVIEW_SPLIT_RANGE, overlay_fade_opacity, preview_scale, snap_rect_to_thirds, reserved_shortcut, recording_timer_label, AppState, HotkeyConfig};
+    use crate::ffmpeg_log::{is_dropping_frames, parse_stats, summarize_stats, EncoderStats, StatsSummary, FfmpegLog, LogLevel, LOG_CAPACITY};
+    use crate::config::{AudioCodec, VideoCodec, backup_path, default_output_directory, write_atomically, RECORDINGS_FOLDER, AudioExportFormat, DiscordTheme, AudioQuality, BackgroundBlur, ChromaKey, ClickHighlight, Config, ConfigFile, FlacBitDepth, HwAccel, MicGateMode, OutputContainer, OverlayBorder, OverlayLayout, SrtSettings, VideoQuality};
+    use crate::streaming::{srt_output_url, StreamTarget};
+    use crate::upload::{expand_command, expand_url};
+    use crate::postprocess::{
+        bumper_concat_args, bumpers_can_copy, av_offset, packet_span, AV_SYNC_TOLERANCE, concat_args, container_flags, concat_list, subtitle_codec, contact_sheet_args, expand_step, final_path, parse_media_format, BumperPart, MediaFormat, merge_args, merged_path, thumbnail_args, thumbnail_time, parse_keyframes, parse_progress_time, share_bitrates, share_path, trim_args, trim_can_copy,
+        SHARE_TARGET_BYTES,
+    };
+    use crate::webcam::{match_camera, reconnect_delay, unique_camera_names, CameraControlRange, CameraSetting, WebcamStatus, MAX_RECONNECT_ATTEMPTS};
+    use crate::video::{clamp_capture_region, composite_graph, OverlayPlacement, hw_accel_probe_args, hw_codec_args, VAAPI_DEVICE, cursor_zoom_window, ffmpeg_args, screen_fit_filter, OutputTarget, webcam_blur_filter, webcam_border_filter, webcam_key_filter, EffectiveInputs, RecorderOptions, RecordingOutputs, VideoEncoder};
     use std::env;
-    use std::path::PathBuf;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+    use chrono::TimeZone;
 
     #[test]
     fn config_default_creates_output_dir() -> Result<()> {
@@ -30,14 +53,2080 @@ mod tests {
             include_webcam: false,
             separate_outputs: false,
             selected_screen: None,
+            capture_region: None,
+            cursor_zoom: None,
+            screen_output_size: None,
+            click_highlight: None,
+            piped_screen: false,
             audio_device: None,
             webcam_device: None,
             ffmpeg_path: "ffmpeg".to_string(),
             audio_gain_db: 0.0,
+            audio_balance: 0.0,
+            limiter_threshold_db: None,
+            limiter_makeup_db: 0.0,
+            mic_gate: MicGateMode::Off,
+            system_audio: false,
+            system_audio_device: None,
+            ducking: None,
+            audio_export_format: AudioExportFormat::Flac,
+            output_container: OutputContainer::Mkv,
+            video_codec: VideoCodec::H264,
+            audio_codec: AudioCodec::Aac,
+            webcam_overlay: OverlayPlacement::default(),
+            hw_accel: HwAccel::Software,
+            flac_bit_depth: FlacBitDepth::Bits16,
+            separate_webcam: false,
+            composite_webcam: true,
+            quit_timeout: Duration::from_secs(5),
+            terminate_timeout: Duration::from_secs(3),
+            seamless_pause: false,
+            low_latency: false,
+            threads: None,
+            overlay_layout: OverlayLayout::default(),
+            webcam_border: OverlayBorder::default(),
+            chroma_key: None,
+            background_blur: None,
+            stream: None,
+            output_target: OutputTarget::File,
+            file_name_template: DEFAULT_FILE_NAME_TEMPLATE.to_string(),
         };
 
         let encoder = VideoEncoder::new(options);
         assert!(encoder.is_ok());
         Ok(())
     }
-}
\ No newline at end of file
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn capture_options(include_audio: bool, include_video: bool, include_webcam: bool) -> RecorderOptions {
+        RecorderOptions {
+            output_directory: PathBuf::from("/recordings"),
+            include_audio,
+            include_video,
+            include_webcam,
+            separate_outputs: false,
+            ..RecorderOptions::from_config(&Config::default())
+        }
+    }
+
+    fn screen_input() -> Vec<String> {
+        strings(&["-f", "x11grab", "-video_size", "1920x1080", "-i", ":0.0+0,0"])
+    }
+
+    fn audio_input() -> Vec<String> {
+        strings(&["-f", "pulse", "-i", "default"])
+    }
+
+    /// Values following every occurrence of `flag`.
+    fn values_of<'a>(args: &'a [String], flag: &str) -> Vec<&'a str> {
+        args.windows(2)
+            .filter(|w| w[0] == flag)
+            .map(|w| w[1].as_str())
+            .collect()
+    }
+
+    #[test]
+    fn ffmpeg_args_video_only() -> Result<()> {
+        let options = capture_options(false, true, false);
+        let inputs = EffectiveInputs { screen: Some(screen_input()), ..Default::default() };
+        let (args, outputs) = ffmpeg_args(&options, &inputs, "rec")?;
+
+        assert_eq!(values_of(&args, "-i"), vec![":0.0+0,0"]);
+        assert_eq!(values_of(&args, "-map"), vec!["0:v"]);
+        assert_eq!(values_of(&args, "-vf"), vec!["scale=trunc(iw/2)*2:trunc(ih/2)*2"]);
+        assert!(values_of(&args, "-c:a").is_empty());
+        assert_eq!(args.last().map(String::as_str), Some("/recordings/rec.mkv"));
+        assert_eq!(outputs.audio_only, None);
+        Ok(())
+    }
+
+    #[test]
+    fn ffmpeg_args_audio_only() -> Result<()> {
+        let options = capture_options(true, false, false);
+        let inputs = EffectiveInputs { audio: Some(audio_input()), ..Default::default() };
+        let (args, outputs) = ffmpeg_args(&options, &inputs, "rec")?;
+
+        assert_eq!(values_of(&args, "-i"), vec!["default"]);
+        assert_eq!(values_of(&args, "-map"), vec!["0:a"]);
+        assert_eq!(values_of(&args, "-c:a"), vec!["flac"]);
+        assert_eq!(values_of(&args, "-sample_fmt"), vec!["s16"]);
+        assert!(values_of(&args, "-vf").is_empty());
+        assert_eq!(args.last().map(String::as_str), Some("/recordings/rec.flac"));
+        assert_eq!(outputs.audio_only, Some(PathBuf::from("/recordings/rec.flac")));
+        Ok(())
+    }
+
+    #[test]
+    fn ffmpeg_args_flac_24_bit() -> Result<()> {
+        let mut options = capture_options(true, false, false);
+        options.flac_bit_depth = FlacBitDepth::Bits24;
+        let inputs = EffectiveInputs { audio: Some(audio_input()), ..Default::default() };
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+
+        assert_eq!(values_of(&args, "-sample_fmt"), vec!["s32"]);
+        assert_eq!(values_of(&args, "-bits_per_raw_sample"), vec!["24"]);
+        Ok(())
+    }
+
+    #[test]
+    fn flac_depth_follows_audio_quality() {
+        let mut config = Config { audio_quality: AudioQuality::Lossless, ..Config::default() };
+        assert_eq!(config.get_flac_bit_depth(), FlacBitDepth::Bits24);
+        config.audio_quality = AudioQuality::High;
+        assert_eq!(config.get_flac_bit_depth(), FlacBitDepth::Bits16);
+        config.flac_bit_depth = Some(FlacBitDepth::Bits24);
+        assert_eq!(config.get_flac_bit_depth(), FlacBitDepth::Bits24);
+    }
+
+    #[test]
+    fn ffmpeg_args_lossless_video() -> Result<()> {
+        let mut options = capture_options(false, true, false);
+        options.video_quality = VideoQuality::Lossless;
+        let inputs = EffectiveInputs { screen: Some(screen_input()), ..Default::default() };
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+
+        assert_eq!(values_of(&args, "-qp"), vec!["0"]);
+        assert_eq!(values_of(&args, "-preset"), vec!["ultrafast"]);
+        assert_eq!(values_of(&args, "-pix_fmt"), vec!["yuv444p"], "no chroma subsampling");
+        let nvenc = hw_codec_args(HwAccel::Nvenc, VideoQuality::Lossless, 8000);
+        assert_eq!(values_of(&nvenc, "-pix_fmt"), vec!["yuv444p"]);
+        assert_eq!(values_of(&hw_codec_args(HwAccel::Nvenc, VideoQuality::High, 8000), "-pix_fmt"), vec!["yuv420p"]);
+        assert!(values_of(&args, "-crf").is_empty());
+        assert!(values_of(&args, "-b:v").is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn ffmpeg_args_separate_webcam_file() -> Result<()> {
+        let mut options = capture_options(true, true, true);
+        options.separate_webcam = true;
+        let inputs = EffectiveInputs {
+            screen: Some(screen_input()),
+            audio: Some(audio_input()),
+            webcam: Some("/dev/video0".to_string()),
+            ..Default::default()
+        };
+        let (args, outputs) = ffmpeg_args(&options, &inputs, "rec")?;
+
+        // Overlay and raw webcam both come from input 2
+        assert_eq!(values_of(&args, "-map"), vec!["[vout]", "1:a", "2:v"]);
+        assert_eq!(outputs.combined, Some(PathBuf::from("/recordings/rec.mkv")));
+        assert_eq!(outputs.webcam, Some(PathBuf::from("/recordings/rec.webcam.mkv")));
+        assert_eq!(args.last().map(String::as_str), Some("/recordings/rec.webcam.mkv"));
+
+        // Without compositing the screen is recorded as captured
+        options.composite_webcam = false;
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert!(values_of(&args, "-filter_complex").is_empty());
+        assert_eq!(values_of(&args, "-map"), vec!["0:v", "1:a", "2:v"]);
+        Ok(())
+    }
+
+    #[test]
+    fn ffmpeg_args_mp3_export() -> Result<()> {
+        let mut options = capture_options(true, true, false);
+        options.separate_outputs = true;
+        options.audio_export_format = AudioExportFormat::Mp3;
+        options.audio_bitrate_kbps = 512;
+        options.audio_sample_rate = 96_000;
+        let inputs = EffectiveInputs {
+            screen: Some(screen_input()),
+            audio: Some(audio_input()),
+            ..Default::default()
+        };
+        let (args, outputs) = ffmpeg_args(&options, &inputs, "rec")?;
+
+        assert_eq!(values_of(&args, "-c:a"), vec!["libmp3lame"]);
+        assert_eq!(values_of(&args, "-b:a"), vec!["320k"]);
+        assert_eq!(values_of(&args, "-ar"), vec!["48000"]);
+        assert_eq!(outputs.audio_only, Some(PathBuf::from("/recordings/rec.audio.mp3")));
+        assert_eq!(args.last().map(String::as_str), Some("/recordings/rec.audio.mp3"));
+        Ok(())
+    }
+
+    #[test]
+    fn ffmpeg_args_video_and_audio_combined() -> Result<()> {
+        let options = capture_options(true, true, false);
+        let inputs = EffectiveInputs {
+            screen: Some(screen_input()),
+            audio: Some(audio_input()),
+            ..Default::default()
+        };
+        let (args, outputs) = ffmpeg_args(&options, &inputs, "rec")?;
+
+        assert_eq!(values_of(&args, "-i"), vec![":0.0+0,0", "default"]);
+        assert_eq!(values_of(&args, "-map"), vec!["0:v", "1:a"]);
+        assert_eq!(values_of(&args, "-c:v"), vec!["libx264"]);
+        assert_eq!(values_of(&args, "-c:a"), vec!["aac"]);
+        assert_eq!(args.last().map(String::as_str), Some("/recordings/rec.mkv"));
+        assert_eq!(outputs.combined, Some(PathBuf::from("/recordings/rec.mkv")));
+        Ok(())
+    }
+
+    #[test]
+    fn ffmpeg_args_separate_outputs() -> Result<()> {
+        let options = RecorderOptions { separate_outputs: true, ..capture_options(true, true, false) };
+        let inputs = EffectiveInputs {
+            screen: Some(screen_input()),
+            audio: Some(audio_input()),
+            ..Default::default()
+        };
+        let (args, outputs) = ffmpeg_args(&options, &inputs, "rec")?;
+
+        assert_eq!(values_of(&args, "-map"), vec!["0:v", "1:a"]);
+        assert_eq!(values_of(&args, "-c:a"), vec!["flac"]);
+        let video_out = args.iter().position(|a| a == "/recordings/rec.video.mkv").expect("video output");
+        let audio_map = args.iter().position(|a| a == "1:a").expect("audio map");
+        assert!(video_out < audio_map, "video output must precede the audio output's options");
+        assert_eq!(args.last().map(String::as_str), Some("/recordings/rec.audio.flac"));
+        assert_eq!(outputs.combined, None);
+        Ok(())
+    }
+
+    #[test]
+    fn ffmpeg_args_webcam_overlay_indices() -> Result<()> {
+        let options = capture_options(true, true, true);
+        let inputs = EffectiveInputs {
+            screen: Some(screen_input()),
+            audio: Some(audio_input()),
+            webcam: Some("/dev/video0".to_string()),
+            ..Default::default()
+        };
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+
+        assert_eq!(values_of(&args, "-i"), vec![":0.0+0,0", "default", "/dev/video0"]);
+        let filter = values_of(&args, "-filter_complex");
+        assert_eq!(filter.len(), 1);
+        assert!(filter[0].starts_with(
+            "[2:v]scale=320:180,drawbox=x=0:y=0:w=iw:h=ih:color=0x5865F2:t=2,format=rgba,colorchannelmixer=aa=0.900[cam_scaled];[0:v][cam_scaled]overlay=40:40"
+        ));
+        assert!(filter[0].ends_with("[vout]"));
+        assert_eq!(values_of(&args, "-map"), vec!["[vout]", "1:a"]);
+        assert!(values_of(&args, "-vf").is_empty());
+
+        // Webcam alone becomes the video stream
+        let options = capture_options(true, false, true);
+        let inputs = EffectiveInputs {
+            audio: Some(audio_input()),
+            webcam: Some("/dev/video0".to_string()),
+            ..Default::default()
+        };
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(values_of(&args, "-map"), vec!["1:v", "0:a"]);
+        assert!(values_of(&args, "-filter_complex").is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn ffmpeg_args_skips_unresolved_webcam() -> Result<()> {
+        let options = capture_options(false, true, true);
+        let inputs = EffectiveInputs { screen: Some(screen_input()), ..Default::default() };
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+
+        assert_eq!(values_of(&args, "-i"), vec![":0.0+0,0"]);
+        assert!(values_of(&args, "-filter_complex").is_empty());
+        assert_eq!(values_of(&args, "-map"), vec!["0:v"]);
+        Ok(())
+    }
+
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    #[test]
+    fn ffmpeg_args_pipewire_input() -> Result<()> {
+        let options = capture_options(false, true, false);
+        let inputs = EffectiveInputs {
+            screen: Some(crate::video::pipewire_screen_input()),
+            ..Default::default()
+        };
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+
+        assert_eq!(values_of(&args, "-f"), vec!["pipewire"]);
+        assert_eq!(values_of(&args, "-i"), vec!["0"]);
+        assert_eq!(values_of(&args, "-map"), vec!["0:v"]);
+        assert_eq!(values_of(&args, "-vf"), vec!["scale=trunc(iw/2)*2:trunc(ih/2)*2"]);
+        Ok(())
+    }
+
+    #[test]
+    fn channel_gains_scale_interleaved_stereo() {
+        let processor = AudioProcessor::new(48_000, 2);
+        let mut data = vec![1.0, 1.0, -0.5, -0.5, 0.25, 0.25];
+        processor.apply_channel_gains(&mut data, &[0.5, 2.0]);
+        assert_eq!(data, vec![0.5, 2.0, -0.25, -1.0, 0.125, 0.5]);
+
+        // Full right balance silences the left channel and keeps the right at unity
+        let mut data = vec![0.8, 0.8, 0.4, 0.4];
+        processor.apply_channel_gains(&mut data, &balance_gains(1.0));
+        assert_eq!(data, vec![0.0, 0.8, 0.0, 0.4]);
+        assert_eq!(balance_gains(0.0), [1.0, 1.0]);
+        assert_eq!(balance_gains(-0.5), [1.0, 0.5]);
+    }
+
+    #[test]
+    fn limiter_holds_clipping_sine_under_threshold() {
+        let sample_rate = 48_000;
+        let processor = AudioProcessor::new(sample_rate, 2);
+        // 440 Hz stereo sine at twice full scale
+        let mut data: Vec<f32> = (0..sample_rate / 10)
+            .flat_map(|i| {
+                let s = 2.0 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / sample_rate as f32).sin();
+                [s, s]
+            })
+            .collect();
+
+        processor.apply_limiter(&mut data, -6.0, 50.0);
+
+        let threshold = 10f32.powf(-6.0 / 20.0);
+        let peak = data.iter().fold(0.0f32, |acc, s| acc.max(s.abs()));
+        assert!(peak <= threshold + 1e-4, "peak {} above threshold {}", peak, threshold);
+        assert!(peak > threshold * 0.9, "limiter should not squash the signal far below the ceiling");
+
+        // Signals already under the threshold pass through untouched
+        let mut quiet = vec![0.1, -0.1, 0.2, -0.2];
+        processor.apply_limiter(&mut quiet, -6.0, 50.0);
+        assert_eq!(quiet, vec![0.1, -0.1, 0.2, -0.2]);
+    }
+
+    #[test]
+    fn ffmpeg_log_collapses_progress_and_caps_lines() {
+        let log = FfmpegLog::default();
+        let output = "Input #0, x11grab\nframe=   10 fps=30\rframe=   20 fps=30\r\n\
+                      [x11grab @ 0x1] Thread message queue blocking\nConversion failed!\n";
+        log.drain(output.as_bytes());
+
+        let lines = log.snapshot();
+        let texts: Vec<&str> = lines.iter().map(|l| l.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![
+                "Input #0, x11grab",
+                "frame=   20 fps=30",
+                "[x11grab @ 0x1] Thread message queue blocking",
+                "Conversion failed!"
+            ]
+        );
+        assert_eq!(lines[2].level, LogLevel::Warning);
+        assert_eq!(lines[3].level, LogLevel::Error);
+
+        for i in 0..LOG_CAPACITY + 10 {
+            log.push(LogLevel::Info, format!("line {}", i));
+        }
+        let lines = log.snapshot();
+        assert_eq!(lines.len(), LOG_CAPACITY);
+        assert_eq!(lines.last().map(|l| l.text.clone()), Some(format!("line {}", LOG_CAPACITY + 9)));
+    }
+
+    #[test]
+    fn frame_budget_rejects_frames_over_the_limit() {
+        let mib = 1024 * 1024;
+        let budget = FrameBudget::new(10);
+        // An empty budget always admits one frame, even an oversized one
+        assert!(budget.try_reserve(12 * mib));
+        assert!(!budget.try_reserve(mib));
+        budget.release(12 * mib);
+
+        assert!(budget.try_reserve(6 * mib));
+        assert!(budget.try_reserve(4 * mib));
+        assert!(!budget.try_reserve(1));
+        budget.release(4 * mib);
+        assert_eq!(budget.used_bytes(), 6 * mib);
+
+        budget.set_limit_mb(20);
+        assert!(budget.try_reserve(14 * mib));
+    }
+
+    #[test]
+    fn preview_scale_stretches_width_by_sample_aspect_ratio() {
+        // Square pixels keep the old uniform scale
+        let square = preview_scale(1920.0, 1080.0, 1.0, 960.0, 1080.0, 1.0);
+        assert_eq!(square.x, square.y);
+        assert_eq!(1920.0 * square.x, 960.0);
+
+        // 720x576 with 16:15 pixels shows as 768x576 when it fits
+        let wide = preview_scale(720.0, 576.0, 16.0 / 15.0, 2000.0, 2000.0, 1.0);
+        assert!((720.0 * wide.x - 768.0).abs() < 0.01);
+        assert_eq!(576.0 * wide.y, 576.0);
+
+        // Unknown or bogus ratios fall back to square
+        assert_eq!(preview_scale(640.0, 480.0, 0.0, 2000.0, 2000.0, 1.0), preview_scale(640.0, 480.0, 1.0, 2000.0, 2000.0, 1.0));
+    }
+
+    #[test]
+    fn capture_region_is_clamped_to_the_screen() {
+        assert_eq!(clamp_capture_region((100, 50, 640, 480), 1920, 1080), Some((100, 50, 640, 480)));
+        // Overhanging regions are cut at the screen edge and kept even for yuv420p
+        assert_eq!(clamp_capture_region((1500, 900, 1000, 1000), 1920, 1080), Some((1500, 900, 420, 180)));
+        assert_eq!(clamp_capture_region((10, 10, 101, 51), 1920, 1080), Some((10, 10, 100, 50)));
+        assert_eq!(clamp_capture_region((1919, 0, 100, 100), 1920, 1080), None);
+        assert_eq!(clamp_capture_region((3000, 3000, 100, 100), 1920, 1080), None);
+    }
+
+    #[test]
+    fn reserved_shortcuts_are_flagged_but_defaults_are_not() {
+        use egui::{Key, KeyboardShortcut, Modifiers};
+
+        let defaults = HotkeyConfig::default();
+        for hotkey in [&defaults.start_stop, &defaults.pause_resume, &defaults.toggle_webcam, &defaults.add_marker, &defaults.webcam_snapshot, &defaults.next_scene, &defaults.push_to_talk] {
+            assert_eq!(reserved_shortcut(&hotkey.shortcut), None);
+        }
+        assert!(reserved_shortcut(&KeyboardShortcut::new(Modifiers::ALT, Key::F4)).is_some());
+        assert!(reserved_shortcut(&KeyboardShortcut::new(Modifiers::ALT, Key::Tab)).is_some());
+        assert!(reserved_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::A)).is_some());
+        assert_eq!(reserved_shortcut(&KeyboardShortcut::new(Modifiers::NONE, Key::F9)), None);
+    }
+
+    #[test]
+    fn hotkeys_round_trip_through_config() {
+        use egui::{Key, KeyboardShortcut, Modifiers};
+
+        let mut hotkeys = HotkeyConfig::default();
+        assert_eq!(hotkeys.to_stored(), Default::default(), "defaults are not written out");
+
+        hotkeys.toggle_webcam.enabled = false;
+        hotkeys.add_marker.shortcut = KeyboardShortcut::new(Modifiers::CTRL | Modifiers::SHIFT, Key::F9);
+        let stored = hotkeys.to_stored();
+        assert!(stored.start_stop.is_none());
+        assert_eq!(stored.toggle_webcam.as_ref().map(|h| h.enabled), Some(false));
+
+        let json = serde_json::to_string(&stored).unwrap();
+        let restored = HotkeyConfig::from_stored(&serde_json::from_str(&json).unwrap());
+        assert!(!restored.toggle_webcam.enabled);
+        assert_eq!(restored.toggle_webcam.shortcut, hotkeys.toggle_webcam.shortcut);
+        assert_eq!(restored.add_marker.shortcut, hotkeys.add_marker.shortcut);
+        assert!(restored.add_marker.enabled);
+    }
+
+    #[test]
+    fn translations_fall_back_to_english_then_the_key() {
+        assert_eq!(translate(Language::English, "heading.audio"), "Audio");
+        assert_eq!(translate(Language::German, "settings.title"), "Einstellungen");
+        assert_eq!(translate(Language::German, "no.such.key"), "no.such.key");
+    }
+
+    #[test]
+    fn toasts_collapse_repeats_and_expire() {
+        let toasts = Toasts::default();
+        toasts.error("Webcam device busy");
+        toasts.error("Webcam device busy");
+        toasts.info("Recording saved");
+
+        let now = std::time::Instant::now();
+        let shown = toasts.visible(now);
+        assert_eq!(shown.len(), 2);
+        assert_eq!(shown[0].kind, ToastKind::Error);
+        assert!(toasts.visible(now + TOAST_DURATION + Duration::from_millis(10)).is_empty());
+    }
+
+    #[test]
+    fn fps_counter_averages_over_the_last_second() {
+        let start = std::time::Instant::now();
+        let mut counter = FpsCounter::default();
+        for frame in 0..=30 {
+            counter.tick(start + Duration::from_millis(frame * 20));
+        }
+        let at = start + Duration::from_millis(600);
+        assert!((counter.fps(at) - 50.0).abs() < 0.5);
+        // Frames stopped: the window empties and the rate drops to zero
+        assert_eq!(counter.fps(at + Duration::from_secs(2)), 0.0);
+    }
+
+    #[test]
+    fn srt_url_gets_latency_and_encoded_passphrase() -> Result<()> {
+        let settings = SrtSettings {
+            url: "srt://relay.example:9000".to_string(),
+            latency_ms: 200,
+            passphrase: "secret pass&word".to_string(),
+        };
+        assert_eq!(
+            srt_output_url(&settings)?,
+            "srt://relay.example:9000?latency=200000&passphrase=secret%20pass%26word"
+        );
+
+        let explicit = SrtSettings { url: "srt://10.0.0.2:7000?latency=50000".to_string(), ..SrtSettings::default() };
+        assert_eq!(srt_output_url(&explicit)?, "srt://10.0.0.2:7000?latency=50000");
+
+        for bad in ["rtmp://host:9000", "srt://host", "srt://:9000", "srt://host:99999"] {
+            let settings = SrtSettings { url: bad.to_string(), ..SrtSettings::default() };
+            assert!(srt_output_url(&settings).is_err(), "{} should be rejected", bad);
+        }
+        let short = SrtSettings { passphrase: "short".to_string(), ..settings };
+        assert!(srt_output_url(&short).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn ffmpeg_args_stream_adds_mpegts_output() -> Result<()> {
+        let mut options = capture_options(true, true, false);
+        options.audio_gain_db = 6.0;
+        options.stream = Some(SrtSettings { url: "srt://relay.example:9000".to_string(), ..SrtSettings::default() });
+        let inputs = EffectiveInputs { screen: Some(screen_input()), audio: Some(audio_input()), ..Default::default() };
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+
+        assert_eq!(values_of(&args, "-map"), vec!["0:v", "1:a", "0:v", "1:a"]);
+        // Per-output filters are repeated for the stream
+        assert_eq!(values_of(&args, "-vf").len(), 2);
+        assert_eq!(values_of(&args, "-filter:a").len(), 2);
+        assert_eq!(values_of(&args, "-tune"), vec!["zerolatency"]);
+        // The stream is a tee leg, so losing it doesn't take the file down
+        assert_eq!(values_of(&args, "-f").last().copied(), Some("tee"));
+        assert_eq!(
+            args.last().map(String::as_str),
+            Some("[f=mpegts:onfail=ignore]srt://relay.example:9000?latency=120000|[f=null]-")
+        );
+        let quirky = StreamTarget { protocol: "srt", format: "mpegts", url: "srt://h:1?streamid=a|b'c".to_string() };
+        assert_eq!(quirky.tee_output(), "[f=mpegts:onfail=ignore]srt://h:1?streamid=a\\|b\\'c|[f=null]-");
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn upload_templates_quote_path_and_encode_name() {
+        let path = PathBuf::from("/recordings/my rec.mkv");
+        assert_eq!(
+            expand_command("rclone copy {path} remote:{name}", &path),
+            "rclone copy '/recordings/my rec.mkv' remote:my rec.mkv"
+        );
+        assert_eq!(
+            expand_url("https://bucket.example.com/uploads/{name}", &path),
+            "https://bucket.example.com/uploads/my%20rec.mkv"
+        );
+    }
+
+    #[test]
+    fn clipboard_gets_the_file_or_the_folder_of_several() {
+        let single = RecordingOutputs {
+            combined: Some(PathBuf::from("/recordings/rec.mkv")),
+            video_only: None,
+            audio_only: None,
+            webcam: None,
+            desktop_audio: None,
+        };
+        assert_eq!(single.clipboard_text().as_deref(), Some("/recordings/rec.mkv"));
+
+        let separate = RecordingOutputs {
+            combined: None,
+            video_only: Some(PathBuf::from("/recordings/rec.video.mkv")),
+            audio_only: Some(PathBuf::from("/recordings/rec.flac")),
+            webcam: None,
+            desktop_audio: None,
+        };
+        assert_eq!(separate.clipboard_text().as_deref(), Some("/recordings"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn post_step_tokens_expand_per_file() {
+        let command = expand_step(
+            "ffmpeg -i {input} -frames:v 1 {output_dir}/{basename}.jpg",
+            &PathBuf::from("/my recordings/rec.mkv"),
+        );
+        assert_eq!(command, "ffmpeg -i '/my recordings/rec.mkv' -frames:v 1 '/my recordings'/rec.jpg");
+    }
+
+    #[test]
+    fn quick_share_fits_the_target_size() {
+        let duration = Duration::from_secs(300);
+        let (video_kbps, audio_kbps) = share_bitrates(duration, SHARE_TARGET_BYTES);
+        let bytes = (video_kbps + audio_kbps) as u64 * 1000 / 8 * duration.as_secs();
+        assert!(bytes < SHARE_TARGET_BYTES, "{} bytes", bytes);
+        assert!(bytes > SHARE_TARGET_BYTES * 9 / 10, "{} bytes wastes the budget", bytes);
+
+        assert_eq!(share_path(&PathBuf::from("/recordings/rec.mkv")), PathBuf::from("/recordings/rec.share.mp4"));
+        assert_eq!(parse_progress_time("out_time_us=1500000"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_progress_time("out_time=00:00:01.500000"), None);
+    }
+
+    #[test]
+    fn trim_copies_only_cuts_starting_on_keyframes() {
+        let keyframes = parse_keyframes("0.000000,K__\n0.033000,___\n2.000000,K__\n2.033000,___\n");
+        assert_eq!(keyframes, vec![Duration::ZERO, Duration::from_secs(2)]);
+
+        assert!(trim_can_copy(Duration::from_millis(2020), Some(&keyframes)));
+        assert!(!trim_can_copy(Duration::from_millis(1500), Some(&keyframes)));
+        assert!(trim_can_copy(Duration::from_millis(1500), None));
+
+        let config = Config::default();
+        let input = PathBuf::from("/recordings/rec.mkv");
+        let output = PathBuf::from("/recordings/rec.trimmed.mkv");
+        let args = trim_args(&input, &output, Duration::from_secs(2), Duration::from_secs(10), true, &config);
+        assert_eq!(values_of(&args, "-ss"), vec!["2.000"]);
+        assert_eq!(values_of(&args, "-t"), vec!["8.000"]);
+        assert_eq!(values_of(&args, "-c"), vec!["copy"]);
+        let args = trim_args(&input, &output, Duration::from_millis(1500), Duration::from_secs(10), false, &config);
+        assert_eq!(values_of(&args, "-c:v"), vec!["libx264"]);
+        assert!(values_of(&args, "-movflags").is_empty());
+
+        // A re-encode stays in the input's container
+        let (input, output) = (PathBuf::from("/recordings/rec.webm"), PathBuf::from("/recordings/rec.trimmed.webm"));
+        let args = trim_args(&input, &output, Duration::from_millis(1500), Duration::from_secs(10), false, &config);
+        assert_eq!(values_of(&args, "-c:v"), vec!["libvpx-vp9"]);
+        let (input, output) = (PathBuf::from("/recordings/rec.mp4"), PathBuf::from("/recordings/rec.trimmed.mp4"));
+        let nvenc = Config { hw_accel: HwAccel::Nvenc, ..Config::default() };
+        let args = trim_args(&input, &output, Duration::from_millis(1500), Duration::from_secs(10), false, &nvenc);
+        assert_eq!(values_of(&args, "-c:v"), vec!["h264_nvenc"]);
+        assert_eq!(values_of(&args, "-movflags"), vec!["+faststart"]);
+        let args = trim_args(&input, &output, Duration::from_secs(2), Duration::from_secs(10), true, &nvenc);
+        assert_eq!(values_of(&args, "-movflags"), vec!["+faststart"], "copies keep the index in front too");
+    }
+
+    #[test]
+    fn merge_restores_audio_offset_and_names_the_result() {
+        let video = PathBuf::from("/recordings/rec.video.mkv");
+        let audio = PathBuf::from("/recordings/rec.audio.flac");
+        let output = merged_path(&video);
+        assert_eq!(output, PathBuf::from("/recordings/rec.merged.mkv"));
+
+        let args = merge_args(&video, &audio, &output, 0.25, false);
+        assert_eq!(values_of(&args, "-itsoffset"), vec!["0.250"]);
+        assert_eq!(values_of(&args, "-map"), vec!["0:v", "1:a"]);
+        assert_eq!(values_of(&args, "-c"), vec!["copy"]);
+
+        let args = merge_args(&video, &audio, &output, 0.0, true);
+        assert!(values_of(&args, "-itsoffset").is_empty());
+        assert_eq!(values_of(&args, "-c:a"), vec!["aac"]);
+        assert!(values_of(&args, "-movflags").is_empty());
+
+        // The merged file stays in the video's container
+        let output = merged_path(&PathBuf::from("/recordings/rec.video.mp4"));
+        assert_eq!(output, PathBuf::from("/recordings/rec.merged.mp4"));
+        let args = merge_args(&video, &audio, &output, 0.0, false);
+        assert_eq!(values_of(&args, "-movflags"), vec!["+faststart"]);
+        let output = merged_path(&PathBuf::from("/recordings/rec.video.webm"));
+        assert_eq!(output, PathBuf::from("/recordings/rec.merged.webm"));
+        assert_eq!(values_of(&merge_args(&video, &audio, &output, 0.0, true), "-c:a"), vec!["libopus"]);
+    }
+
+    #[test]
+    fn batch_reencode_never_overwrites_the_input() {
+        let input = PathBuf::from("/recordings/rec.mkv");
+        assert_eq!(batch_output_path(&input, BatchContainer::Mp4), PathBuf::from("/recordings/rec.mp4"));
+        assert_eq!(batch_output_path(&input, BatchContainer::Mkv), PathBuf::from("/recordings/rec.reencoded.mkv"));
+
+        let output = batch_output_path(&input, BatchContainer::Mp4);
+        let config = Config { video_quality: VideoQuality::High, ..Config::default() };
+        let args = batch_args(&input, &output, BatchContainer::Mp4, false, &config);
+        assert_eq!(values_of(&args, "-crf"), vec!["20"]);
+        assert_eq!(values_of(&args, "-movflags"), vec!["+faststart"]);
+        assert_eq!(args.last().map(String::as_str), Some("/recordings/rec.mp4"));
+        assert!(!args.iter().any(|a| a == "-re"));
+    }
+
+    #[test]
+    fn batch_reencode_uses_the_configured_encoder() {
+        let input = PathBuf::from("/recordings/rec.mkv");
+        let output = batch_output_path(&input, BatchContainer::Mkv);
+        let lossless = Config { video_quality: VideoQuality::Lossless, ..Config::default() };
+        let args = batch_args(&input, &output, BatchContainer::Mkv, false, &lossless);
+        assert_eq!(values_of(&args, "-qp"), vec!["0"]);
+        assert!(values_of(&args, "-movflags").is_empty());
+
+        let nvenc = Config { hw_accel: HwAccel::Nvenc, ..Config::default() };
+        let args = batch_args(&input, &output, BatchContainer::Mkv, false, &nvenc);
+        assert_eq!(values_of(&args, "-c:v"), vec!["h264_nvenc"]);
+
+        let vaapi = Config { hw_accel: HwAccel::Vaapi, ..Config::default() };
+        let args = batch_args(&input, &output, BatchContainer::Mp4, false, &vaapi);
+        assert_eq!(values_of(&args, "-c:v"), vec!["h264_vaapi"]);
+        assert_eq!(values_of(&args, "-vaapi_device"), vec![VAAPI_DEVICE]);
+        assert!(args.iter().position(|a| a == "-vaapi_device") < args.iter().position(|a| a == "-i"));
+        assert_eq!(values_of(&args, "-vf"), vec!["format=nv12,hwupload"]);
+    }
+
+    #[test]
+    fn realtime_pacing_only_applies_to_file_inputs() -> Result<()> {
+        let input = PathBuf::from("/recordings/rec.mkv");
+        let output = batch_output_path(&input, BatchContainer::Mkv);
+        let args = batch_args(&input, &output, BatchContainer::Mkv, true, &Config::default());
+        let re = args.iter().position(|a| a == "-re").expect("-re is passed");
+        assert_eq!(args[re + 1], "-i", "-re must precede the file input");
+
+        let cli = CliArgs::parse(strings(&["--batch", "--realtime", "a.mkv"]))?;
+        assert!(cli.realtime);
+        assert_eq!(cli.batch_files, vec![PathBuf::from("a.mkv")]);
+
+        let inputs = EffectiveInputs { screen: Some(screen_input()), ..Default::default() };
+        let (args, _) = ffmpeg_args(&capture_options(false, true, false), &inputs, "rec")?;
+        assert!(!args.iter().any(|a| a == "-re"), "live capture is never paced");
+        Ok(())
+    }
+
+    #[test]
+    fn intro_and_outro_are_normalized_to_the_recording() -> Result<()> {
+        let recording = parse_media_format(
+            "codec_name=h264|codec_type=video|width=1920|height=1080|r_frame_rate=60/1\n\
+             codec_name=aac|codec_type=audio|sample_rate=48000|channels=2\n",
+        );
+        let intro = parse_media_format("codec_name=h264|codec_type=video|width=1280|height=720|r_frame_rate=30/1\n");
+        assert_eq!(recording.video.as_ref().map(|v| (v.width, v.frame_rate.as_str())), Some((1920, "60/1")));
+        assert_eq!(recording.audio.as_ref().map(|a| a.sample_rate), Some(48000));
+        assert!(intro.audio.is_none());
+
+        let part = |path: &str, format: &MediaFormat| BumperPart {
+            path: PathBuf::from(path),
+            format: format.clone(),
+            duration: Duration::from_secs(5),
+        };
+        let rec_path = PathBuf::from("/recordings/rec.mkv");
+        assert_eq!(final_path(&rec_path), PathBuf::from("/recordings/rec.final.mkv"));
+        assert_eq!(final_path(&PathBuf::from("/recordings/rec.mp4")), PathBuf::from("/recordings/rec.final.mp4"));
+        assert_eq!(final_path(&PathBuf::from("/recordings/rec.webm")), PathBuf::from("/recordings/rec.final.webm"));
+        assert!(bumpers_can_copy(&[part("/clips/a.mkv", &recording), part("/recordings/rec.mkv", &recording)]));
+        let parts = [part("/clips/intro.mp4", &intro), part("/recordings/rec.mkv", &recording)];
+        assert!(!bumpers_can_copy(&parts));
+
+        let config = Config::default();
+        let args = bumper_concat_args(&parts, &recording, &final_path(&rec_path), &config)?;
+        assert_eq!(values_of(&args, "-i"), vec!["/clips/intro.mp4", "/recordings/rec.mkv", "anullsrc=channel_layout=stereo:sample_rate=48000"]);
+        let graph = values_of(&args, "-filter_complex")[0];
+        assert!(graph.contains("[0:v]scale=1920:1080:force_original_aspect_ratio=decrease,pad=1920:1080"));
+        assert!(graph.contains("fps=60/1"));
+        assert!(graph.contains("[2:a]aresample=48000"), "the silent intro gets generated audio");
+        assert!(graph.ends_with("[v0][a0][v1][a1]concat=n=2:v=1:a=1[v][a]"));
+        assert_eq!(values_of(&args, "-map"), vec!["[v]", "[a]"]);
+        assert_eq!(values_of(&args, "-c:v"), vec!["libx264"]);
+        assert_eq!(values_of(&args, "-c:a"), vec!["aac"]);
+        assert!(values_of(&args, "-movflags").is_empty());
+        assert_eq!(args.last().map(String::as_str), Some("/recordings/rec.final.mkv"));
+
+        // The recording's container and the configured encoder carry over
+        let vaapi = Config { hw_accel: HwAccel::Vaapi, ..Config::default() };
+        let args = bumper_concat_args(&parts, &recording, &PathBuf::from("/recordings/rec.final.mp4"), &vaapi)?;
+        assert_eq!(values_of(&args, "-vaapi_device"), vec![VAAPI_DEVICE]);
+        assert_eq!(values_of(&args, "-c:v"), vec!["h264_vaapi"]);
+        assert!(values_of(&args, "-vf").is_empty(), "the upload joins the filter graph");
+        assert!(values_of(&args, "-filter_complex")[0].ends_with("[joined]format=nv12,hwupload[v]"));
+        assert_eq!(values_of(&args, "-movflags"), vec!["+faststart"]);
+        let args = bumper_concat_args(&parts, &recording, &PathBuf::from("/recordings/rec.final.webm"), &vaapi)?;
+        assert_eq!(values_of(&args, "-c:v"), vec!["libvpx-vp9"]);
+        assert_eq!(values_of(&args, "-c:a"), vec!["libopus"]);
+        Ok(())
+    }
+
+    #[test]
+    fn thumbnails_grab_a_few_seconds_in_and_tile_the_sheet() {
+        assert_eq!(thumbnail_time(Duration::from_secs(600)), Duration::from_secs(3));
+        assert_eq!(thumbnail_time(Duration::from_secs(2)), Duration::from_secs(1));
+
+        let input = PathBuf::from("/recordings/rec.mkv");
+        let args = thumbnail_args(&input, &PathBuf::from("/recordings/rec.jpg"), Duration::from_secs(3));
+        assert_eq!(values_of(&args, "-ss"), vec!["3.000"]);
+        assert_eq!(values_of(&args, "-frames:v"), vec!["1"]);
+
+        let args = contact_sheet_args(&input, &PathBuf::from("/recordings/rec.sheet.jpg"), Duration::from_secs(160));
+        assert_eq!(values_of(&args, "-vf"), vec!["fps=16/160.000,scale=320:-2,tile=4x4"]);
+    }
+
+    #[test]
+    fn encoder_stats_are_parsed_from_progress_lines() {
+        let stats = parse_stats("frame=  120 fps= 60 q=23.0 size=    1024KiB time=00:00:02.00 bitrate=4194.3kbits/s speed=1x")
+            .unwrap();
+        assert_eq!(stats.fps, Some(60.0));
+        assert_eq!(stats.size_bytes, Some(1024 * 1024));
+        assert_eq!(stats.bitrate_kbps, Some(4194.3));
+        assert_eq!(stats.time, Some(Duration::from_secs(2)));
+        assert_eq!(stats.speed, Some(1.0));
+
+        let stats = parse_stats("frame=    0 fps=0.0 q=0.0 size=       0KiB time=-577014:32:22.77 bitrate=N/A speed=N/A").unwrap();
+        assert_eq!(stats.size_bytes, Some(0));
+        assert_eq!(stats.bitrate_kbps, None);
+        assert_eq!(stats.time, None);
+        assert!(parse_stats("Input #0, x11grab, from ':0.0':").is_none());
+    }
+
+    #[test]
+    fn rising_drop_counts_are_reported() {
+        let stats =
+            parse_stats("frame=  100 fps= 58 q=23.0 size=    1024KiB time=00:00:01.66 bitrate=5000.0kbits/s dup=3 drop=12 speed=0.97x")
+                .unwrap();
+        assert_eq!(stats.dropped_frames, Some(12));
+        assert_eq!(stats.duplicated_frames, Some(3));
+
+        let sample = |drop| EncoderStats { dropped_frames: Some(drop), ..Default::default() };
+        let steady: Vec<_> = (0..40).map(|_| sample(12)).collect();
+        assert!(!is_dropping_frames(&steady));
+        let rising: Vec<_> = (0..40).map(sample).collect();
+        assert!(is_dropping_frames(&rising));
+        assert!(!is_dropping_frames(&[EncoderStats::default()]));
+    }
+
+    #[test]
+    fn file_name_template_fills_in_a_sanitized_window_title() {
+        let now = chrono::Local.with_ymd_and_hms(2024, 5, 6, 7, 8, 9).unwrap();
+        assert_eq!(expand_file_name(DEFAULT_FILE_NAME_TEMPLATE, now, None), "recording_20240506_070809");
+        assert_eq!(
+            expand_file_name("{window}_{date}", now, Some("main.rs — ~/src/octocord: Editor")),
+            "main.rs_src_octocord_Editor_20240506"
+        );
+        assert_eq!(expand_file_name("{window}", now, None), "recording");
+        assert_eq!(expand_file_name("{window}", now, Some("../../")), "recording");
+        assert_eq!(expand_file_name("  ", now, None), "recording_20240506_070809");
+    }
+
+    #[test]
+    fn webcam_snapshots_are_named_by_time() {
+        let now = chrono::Local.with_ymd_and_hms(2024, 5, 6, 7, 8, 9).unwrap();
+        assert_eq!(
+            crate::webcam::snapshot_path(&PathBuf::from("/recordings"), now),
+            PathBuf::from("/recordings/webcam_20240506_070809.jpg")
+        );
+    }
+
+    #[test]
+    fn webcam_border_becomes_a_drawbox_unless_disabled() {
+        assert_eq!(
+            webcam_border_filter(&OverlayBorder::default()).as_deref(),
+            Some("drawbox=x=0:y=0:w=iw:h=ih:color=0x5865F2:t=2")
+        );
+        assert_eq!(webcam_border_filter(&OverlayBorder { color: [255, 0, 0], width: 0 }), None);
+    }
+
+    #[test]
+    fn chroma_key_matches_colorkey_in_preview_and_recording() {
+        use crate::webcam::chroma_key_alpha;
+
+        let green = [0, 177, 64];
+        assert_eq!(chroma_key_alpha(green, green, 0.3, 0.1), 0.0);
+        assert_eq!(chroma_key_alpha([255, 255, 255], green, 0.3, 0.1), 1.0);
+        let edge = chroma_key_alpha([60, 177, 64], green, 0.1, 0.2);
+        assert!(edge > 0.0 && edge < 1.0, "{}", edge);
+        assert_eq!(chroma_key_alpha([60, 177, 64], green, 0.1, 0.0), 1.0);
+
+        let key = ChromaKey { enabled: true, ..ChromaKey::default() };
+        assert_eq!(webcam_key_filter(&key), "colorkey=color=0x00B140:similarity=0.300:blend=0.100");
+    }
+
+    #[test]
+    fn background_blur_keeps_the_subject_box_sharp() {
+        use image::{DynamicImage, Rgb, RgbImage};
+
+        let mut frame = RgbImage::from_pixel(40, 20, Rgb([0, 0, 0]));
+        frame.put_pixel(20, 10, Rgb([255, 255, 255]));
+        frame.put_pixel(1, 1, Rgb([255, 255, 255]));
+        let blur = BackgroundBlur { enabled: true, ..BackgroundBlur::default() };
+        let out = crate::webcam::blur_background(&DynamicImage::ImageRgb8(frame), &blur).to_rgb8();
+        assert_eq!(out.get_pixel(20, 10), &Rgb([255, 255, 255]));
+        assert_ne!(out.get_pixel(1, 1), &Rgb([255, 255, 255]));
+
+        let filter = webcam_blur_filter(&blur);
+        assert!(filter.starts_with("split[cam_bg][cam_fg];[cam_bg]boxblur=12:1[cam_blur];"), "{}", filter);
+        assert!(filter.contains("crop=trunc(iw*0.500/2)*2:trunc(ih*0.800/2)*2"), "{}", filter);
+    }
+
+    #[test]
+    fn screen_over_layout_swaps_base_and_overlay() {
+        let mut options = capture_options(true, true, true);
+        options.overlay_layout = OverlayLayout::ScreenOver;
+        options.webcam_overlay = OverlayPlacement { position: (900, 500), size: (480, 270), opacity: 0.8 };
+        options.capture_region = Some((100, 50, 1280, 720));
+        let graph = composite_graph(&options, "[0:v]", 2);
+        assert!(graph.starts_with("[0:v]split[screen_ref][screen_small];[2:v]null[cam_raw];"), "{}", graph);
+        // The webcam is scaled to fill the screen frame, as in the preview
+        assert!(graph.contains("[cam_raw][screen_ref]scale2ref="), "{}", graph);
+        assert!(graph.contains("[screen_canvas][cam_fill]overlay=(W-w)/2:(H-h)/2[cam_base]"), "{}", graph);
+        // and the screen goes into the overlay box, moved into the region
+        assert!(graph.contains("[screen_small]scale=480:270,drawbox="), "{}", graph);
+        assert!(graph.contains("colorchannelmixer=aa=0.800[screen_scaled]"), "{}", graph);
+        assert!(graph.ends_with("[cam_base][screen_scaled]overlay=800:450[overlayed]"), "{}", graph);
+        assert!(!graph.contains("colorkey"));
+    }
+
+    #[test]
+    fn cursor_zoom_captures_a_window_and_scales_it_back_up() -> Result<()> {
+        assert_eq!(cursor_zoom_window(1920, 1080, 2.0), (960, 540));
+        assert_eq!(cursor_zoom_window(1920, 1080, 3.0), (640, 360));
+        assert_eq!(cursor_zoom_window(1366, 768, 1.5), (910, 512));
+        assert_eq!(cursor_zoom_window(4, 4, 100.0), (2, 2));
+
+        let inputs = EffectiveInputs {
+            screen: Some(screen_input()),
+            webcam: Some("/dev/video0".to_string()),
+            zoomed_screen_size: Some((1920, 1080)),
+            ..Default::default()
+        };
+        let (args, _) = ffmpeg_args(&capture_options(false, true, false), &inputs, "rec")?;
+        assert_eq!(values_of(&args, "-vf"), vec!["scale=1920:1080:flags=lanczos"]);
+
+        // The webcam is laid over the zoomed screen, not magnified with it
+        let (args, _) = ffmpeg_args(&capture_options(false, true, true), &inputs, "rec")?;
+        let graph = values_of(&args, "-filter_complex")[0];
+        assert!(
+            graph.starts_with("[0:v]scale=1920:1080:flags=lanczos[screen];[1:v]scale=320:180"),
+            "{}",
+            graph
+        );
+        assert!(graph.contains("[screen][cam_scaled]overlay"), "{}", graph);
+        Ok(())
+    }
+
+    #[test]
+    fn clicks_are_read_from_xinput_and_drawn_as_a_growing_ring() -> Result<()> {
+        let mut parser = XinputParser::default();
+        let events = "EVENT type 17 (RawMotion)\n    device: 11 (11)\n    detail: 0\n\
+                      EVENT type 15 (RawButtonPress)\n    device: 11 (11)\n    detail: 1\n\
+                      EVENT type 15 (RawButtonPress)\n    device: 11 (11)\n    detail: 4\n\
+                      EVENT type 16 (RawButtonRelease)\n    device: 11 (11)\n    detail: 1\n";
+        let buttons: Vec<u32> = events.lines().filter_map(|line| parser.feed(line)).collect();
+        assert_eq!(buttons, vec![1], "wheel steps and releases are not clicks");
+        assert_eq!(parse_mouse_location("X=812\nY=403\nSCREEN=0\nWINDOW=123\n"), Some((812, 403)));
+        assert_eq!(parse_mouse_location("SCREEN=0\n"), None);
+
+        assert_eq!(click_ring_size(Duration::ZERO, 48), Some(16));
+        assert!(click_ring_size(CLICK_ANIMATION / 2, 48).is_some_and(|side| side > 16 && side < 48));
+        assert_eq!(click_ring_size(CLICK_ANIMATION, 48), None);
+
+        let mut options = capture_options(false, true, false);
+        options.click_highlight = Some(ClickHighlight { enabled: true, ..Default::default() });
+        let inputs = EffectiveInputs { screen: Some(screen_input()), ..Default::default() };
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(
+            values_of(&args, "-vf"),
+            vec!["drawbox@click=x=-1000:y=-1000:w=2:h=2:color=0xFFC800@0.9:t=4,scale=trunc(iw/2)*2:trunc(ih/2)*2"]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn piped_screen_reads_raw_frames_and_draws_effects_itself() -> Result<()> {
+        let input = rawvideo_input_args((1280, 720), 30);
+        assert_eq!(values_of(&input, "-f"), vec!["rawvideo"]);
+        assert_eq!(values_of(&input, "-pix_fmt"), vec!["rgba"]);
+        assert_eq!(values_of(&input, "-video_size"), vec!["1280x720"]);
+        assert_eq!(values_of(&input, "-i"), vec!["pipe:0"]);
+        assert_eq!(values_of(&input, "-thread_queue_size"), vec![PIPE_QUEUE_FRAMES.to_string()]);
+
+        let frame = image::RgbaImage::new(64, 48);
+        assert_eq!(crop_to_region(frame.clone(), Some((8, 8, 32, 16))).dimensions(), (32, 16));
+        assert_eq!(crop_to_region(frame, None).dimensions(), (64, 48));
+
+        let mut frame = image::RgbaImage::new(64, 64);
+        draw_ring(&mut frame, (32, 32), 40, [255, 200, 0]);
+        assert_eq!(frame.get_pixel(32, 13).0, [255, 200, 0, 255], "on the ring");
+        assert_eq!(frame.get_pixel(32, 32).0, [0, 0, 0, 0], "center untouched");
+        assert_eq!(frame.get_pixel(13, 13).0, [0, 0, 0, 0], "corners untouched");
+
+        let mut options = capture_options(false, true, false);
+        options.piped_screen = true;
+        options.click_highlight = Some(ClickHighlight { enabled: true, ..Default::default() });
+        let inputs = EffectiveInputs {
+            screen: Some(input),
+            screen_feed: Some(FeedSource { screen_index: 0, region: None, size: (1280, 720) }),
+            ..Default::default()
+        };
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(values_of(&args, "-i"), vec!["pipe:0"]);
+        assert_eq!(values_of(&args, "-vf"), vec!["scale=trunc(iw/2)*2:trunc(ih/2)*2"], "no drawbox ring");
+
+        let mut options = capture_options(true, true, false);
+        options.piped_screen = true;
+        options.mic_gate = MicGateMode::PushToTalk;
+        assert!(VideoEncoder::new(options).is_err(), "push-to-talk needs stdin");
+        Ok(())
+    }
+
+    #[test]
+    fn annotations_follow_the_drag_and_burn_into_the_captured_region() {
+        let mut stroke = Annotation::new(AnnotationTool::Pen, (10.0, 10.0), [255, 0, 0]);
+        stroke.drag_to((10.2, 10.3));
+        stroke.drag_to((20.0, 10.0));
+        stroke.drag_to((20.0, 30.0));
+        assert_eq!(stroke.segments().len(), 3, "sub-pixel move skipped");
+
+        let mut rect = Annotation::new(AnnotationTool::Rectangle, (0.0, 0.0), [255, 0, 0]);
+        rect.drag_to((5.0, 5.0));
+        rect.drag_to((40.0, 20.0));
+        assert_eq!(rect.points, vec![(0.0, 0.0), (40.0, 20.0)], "only the far corner moves");
+        assert_eq!(rect.segments().len(), 4);
+
+        let [left, right] = arrow_head((0.0, 0.0), (100.0, 0.0), 10.0);
+        assert!(left.0 < 100.0 && right.0 < 100.0, "head points back along the shaft");
+        assert!((left.1 + right.1).abs() < 1e-4 && (left.1 - right.1).abs() > 1.0);
+
+        // Screen pixels shifted into a capture region starting at (100, 50)
+        let line = Annotation { tool: AnnotationTool::Arrow, points: vec![(110.0, 60.0), (140.0, 60.0)], color: [0, 255, 0] };
+        let mut frame = image::RgbaImage::new(64, 32);
+        burn_in(&mut frame, &[line], (100, 50));
+        assert_eq!(frame.get_pixel(20, 10).0, [0, 255, 0, 255]);
+        assert_eq!(frame.get_pixel(20, 25).0, [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn segments_after_a_display_change_are_letterboxed_to_the_first_size() -> Result<()> {
+        assert_eq!(
+            screen_fit_filter(1921, 1080),
+            "scale=1920:1080:force_original_aspect_ratio=decrease,pad=1920:1080:(ow-iw)/2:(oh-ih)/2,setsar=1"
+        );
+
+        let mut options = capture_options(false, true, false);
+        options.screen_output_size = Some((1920, 1080));
+        let inputs = EffectiveInputs { screen: Some(screen_input()), ..Default::default() };
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(values_of(&args, "-vf"), vec![screen_fit_filter(1920, 1080)]);
+
+        let mut options = capture_options(false, true, true);
+        options.screen_output_size = Some((1920, 1080));
+        let inputs = EffectiveInputs {
+            screen: Some(screen_input()),
+            webcam: Some("/dev/video0".to_string()),
+            ..Default::default()
+        };
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        let graph = values_of(&args, "-filter_complex").join("");
+        assert!(graph.starts_with(&format!("[0:v]{}[screen];", screen_fit_filter(1920, 1080))), "{}", graph);
+
+        // Without seamless pause there is no segment to restart into
+        let mut encoder = VideoEncoder::new(RecorderOptions {
+            output_directory: env::temp_dir().join("discord_recorder_tests"),
+            seamless_pause: false,
+            ..capture_options(false, true, false)
+        })?;
+        assert!(!encoder.display_changed()?);
+        Ok(())
+    }
+
+    #[test]
+    fn capture_backend_panics_become_a_readable_failure() {
+        assert_eq!(capture_guarded(|| Ok::<_, String>(7)), Ok(7));
+        assert_eq!(capture_guarded(|| Err::<(), _>("no display")), Err("no display".to_string()));
+        let panicked = capture_guarded(|| -> Result<(), String> { panic!("ZwlrScreencopyManagerV1 not found") });
+        assert_eq!(panicked, Err(CAPTURE_UNSUPPORTED.to_string()));
+    }
+
+    #[test]
+    fn webcam_reconnects_back_off_and_give_up() {
+        let delays: Vec<u64> = (1..=MAX_RECONNECT_ATTEMPTS).map(|attempt| reconnect_delay(attempt).as_millis() as u64).collect();
+        assert_eq!(delays, vec![500, 1000, 2000, 4000, 8000, 8000]);
+        let total: Duration = (1..=MAX_RECONNECT_ATTEMPTS).map(reconnect_delay).sum();
+        assert!(total < Duration::from_secs(30), "a lost camera is reported within half a minute");
+        assert_eq!(WebcamStatus::default(), WebcamStatus::Streaming);
+    }
+
+    #[test]
+    fn reloaded_config_replaces_settings_but_keeps_the_session() {
+        let mut state = AppState::from_config(Config::default());
+        state.is_recording = true;
+        state.screen_zoom = 2.0;
+        state.audio_gain_db = 6.0;
+        let edited = Config { frame_rate: 60, record_webcam: true, ..Config::default() };
+        assert!(!edited.same_on_disk(&state.config));
+
+        state.reload_config(edited);
+        assert_eq!((state.frame_rate, state.record_webcam), (60, true));
+        assert_eq!(state.config.frame_rate, 60);
+        assert!(state.is_recording);
+        assert_eq!((state.screen_zoom, state.audio_gain_db), (2.0, 6.0));
+        assert!(state.config.same_on_disk(&Config { frame_rate: 60, record_webcam: true, ..Config::default() }));
+    }
+
+    #[test]
+    fn scenes_match_sources_and_only_compare_layout_when_combined() {
+        let config = Config::default();
+        let matching: Vec<&str> = config
+            .scenes
+            .iter()
+            .filter(|scene| scene.matches(true, true, OverlayLayout::SideBySide))
+            .map(|scene| scene.name.as_str())
+            .collect();
+        assert_eq!(matching, vec!["Side by side"]);
+        let full_screen = &config.scenes[0];
+        assert!(full_screen.matches(true, false, OverlayLayout::ScreenOver));
+        assert!(!full_screen.matches(true, true, OverlayLayout::WebcamOver));
+
+        let screen = image::DynamicImage::new_rgba8(160, 90);
+        let webcam = image::DynamicImage::new_rgba8(40, 30);
+        let combined = crate::webcam::side_by_side(&screen, &webcam);
+        assert_eq!((combined.width(), combined.height()), (160 + 120, 90));
+    }
+
+    #[test]
+    fn low_latency_adds_unbuffered_inputs_and_zerolatency() -> Result<()> {
+        let mut options = capture_options(true, true, true);
+        options.low_latency = true;
+        let inputs = EffectiveInputs {
+            screen: Some(screen_input()),
+            audio: Some(audio_input()),
+            webcam: Some("/dev/video0".to_string()),
+            ..Default::default()
+        };
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(values_of(&args, "-tune"), vec!["zerolatency"]);
+        assert_eq!(values_of(&args, "-flags").len(), 3, "screen, webcam and encoder");
+        assert!(values_of(&args, "-fflags").iter().all(|f| *f == "+nobuffer"));
+
+        options.low_latency = false;
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert!(values_of(&args, "-tune").is_empty());
+        assert!(values_of(&args, "-flags").is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn thread_cap_reaches_encoders_and_filter_graphs() -> Result<()> {
+        let config = Config { encoder_threads: Some(0), ..Config::default() };
+        assert_eq!(RecorderOptions::from_config(&config).threads, None, "zero means automatic");
+
+        let mut options = capture_options(true, true, true);
+        let inputs = EffectiveInputs {
+            screen: Some(screen_input()),
+            audio: Some(audio_input()),
+            webcam: Some("/dev/video0".to_string()),
+            ..Default::default()
+        };
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert!(values_of(&args, "-threads").is_empty(), "ffmpeg picks its own thread counts");
+        assert!(values_of(&args, "-filter_threads").is_empty());
+        assert!(values_of(&args, "-filter_complex_threads").is_empty());
+
+        options.threads = Some(4);
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(values_of(&args, "-threads"), vec!["4", "4"]);
+        assert_eq!(values_of(&args, "-filter_threads"), vec!["4"]);
+        assert_eq!(values_of(&args, "-filter_complex_threads"), vec!["4"]);
+        Ok(())
+    }
+
+    #[test]
+    fn pipe_output_writes_one_matroska_stream_and_no_files() -> Result<()> {
+        let mut options = capture_options(true, true, false);
+        options.separate_outputs = true;
+        options.output_target = OutputTarget::parse("-");
+        let inputs = EffectiveInputs {
+            screen: Some(screen_input()),
+            audio: Some(audio_input()),
+            ..Default::default()
+        };
+        let (args, outputs) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(&args[args.len() - 3..], ["-f", "matroska", "pipe:1"]);
+        assert!(outputs.paths().is_empty());
+
+        assert_eq!(OutputTarget::parse("/tmp/rec.fifo"), OutputTarget::Fifo(PathBuf::from("/tmp/rec.fifo")));
+
+        let cli = CliArgs::parse(strings(&["--dry-run", "--output", "-"]))?;
+        assert_eq!(cli.output_target, Some(OutputTarget::Stdout));
+        assert!(CliArgs::parse(strings(&["--output", "-"])).is_err(), "the window can't record to a pipe");
+        Ok(())
+    }
+
+    #[test]
+    fn config_save_replaces_the_file_atomically() -> Result<()> {
+        let dir = env::temp_dir().join("discord_recorder_tests").join(format!("config_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("config.json");
+        std::fs::write(&path, "{ half-written")?;
+
+        let config = Config { frame_rate: 48, ..Config::default() };
+        config.save_to(&path)?;
+        let saved: Config = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        assert!(saved.same_on_disk(&config));
+
+        write_atomically(&path, b"{}")?;
+        assert_eq!(std::fs::read_to_string(&path)?, "{}");
+        let mut names: Vec<_> = std::fs::read_dir(&dir)?.filter_map(|e| e.ok()?.file_name().into_string().ok()).collect();
+        names.sort();
+        assert_eq!(names, ["config.json", "config.json.lock"], "no temporary file is left behind");
+
+        assert_eq!(backup_path(&path), dir.join("config.json.bak"));
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_tone_fades_in_and_out_below_full_scale() {
+        let samples = tone_samples(TEST_TONE_HZ, 48_000, Duration::from_millis(500));
+        assert_eq!(samples.len(), 24_000);
+        let peak = samples.iter().fold(0.0f32, |acc, s| acc.max(s.abs()));
+        assert!(peak > 0.1 && peak < 0.5, "peak {}", peak);
+        assert!(samples[0].abs() < 1e-6 && samples[samples.len() - 1].abs() < 0.01);
+        assert!(samples[..48].iter().all(|s| s.abs() < peak / 2.0), "no click at the start");
+
+        assert!(tone_samples(TEST_TONE_HZ, 48_000, Duration::ZERO).is_empty());
+        assert_eq!(tone_samples(TEST_TONE_HZ, 48_000, Duration::from_micros(21)).len(), 1);
+    }
+
+    #[test]
+    fn amoled_theme_is_true_black_with_blurple_widgets() {
+        let amoled = discord_style(DiscordTheme::AMOLED).visuals;
+        let dark = discord_style(DiscordTheme::Dark).visuals;
+        for fill in [amoled.panel_fill, amoled.window_fill, amoled.extreme_bg_color] {
+            assert_eq!(fill, egui::Color32::BLACK);
+        }
+        assert_eq!(amoled.widgets.inactive.bg_fill, dark.widgets.inactive.bg_fill, "accent stays blurple");
+        let text = amoled.widgets.noninteractive.fg_stroke.color;
+        assert!(text.r() > dark.widgets.noninteractive.fg_stroke.color.r(), "text is brighter than on the dark theme");
+    }
+
+    #[test]
+    fn switched_scene_is_remembered_for_the_next_start() {
+        let mut config = Config { record_video: true, record_webcam: false, ..Config::default() };
+        let side_by_side = config.scenes.iter().find(|scene| scene.layout == OverlayLayout::SideBySide).cloned().unwrap();
+        side_by_side.remember_in(&mut config);
+
+        let restored = AppState::from_config(config);
+        assert!(restored.record_video && restored.record_webcam);
+        assert_eq!(restored.overlay_layout, OverlayLayout::SideBySide);
+        assert!(restored.enable_preview_overlay);
+        assert!(side_by_side.matches(restored.record_video, restored.record_webcam, restored.overlay_layout));
+    }
+
+    #[test]
+    fn hand_edited_config_values_are_pulled_back_into_range() -> Result<()> {
+        let mut value = serde_json::to_value(Config::default())?;
+        value["output_directory"] = "  ".into();
+        value["frame_rate"] = 0.into();
+        value["audio_balance"] = (-7.5).into();
+        value["monitor_volume"] = 40.0.into();
+        value["cursor_zoom"] = 0.5.into();
+        value["capture_region"] = serde_json::json!([10, 10, 0, 300]);
+        value["ducking"]["ratio"] = 0.0.into();
+        let mut config: Config = serde_json::from_value(value)?;
+
+        let fixes = config.validate_and_fix();
+        assert_eq!(fixes.len(), 7, "{:?}", fixes);
+        assert_eq!(config.output_directory, Config::default().output_directory);
+        assert_eq!(config.frame_rate, 10);
+        assert_eq!(config.audio_balance, -1.0);
+        assert_eq!(config.monitor_volume, 2.0);
+        assert_eq!(config.cursor_zoom, Some(1.25));
+        assert_eq!(config.capture_region, None);
+        assert_eq!(config.ducking.ratio, 1.0);
+
+        let mut nan = Config { limiter_makeup_db: f32::NAN, ..Config::default() };
+        nan.validate_and_fix();
+        assert_eq!(nan.limiter_makeup_db, Config::default().limiter_makeup_db);
+
+        assert!(Config::default().validate_and_fix().is_empty(), "defaults are left alone");
+        Ok(())
+    }
+
+    #[test]
+    fn camera_control_values_snap_to_the_device_range() {
+        let exposure = CameraControlRange { setting: CameraSetting::Exposure, min: 3, max: 2047, step: 4, value: 250, default: 250 };
+        assert_eq!(exposure.snap(100), 99);
+        assert_eq!(exposure.snap(102), 103);
+        assert_eq!(exposure.snap(-50), 3);
+        assert_eq!(exposure.snap(5000), 2047);
+
+        let gain = CameraControlRange { setting: CameraSetting::Gain, min: 0, max: 255, step: 0, value: 0, default: 0 };
+        assert_eq!(gain.snap(17), 17, "a zero step counts as 1");
+    }
+
+    #[test]
+    fn overlay_fade_eases_to_the_configured_opacity() {
+        assert_eq!(overlay_fade_opacity(0.9, 0.0), 0.0);
+        assert!((overlay_fade_opacity(0.9, 1.0) - 0.9).abs() < 1e-6);
+        assert!((overlay_fade_opacity(0.6, 0.5) - 0.3).abs() < 1e-6, "halfway in is half the target");
+        assert!(overlay_fade_opacity(1.0, 0.1) < 0.1, "starts slower than linear");
+        assert_eq!(overlay_fade_opacity(0.5, 3.0), 0.5);
+    }
+
+    #[test]
+    fn overlay_snaps_edges_and_center_to_third_lines() {
+        let frame = (1920.0, 1080.0);
+        let size = (320.0, 180.0);
+        // Left edge near x = 640, top edge near y = 360
+        assert_eq!(snap_rect_to_thirds((645.0, 356.0), size, frame, 10.0), (640.0, 360.0));
+        // Right edge near x = 1280, center near y = 720
+        assert_eq!(snap_rect_to_thirds((955.0, 633.0), size, frame, 10.0), (960.0, 630.0));
+        // Too far from any line on x; y still snaps
+        assert_eq!(snap_rect_to_thirds((100.0, 362.0), size, frame, 10.0), (100.0, 360.0));
+    }
+
+    #[test]
+    fn identical_cameras_get_numbered_names_and_match_exactly() {
+        let names = unique_camera_names(&["USB Cam".to_string(), "Integrated".to_string(), "USB Cam".to_string()]);
+        assert_eq!(names, ["USB Cam", "Integrated", "USB Cam #2"]);
+
+        assert_eq!(match_camera(&names, "USB Cam #2"), Some(2));
+        assert_eq!(match_camera(&names, "USB Cam"), Some(0), "an exact match beats a longer name containing it");
+        assert_eq!(match_camera(&names, "Integ"), Some(1));
+        assert_eq!(match_camera(&names, "Gone"), Some(0), "unknown names fall back to the first camera");
+        assert_eq!(match_camera(&[], "USB Cam"), None);
+    }
+
+    #[test]
+    fn default_output_directory_follows_the_platform_videos_folder() {
+        let videos = PathBuf::from("/data/clips");
+        assert_eq!(default_output_directory(Some(videos.clone()), None), videos.join(RECORDINGS_FOLDER));
+
+        let home = PathBuf::from("/home/sam");
+        let fallback = default_output_directory(None, Some(home.clone()));
+        let videos_name = if cfg!(target_os = "macos") { "Movies" } else { "Videos" };
+        assert_eq!(fallback, home.join(videos_name).join(RECORDINGS_FOLDER));
+
+        let expected = default_output_directory(dirs::video_dir(), dirs::home_dir());
+        assert_eq!(Config::default().output_directory, expected.to_string_lossy());
+    }
+
+    #[test]
+    fn preview_splitter_moves_with_the_drag_within_limits() {
+        assert!((drag_split(0.5, 90.0, 900.0) - 0.6).abs() < 1e-6);
+        assert!((drag_split(0.5, -90.0, 900.0) - 0.4).abs() < 1e-6);
+        assert_eq!(drag_split(0.5, 5000.0, 900.0), *PREVIEW_SPLIT_RANGE.end());
+        assert_eq!(drag_split(0.5, -5000.0, 900.0), *PREVIEW_SPLIT_RANGE.start());
+        assert_eq!(drag_split(0.5, 40.0, 0.0), 0.5, "no height, no change");
+
+        let restored = AppState::from_config(Config { preview_split: 0.4, ..Config::default() });
+        assert_eq!(restored.preview_split, 0.4);
+    }
+
+    #[test]
+    fn dropped_paths_pick_a_writable_output_directory() {
+        let dir = env::temp_dir().join("discord_recorder_tests").join("dropped_output");
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("clip.mkv");
+        std::fs::write(&file, b"").unwrap();
+
+        assert_eq!(dropped_output_directory(&dir), Some(dir.clone()));
+        assert_eq!(dropped_output_directory(&file), Some(dir.clone()));
+        assert_eq!(dropped_output_directory(Path::new("clip.mkv")), None);
+
+        check_writable_directory(&dir).unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1, "probe file is removed");
+        assert!(check_writable_directory(&file).is_err());
+        assert!(check_writable_directory(&dir.join("missing")).is_err());
+    }
+
+    #[test]
+    fn statistics_summary_separates_current_and_average_rates() {
+        assert_eq!(summarize_stats(&[]), StatsSummary::default());
+
+        // 60 fps for ten seconds, then 30 fps and half the bitrate
+        let sample = |second: u64| {
+            let (frames, kbytes) = if second <= 10 {
+                (second * 60, second * 500)
+            } else {
+                (600 + (second - 10) * 30, 5000 + (second - 10) * 250)
+            };
+            parse_stats(&format!(
+                "frame={} fps=45 q=23.0 size={}kB time=00:00:{:02}.00 bitrate=3000.0kbits/s speed=1x drop=2",
+                frames, kbytes, second
+            ))
+            .unwrap()
+        };
+        let history: Vec<EncoderStats> = (1..=20).map(sample).collect();
+        let summary = summarize_stats(&history);
+        assert_eq!(history[0].frames, Some(60));
+        assert!((summary.current_fps.unwrap() - 30.0).abs() < 0.01);
+        assert!((summary.average_fps.unwrap() - 45.0).abs() < 0.01);
+        assert!((summary.bitrate_kbps.unwrap() - 2048.0).abs() < 1.0, "{:?}", summary.bitrate_kbps);
+        assert_eq!(summary.time, Some(Duration::from_secs(20)));
+        assert_eq!(summary.dropped_frames, Some(2));
+        assert_eq!(summary.duplicated_frames, None);
+
+        // A single sample falls back to ffmpeg's own averages
+        let first = summarize_stats(&history[..1]);
+        assert_eq!(first.current_fps, Some(45.0));
+        assert_eq!(first.bitrate_kbps, Some(3000.0));
+    }
+
+    #[test]
+    fn overlay_size_presets_keep_the_corner_and_fit_the_screen() {
+        let large = OVERLAY_SIZE_PRESETS[2].1;
+        let frame = Some((1920, 1080));
+        // Bottom-right stays bottom-right, top-left stays put
+        assert_eq!(overlay_size_preset((1580, 880), (320, 180), large, frame), ((1420, 790), large));
+        assert_eq!(overlay_size_preset((20, 20), (320, 180), large, frame), ((20, 20), large));
+        // Screen not known yet: just the size
+        assert_eq!(overlay_size_preset((1580, 880), (320, 180), large, None), ((1580, 880), large));
+        // Smaller than the preset: shrunk to fit, still 16:9
+        assert_eq!(overlay_size_preset((0, 0), (320, 180), large, Some((400, 300))), ((0, 0), (400, 225)));
+    }
+
+    #[test]
+    fn auto_stop_durations_parse_and_stay_in_range() {
+        use crate::postprocess::{format_timestamp, parse_timestamp};
+
+        assert_eq!(parse_timestamp("01:30:00"), Some(Duration::from_secs(5400)));
+        assert_eq!(parse_timestamp(" 45:10 "), Some(Duration::from_secs(2710)));
+        assert_eq!(parse_timestamp("90"), Some(Duration::from_secs(90)));
+        assert_eq!(parse_timestamp(&format_timestamp(Duration::from_secs(3725))), Some(Duration::from_secs(3725)));
+        for bad in ["", "1:60:00", "00:00:75", "1:2:3:4", "ten", "-5"] {
+            assert_eq!(parse_timestamp(bad), None, "{:?}", bad);
+        }
+
+        let mut config = Config { auto_stop: true, auto_stop_after_secs: 0, ..Config::default() };
+        assert!(!config.validate_and_fix().is_empty());
+        assert_eq!(config.auto_stop_after_secs, 1);
+        assert!(!Config::default().auto_stop);
+    }
+
+    #[test]
+    fn accessible_names_are_translated_and_free_of_glyphs() {
+        use crate::i18n::{translate, Language};
+
+        let keys = [
+            "a11y.record.start", "a11y.record.stop", "a11y.record.pause", "a11y.record.resume",
+            "a11y.record.marker", "a11y.marker_note", "a11y.webcam", "a11y.scene", "a11y.settings", "a11y.shortcuts",
+            "a11y.status.recording", "a11y.status.paused", "a11y.status.finalizing", "a11y.status.idle",
+            "a11y.status.pausing", "a11y.status.resuming", "a11y.status.restarting", "a11y.auto_stop",
+            "a11y.auto_stop.duration",
+        ];
+        for key in keys {
+            let english = translate(Language::English, key);
+            let german = translate(Language::German, key);
+            assert_ne!(english, key, "{} has no English name", key);
+            assert_ne!(german, english, "{} has no German name", key);
+            for name in [english, german] {
+                assert!(name.chars().all(|c| (c as u32) < 0x2000), "{:?} contains a symbol", name);
+            }
+        }
+    }
+
+    #[test]
+    fn fit_to_window_ignores_the_manual_zoom() {
+        assert_eq!(effective_zoom(2.5, false), 2.5);
+        assert_eq!(effective_zoom(2.5, true), 1.0);
+        assert_eq!(effective_zoom(9.0, false), 4.0);
+        let fitted = preview_scale(1920.0, 1080.0, 1.0, 960.0, 1080.0, effective_zoom(3.0, true));
+        assert_eq!(fitted, preview_scale(1920.0, 1080.0, 1.0, 960.0, 1080.0, 1.0));
+
+        let state = AppState::from_config(Config::default());
+        assert!(!state.screen_fit && !state.webcam_fit);
+    }
+
+    #[test]
+    fn marker_notes_become_subtitle_cues() {
+        use crate::config::MarkerSubtitles;
+        use crate::postprocess::{subtitle_cues, subtitles_document};
+        use crate::video::Marker;
+
+        let marker = |secs: u64, note: &str| Marker { at: Duration::from_secs(secs), note: note.to_string() };
+        let markers = [marker(62, ""), marker(5, "Boss fight"), marker(7, "Loot"), marker(500, "after the end")];
+        let cues = subtitle_cues(&markers, Duration::from_secs(64));
+        assert_eq!(
+            cues,
+            vec![
+                (Duration::from_secs(5), Duration::from_secs(7), "Boss fight".to_string()),
+                (Duration::from_secs(7), Duration::from_secs(11), "Loot".to_string()),
+                (Duration::from_secs(62), Duration::from_secs(64), "Marker 3 (01:02)".to_string()),
+            ]
+        );
+
+        let vtt = subtitles_document(MarkerSubtitles::WebVtt, &cues[..1]);
+        assert_eq!(vtt, "WEBVTT\n\n00:00:05.000 --> 00:00:07.000\nBoss fight\n\n");
+        let srt = subtitles_document(MarkerSubtitles::Srt, &cues[1..2]);
+        assert_eq!(srt, "1\n00:00:07,000 --> 00:00:11,000\nLoot\n\n");
+        assert_eq!(MarkerSubtitles::Off.extension(), None);
+    }
+
+    #[test]
+    fn presets_round_trip_and_fall_back_on_missing_devices() {
+        use crate::config::{OverlayLayout, VideoQuality};
+        use crate::presets::{import_preset, list_presets, preset_file_name, save_preset, Preset, PRESET_VERSION};
+
+        let source = Config {
+            video_quality: VideoQuality::High,
+            frame_rate: 30,
+            overlay_layout: OverlayLayout::SideBySide,
+            default_audio_device: Some("Studio Mic".to_string()),
+            default_webcam: Some("Elsewhere Cam".to_string()),
+            ..Config::default()
+        };
+        let preset = Preset::from_config(" Stream: night ", &source);
+        let json = serde_json::to_string(&preset).unwrap();
+        assert_eq!(Preset::parse(&json).unwrap(), preset);
+        assert_eq!(preset_file_name(&preset.name), "Stream_ night.json");
+
+        let mut target = Config { default_webcam: Some("Local Cam".to_string()), ..Config::default() };
+        let notes = preset.apply_to(&mut target, &["Studio Mic".to_string()], &["Local Cam".to_string()]);
+        assert_eq!((target.video_quality, target.frame_rate), (VideoQuality::High, 30));
+        assert_eq!(target.overlay_layout, OverlayLayout::SideBySide);
+        assert_eq!(target.default_audio_device.as_deref(), Some("Studio Mic"));
+        assert_eq!(target.default_webcam.as_deref(), Some("Local Cam"));
+        assert_eq!(notes.len(), 1, "{:?}", notes);
+
+        // Settings the file leaves out stay as they were
+        let partial = r#"{"format":"discord-recorder-preset","version":1,"name":"Fast","settings":{"frame_rate":120}}"#;
+        let mut kept = Config { video_quality: VideoQuality::Low, ..Config::default() };
+        Preset::parse(partial).unwrap().apply_to(&mut kept, &[], &[]);
+        assert_eq!((kept.video_quality, kept.frame_rate), (VideoQuality::Low, 120));
+
+        let newer = json.replace(&format!("\"version\":{}", PRESET_VERSION), "\"version\":99");
+        assert!(Preset::parse(&newer).is_err());
+        assert!(Preset::parse(r#"{"format":"something-else","version":1,"name":"x"}"#).is_err());
+
+        let dir = env::temp_dir().join("discord_recorder_tests").join("presets");
+        let _ = std::fs::remove_dir_all(&dir);
+        let shared = save_preset(&dir.join("outbox"), &preset).unwrap();
+        assert_eq!(import_preset(&shared, &dir).unwrap(), preset);
+        let listed = list_presets(&dir);
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].1.name, "Stream: night");
+    }
+
+    #[test]
+    fn flac_recordings_convert_to_mp3_beside_the_original() -> Result<()> {
+        use crate::postprocess::{mp3_args, mp3_path};
+
+        let input = PathBuf::from("/recordings/call.audio.flac");
+        let output = mp3_path(&input);
+        assert_eq!(output, PathBuf::from("/recordings/call.audio.mp3"));
+        assert_eq!(mp3_path(&output), PathBuf::from("/recordings/call.audio.converted.mp3"));
+        let args = mp3_args(&input, &output, 256);
+        assert!(args.windows(2).any(|w| w == ["-c:a", "libmp3lame"]));
+        assert!(args.windows(2).any(|w| w == ["-b:a", "256k"]));
+        assert_eq!(args.last().map(String::as_str), Some("/recordings/call.audio.mp3"));
+
+        let cli = CliArgs::parse(strings(&["--convert-mp3", "a.flac", "--mp3-bitrate", "320", "b.flac"]))?;
+        assert!(cli.convert_mp3);
+        assert_eq!(cli.convert_files, vec![PathBuf::from("a.flac"), PathBuf::from("b.flac")]);
+        assert_eq!(cli.mp3_bitrate, 320);
+        assert_eq!(CliArgs::parse(strings(&["--convert-mp3"]))?.mp3_bitrate, 192);
+        assert!(CliArgs::parse(strings(&["--convert-mp3", "--mp3-bitrate=100"])).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn seamless_pause_is_the_default_and_joins_parts_in_order() -> Result<()> {
+        assert!(Config::default().seamless_pause);
+        // Configs saved before the setting existed get it too
+        let mut json = serde_json::to_value(Config::default())?;
+        json.as_object_mut().unwrap().remove("seamless_pause");
+        let old: Config = serde_json::from_value(json)?;
+        assert!(old.seamless_pause);
+
+        // Paused twice: three parts, joined in the order they were recorded
+        let parts: Vec<PathBuf> = (0..3).map(|i| PathBuf::from(format!("/tmp/rec.part{:03}.mp4", i))).collect();
+        let refs: Vec<&PathBuf> = parts.iter().collect();
+        assert_eq!(
+            concat_list(&refs),
+            "file '/tmp/rec.part000.mp4'\nfile '/tmp/rec.part001.mp4'\nfile '/tmp/rec.part002.mp4'\n"
+        );
+        let quoted = PathBuf::from("/tmp/it's.part000.mp4");
+        assert_eq!(concat_list(&[&quoted]), "file '/tmp/it'\\''s.part000.mp4'\n");
+
+        let args = concat_args(Path::new("/tmp/rec.concat.txt"), Path::new("/tmp/rec.mp4"), Some(OutputContainer::Mp4));
+        let position = |flag: &str| args.iter().position(|a| a == flag);
+        assert_eq!(args[position("-i").unwrap() + 1], "/tmp/rec.concat.txt");
+        assert_eq!(args[position("-c").unwrap() + 1], "copy");
+        assert_eq!(args[position("-avoid_negative_ts").unwrap() + 1], "make_zero");
+        assert_eq!(values_of(&args, "-movflags"), vec!["+faststart"], "the joined MP4 keeps its index up front");
+        assert_eq!(args.last().map(String::as_str), Some("/tmp/rec.mp4"));
+        let audio = concat_args(Path::new("/tmp/rec.concat.txt"), Path::new("/tmp/rec.audio.flac"), None);
+        assert!(values_of(&audio, "-movflags").is_empty());
+        assert_eq!(container_flags(Some(OutputContainer::Mkv)), Vec::<String>::new());
+        Ok(())
+    }
+
+    #[test]
+    fn output_container_sets_extension_codecs_and_faststart() -> Result<()> {
+        let inputs = EffectiveInputs {
+            screen: Some(screen_input()),
+            audio: Some(audio_input()),
+            ..Default::default()
+        };
+        let mut options = capture_options(true, true, false);
+        options.audio_export_format = AudioExportFormat::Flac;
+        options.output_container = OutputContainer::Mp4;
+        let (args, outputs) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(outputs.combined, Some(PathBuf::from("/recordings/rec.mp4")));
+        assert_eq!(values_of(&args, "-c:a"), vec!["aac"]);
+        assert_eq!(values_of(&args, "-movflags"), vec!["+faststart"]);
+
+        options.separate_outputs = true;
+        let (args, outputs) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(outputs.video_only, Some(PathBuf::from("/recordings/rec.video.mp4")));
+        assert_eq!(outputs.audio_only, Some(PathBuf::from("/recordings/rec.audio.flac")));
+        assert_eq!(values_of(&args, "-movflags"), vec!["+faststart"]);
+
+        options.separate_outputs = false;
+        options.output_container = OutputContainer::WebM;
+        (options.video_codec, options.audio_codec) = OutputContainer::WebM.default_codecs();
+        let (args, outputs) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(outputs.combined, Some(PathBuf::from("/recordings/rec.webm")));
+        assert_eq!(values_of(&args, "-c:v"), vec!["libvpx-vp9"]);
+        assert_eq!(values_of(&args, "-c:a"), vec!["libopus"]);
+        assert!(values_of(&args, "-movflags").is_empty());
+
+        assert_eq!(OutputContainer::from_path(Path::new("/r/rec.video.MP4")), Some(OutputContainer::Mp4));
+        assert_eq!(OutputContainer::from_path(Path::new("/r/rec.flac")), None);
+        assert_eq!(subtitle_codec(OutputContainer::Mp4), "mov_text");
+        assert_eq!(subtitle_codec(OutputContainer::Mkv), "copy");
+        Ok(())
+    }
+
+    #[test]
+    fn hardware_encoders_swap_codec_and_upload_frames() -> Result<()> {
+        let inputs = EffectiveInputs {
+            screen: Some(screen_input()),
+            audio: Some(audio_input()),
+            ..Default::default()
+        };
+        let mut options = capture_options(true, true, false);
+        options.hw_accel = HwAccel::Vaapi;
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(values_of(&args, "-vaapi_device"), vec![VAAPI_DEVICE]);
+        assert_eq!(values_of(&args, "-c:v"), vec!["h264_vaapi"]);
+        assert!(values_of(&args, "-vf")[0].ends_with(",format=nv12,hwupload"));
+
+        // With the webcam overlay the upload ends the graph
+        let mut options = capture_options(true, true, true);
+        options.hw_accel = HwAccel::Vaapi;
+        let inputs = EffectiveInputs { webcam: Some("/dev/video0".to_string()), ..inputs };
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        let graph = values_of(&args, "-filter_complex").join("");
+        assert!(graph.ends_with(";[vout]format=nv12,hwupload[vhw]"), "{}", graph);
+        assert_eq!(values_of(&args, "-map")[0], "[vhw]");
+
+        options.hw_accel = HwAccel::Nvenc;
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(values_of(&args, "-c:v"), vec!["h264_nvenc"]);
+        assert!(values_of(&args, "-vaapi_device").is_empty());
+        assert!(!values_of(&args, "-filter_complex").join("").contains("hwupload"));
+
+        // The hardware encoders are H.264 only, so VP9 stays in software
+        options.video_codec = VideoCodec::Vp9;
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(values_of(&args, "-c:v"), vec!["libvpx-vp9"]);
+
+        let probe = hw_accel_probe_args(HwAccel::Vaapi);
+        assert_eq!(values_of(&probe, "-c:v"), vec!["h264_vaapi"]);
+        assert_eq!(values_of(&probe, "-vf"), vec!["format=nv12,hwupload"]);
+        Ok(())
+    }
+
+    #[test]
+    fn separate_outputs_keep_desktop_audio_apart() -> Result<()> {
+        let mut options = capture_options(true, true, false);
+        options.system_audio = true;
+        let inputs = EffectiveInputs {
+            screen: Some(screen_input()),
+            audio: Some(audio_input()),
+            system_audio: Some(strings(&["-f", "pulse", "-i", "monitor"])),
+            ..Default::default()
+        };
+        // Without split outputs the two are mixed
+        let (args, outputs) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(values_of(&args, "-map"), vec!["0:v", "[aout]"]);
+        assert!(values_of(&args, "-filter_complex").join("").contains("amix"));
+        assert_eq!(outputs.desktop_audio, None);
+
+        options.separate_outputs = true;
+        let (args, outputs) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert!(!values_of(&args, "-filter_complex").join("").contains("amix"));
+        assert_eq!(outputs.video_only, Some(PathBuf::from("/recordings/rec.video.mkv")));
+        assert_eq!(outputs.audio_only, Some(PathBuf::from("/recordings/rec.audio1.flac")));
+        assert_eq!(outputs.desktop_audio, Some(PathBuf::from("/recordings/rec.audio2.flac")));
+        assert_eq!(args.last().map(String::as_str), Some("/recordings/rec.audio2.flac"));
+
+        // A single stream can't be split, so it gets two titled tracks
+        options.output_target = OutputTarget::Stdout;
+        let (args, outputs) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(values_of(&args, "-map"), vec!["0:v", "[aout]", "2:a"]);
+        assert_eq!(values_of(&args, "-metadata:s:a:0"), vec!["title=Microphone"]);
+        assert_eq!(values_of(&args, "-metadata:s:a:1"), vec!["title=Desktop"]);
+        assert_eq!(outputs.desktop_audio, None);
+
+        // Audio-only recordings get a file per track too
+        let mut options = capture_options(true, false, false);
+        options.system_audio = true;
+        options.separate_outputs = true;
+        options.ducking = Some(Default::default());
+        let inputs = EffectiveInputs { screen: None, ..inputs };
+        let (args, outputs) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(outputs.combined, Some(PathBuf::from("/recordings/rec.audio1.flac")));
+        assert_eq!(outputs.desktop_audio, Some(PathBuf::from("/recordings/rec.audio2.flac")));
+        assert_eq!(values_of(&args, "-map"), vec!["[aout]", "[desktop]"]);
+        assert!(values_of(&args, "-filter_complex")[0].ends_with("[desktop]"));
+        Ok(())
+    }
+
+    #[test]
+    fn webcam_overlay_is_recorded_where_the_preview_shows_it() {
+        let mut options = capture_options(false, true, true);
+        options.webcam_overlay = OverlayPlacement { position: (1500, 820), size: (384, 216), opacity: 1.0 };
+        let graph = composite_graph(&options, "[0:v]", 1);
+        assert!(graph.starts_with("[1:v]scale=384:216,"), "{}", graph);
+        assert!(graph.ends_with("[0:v][cam_scaled]overlay=1500:820[overlayed]"), "{}", graph);
+        assert!(!graph.contains("colorchannelmixer"), "{}", graph);
+
+        // Inside a capture region the box moves with the region's corner
+        options.capture_region = Some((1000, 500, 1280, 720));
+        options.webcam_overlay.opacity = 0.5;
+        let graph = composite_graph(&options, "[0:v]", 1);
+        assert!(graph.contains("format=rgba,colorchannelmixer=aa=0.500[cam_scaled]"), "{}", graph);
+        assert!(graph.ends_with("overlay=500:320[overlayed]"), "{}", graph);
+        assert_eq!(
+            options.webcam_overlay.in_frame(Some((1600, 900, 100, 100))),
+            ((0, 0), (384, 216))
+        );
+    }
+
+    #[test]
+    fn full_preview_queue_drops_the_oldest_frame() {
+        let frame = |shade: u8| image::DynamicImage::ImageRgba8(image::RgbaImage::from_pixel(4, 4, image::Rgba([shade; 4])));
+        let shade = |frame: &image::DynamicImage| frame.to_rgba8().get_pixel(0, 0)[0];
+        let budget = FrameBudget::new(10);
+        let (sender, receiver) = crossbeam::channel::bounded(PREVIEW_QUEUE_CAPACITY);
+
+        // The consumer falls behind: five frames, room for two
+        let dropped: u64 = (1..=5).map(|i| queue_newest(&sender, &receiver, &budget, frame(i))).sum();
+        assert_eq!(dropped, 3);
+        assert_eq!(receiver.len(), PREVIEW_QUEUE_CAPACITY);
+        assert_eq!(budget.used_bytes(), 2 * 64);
+
+        // The newest frame is shown and everything queued is handed back
+        assert_eq!(take_newest(&receiver, &budget).as_ref().map(shade), Some(5));
+        assert_eq!(budget.used_bytes(), 0);
+        assert!(take_newest(&receiver, &budget).is_none());
+
+        // A budget taken by another capture drops the new frame instead
+        let other = budget.clone();
+        assert!(other.try_reserve(10 * 1024 * 1024));
+        assert_eq!(queue_newest(&sender, &receiver, &budget, frame(6)), 1);
+        assert!(receiver.is_empty());
+    }
+
+    #[test]
+    fn recording_timer_freezes_while_paused() {
+        let mut state = AppState::from_config(Config::default());
+        let start = std::time::Instant::now();
+        assert_eq!(state.recording_elapsed(start), None);
+
+        state.start_timer(start);
+        assert_eq!(state.recording_elapsed(start + Duration::from_secs(65)), Some(Duration::from_secs(65)));
+
+        // Paused at 1:10 and held for ten minutes: the timer doesn't move
+        state.set_paused(true, start + Duration::from_secs(70));
+        assert!(state.is_paused);
+        assert_eq!(state.recording_elapsed(start + Duration::from_secs(670)), Some(Duration::from_secs(70)));
+
+        // Resumed, it carries on from where it stopped
+        state.set_paused(false, start + Duration::from_secs(670));
+        assert_eq!(state.recording_elapsed(start + Duration::from_secs(3700)), Some(Duration::from_secs(3100)));
+        assert_eq!(recording_timer_label(Duration::from_secs(3100)), "00:51:40");
+        assert_eq!(recording_timer_label(Duration::from_secs(3725)), "01:02:05");
+
+        state.stop_timer();
+        assert_eq!(state.recording_elapsed(start + Duration::from_secs(3800)), None);
+        assert!(!state.is_paused);
+    }
+
+    #[test]
+    fn joined_segments_are_checked_for_av_drift() {
+        // pts_time,duration_time per packet; a packet without a time is skipped
+        let video = "0.000000,0.033333\n0.033333,0.033333\nN/A,0.033333\n9.966667,0.033333\n";
+        assert_eq!(packet_span(video), Some((0.0, 10.0)));
+        let audio = "0.021000,0.021333\n10.100000,0.021333";
+        let (start, end) = packet_span(audio).unwrap();
+        assert!((start - 0.021).abs() < 1e-9 && (end - 10.121333).abs() < 1e-9);
+        assert_eq!(packet_span(""), None);
+
+        // About a frame of difference is fine; audio running 300 ms long is not
+        assert_eq!(av_offset((0.0, 10.0), (0.021, 10.0)), Duration::from_millis(21));
+        assert!(av_offset((0.0, 10.0), (0.021, 10.02)) <= AV_SYNC_TOLERANCE);
+        assert!(av_offset((0.0, 10.0), (0.0, 10.3)) > AV_SYNC_TOLERANCE);
+    }
+
+    #[test]
+    fn joined_segments_keep_continuous_timestamps() -> Result<()> {
+        use crate::postprocess::{join_segments, probe_av_offset, probe_duration, probe_stream_span};
+        use std::process::{Command, Stdio};
+
+        let runs = |tool: &str| Command::new(tool).arg("-version").stdout(Stdio::null()).status().is_ok_and(|s| s.success());
+        if !runs("ffmpeg") || !runs("ffprobe") {
+            eprintln!("ffmpeg or ffprobe not installed; skipping the segment join check");
+            return Ok(());
+        }
+        let dir = env::temp_dir().join("discord_recorder_tests").join(format!("join_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let combined = |path: PathBuf| RecordingOutputs {
+            combined: Some(path),
+            video_only: None,
+            audio_only: None,
+            webcam: None,
+            desktop_audio: None,
+        };
+
+        // Paused twice: three parts of different lengths with AAC priming at each start
+        let lengths = [1.0, 2.0, 1.5];
+        let mut segments = Vec::new();
+        for (i, length) in lengths.iter().enumerate() {
+            let part = dir.join(format!("rec.part{:03}.mkv", i));
+            let status = Command::new("ffmpeg")
+                .args(["-y", "-hide_banner", "-loglevel", "error"])
+                .args(["-f", "lavfi", "-i", "testsrc=size=160x120:rate=30"])
+                .args(["-f", "lavfi", "-i", "sine=frequency=440:sample_rate=48000"])
+                .args(["-t", &length.to_string()])
+                .args(["-c:v", "libx264", "-preset", "ultrafast", "-pix_fmt", "yuv420p", "-c:a", "aac"])
+                .arg(&part)
+                .status()?;
+            assert!(status.success(), "couldn't encode {}", part.display());
+            segments.push(combined(part));
+        }
+        let output = dir.join("rec.mkv");
+        join_segments("ffmpeg", &segments, &combined(output.clone()))?;
+
+        // One file as long as the parts together, both streams starting at zero
+        let total: f64 = lengths.iter().sum();
+        let duration = probe_duration(&output)?.as_secs_f64();
+        assert!((duration - total).abs() < 0.1, "joined file is {} s, parts add up to {} s", duration, total);
+        let (video_start, video_end) = probe_stream_span(&output, "v:0")?.expect("joined file has video");
+        let (audio_start, _) = probe_stream_span(&output, "a:0")?.expect("joined file has audio");
+        assert!(video_start.abs() < 0.05 && audio_start.abs() < 0.05, "streams start at {} and {}", video_start, audio_start);
+        assert!((video_end - total).abs() < 0.1, "video ends at {}", video_end);
+        let offset = probe_av_offset(&output)?.expect("joined file has audio and video");
+        assert!(offset <= AV_SYNC_TOLERANCE, "audio and video drift {:?} apart", offset);
+        assert!(segments.iter().all(|segment| !segment.combined.as_ref().unwrap().exists()));
+
+        let _ = std::fs::remove_dir_all(&dir);
+        Ok(())
+    }
+
+    #[test]
+    fn capture_region_is_cropped_where_the_input_grabs_the_whole_screen() -> Result<()> {
+        let mut options = capture_options(false, true, false);
+        options.capture_region = Some((100, 50, 640, 360));
+        let inputs = EffectiveInputs {
+            screen: Some(screen_input()),
+            screen_crop: options.capture_region,
+            ..Default::default()
+        };
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert_eq!(values_of(&args, "-vf"), vec!["crop=640:360:100:50,scale=trunc(iw/2)*2:trunc(ih/2)*2"]);
+
+        // The overlay lands inside the cropped frame, where in_frame puts it
+        options.include_webcam = true;
+        options.composite_webcam = true;
+        let inputs = EffectiveInputs { webcam: Some("/dev/video0".to_string()), ..inputs };
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        let graph = values_of(&args, "-filter_complex")[0];
+        assert!(graph.starts_with("[0:v]crop=640:360:100:50[screen];"), "{}", graph);
+        let ((x, y), _) = options.webcam_overlay.in_frame(options.capture_region);
+        assert!(graph.contains(&format!("overlay={}:{}", x, y)), "{}", graph);
+
+        // Inputs that select the region themselves get no crop
+        let inputs = EffectiveInputs { screen: Some(screen_input()), ..Default::default() };
+        options.include_webcam = false;
+        let (args, _) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert!(!args.iter().any(|arg| arg.contains("crop=")));
+        Ok(())
+    }
+
+    #[test]
+    fn taken_file_names_get_a_numbered_suffix() -> Result<()> {
+        let names = strings(&["Firefox.mkv", "Firefox.chapters.txt", "Firefox-1.video.mkv", "Firefox-2x.mkv", "Firefox_old.mkv"]);
+        assert_eq!(unique_among(&names, "Firefox"), "Firefox-2");
+        assert_eq!(unique_among(&names, "Terminal"), "Terminal");
+        // Only whole names count, not names that merely start the same
+        assert_eq!(unique_among(&strings(&["Firefox_old.mkv"]), "Firefox"), "Firefox");
+
+        let dir = env::temp_dir().join("discord_recorder_tests").join(format!("unique_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        assert_eq!(unique_base_name(&dir, "rec"), "rec");
+        std::fs::write(dir.join("rec.part000.mkv"), b"")?;
+        assert_eq!(unique_base_name(&dir, "rec"), "rec-1");
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn presets_save_and_apply_what_the_panel_shows() {
+        use crate::config::VideoQuality;
+        use crate::presets::Preset;
+
+        let mut state = AppState::from_config(Config::default());
+        // Edited in the main panel, not yet written to `config`
+        state.frame_rate = 24;
+        state.video_quality = VideoQuality::Low;
+        state.record_webcam = true;
+        let preset = Preset::from_config("Panel", &state.config_snapshot());
+        assert_eq!(preset.settings.frame_rate, Some(24));
+        assert_eq!(preset.settings.video_quality, Some(VideoQuality::Low));
+
+        // A preset that only sets the frame rate leaves the other edits alone
+        let partial = r#"{"format":"discord-recorder-preset","version":1,"name":"Fast","settings":{"frame_rate":120}}"#;
+        let mut config = state.config_snapshot();
+        Preset::parse(partial).unwrap().apply_to(&mut config, &[], &[]);
+        state.replace_config(config);
+        assert_eq!(state.frame_rate, 120);
+        assert_eq!(state.video_quality, VideoQuality::Low);
+        assert!(state.record_webcam);
+    }
+
+    #[test]
+    fn clicks_are_moved_from_the_root_window_into_the_recorded_frame() {
+        use crate::video::click_in_frame;
+
+        assert_eq!(click_in_frame((500, 300), (0, 0), None), (500, 300));
+        // Second monitor to the right of a 1920 px one
+        assert_eq!(click_in_frame((2420, 300), (1920, 0), None), (500, 300));
+        assert_eq!(click_in_frame((2420, 300), (1920, 0), Some((100, 50, 1280, 720))), (400, 250));
+        // A click on the other monitor lands outside the frame
+        assert!(click_in_frame((500, 300), (1920, 0), None).0 < 0);
+    }
+
+    #[test]
+    fn hw_encoder_checks_are_cached_per_ffmpeg_binary() {
+        use crate::video::{cached_hw_accel_support, ffmpeg_has_encoder, ffmpeg_supports_hw_accel, probe_hw_accels};
+
+        let missing = "/nonexistent/ffmpeg-for-probe-test";
+        let other = "/nonexistent/other-ffmpeg-for-probe-test";
+        assert_eq!(cached_hw_accel_support(missing, HwAccel::Software), Some(true));
+        assert_eq!(cached_hw_accel_support(missing, HwAccel::Nvenc), None, "nothing runs until probed");
+
+        probe_hw_accels(missing);
+        assert_eq!(cached_hw_accel_support(missing, HwAccel::Nvenc), Some(false));
+        assert_eq!(cached_hw_accel_support(missing, HwAccel::Vaapi), Some(false));
+        assert!(!ffmpeg_supports_hw_accel(missing, HwAccel::Nvenc));
+        assert!(!ffmpeg_has_encoder(missing, "libx264"));
+        // Another binary gets its own answer
+        assert_eq!(cached_hw_accel_support(other, HwAccel::Nvenc), None);
+    }
+
+    #[test]
+    fn reloading_an_edited_file_keeps_unsaved_panel_edits() {
+        let mut state = AppState::from_config(Config::default());
+        // Changed in the main panel; only a recording start would save them
+        state.record_webcam = true;
+        state.video_quality = VideoQuality::Low;
+        state.frame_rate = 24;
+
+        // Meanwhile the file gets a new frame rate and layout
+        let on_disk = Config { frame_rate: 50, overlay_layout: OverlayLayout::SideBySide, ..Config::default() };
+        state.reload_config(on_disk.clone());
+        assert_eq!(state.frame_rate, 50, "the file changed it, so the file wins");
+        assert_eq!(state.overlay_layout, OverlayLayout::SideBySide);
+        assert!(state.record_webcam);
+        assert_eq!(state.video_quality, VideoQuality::Low);
+        assert!(state.config.same_on_disk(&on_disk), "config still mirrors the file");
+
+        let merged = Config::default().merge_edits(&Config::default(), &on_disk);
+        assert!(merged.same_on_disk(&on_disk));
+    }
+
+    #[test]
+    fn command_line_overrides_stay_out_of_the_config_file() -> Result<()> {
+        let dir = env::temp_dir().join("discord_recorder_tests").join(format!("overrides_{}", std::process::id()));
+        let path = dir.join("config.json");
+        let on_disk = Config { video_quality: VideoQuality::Medium, ..Config::default() };
+        let mut runtime = on_disk.clone();
+        CliArgs::parse(strings(&["--video-quality", "ultra", "--no-audio"]))?.apply_to(&mut runtime);
+        let mut state = AppState { config_file: ConfigFile::new(on_disk, &runtime), ..AppState::from_config(runtime) };
+        assert_eq!(state.video_quality, VideoQuality::Ultra);
+
+        // Starting a recording saves what the panel shows
+        state.frame_rate = 24;
+        let snapshot = state.config_snapshot();
+        state.config_file.save_to(&path, &snapshot)?;
+        let saved: Config = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        assert_eq!(saved.video_quality, VideoQuality::Medium, "the override isn't written");
+        assert!(saved.record_audio);
+        assert_eq!(saved.frame_rate, 24, "edits made in the app are");
+
+        // Changing an overridden setting in the app saves the new value
+        state.config = snapshot;
+        state.config.video_quality = VideoQuality::Low;
+        state.config_file.save_to(&path, &state.config)?;
+        let saved: Config = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+        assert_eq!(saved.video_quality, VideoQuality::Low);
+        assert!(saved.record_audio);
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn codecs_are_stored_and_fixed_to_fit_the_container() -> Result<()> {
+        let config = Config::default();
+        assert_eq!((config.output_container, config.video_codec, config.audio_codec), (OutputContainer::Mkv, VideoCodec::H264, AudioCodec::Aac));
+        let old: Config = serde_json::from_str(r#"{"output_directory":"/r","video_quality":"High","audio_quality":"High","default_screen":null,"default_audio_device":null,"default_webcam":null,"record_audio":true,"record_video":true,"record_webcam":false,"discord_theme":"Dark","container":"WebM"}"#)?;
+        assert_eq!(old.output_container, OutputContainer::WebM);
+
+        // WebM takes only VP9 and Opus, MP4 only H.264 and AAC
+        let mut webm = Config { output_container: OutputContainer::WebM, ..Config::default() };
+        assert_eq!(webm.codecs(), (VideoCodec::Vp9, AudioCodec::Opus));
+        assert_eq!(webm.validate_and_fix().len(), 1);
+        assert_eq!((webm.video_codec, webm.audio_codec), (VideoCodec::Vp9, AudioCodec::Opus));
+        let mut mp4 = Config { output_container: OutputContainer::Mp4, audio_codec: AudioCodec::Opus, ..Config::default() };
+        mp4.validate_and_fix();
+        assert_eq!((mp4.video_codec, mp4.audio_codec), (VideoCodec::H264, AudioCodec::Aac));
+        let mut mkv = Config { video_codec: VideoCodec::Vp9, audio_codec: AudioCodec::Opus, ..Config::default() };
+        assert!(mkv.validate_and_fix().is_empty(), "MKV holds either pair");
+
+        let inputs = EffectiveInputs { screen: Some(screen_input()), audio: Some(audio_input()), ..Default::default() };
+        let options = RecorderOptions { hw_accel: HwAccel::Nvenc, ..RecorderOptions::from_config(&mkv) };
+        let (args, outputs) = ffmpeg_args(&options, &inputs, "rec")?;
+        assert!(outputs.combined.is_some_and(|path| path.extension().is_some_and(|ext| ext == "mkv")));
+        assert_eq!(values_of(&args, "-c:v"), vec!["libvpx-vp9"]);
+        assert_eq!(values_of(&args, "-c:a"), vec!["libopus"]);
+        Ok(())
+    }
+
+    #[test]
+    fn recordings_use_the_configured_ffmpeg_binary() {
+        let mut config = Config::default();
+        assert_eq!(config.ffmpeg_path, "ffmpeg");
+        config.ffmpeg_path = "/opt/ffmpeg/bin/ffmpeg".to_string();
+        let options = RecorderOptions::from_config(&config);
+        assert_eq!(options.ffmpeg_path, "/opt/ffmpeg/bin/ffmpeg");
+
+        let mut blank = Config { ffmpeg_path: "  ".to_string(), ..Config::default() };
+        let fixes = blank.validate_and_fix();
+        assert_eq!(blank.ffmpeg_path, "ffmpeg");
+        assert!(fixes.iter().any(|fix| fix.contains("ffmpeg_path")));
+    }
+
+    #[test]
+    fn batch_finds_recordings_in_every_container() -> Result<()> {
+        use crate::batch::find_recordings;
+
+        let dir = env::temp_dir().join("discord_recorder_tests").join(format!("batch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        for name in [
+            "a.mkv", "b.mp4", "c.webm", "a.final.mkv", "b.final.mp4", "a.trimmed.mkv", "b.share.mp4",
+            "c.video.webm", "a.flac", "notes.txt",
+        ] {
+            std::fs::write(dir.join(name), b"")?;
+        }
+        assert_eq!(find_recordings(&dir)?, vec![dir.join("a.mkv"), dir.join("b.mp4"), dir.join("c.webm")]);
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn output_protocols_are_cached_per_ffmpeg_binary() -> Result<()> {
+        use crate::video::ffmpeg_has_output_protocol;
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = env::temp_dir().join("discord_recorder_tests").join(format!("protocols_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let fake = dir.join("ffmpeg");
+        std::fs::write(&fake, "#!/bin/sh\nprintf 'Supported file protocols:\\nInput:\\n  srt\\nOutput:\\n  file\\n  srt\\n'\n")?;
+        std::fs::set_permissions(&fake, std::fs::Permissions::from_mode(0o755))?;
+        let fake = fake.to_string_lossy().into_owned();
+
+        assert!(ffmpeg_has_output_protocol(&fake, "srt"));
+        assert!(!ffmpeg_has_output_protocol(&fake, "rtmp"));
+        // A binary without the protocol isn't answered from the first one's list
+        assert!(!ffmpeg_has_output_protocol("/nonexistent/ffmpeg-for-protocol-test", "srt"));
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn loading_a_config_file_validates_it_and_reports_a_broken_one() -> Result<()> {
+        use crate::config::LoadError;
+
+        let dir = env::temp_dir().join("discord_recorder_tests").join(format!("load_{}", std::process::id()));
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("config.json");
+
+        // A missing file gets the defaults written
+        let config = Config::load_from(&path).map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        assert!(config.same_on_disk(&Config::default()));
+        assert!(path.exists());
+
+        Config { frame_rate: 48, ffmpeg_path: String::new(), ..Config::default() }.save_to(&path)?;
+        let config = Config::load_from(&path).map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        assert_eq!(config.frame_rate, 48);
+        assert_eq!(config.ffmpeg_path, "ffmpeg", "loaded files are validated");
+
+        std::fs::write(&path, "{ half-written")?;
+        assert!(matches!(Config::load_from(&path), Err(LoadError::Corrupt(_))));
+        assert_eq!(std::fs::read_to_string(&path)?, "{ half-written", "a broken file is left for the user");
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,153 @@
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use egui::{Align2, Color32, RichText};
+
+/// How long an unpinned toast stays up.
+pub const TOAST_DURATION: Duration = Duration::from_secs(5);
+/// Oldest toasts are dropped beyond this many.
+const MAX_TOASTS: usize = 5;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToastKind {
+    Info,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Toast {
+    id: u64,
+    pub kind: ToastKind,
+    pub text: String,
+    shown_at: Instant,
+    /// Pinned toasts stay until dismissed.
+    pub pinned: bool,
+}
+
+/// Transient messages in the corner of the window. Cloning shares the queue, so
+/// background tasks such as finalizing can report back.
+#[derive(Debug, Clone, Default)]
+pub struct Toasts {
+    queue: Arc<Mutex<VecDeque<Toast>>>,
+}
+
+impl Toasts {
+    pub fn info(&self, text: impl Into<String>) {
+        self.push(ToastKind::Info, text.into());
+    }
+
+    pub fn error(&self, text: impl Into<String>) {
+        self.push(ToastKind::Error, text.into());
+    }
+
+    /// Shows a message that is later changed in place with `update`, e.g. for
+    /// progress of a background task. Returns the id to update.
+    pub fn progress(&self, text: impl Into<String>) -> u64 {
+        let mut queue = self.queue.lock().unwrap();
+        if queue.len() >= MAX_TOASTS {
+            queue.pop_front();
+        }
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        queue.push_back(Toast { id, kind: ToastKind::Info, text: text.into(), shown_at: Instant::now(), pinned: false });
+        id
+    }
+
+    /// Replaces the text of toast `id` and restarts its timer. Returns false if
+    /// it already expired or was dismissed.
+    pub fn update(&self, id: u64, kind: ToastKind, text: impl Into<String>) -> bool {
+        let mut queue = self.queue.lock().unwrap();
+        match queue.iter_mut().find(|t| t.id == id) {
+            Some(toast) => {
+                toast.kind = kind;
+                toast.text = text.into();
+                toast.shown_at = Instant::now();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Final message for a `progress` toast; shown anew if the progress toast
+    /// was already dismissed.
+    pub fn finish(&self, id: u64, kind: ToastKind, text: impl Into<String>) {
+        let text = text.into();
+        if !self.update(id, kind, text.clone()) {
+            self.push(kind, text);
+        }
+    }
+
+    /// A repeat of a visible message restarts its timer instead of stacking,
+    /// so an error raised every frame shows once.
+    fn push(&self, kind: ToastKind, text: String) {
+        let mut queue = self.queue.lock().unwrap();
+        if let Some(existing) = queue.iter_mut().find(|t| t.kind == kind && t.text == text) {
+            existing.shown_at = Instant::now();
+            return;
+        }
+        if queue.len() >= MAX_TOASTS {
+            queue.pop_front();
+        }
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        queue.push_back(Toast { id, kind, text, shown_at: Instant::now(), pinned: false });
+    }
+
+    /// Toasts still showing at `now`, after expired ones are removed.
+    pub fn visible(&self, now: Instant) -> Vec<Toast> {
+        let mut queue = self.queue.lock().unwrap();
+        queue.retain(|t| t.pinned || now.duration_since(t.shown_at) < TOAST_DURATION);
+        queue.iter().cloned().collect()
+    }
+
+    /// Draws the toasts stacked in the bottom-right corner.
+    pub fn show(&self, ctx: &egui::Context) {
+        let toasts = self.visible(Instant::now());
+        if toasts.is_empty() {
+            return;
+        }
+
+        let mut dismissed = Vec::new();
+        let mut pin_toggled = Vec::new();
+        egui::Area::new(egui::Id::new("toasts"))
+            .anchor(Align2::RIGHT_BOTTOM, egui::vec2(-12.0, -12.0))
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                for toast in &toasts {
+                    let (fill, icon) = match toast.kind {
+                        ToastKind::Info => (Color32::from_rgb(47, 49, 54), "ℹ"),
+                        ToastKind::Error => (Color32::from_rgb(120, 32, 36), "⚠"),
+                    };
+                    egui::Frame::popup(ui.style()).fill(fill).show(ui, |ui| {
+                        ui.set_max_width(360.0);
+                        ui.horizontal(|ui| {
+                            ui.label(RichText::new(format!("{} {}", icon, toast.text)).color(Color32::WHITE));
+                            if ui.small_button("✕").on_hover_text("Dismiss").clicked() {
+                                dismissed.push(toast.id);
+                            }
+                            if toast.kind == ToastKind::Error {
+                                let pin = if toast.pinned { "📌 Unpin" } else { "📌" };
+                                if ui.small_button(pin).on_hover_text("Keep this message until dismissed").clicked() {
+                                    pin_toggled.push(toast.id);
+                                }
+                            }
+                        });
+                    });
+                }
+            });
+
+        // Matched by id: a background task may have pushed in the meantime
+        let mut queue = self.queue.lock().unwrap();
+        for toast in queue.iter_mut().filter(|t| pin_toggled.contains(&t.id)) {
+            toast.pinned = !toast.pinned;
+            toast.shown_at = Instant::now();
+        }
+        queue.retain(|t| !dismissed.contains(&t.id));
+        // Wake up to expire the next toast even if nothing else repaints
+        if queue.iter().any(|t| !t.pinned) {
+            ctx.request_repaint_after(Duration::from_millis(250));
+        }
+    }
+}
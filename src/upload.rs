@@ -0,0 +1,131 @@
+use anyhow::{anyhow, Context, Result};
+use log::{error, info};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::config::{UploadMethod, UploadSettings};
+use crate::postprocess::{quote_path, shell_command};
+use crate::runtime::runtime_handle;
+use crate::streaming::percent_encode;
+use crate::toast::{ToastKind, Toasts};
+
+/// Uploads `paths` one after another on the runtime's blocking pool, reporting
+/// progress and the outcome of each file as toasts. Returns immediately.
+pub fn spawn_uploads(settings: &UploadSettings, paths: Vec<PathBuf>, toasts: Toasts) {
+    if settings.method == UploadMethod::Off || paths.is_empty() {
+        return;
+    }
+    let settings = settings.clone();
+    runtime_handle().spawn_blocking(move || {
+        for path in paths {
+            let name = file_name(&path);
+            let toast = toasts.progress(format!("Uploading {}…", name));
+            let result = match settings.method {
+                UploadMethod::Off => Ok(()),
+                UploadMethod::Command => run_command(&settings.command, &path),
+                UploadMethod::HttpPut => http_put(&settings, &path, |percent| {
+                    toasts.update(toast, ToastKind::Info, format!("Uploading {}: {}%", name, percent));
+                }),
+            };
+            let (kind, text) = match result {
+                Ok(()) => {
+                    info!("Uploaded {}", path.display());
+                    (ToastKind::Info, format!("Uploaded {}", name))
+                }
+                Err(e) => {
+                    error!("Upload of {} failed: {:#}", path.display(), e);
+                    (ToastKind::Error, format!("Upload of {} failed: {:#}", name, e))
+                }
+            };
+            toasts.finish(toast, kind, text);
+        }
+    });
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+/// Fills in `{path}` (quoted for the platform shell) and `{name}`.
+pub fn expand_command(template: &str, path: &Path) -> String {
+    template
+        .replace("{path}", &quote_path(path))
+        .replace("{name}", &file_name(path))
+}
+
+/// Fills in `{name}`, percent-encoded for use in a URL path.
+pub fn expand_url(template: &str, path: &Path) -> String {
+    template.replace("{name}", &percent_encode(&file_name(path)))
+}
+
+fn run_command(template: &str, path: &Path) -> Result<()> {
+    if template.trim().is_empty() {
+        return Err(anyhow!("no upload command configured"));
+    }
+    let command = expand_command(template, path);
+    let output = shell_command(&command)
+        .output()
+        .with_context(|| format!("Failed to run {}", command))?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let detail = stderr.lines().rev().find(|l| !l.trim().is_empty()).unwrap_or("").trim();
+    Err(anyhow!("command exited with {} {}", output.status, detail))
+}
+
+fn http_put(settings: &UploadSettings, path: &Path, mut on_progress: impl FnMut(u64)) -> Result<()> {
+    if settings.put_url.trim().is_empty() {
+        return Err(anyhow!("no upload URL configured"));
+    }
+    let url = expand_url(settings.put_url.trim(), path);
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let total = file.metadata()?.len();
+
+    let mut request = ureq::put(&url)
+        .set("Content-Type", "application/octet-stream")
+        // Object stores reject chunked uploads without a length
+        .set("Content-Length", &total.to_string());
+    if !settings.authorization.is_empty() {
+        request = request.set("Authorization", &settings.authorization);
+    }
+
+    let mut last_percent = 0;
+    let reader = ProgressReader {
+        inner: file,
+        read: 0,
+        on_read: |read: u64| {
+            let percent = (read * 100).checked_div(total).unwrap_or(100);
+            // One update per 5% keeps the toast readable
+            if percent >= last_percent + 5 {
+                last_percent = percent;
+                on_progress(percent);
+            }
+        },
+    };
+    match request.send(reader) {
+        Ok(_) => Ok(()),
+        Err(ureq::Error::Status(code, response)) => {
+            Err(anyhow!("server answered {} {}", code, response.status_text()))
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+struct ProgressReader<R, F> {
+    inner: R,
+    read: u64,
+    on_read: F,
+}
+
+impl<R: Read, F: FnMut(u64)> Read for ProgressReader<R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.read += n as u64;
+        (self.on_read)(self.read);
+        Ok(n)
+    }
+}
@@ -2,18 +2,26 @@ use anyhow::{anyhow, Context, Result};
 use chrono::Local;
 use log::{error, warn, info};
 use screenshots::Screen;
-use std::env;
+use std::collections::HashMap;
+use std::ffi::OsStr;
 use std::fs;
-use std::io::{BufRead, BufReader, Write};
+use std::io::Write;
 use std::path::PathBuf;
-use std::process::{Child, Command, Stdio};
+use std::process::{Child, ChildStdout, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 use std::panic::{catch_unwind, AssertUnwindSafe};
 
-use crate::config::VideoQuality;
+use crate::audio;
+use crate::ffmpeg_log::{FfmpegLog, LogLevel};
+use crate::config::{AudioCodec, AudioExportFormat, BackgroundBlur, ChromaKey, ClickHighlight, Config, DuckingSettings, FlacBitDepth, HwAccel, MicGateMode, OutputContainer, OverlayBorder, OverlayLayout, SrtSettings, VideoCodec, VideoQuality};
+use crate::streaming::StreamTarget;
+use crate::naming::{recording_base_name, unique_base_name};
+use crate::postprocess;
 use crate::runtime::runtime_handle;
-use once_cell::sync::OnceCell;
+use crate::screen_feed::{self, FeedSource, FrameEffect, FrameEffects, ScreenFeed};
+use once_cell::sync::{Lazy, OnceCell};
 
 #[derive(Debug, Clone)]
 pub struct RecorderOptions {
@@ -28,10 +36,198 @@ pub struct RecorderOptions {
     pub include_webcam: bool,
     pub separate_outputs: bool,
     pub selected_screen: Option<usize>,
+    /// (x, y, width, height) within the selected screen; `None` records all of it.
+    pub capture_region: Option<(u32, u32, u32, u32)>,
+    /// Magnification of a capture window that follows the cursor, scaled
+    /// back up to the screen size. Only x11grab can follow the cursor.
+    pub cursor_zoom: Option<f32>,
+    /// Fit the screen into this size instead of recording it as captured;
+    /// set by `VideoEncoder::display_changed`.
+    pub screen_output_size: Option<(u32, u32)>,
+    /// Rings drawn around mouse clicks, positioned with `set_click_ring`.
+    pub click_highlight: Option<ClickHighlight>,
+    /// Capture the screen in-process and pipe raw frames to ffmpeg's stdin,
+    /// so frame effects can be applied in Rust. Takes over the stdin that
+    /// push-to-talk commands otherwise use.
+    pub piped_screen: bool,
     pub audio_device: Option<String>,
     pub webcam_device: Option<String>,
     pub ffmpeg_path: String,
     pub audio_gain_db: f32,
+    /// Stereo balance in `-1.0..=1.0`, applied on top of `audio_gain_db`.
+    pub audio_balance: f32,
+    /// Peak limiter ceiling in dBFS, applied after gain and balance; `None` disables it.
+    pub limiter_threshold_db: Option<f32>,
+    /// Gain applied after the limiter, in dB.
+    pub limiter_makeup_db: f32,
+    /// Push-to-talk/mute gate on the audio input, toggled at runtime through ffmpeg commands.
+    pub mic_gate: MicGateMode,
+    /// Also capture desktop audio and mix it with the input device.
+    pub system_audio: bool,
+    pub system_audio_device: Option<String>,
+    /// Duck desktop audio while the microphone is active; needs `system_audio`.
+    pub ducking: Option<DuckingSettings>,
+    /// Webcam box as placed on the screen preview.
+    pub webcam_overlay: OverlayPlacement,
+    /// Format of audio-only files; the audio inside video files follows `audio_codec`.
+    pub audio_export_format: AudioExportFormat,
+    /// Container of the files with video.
+    pub output_container: OutputContainer,
+    pub video_codec: VideoCodec,
+    /// Codec of the audio muxed with the video.
+    pub audio_codec: AudioCodec,
+    /// H.264 encoder; falls back to libx264 when ffmpeg can't use it.
+    pub hw_accel: HwAccel,
+    pub flac_bit_depth: FlacBitDepth,
+    /// Write the webcam to its own file as well when the screen is recorded.
+    pub separate_webcam: bool,
+    /// Overlay the webcam onto the screen video.
+    pub composite_webcam: bool,
+    /// How long to wait for ffmpeg to honor `q` before escalating.
+    pub quit_timeout: Duration,
+    /// How long to wait after SIGTERM before falling back to SIGKILL (unix only).
+    pub terminate_timeout: Duration,
+    /// Pause by ending the current ffmpeg segment and start a new one on resume;
+    /// segments are concatenated when the recording stops.
+    pub seamless_pause: bool,
+    /// Whether the webcam sits over the screen or the other way round.
+    pub overlay_layout: OverlayLayout,
+    /// Frame drawn around the composited webcam.
+    pub webcam_border: OverlayBorder,
+    /// Green-screen removal on the composited webcam.
+    pub chroma_key: Option<ChromaKey>,
+    /// Blur around a centered subject box on the composited webcam.
+    pub background_blur: Option<BackgroundBlur>,
+    /// Read inputs unbuffered and encode with `-tune zerolatency`, trading
+    /// some compression for less delay. Helps live streams, not files.
+    pub low_latency: bool,
+    /// Threads for the encoders and filter graphs; `None` means ffmpeg's
+    /// automatic choice (one per core).
+    pub threads: Option<u32>,
+    /// Also stream the recording over SRT.
+    pub stream: Option<SrtSettings>,
+    /// Where the main recording goes; anything but `File` skips the
+    /// separate audio/video split and writes one Matroska stream.
+    pub output_target: OutputTarget,
+    /// Base name of the output files, see `naming::expand_file_name`.
+    pub file_name_template: String,
+}
+
+impl RecorderOptions {
+    /// Options for recording with the persisted settings. Input gain is a
+    /// per-session setting and starts at 0 dB.
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            output_directory: PathBuf::from(config.get_output_directory()),
+            video_quality: config.video_quality,
+            video_bitrate_kbps: config.get_video_bitrate(),
+            audio_bitrate_kbps: config.get_audio_bitrate(),
+            audio_sample_rate: config.get_audio_sample_rate(),
+            frame_rate: config.frame_rate.max(1),
+            include_audio: config.record_audio,
+            include_video: config.record_video,
+            include_webcam: config.record_webcam,
+            separate_outputs: config.separate_outputs,
+            selected_screen: config.default_screen,
+            capture_region: config.capture_region,
+            cursor_zoom: config.cursor_zoom.filter(|&zoom| zoom > 1.0),
+            screen_output_size: None,
+            click_highlight: config.click_highlight.enabled.then_some(config.click_highlight),
+            piped_screen: config.piped_screen_capture,
+            audio_device: config.default_audio_device.clone(),
+            webcam_device: config.default_webcam.clone(),
+            ffmpeg_path: config.ffmpeg_path.clone(),
+            audio_gain_db: 0.0,
+            audio_balance: config.audio_balance,
+            limiter_threshold_db: config.limiter_enabled.then_some(config.limiter_threshold_db),
+            limiter_makeup_db: config.limiter_makeup_db,
+            mic_gate: config.mic_gate,
+            system_audio: config.record_system_audio,
+            system_audio_device: config.system_audio_device.clone(),
+            ducking: config.ducking_enabled.then_some(config.ducking),
+            webcam_overlay: OverlayPlacement::default(),
+            audio_export_format: config.audio_export_format,
+            output_container: config.output_container,
+            video_codec: config.codecs().0,
+            audio_codec: config.codecs().1,
+            hw_accel: config.hw_accel,
+            flac_bit_depth: config.get_flac_bit_depth(),
+            separate_webcam: config.separate_webcam,
+            // Without a separate file, skipping the overlay would drop the webcam entirely
+            composite_webcam: config.composite_webcam || !config.separate_webcam,
+            quit_timeout: Duration::from_secs(config.ffmpeg_quit_timeout_secs),
+            terminate_timeout: Duration::from_secs(config.ffmpeg_terminate_timeout_secs),
+            seamless_pause: config.seamless_pause,
+            overlay_layout: config.overlay_layout,
+            webcam_border: config.overlay_border,
+            chroma_key: config.chroma_key.enabled.then_some(config.chroma_key),
+            background_blur: config.background_blur.enabled.then_some(config.background_blur),
+            low_latency: config.low_latency,
+            threads: config.encoder_threads.filter(|&n| n > 0),
+            stream: config.stream_enabled.then(|| config.srt.clone()),
+            output_target: OutputTarget::File,
+            file_name_template: config.file_name_template.clone(),
+        }
+    }
+}
+
+/// The webcam overlay's box in screen pixels (the preview shows the whole
+/// screen at its native size) and how opaque it is.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct OverlayPlacement {
+    pub position: (u32, u32),
+    pub size: (u32, u32),
+    pub opacity: f32,
+}
+
+impl Default for OverlayPlacement {
+    fn default() -> Self {
+        Self { position: (40, 40), size: (320, 180), opacity: 0.9 }
+    }
+}
+
+impl OverlayPlacement {
+    /// Position and size within the recorded frame, which starts at the
+    /// capture region's corner rather than the screen's.
+    pub fn in_frame(&self, region: Option<(u32, u32, u32, u32)>) -> ((u32, u32), (u32, u32)) {
+        let (left, top) = region.map_or((0, 0), |(x, y, _, _)| (x, y));
+        (
+            (self.position.0.saturating_sub(left), self.position.1.saturating_sub(top)),
+            (self.size.0.max(1), self.size.1.max(1)),
+        )
+    }
+}
+
+/// Destination of the main ffmpeg output.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum OutputTarget {
+    /// Files in the output directory.
+    #[default]
+    File,
+    /// ffmpeg's stdout, read through `VideoEncoder::take_output`.
+    Stdout,
+    /// An existing named pipe, e.g. made with `mkfifo`; something must be
+    /// reading it or ffmpeg blocks.
+    Fifo(PathBuf),
+}
+
+impl OutputTarget {
+    /// `-` for stdout, anything else a FIFO path.
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "-" => OutputTarget::Stdout,
+            path => OutputTarget::Fifo(PathBuf::from(path)),
+        }
+    }
+
+    /// The output URL ffmpeg is given, or `None` for files.
+    fn ffmpeg_url(&self) -> Option<String> {
+        match self {
+            OutputTarget::File => None,
+            OutputTarget::Stdout => Some("pipe:1".to_string()),
+            OutputTarget::Fifo(path) => Some(path.to_string_lossy().into_owned()),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -39,14 +235,102 @@ pub struct RecordingOutputs {
     pub combined: Option<PathBuf>,
     pub video_only: Option<PathBuf>,
     pub audio_only: Option<PathBuf>,
+    /// Raw webcam next to a screen recording.
+    pub webcam: Option<PathBuf>,
+    /// Desktop audio file when it is kept apart from the microphone in
+    /// `audio_only`.
+    pub desktop_audio: Option<PathBuf>,
+}
+
+impl RecordingOutputs {
+    /// Output slots in combined/video/audio/webcam/desktop audio order.
+    pub fn slots(&self) -> [&Option<PathBuf>; 5] {
+        [&self.combined, &self.video_only, &self.audio_only, &self.webcam, &self.desktop_audio]
+    }
+
+    /// All files produced by the recording, in slot order.
+    pub fn paths(&self) -> Vec<&PathBuf> {
+        self.slots().into_iter().flatten().collect()
+    }
+
+    /// What to put on the clipboard for this recording: the file itself, or
+    /// the folder holding them when there are several.
+    pub fn clipboard_text(&self) -> Option<String> {
+        match self.paths().as_slice() {
+            [] => None,
+            [single] => Some(single.display().to_string()),
+            [first, ..] => first.parent().map(|dir| dir.display().to_string()),
+        }
+    }
+
+    /// Same outputs with the `from` file-name prefix swapped for `to`.
+    fn rebased(&self, from: &str, to: &str) -> Self {
+        let rebase = |path: &Option<PathBuf>| {
+            path.as_ref().map(|path| {
+                let name = path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                match name.strip_prefix(from) {
+                    Some(rest) => path.with_file_name(format!("{}{}", to, rest)),
+                    None => path.clone(),
+                }
+            })
+        };
+        Self {
+            combined: rebase(&self.combined),
+            video_only: rebase(&self.video_only),
+            audio_only: rebase(&self.audio_only),
+            webcam: rebase(&self.webcam),
+            desktop_audio: rebase(&self.desktop_audio),
+        }
+    }
+}
+
+/// Named volume filter the push-to-talk gate sends commands to.
+const MIC_GATE_FILTER: &str = "volume@ptt";
+
+/// Named drawbox filter the click highlight moves around.
+const CLICK_FILTER: &str = "drawbox@click";
+
+/// Where the click ring waits, outside the frame, between clicks.
+const CLICK_RING_HIDDEN: (i32, i32, u32) = (-1000, -1000, 2);
+
+/// A chapter marker and the note typed for it, which may be empty.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Marker {
+    pub at: Duration,
+    pub note: String,
 }
 
 pub struct VideoEncoder {
     options: RecorderOptions,
+    mic_open: bool,
+    /// Click ring last sent to ffmpeg as (x, y, side) in capture pixels.
+    click_ring: (i32, i32, u32),
+    /// Ring drawn by the piped feed's built-in effect, centered, in capture pixels.
+    piped_click_ring: Arc<Mutex<Option<(i32, i32, u32)>>>,
+    /// Root-window position of the recorded screen, which clicks are
+    /// reported relative to; looked up when a segment starts.
+    screen_origin: (i32, i32),
+    frame_effects: FrameEffects,
+    screen_feed: Option<ScreenFeed>,
+    /// Even size of the first segment's screen video, when known.
+    screen_video_size: Option<(u32, u32)>,
     process: Option<Child>,
     outputs: Option<RecordingOutputs>,
     stdout_task: Option<tokio::task::JoinHandle<()>>,
     stderr_task: Option<tokio::task::JoinHandle<()>>,
+    /// ffmpeg's stdout when recording to `OutputTarget::Stdout`, until taken.
+    output_pipe: Option<ChildStdout>,
+    log: FfmpegLog,
+    started_at: Option<Instant>,
+    stopped_at: Option<Instant>,
+    paused_at: Option<Instant>,
+    paused_total: Duration,
+    markers: Vec<Marker>,
+    base_name: Option<String>,
+    segments: Vec<RecordingOutputs>,
 }
 
 impl VideoEncoder {
@@ -54,16 +338,54 @@ impl VideoEncoder {
         if !options.include_audio && !options.include_video && !options.include_webcam {
             return Err(anyhow!("At least one of audio, video, or webcam capture must be enabled"));
         }
+        if options.output_target != OutputTarget::File && options.seamless_pause {
+            return Err(anyhow!("Seamless pause starts a new file per segment and can't write to a pipe"));
+        }
+        if let OutputTarget::Fifo(path) = &options.output_target {
+            if !path.exists() {
+                return Err(anyhow!("Named pipe {} doesn't exist; create it first (mkfifo)", path.display()));
+            }
+        }
+        if options.piped_screen && options.include_video && options.include_audio && options.mic_gate != MicGateMode::Off {
+            return Err(anyhow!("Push-to-talk needs ffmpeg's command input, which piped screen capture uses for frames"));
+        }
 
         fs::create_dir_all(&options.output_directory)
             .with_context(|| format!("Failed to create output directory: {}", options.output_directory.display()))?;
 
+        let piped_click_ring = Arc::new(Mutex::new(None));
+        let mut effects: Vec<FrameEffect> = Vec::new();
+        if let Some(highlight) = options.click_highlight.filter(|_| options.piped_screen) {
+            let ring = Arc::clone(&piped_click_ring);
+            effects.push(Box::new(move |frame, _| {
+                if let Some((x, y, side)) = *ring.lock().unwrap() {
+                    screen_feed::draw_ring(frame, (x, y), side, highlight.color);
+                }
+            }));
+        }
+
         Ok(Self {
+            mic_open: options.mic_gate.mic_open(false),
+            click_ring: CLICK_RING_HIDDEN,
+            piped_click_ring,
+            screen_origin: (0, 0),
+            frame_effects: Arc::new(Mutex::new(effects)),
+            screen_feed: None,
+            screen_video_size: None,
             options,
             process: None,
             outputs: None,
             stdout_task: None,
             stderr_task: None,
+            output_pipe: None,
+            log: FfmpegLog::default(),
+            started_at: None,
+            stopped_at: None,
+            paused_at: None,
+            paused_total: Duration::ZERO,
+            markers: Vec::new(),
+            base_name: None,
+            segments: Vec::new(),
         })
     }
 
@@ -75,7 +397,29 @@ impl VideoEncoder {
         ensure_ffmpeg_available(&self.options.ffmpeg_path)?;
 
         info!("Recorder options: {:?}", self.options);
-        let (mut child, outputs) = build_ffmpeg(&self.options).with_context(|| "Failed to start ffmpeg with computed inputs/outputs")?;
+        let base_name = self
+            .base_name
+            .get_or_insert_with(|| {
+                let name = recording_base_name(&self.options.file_name_template, Local::now());
+                unique_base_name(&self.options.output_directory, &name)
+            })
+            .clone();
+        let segment_name = if self.options.seamless_pause {
+            format!("{}.part{:03}", base_name, self.segments.len())
+        } else {
+            base_name.clone()
+        };
+        let (mut child, outputs, inputs) = build_ffmpeg(&self.options, &segment_name).with_context(|| "Failed to start ffmpeg with computed inputs/outputs")?;
+        if self.screen_video_size.is_none() {
+            self.screen_video_size = inputs.screen_size.map(|(width, height)| (width & !1, height & !1));
+        }
+        if self.options.click_highlight.is_some() {
+            self.screen_origin = display_geometry(self.options.selected_screen).map_or((0, 0), |(x, y, _, _)| (x, y));
+        }
+        if let Some(source) = inputs.screen_feed {
+            let stdin = child.stdin.take().ok_or_else(|| anyhow!("ffmpeg has no stdin for the screen feed"))?;
+            self.screen_feed = Some(ScreenFeed::start(source, self.options.frame_rate, stdin, Arc::clone(&self.frame_effects)));
+        }
 
         info!(
             "ffmpeg started. Outputs: {:?}",
@@ -95,52 +439,164 @@ impl VideoEncoder {
             )
         );
 
-        // Drain stdout/stderr in background to avoid pipe blockage
-        if let Some(stdout) = child.stdout.take() {
-            let task = runtime_handle().spawn_blocking(move || {
-                let reader = BufReader::new(stdout);
-                for line in reader.lines().flatten() {
-                    info!("ffmpeg: {}", line);
-                }
-            });
+        self.log.push(
+            LogLevel::Info,
+            format!("Recording to {}", outputs.paths().iter().map(|p| p.display().to_string()).collect::<Vec<_>>().join(", ")),
+        );
+
+        // Drain stdout/stderr in background to avoid pipe blockage, unless
+        // stdout carries the recording itself
+        if self.options.output_target == OutputTarget::Stdout {
+            self.output_pipe = child.stdout.take();
+        } else if let Some(stdout) = child.stdout.take() {
+            let log = self.log.clone();
+            let task = runtime_handle().spawn_blocking(move || log.drain(stdout));
             self.stdout_task = Some(task);
         }
         if let Some(stderr) = child.stderr.take() {
-            let task = runtime_handle().spawn_blocking(move || {
-                let reader = BufReader::new(stderr);
-                for line in reader.lines().flatten() {
-                    error!("ffmpeg: {}", line);
-                }
-            });
+            let log = self.log.clone();
+            let task = runtime_handle().spawn_blocking(move || log.drain(stderr));
             self.stderr_task = Some(task);
         }
 
-        self.outputs = Some(outputs);
+        if self.options.seamless_pause {
+            self.outputs = Some(outputs.rebased(&segment_name, &base_name));
+            self.segments.push(outputs);
+        } else {
+            self.outputs = Some(outputs);
+        }
         self.process = Some(child);
+        self.started_at.get_or_insert_with(Instant::now);
+
+        // A new segment starts from the filter's initial volume; restore the live state
+        if self.mic_open != self.options.mic_gate.mic_open(false) {
+            self.send_mic_gate()?;
+        }
+        self.click_ring = CLICK_RING_HIDDEN;
+        *self.piped_click_ring.lock().unwrap() = None;
+        Ok(())
+    }
+
+    /// Adds an effect applied to every screen frame. Only piped screen
+    /// capture runs effects; it takes effect from the next frame.
+    pub fn add_frame_effect(&mut self, effect: FrameEffect) {
+        self.frame_effects.lock().unwrap().push(effect);
+    }
+
+    /// Opens or closes the microphone gate. No-op unless a gate mode is configured.
+    pub fn set_mic_open(&mut self, open: bool) -> Result<()> {
+        if self.options.mic_gate == MicGateMode::Off || !self.options.include_audio || open == self.mic_open {
+            return Ok(());
+        }
+        self.mic_open = open;
+        self.send_mic_gate()
+    }
+
+    /// Uses ffmpeg's interactive `c` command to retarget the gate filter's volume.
+    fn send_mic_gate(&mut self) -> Result<()> {
+        let volume = if self.mic_open { 1 } else { 0 };
+        if let Some(stdin) = self.process.as_mut().and_then(|child| child.stdin.as_mut()) {
+            stdin
+                .write_all(format!("c{} -1 volume {}\n", MIC_GATE_FILTER, volume).as_bytes())
+                .context("Failed to send mic gate command to ffmpeg")?;
+            stdin.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Shows a square ring of side `side` centered on `(x, y)` in root-window
+    /// coordinates, or hides it. No-op unless click highlighting is on.
+    pub fn set_click_ring(&mut self, ring: Option<(i32, i32, u32)>) -> Result<()> {
+        if self.options.click_highlight.is_none() {
+            return Ok(());
+        }
+        let in_frame = |x, y| click_in_frame((x, y), self.screen_origin, self.options.capture_region);
+        if self.options.piped_screen {
+            *self.piped_click_ring.lock().unwrap() = ring.map(|(x, y, side)| {
+                let (x, y) = in_frame(x, y);
+                (x, y, side)
+            });
+            return Ok(());
+        }
+        let ring = ring.map_or(CLICK_RING_HIDDEN, |(x, y, side)| {
+            let (x, y) = in_frame(x, y);
+            (x - side as i32 / 2, y - side as i32 / 2, side)
+        });
+        if ring == self.click_ring {
+            return Ok(());
+        }
+        self.click_ring = ring;
+        let (x, y, side) = ring;
+        if let Some(stdin) = self.process.as_mut().and_then(|child| child.stdin.as_mut()) {
+            let commands: String = [("x", x), ("y", y), ("w", side as i32), ("h", side as i32)]
+                .iter()
+                .map(|(param, value)| format!("c{} -1 {} {}\n", CLICK_FILTER, param, value))
+                .collect();
+            stdin
+                .write_all(commands.as_bytes())
+                .context("Failed to send click highlight to ffmpeg")?;
+            stdin.flush()?;
+        }
         Ok(())
     }
 
     pub fn stop(&mut self) -> Result<()> {
+        if self.started_at.is_some() && self.stopped_at.is_none() {
+            self.stopped_at = Some(Instant::now());
+        }
+
+        self.end_process()?;
+
+        if !self.segments.is_empty() {
+            let segments = std::mem::take(&mut self.segments);
+            if let Some(outputs) = self.outputs.as_ref() {
+                postprocess::join_segments(&self.options.ffmpeg_path, &segments, outputs)?;
+                if segments.len() > 1 {
+                    self.check_joined_sync(outputs);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compares where audio and video start and end in the joined combined
+    /// file and reports a mismatch past `AV_SYNC_TOLERANCE` in the log.
+    fn check_joined_sync(&self, outputs: &RecordingOutputs) {
+        let Some(combined) = outputs.combined.as_ref().filter(|path| path.exists()) else {
+            return;
+        };
+        match postprocess::probe_av_offset(combined) {
+            Ok(Some(offset)) if offset > postprocess::AV_SYNC_TOLERANCE => {
+                let message = format!(
+                    "Audio and video in {} drift {} ms apart across the paused segments",
+                    combined.display(),
+                    offset.as_millis()
+                );
+                warn!("{}", message);
+                self.log.push(LogLevel::Warning, message);
+            }
+            Ok(Some(offset)) => info!("Joined segments stay in sync ({} ms apart)", offset.as_millis()),
+            Ok(None) => {}
+            Err(e) => warn!("Couldn't check A/V sync of {}: {}", combined.display(), e),
+        }
+    }
+
+    /// Ask the running ffmpeg process to finish its current output and wait for it.
+    fn end_process(&mut self) -> Result<()> {
         if let Some(mut child) = self.process.take() {
+            // Closing the frame pipe ends the screen input, and with it the recording
+            if let Some(mut feed) = self.screen_feed.take() {
+                feed.stop();
+            }
             if let Some(stdin) = child.stdin.as_mut() {
                 let _ = stdin.write_all(b"q\n");
             }
 
-            let timeout = Instant::now() + Duration::from_secs(5);
-            loop {
-                if let Some(status) = child.try_wait()? {
-                    info!("ffmpeg exited with status {}", status);
-                    break;
-                }
-
-                if Instant::now() > timeout {
-                    info!("ffmpeg did not exit gracefully, sending kill signal");
-                    child.kill()?;
-                    child.wait()?;
-                    break;
-                }
-
-                thread::sleep(Duration::from_millis(100));
+            if let Some(status) = wait_with_timeout(&mut child, self.options.quit_timeout)? {
+                info!("ffmpeg exited with status {}", status);
+            } else {
+                terminate_child(&mut child, self.options.terminate_timeout)?;
             }
         }
 
@@ -151,21 +607,129 @@ impl VideoEncoder {
         Ok(())
     }
 
-    pub fn toggle_pause(&mut self) -> Result<()> {
-        if let Some(child) = self.process.as_mut() {
-            if let Some(stdin) = child.stdin.as_mut() {
-                if stdin.write_all(b"p\n").is_err() {
-                    warn!("Pause toggle ignored: ffmpeg stdin not writable (process likely exited)");
-                    return Ok(());
-                }
-                let _ = stdin.flush();
-            }
+    pub fn outputs(&self) -> Option<&RecordingOutputs> {
+        self.outputs.as_ref()
+    }
+
+    /// The encoded Matroska stream when recording to `OutputTarget::Stdout`.
+    /// Read it continuously from another thread: ffmpeg stalls once the pipe
+    /// is full, and `stop` can only finish cleanly while it is being drained.
+    #[allow(dead_code)] // library API; the GUI always records to files
+    pub fn take_output(&mut self) -> Option<ChildStdout> {
+        self.output_pipe.take()
+    }
+
+    /// Whether pausing actually stops media from being written.
+    ///
+    /// ffmpeg's interactive `p` key does not pause live capture devices:
+    /// x11grab, pipewire, pulse/alsa and v4l2 keep delivering data and the
+    /// wallclock timestamps keep advancing, so only segment-based pausing
+    /// gives a real pause for the inputs this recorder uses.
+    pub fn can_pause(&self) -> bool {
+        self.options.seamless_pause
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// Toggle pause and return whether the paused state changed. Returns
+    /// `Ok(false)` when the current inputs cannot be paused. Ending or
+    /// starting a segment waits on ffmpeg, so call it off the UI thread.
+    pub fn toggle_pause(&mut self) -> Result<bool> {
+        if !self.can_pause() {
+            warn!("Pause not supported for current inputs; enable seamless pause to pause recordings");
+            return Ok(false);
         }
-        Ok(())
+
+        if self.paused_at.is_some() {
+            self.start()?;
+        } else {
+            self.end_process()?;
+        }
+
+        match self.paused_at.take() {
+            Some(paused_at) => self.paused_total += paused_at.elapsed(),
+            None => self.paused_at = Some(Instant::now()),
+        }
+        Ok(true)
+    }
+
+    /// Reacts to the recorded display changing size or going away. With
+    /// seamless pause, the running segment is ended and a new one started
+    /// at the new size, letterboxed to the first segment's size so they
+    /// still join; a paused recording picks that up on resume. Returns
+    /// `Ok(false)` when the inputs can't be restarted, leaving ffmpeg on the
+    /// stale geometry. Like `toggle_pause`, this blocks while ffmpeg restarts.
+    pub fn display_changed(&mut self) -> Result<bool> {
+        if !self.can_pause() || !self.options.include_video {
+            return Ok(false);
+        }
+        self.options.screen_output_size = self.screen_video_size;
+        if self.paused_at.is_none() && self.process.is_some() {
+            info!("Display changed; starting a new segment");
+            self.end_process()?;
+            self.start()?;
+        }
+        Ok(true)
+    }
+
+    /// Recorded time so far, excluding paused spans.
+    pub fn elapsed(&self) -> Duration {
+        let Some(started_at) = self.started_at else {
+            return Duration::ZERO;
+        };
+        let end = self.stopped_at.unwrap_or_else(Instant::now);
+        let current_pause = self
+            .paused_at
+            .map(|paused_at| end.saturating_duration_since(paused_at))
+            .unwrap_or_default();
+        end.saturating_duration_since(started_at)
+            .saturating_sub(self.paused_total + current_pause)
+    }
+
+    /// Record a chapter marker at the current elapsed time.
+    pub fn add_marker(&mut self) -> Duration {
+        let at = self.elapsed();
+        self.markers.push(Marker { at, note: String::new() });
+        info!("Marker added at {:?}", at);
+        at
+    }
+
+    /// Attaches `note` to the marker at `index`, e.g. the one just added.
+    pub fn set_marker_note(&mut self, index: usize, note: &str) {
+        if let Some(marker) = self.markers.get_mut(index) {
+            marker.note = note.trim().to_string();
+        }
+    }
+
+    pub fn markers(&self) -> &[Marker] {
+        &self.markers
+    }
+
+    /// Shares ffmpeg's output with `log` (e.g. the GUI's log panel) from the next start on.
+    pub fn set_log(&mut self, log: FfmpegLog) {
+        self.log = log;
+    }
+
+    pub fn ffmpeg_path(&self) -> &str {
+        &self.options.ffmpeg_path
     }
 }
 static PIPEWIRE_SUPPORTED: OnceCell<bool> = OnceCell::new();
 static PULSE_SUPPORTED: OnceCell<bool> = OnceCell::new();
+/// `ffmpeg -encoders` output per ffmpeg binary.
+static ENCODER_LISTS: Lazy<Mutex<HashMap<String, Arc<String>>>> = Lazy::new(Default::default);
+/// `ffmpeg -protocols` output per ffmpeg binary.
+static PROTOCOL_LISTS: Lazy<Mutex<HashMap<String, Arc<String>>>> = Lazy::new(Default::default);
+/// Test encode results per ffmpeg binary and hardware encoder.
+static HW_ACCEL_SUPPORT: Lazy<Mutex<HashMap<(String, HwAccel), bool>>> = Lazy::new(Default::default);
+
+/// Render node the VAAPI encoder runs on.
+pub const VAAPI_DEVICE: &str = "/dev/dri/renderD128";
+
+/// Filter turning frames into VAAPI surfaces for `h264_vaapi`.
+const VAAPI_UPLOAD_FILTER: &str = "format=nv12,hwupload";
 
 pub fn ffmpeg_supports_pipewire(ffmpeg_path: &str) -> bool {
     *PIPEWIRE_SUPPORTED.get_or_init(|| {
@@ -201,6 +765,131 @@ pub fn ffmpeg_supports_pulse(ffmpeg_path: &str) -> bool {
     })
 }
 
+/// Whether ffmpeg can encode with `accel` on this machine. Being listed in
+/// `-encoders` only means ffmpeg was built with it, so a few frames are
+/// encoded as well, which fails without the GPU or its driver. Checked once
+/// per run and ffmpeg binary; the test encode takes a moment, so the GUI
+/// runs `probe_hw_accels` in the background at startup.
+pub fn ffmpeg_supports_hw_accel(ffmpeg_path: &str, accel: HwAccel) -> bool {
+    if let Some(known) = cached_hw_accel_support(ffmpeg_path, accel) {
+        return known;
+    }
+    let res = ffmpeg_has_encoder(ffmpeg_path, accel.encoder())
+        && (accel != HwAccel::Vaapi || std::path::Path::new(VAAPI_DEVICE).exists())
+        && Command::new(ffmpeg_path)
+            .args(hw_accel_probe_args(accel))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .map(|s| s.success())
+            .unwrap_or(false);
+    info!("ffmpeg {} support: {}", accel.encoder(), res);
+    HW_ACCEL_SUPPORT.lock().unwrap().insert((ffmpeg_path.to_string(), accel), res);
+    res
+}
+
+/// `accel` if this ffmpeg and GPU can encode with it, else software with a
+/// warning; for re-encoding files, which have no live fallback of their own.
+pub fn usable_hw_accel(ffmpeg_path: &str, accel: HwAccel) -> HwAccel {
+    if ffmpeg_supports_hw_accel(ffmpeg_path, accel) {
+        return accel;
+    }
+    warn!("{} isn't usable with this ffmpeg or GPU; encoding with libx264", accel.encoder());
+    HwAccel::Software
+}
+
+/// What `ffmpeg_supports_hw_accel` found for `accel`, without probing;
+/// `None` until it has run.
+pub fn cached_hw_accel_support(ffmpeg_path: &str, accel: HwAccel) -> Option<bool> {
+    if accel == HwAccel::Software {
+        return Some(true);
+    }
+    HW_ACCEL_SUPPORT.lock().unwrap().get(&(ffmpeg_path.to_string(), accel)).copied()
+}
+
+/// Runs the test encode for every hardware encoder, so later checks read
+/// the cache. Blocks; call it off the UI thread.
+pub fn probe_hw_accels(ffmpeg_path: &str) {
+    for accel in HwAccel::ALL {
+        ffmpeg_supports_hw_accel(ffmpeg_path, accel);
+    }
+}
+
+/// Arguments encoding a few frames of a test pattern with `accel` into nothing.
+pub fn hw_accel_probe_args(accel: HwAccel) -> Vec<String> {
+    let mut cmd = FfmpegArgs(Vec::new());
+    cmd.arg("-v").arg("error");
+    cmd.0.extend(hw_device_args(accel));
+    cmd.arg("-f").arg("lavfi")
+        .arg("-i").arg("testsrc2=size=256x256:rate=30")
+        .arg("-frames:v").arg("3");
+    if accel == HwAccel::Vaapi {
+        cmd.arg("-vf").arg(VAAPI_UPLOAD_FILTER);
+    }
+    cmd.arg("-c:v").arg(accel.encoder())
+        .arg("-f").arg("null")
+        .arg("-");
+    cmd.0
+}
+
+/// Global arguments setting up the device `accel` encodes on; NVENC finds
+/// its GPU by itself.
+fn hw_device_args(accel: HwAccel) -> Vec<String> {
+    match accel {
+        HwAccel::Vaapi => vec!["-vaapi_device".to_string(), VAAPI_DEVICE.to_string()],
+        HwAccel::Software | HwAccel::Nvenc => Vec::new(),
+    }
+}
+
+/// Whether `ffmpeg -encoders` lists `encoder`. The list is read once per run
+/// and ffmpeg binary.
+pub fn ffmpeg_has_encoder(ffmpeg_path: &str, encoder: &str) -> bool {
+    let cached = ENCODER_LISTS.lock().unwrap().get(ffmpeg_path).cloned();
+    let list = cached.unwrap_or_else(|| {
+        let list = Command::new(ffmpeg_path)
+            .arg("-hide_banner")
+            .arg("-encoders")
+            .stderr(Stdio::null())
+            .output()
+            .map(|out| String::from_utf8_lossy(&out.stdout).into_owned())
+            .unwrap_or_else(|e| {
+                warn!("Failed to list ffmpeg encoders: {}", e);
+                String::new()
+            });
+        let list = Arc::new(list);
+        ENCODER_LISTS.lock().unwrap().insert(ffmpeg_path.to_string(), Arc::clone(&list));
+        list
+    });
+    // Lines look like " A....D libmp3lame           libmp3lame MP3 (MPEG audio layer 3)"
+    list.lines().any(|line| line.split_whitespace().nth(1) == Some(encoder))
+}
+
+/// Whether ffmpeg can write to `protocol` (e.g. "srt", which needs libsrt).
+/// The list is read once per run and ffmpeg binary.
+pub fn ffmpeg_has_output_protocol(ffmpeg_path: &str, protocol: &str) -> bool {
+    let cached = PROTOCOL_LISTS.lock().unwrap().get(ffmpeg_path).cloned();
+    let list = cached.unwrap_or_else(|| {
+        let list = Command::new(ffmpeg_path)
+            .arg("-hide_banner")
+            .arg("-protocols")
+            .stderr(Stdio::null())
+            .output()
+            .map(|out| String::from_utf8_lossy(&out.stdout).into_owned())
+            .unwrap_or_else(|e| {
+                warn!("Failed to list ffmpeg protocols: {}", e);
+                String::new()
+            });
+        let list = Arc::new(list);
+        PROTOCOL_LISTS.lock().unwrap().insert(ffmpeg_path.to_string(), Arc::clone(&list));
+        list
+    });
+    // "Input:" and "Output:" headers, each followed by one protocol per line
+    list.lines()
+        .skip_while(|line| line.trim() != "Output:")
+        .any(|line| line.trim() == protocol)
+}
+
 impl Drop for VideoEncoder {
     fn drop(&mut self) {
         if let Err(err) = self.stop() {
@@ -209,6 +898,49 @@ impl Drop for VideoEncoder {
     }
 }
 
+fn wait_with_timeout(child: &mut Child, timeout: Duration) -> Result<Option<ExitStatus>> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() > deadline {
+            return Ok(None);
+        }
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Ask ffmpeg to shut down with SIGTERM so it still gets a chance to write the
+/// container trailer, and only SIGKILL it if that is ignored as well.
+#[cfg(unix)]
+fn terminate_child(child: &mut Child, timeout: Duration) -> Result<()> {
+    info!("ffmpeg did not exit gracefully, sending SIGTERM");
+    // SAFETY: the pid belongs to a child we spawned and have not reaped yet.
+    let rc = unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGTERM) };
+    if rc == 0 {
+        if let Some(status) = wait_with_timeout(child, timeout)? {
+            info!("ffmpeg exited after SIGTERM with status {}", status);
+            return Ok(());
+        }
+    } else {
+        warn!("Failed to send SIGTERM to ffmpeg: {}", std::io::Error::last_os_error());
+    }
+
+    info!("ffmpeg ignored SIGTERM, sending kill signal");
+    child.kill()?;
+    child.wait()?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn terminate_child(child: &mut Child, _timeout: Duration) -> Result<()> {
+    info!("ffmpeg did not exit gracefully, sending kill signal");
+    child.kill()?;
+    child.wait()?;
+    Ok(())
+}
+
 fn ensure_ffmpeg_available(path: &str) -> Result<()> {
     Command::new(path)
         .arg("-version")
@@ -234,84 +966,252 @@ fn ffmpeg_v4l2_accessible(ffmpeg_path: &str, device_path: &str) -> bool {
         .unwrap_or(false)
 }
 
-fn build_ffmpeg(options: &RecorderOptions) -> Result<(Child, RecordingOutputs)> {
-    let mut cmd = Command::new(&options.ffmpeg_path);
-    cmd.arg("-y")
-        .arg("-hide_banner")
-        .arg("-loglevel")
-        .arg("warning")
-        .arg("-stats")
-        .arg("-threads").arg("0")
+/// Filter graph combining screen input 0 and the webcam into `[overlayed]`:
+/// the webcam where it was placed on the preview, the screen there on top
+/// of a screen-sized webcam, or both side by side, per `overlay_layout`.
+pub fn composite_graph(options: &RecorderOptions, screen: &str, webcam_index: usize) -> String {
+    let join = |filters: Vec<Option<String>>| -> String {
+        filters.into_iter().flatten().map(|f| format!(",{}", f)).collect()
+    };
+    let blur = options.background_blur.as_ref().map(webcam_blur_filter);
+    let border = webcam_border_filter(&options.webcam_border);
+    match options.overlay_layout {
+        OverlayLayout::WebcamOver => {
+            let ((x, y), (width, height)) = options.webcam_overlay.in_frame(options.capture_region);
+            format!(
+                "[{webcam}:v]scale={w}:{h}{cam}[cam_scaled];{screen}[cam_scaled]overlay={x}:{y}[overlayed]",
+                screen = screen,
+                webcam = webcam_index,
+                w = width,
+                h = height,
+                cam = join(vec![
+                    blur,
+                    options.chroma_key.as_ref().map(webcam_key_filter),
+                    border,
+                    overlay_opacity_filter(options.webcam_overlay.opacity),
+                ]),
+                x = x,
+                y = y,
+            )
+        }
+        // hstack needs matching heights and pixel formats
+        OverlayLayout::SideBySide => format!(
+            "{screen}scale=-2:720,format=yuv420p[screen_scaled];[{webcam}:v]scale=-2:720{cam},format=yuv420p[cam_scaled];\
+             [screen_scaled][cam_scaled]hstack=inputs=2[overlayed]",
+            screen = screen,
+            webcam = webcam_index,
+            cam = join(vec![blur, border]),
+        ),
+        // The webcam fills a frame the size of the screen, as in the preview,
+        // so the overlay box lands where it was placed. Nothing to see
+        // through on a full-frame webcam, so no chroma key.
+        OverlayLayout::ScreenOver => {
+            let ((x, y), (width, height)) = options.webcam_overlay.in_frame(options.capture_region);
+            format!(
+                "{screen}split[screen_ref][screen_small];\
+                 [{webcam}:v]null{cam}[cam_raw];\
+                 [cam_raw][screen_ref]scale2ref=w='max(rw,trunc(rh*iw/ih/2)*2)':h='max(rh,trunc(rw*ih/iw/2)*2)'[cam_fill][screen_canvas];\
+                 [screen_canvas][cam_fill]overlay=(W-w)/2:(H-h)/2[cam_base];\
+                 [screen_small]scale={w}:{h}{frame}[screen_scaled];\
+                 [cam_base][screen_scaled]overlay={x}:{y}[overlayed]",
+                screen = screen,
+                webcam = webcam_index,
+                cam = join(vec![blur]),
+                w = width,
+                h = height,
+                frame = join(vec![border, overlay_opacity_filter(options.webcam_overlay.opacity)]),
+                x = x,
+                y = y,
+            )
+        }
+    }
+}
+
+/// Fades the webcam to `opacity` by scaling its alpha, the way the preview
+/// blends it; `None` when it is fully opaque.
+pub fn overlay_opacity_filter(opacity: f32) -> Option<String> {
+    let opacity = opacity.clamp(0.0, 1.0);
+    (opacity < 1.0).then(|| format!("format=rgba,colorchannelmixer=aa={:.3}", opacity))
+}
+
+/// Filter chain blurring the webcam outside a centered subject box: one copy
+/// is box-blurred, the box is cropped from the other and laid back on top.
+/// Same result as `webcam::blur_background` in the preview.
+pub fn webcam_blur_filter(blur: &BackgroundBlur) -> String {
+    format!(
+        "split[cam_bg][cam_fg];[cam_bg]boxblur={radius}:1[cam_blur];\
+         [cam_fg]crop=trunc(iw*{w:.3}/2)*2:trunc(ih*{h:.3}/2)*2[cam_subject];\
+         [cam_blur][cam_subject]overlay=(W-w)/2:(H-h)/2",
+        radius = blur.radius.max(1),
+        w = blur.subject_width.clamp(0.05, 1.0),
+        h = blur.subject_height.clamp(0.05, 1.0)
+    )
+}
+
+/// Moves a click from root-window coordinates into the recorded frame,
+/// which starts at the screen's corner, or the capture region's within it.
+pub fn click_in_frame(
+    (x, y): (i32, i32),
+    (screen_x, screen_y): (i32, i32),
+    region: Option<(u32, u32, u32, u32)>,
+) -> (i32, i32) {
+    let (left, top) = region.map_or((0, 0), |(x, y, _, _)| (x as i32, y as i32));
+    (x - screen_x - left, y - screen_y - top)
+}
+
+/// Named `drawbox` the click ring is drawn with, parked outside the frame
+/// until `VideoEncoder::set_click_ring` moves it onto a click.
+pub fn click_highlight_filter(highlight: &ClickHighlight) -> String {
+    let [r, g, b] = highlight.color;
+    let (x, y, side) = CLICK_RING_HIDDEN;
+    format!(
+        "{}=x={}:y={}:w={}:h={}:color=0x{:02X}{:02X}{:02X}@0.9:t={}",
+        CLICK_FILTER,
+        x,
+        y,
+        side,
+        side,
+        r,
+        g,
+        b,
+        (highlight.size / 12).max(2)
+    )
+}
+
+/// Scales the screen to fit `width` x `height` and pads the rest black, so a
+/// segment recorded after a display change joins the earlier ones.
+pub fn screen_fit_filter(width: u32, height: u32) -> String {
+    format!(
+        "scale={w}:{h}:force_original_aspect_ratio=decrease,pad={w}:{h}:(ow-iw)/2:(oh-ih)/2,setsar=1",
+        w = width & !1,
+        h = height & !1
+    )
+}
+
+/// `colorkey` filter removing the key color from the scaled webcam; the
+/// preview uses the same math in `webcam::chroma_key_alpha`.
+pub fn webcam_key_filter(key: &ChromaKey) -> String {
+    let [r, g, b] = key.color;
+    format!(
+        "colorkey=color=0x{:02X}{:02X}{:02X}:similarity={:.3}:blend={:.3}",
+        r,
+        g,
+        b,
+        key.similarity.clamp(0.01, 1.0),
+        key.blend.clamp(0.0, 1.0)
+    )
+}
+
+/// `drawbox` filter framing the scaled webcam from the inside, like the
+/// preview overlay's border; `None` when the border is off.
+pub fn webcam_border_filter(border: &OverlayBorder) -> Option<String> {
+    (border.width > 0).then(|| {
+        let [r, g, b] = border.color;
+        format!("drawbox=x=0:y=0:w=iw:h=ih:color=0x{:02X}{:02X}{:02X}:t={}", r, g, b, border.width)
+    })
+}
+
+/// ffmpeg argument list under construction, with `Command`-style chaining.
+struct FfmpegArgs(Vec<String>);
+
+impl FfmpegArgs {
+    fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+        self.0.push(arg.as_ref().to_string_lossy().into_owned());
+        self
+    }
+}
+
+fn build_ffmpeg(options: &RecorderOptions, base_name: &str) -> Result<(Child, RecordingOutputs, EffectiveInputs)> {
+    let inputs = resolve_inputs(options)?;
+    let (args, outputs) = ffmpeg_args(options, &inputs, base_name)?;
+    let child = Command::new(&options.ffmpeg_path)
+        .args(&args)
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
-        .stderr(Stdio::piped());
+        .stderr(Stdio::piped())
+        .spawn()
+        .context("Failed to spawn ffmpeg process")?;
+    Ok((child, outputs, inputs))
+}
 
-    let mut video_map: Option<String> = None;
-    let mut audio_map: Option<String> = None;
-    let mut filter_complex: Option<String> = None;
-    let even_scale_filter = "trunc(iw/2)*2:trunc(ih/2)*2";
-    let mut needs_even_scale = false;
-    let effective_include_video = options.include_video;
-    let mut effective_include_webcam = options.include_webcam;
-
-    if effective_include_video {
-        let wayland = std::env::var("WAYLAND_DISPLAY").is_ok();
-        // Derive preference from environment to avoid struct field coupling
-        let prefer_pipewire = std::env::var("OCTOCORD_USE_PIPEWIRE")
-            .map(|v| matches!(v.as_str(), "1" | "true" | "TRUE"))
-            .unwrap_or(false);
-        let have_display = std::env::var("DISPLAY").is_ok();
-
-        if wayland && prefer_pipewire && !have_display {
-            // Experimental PipeWire screen capture (requires xdg-desktop-portal + ffmpeg pipewire)
-            cmd.arg("-thread_queue_size").arg("2048")
-                .arg("-f").arg("pipewire")
-                .arg("-i").arg("0");
-            info!("Video input: pipewire (Wayland)");
-            video_map = Some("0:v".to_string());
-            needs_even_scale = true;
-        } else {
-            let screen_input = determine_screen_input(options.selected_screen)?;
-            let video_size_str = screen_input.video_size.clone();
-            cmd.arg("-thread_queue_size").arg("2048")
-                .arg("-f").arg("x11grab")
-                .arg("-framerate").arg(options.frame_rate.to_string())
-                .arg("-probesize").arg("50M")
-                .arg("-fflags").arg("+nobuffer")
-                .arg("-use_wallclock_as_timestamps").arg("1")
-                .arg("-video_size").arg(video_size_str.clone())
-                .arg("-i").arg(screen_input.display_input);
-            info!("Video input: x11grab {}", video_size_str);
-            video_map = Some("0:v".to_string());
-            needs_even_scale = true;
+/// Inputs resolved against the running system: devices probed, screen size known.
+/// Kept separate from argument construction so `ffmpeg_args` stays pure.
+#[derive(Debug, Clone, Default)]
+pub struct EffectiveInputs {
+    /// Input arguments for the screen capture (on macOS this input also carries audio).
+    pub screen: Option<Vec<String>>,
+    /// Input arguments for the audio capture when it is a separate input.
+    pub audio: Option<Vec<String>>,
+    /// Whether the audio stream comes from the screen input (avfoundation).
+    pub audio_in_screen_input: bool,
+    /// Input arguments for the desktop audio capture mixed with `audio`.
+    pub system_audio: Option<Vec<String>>,
+    /// Accessible v4l2 device for the webcam overlay.
+    pub webcam: Option<String>,
+    /// Size the screen input is scaled back up to when it captures a
+    /// cursor-following window instead of the whole screen.
+    pub zoomed_screen_size: Option<(u32, u32)>,
+    /// Size of the captured screen or region, when the input knows it.
+    pub screen_size: Option<(u32, u32)>,
+    /// Capture region cut out with a `crop` filter, for inputs that can
+    /// only grab the whole screen.
+    pub screen_crop: Option<(u32, u32, u32, u32)>,
+    /// What to capture when `screen` reads raw frames from stdin.
+    pub screen_feed: Option<FeedSource>,
+}
+
+/// Probes the system for the inputs `options` asks for.
+pub fn resolve_inputs(options: &RecorderOptions) -> Result<EffectiveInputs> {
+    let mut inputs = EffectiveInputs::default();
+
+    if options.include_audio
+        && options.audio_export_format == AudioExportFormat::Mp3
+        && !ffmpeg_has_encoder(&options.ffmpeg_path, "libmp3lame")
+    {
+        return Err(anyhow!("MP3 export needs an ffmpeg built with libmp3lame; switch the audio format back to FLAC"));
+    }
+
+    if let Some(srt) = &options.stream {
+        let target = StreamTarget::srt(srt)?;
+        if !ffmpeg_has_output_protocol(&options.ffmpeg_path, target.protocol) {
+            return Err(anyhow!(
+                "Streaming over SRT needs an ffmpeg built with libsrt (--enable-libsrt); this one lists no \"srt\" protocol"
+            ));
         }
     }
 
-    if options.include_audio {
-        // Choose ffmpeg audio backend
-        // Default to pulse when available (common with PipeWire), else ALSA.
-        let ff_backend = std::env::var("OCTOCORD_AUDIO_BACKEND").ok().unwrap_or_else(|| {
-            if ffmpeg_supports_pulse(&options.ffmpeg_path) { "pulse".to_string() } else { "alsa".to_string() }
-        });
-        let ff_format = if ff_backend.eq_ignore_ascii_case("pulse") { "pulse" } else { "alsa" };
-        // Use provided device when compatible, else logical default to avoid busy ALSA hw nodes
-        let ff_device = match (ff_format, options.audio_device.clone()) {
-            ("pulse", Some(dev)) if dev != "default" => dev,
-            _ => "default".to_string(),
-        };
+    if options.include_video && options.piped_screen {
+        if options.cursor_zoom.is_some() {
+            warn!("Cursor zoom needs x11grab; ignored with piped screen capture");
+        }
+        let source = screen_feed::resolve_feed_source(options.selected_screen.unwrap_or(0), options.capture_region)?;
+        inputs.screen = Some(screen_feed::rawvideo_input_args(source.size, options.frame_rate));
+        inputs.screen_size = Some(source.size);
+        inputs.screen_feed = Some(source);
+    } else if options.include_video {
+        let mut screen = FfmpegArgs(Vec::new());
+        let size = push_screen_input(&mut screen, options)?;
+        inputs.screen_size = size.size;
+        inputs.zoomed_screen_size = size.size.filter(|_| size.zoomed);
+        inputs.screen_crop = size.crop;
+        inputs.screen = Some(screen.0);
+    }
 
-        cmd.arg("-thread_queue_size").arg("2048")
-            .arg("-f").arg(ff_format)
-            .arg("-ac").arg("2")
-            .arg("-ar").arg(options.audio_sample_rate.to_string())
-            .arg("-i").arg(ff_device);
-        let audio_index = if options.include_video { 1 } else { 0 };
-        audio_map = Some(format!("{}:a", audio_index));
-        info!("Audio input: {}:{} @ {} Hz", ff_format, "default", options.audio_sample_rate);
+    // avfoundation delivers screen and audio from a single input
+    inputs.audio_in_screen_input = SCREEN_INPUT_CARRIES_AUDIO && inputs.screen.is_some() && inputs.screen_feed.is_none();
+    if options.include_audio && !inputs.audio_in_screen_input {
+        let mut audio = FfmpegArgs(Vec::new());
+        push_audio_input(&mut audio, options)?;
+        inputs.audio = Some(audio.0);
+    }
+    if options.include_audio && options.system_audio {
+        let mut system_audio = FfmpegArgs(Vec::new());
+        push_system_audio_input(&mut system_audio, options)?;
+        inputs.system_audio = Some(system_audio.0);
     }
 
     // Optionally include webcam only if a valid v4l2 path is resolved
-    if effective_include_webcam {
+    if options.include_webcam {
         let requested = options
             .webcam_device
             .clone()
@@ -326,47 +1226,307 @@ fn build_ffmpeg(options: &RecorderOptions) -> Result<(Child, RecordingOutputs)>
                 .find(|p| std::path::Path::new(p).exists())
         };
 
-        let resolved = resolved.and_then(|s| {
-            if ffmpeg_v4l2_accessible(&options.ffmpeg_path, &s) { Some(s) } else { None }
-        });
+        inputs.webcam = resolved.filter(|s| ffmpeg_v4l2_accessible(&options.ffmpeg_path, s));
+        if inputs.webcam.is_none() {
+            info!("Webcam device not accessible; continuing without webcam");
+        }
+    }
 
-        if effective_include_webcam {
-            match resolved {
-                Some(webcam_source) => {
-                    cmd.arg("-thread_queue_size").arg("512")
-                        .arg("-f").arg("v4l2")
-                        .arg("-framerate").arg("30")
-                        .arg("-i").arg(webcam_source);
-                }
-                None => {
-                    effective_include_webcam = false;
-                    info!("Webcam device not accessible; continuing without webcam");
-                }
-            }
-            let webcam_index = if options.include_video { 1 } else { 0 }
-                + if options.include_audio { 1 } else { 0 };
+    Ok(inputs)
+}
+
+/// Builds the ffmpeg arguments (without the program name) for a recording and the
+/// output paths they write to. Probes devices but spawns nothing long-lived.
+pub fn build_ffmpeg_args(options: &RecorderOptions, base_name: &str) -> Result<(Vec<String>, RecordingOutputs)> {
+    let inputs = resolve_inputs(options)?;
+    // A cache hit once the GUI's startup probe is done; the command line probes here
+    if options.hw_accel != HwAccel::Software && !ffmpeg_supports_hw_accel(&options.ffmpeg_path, options.hw_accel) {
+        warn!("{} isn't usable with this ffmpeg or GPU; encoding with libx264", options.hw_accel.encoder());
+        let software = RecorderOptions { hw_accel: HwAccel::Software, ..options.clone() };
+        return ffmpeg_args(&software, &inputs, base_name);
+    }
+    ffmpeg_args(options, &inputs, base_name)
+}
+
+/// Pure argument construction from already-resolved inputs.
+pub fn ffmpeg_args(
+    options: &RecorderOptions,
+    inputs: &EffectiveInputs,
+    base_name: &str,
+) -> Result<(Vec<String>, RecordingOutputs)> {
+    let mut cmd = FfmpegArgs(Vec::new());
+    cmd.arg("-y")
+        .arg("-hide_banner")
+        .arg("-loglevel")
+        .arg("warning")
+        .arg("-stats");
+    if let Some(threads) = options.threads {
+        cmd.arg("-threads").arg(threads.to_string());
+        cmd.arg("-filter_threads").arg(threads.to_string());
+    }
+    let hw_accel = recording_hw_accel(options);
+    cmd.0.extend(hw_device_args(hw_accel));
+    // VAAPI encodes surfaces, so the recording's video is uploaded last
+    let hw_upload = (hw_accel == HwAccel::Vaapi).then_some(VAAPI_UPLOAD_FILTER);
+
+    let mut video_map: Option<String> = None;
+    let mut audio_map: Option<String> = None;
+    let mut filter_complex: Option<String> = None;
+    let even_scale_filter = "trunc(iw/2)*2:trunc(ih/2)*2";
+    // A cursor-following window is blown back up to the screen size
+    let zoom_filter = inputs
+        .zoomed_screen_size
+        .map(|(width, height)| format!("scale={}:{}:flags=lanczos", width & !1, height & !1));
+    // Inputs that grab the whole screen have the region cut out first, so
+    // everything after it works in region coordinates
+    let crop_filter = inputs.screen_crop.map(|(x, y, w, h)| format!("crop={}:{}:{}:{}", w, h, x, y));
+    // The piped feed draws the ring itself
+    let click_filter = options
+        .click_highlight
+        .as_ref()
+        .filter(|_| inputs.screen_feed.is_none())
+        .map(click_highlight_filter);
+    // After a display change, later segments are letterboxed to the first one's size
+    let fit_filter = options.screen_output_size.map(|(width, height)| screen_fit_filter(width, height));
+    let size_filters: Vec<String> = zoom_filter.into_iter().chain(fit_filter).collect();
+    // Filters for the screen alone, ahead of any overlay
+    let leading_filters: Vec<String> = crop_filter.into_iter().chain(click_filter).collect();
+    let screen_filters: Vec<String> = leading_filters.iter().chain(&size_filters).cloned().collect();
+    let screen_scale = if size_filters.is_empty() {
+        leading_filters
+            .iter()
+            .cloned()
+            .chain([format!("scale={}", even_scale_filter)])
+            .collect::<Vec<_>>()
+            .join(",")
+    } else {
+        screen_filters.join(",")
+    };
+    let mut needs_even_scale = false;
+    let effective_include_video = options.include_video && inputs.screen.is_some();
+    let effective_include_webcam = options.include_webcam && inputs.webcam.is_some();
+    let audio_in_screen_input = inputs.audio_in_screen_input && effective_include_video;
+    let mut next_input = 0;
+
+    if let Some(screen) = &inputs.screen {
+        if options.low_latency {
+            cmd.0.extend(low_latency_input_args(screen));
+        }
+        cmd.0.extend(screen.iter().cloned());
+        next_input += 1;
+        video_map = Some("0:v".to_string());
+        needs_even_scale = true;
+    }
+
+    let mut audio_index = 0;
+    if options.include_audio {
+        if !audio_in_screen_input {
+            let audio = inputs
+                .audio
+                .as_ref()
+                .ok_or_else(|| anyhow!("Audio requested but no audio input was resolved"))?;
+            cmd.0.extend(audio.iter().cloned());
+            audio_index = next_input;
+            next_input += 1;
+        }
+        audio_map = Some(format!("{}:a", audio_index));
+    }
+
+    let mut separate_webcam_map: Option<String> = None;
+    let mut system_audio_index = None;
+    // Desktop audio kept as its own track
+    let mut desktop_map: Option<String> = None;
+    if let Some(system_audio) = inputs.system_audio.as_ref().filter(|_| options.include_audio) {
+        cmd.0.extend(system_audio.iter().cloned());
+        system_audio_index = Some(next_input);
+        next_input += 1;
+    }
+
+    if let Some(webcam_source) = inputs.webcam.as_ref().filter(|_| effective_include_webcam) {
+        if options.low_latency {
+            cmd.0.extend(low_latency_input_args(&[]));
+        }
+        cmd.arg("-thread_queue_size").arg("512")
+            .arg("-f").arg("v4l2")
+            .arg("-framerate").arg("30")
+            .arg("-i").arg(webcam_source);
+        let webcam_index = next_input;
 
-            if options.include_video {
+        if effective_include_video {
+            if options.separate_webcam {
+                separate_webcam_map = Some(format!("{}:v", webcam_index));
+            }
+            if options.composite_webcam {
+                let (screen_graph, screen) = if screen_filters.is_empty() {
+                    (String::new(), "[0:v]")
+                } else {
+                    (format!("[0:v]{}[screen];", screen_filters.join(",")), "[screen]")
+                };
                 filter_complex = Some(format!(
-                    "[{webcam}:v]scale=640:-1[cam_scaled];[0:v][cam_scaled]overlay=W-w-40:H-h-40[overlayed];[overlayed]scale={filter}[vout]",
-                    webcam = webcam_index,
-                    filter = even_scale_filter
+                    "{}{};[overlayed]scale={}[vout]",
+                    screen_graph,
+                    composite_graph(options, screen, webcam_index),
+                    even_scale_filter
                 ));
                 video_map = Some("[vout]".to_string());
                 needs_even_scale = false;
-            } else {
-                video_map = Some(format!("{}:v", webcam_index));
             }
         } else {
-            info!("Webcam device not found; continuing without webcam overlay");
+            video_map = Some(format!("{}:v", webcam_index));
+        }
+    }
+
+    // -vf and -filter:a only reach the next output, so the stream repeats them
+    let video_scaled_by_vf = filter_complex.is_none() && needs_even_scale;
+    if video_scaled_by_vf {
+        cmd.arg("-vf").arg(match hw_upload {
+            Some(upload) => format!("{},{}", screen_scale, upload),
+            None => screen_scale.clone(),
+        });
+    } else if let (Some(upload), Some(map)) = (hw_upload, &video_map) {
+        if !map.starts_with('[') {
+            cmd.arg("-vf").arg(upload);
+        }
+    }
+    let mut simple_audio_filter = None;
+
+    if options.include_audio {
+        let mut mic_filters = Vec::new();
+        let volume_scale = 10f32.powf(options.audio_gain_db / 20.0);
+        if (volume_scale - 1.0).abs() > f32::EPSILON {
+            mic_filters.push(format!("volume={:.3}", volume_scale));
+        }
+        if options.audio_balance.abs() > f32::EPSILON {
+            let [left, right] = audio::balance_gains(options.audio_balance);
+            mic_filters.push(format!("pan=stereo|c0={:.3}*c0|c1={:.3}*c1", left, right));
+        }
+        if options.mic_gate != MicGateMode::Off {
+            let initial = if options.mic_gate.mic_open(false) { 1 } else { 0 };
+            mic_filters.push(format!("{}=volume={}", MIC_GATE_FILTER, initial));
+        }
+        // The limiter goes last so it catches the boosted (and mixed) signal
+        let limiter = options.limiter_threshold_db.map(|threshold_db| {
+            // alimiter accepts limits down to -24 dBFS and output levels up to +36 dB
+            let limit = 10f32.powf(threshold_db.clamp(-24.0, 0.0) / 20.0);
+            let makeup = 10f32.powf(options.limiter_makeup_db.clamp(0.0, 36.0) / 20.0);
+            format!(
+                "alimiter=limit={:.4}:level_out={:.3}:release={}:level=disabled",
+                limit,
+                makeup,
+                audio::LIMITER_RELEASE_MS
+            )
+        });
+
+        if let Some(system_index) = system_audio_index {
+            // Mixing two inputs needs a complex graph; -filter:a can't feed it
+            let mic_chain = if mic_filters.is_empty() { "anull".to_string() } else { mic_filters.join(",") };
+            let mix_tail = limiter.map(|l| format!(",{}", l)).unwrap_or_default();
+            // Split outputs keep desktop audio apart: a file of its own, or a
+            // second track where one stream is written
+            let audio_graph = if options.separate_outputs {
+                // Only the microphone is filtered; ducking still reaches the desktop track
+                match options.ducking {
+                    Some(ducking) => {
+                        desktop_map = Some("[desktop]".to_string());
+                        format!(
+                            "[{mic}:a]{chain},asplit=2[mic][mic_sc];[mic]anull{tail}[aout];\
+                             [{sys}:a][mic_sc]{duck}[desktop]",
+                            mic = audio_index,
+                            chain = mic_chain,
+                            tail = mix_tail,
+                            sys = system_index,
+                            duck = sidechain_filter(&ducking)
+                        )
+                    }
+                    None => {
+                        desktop_map = Some(format!("{}:a", system_index));
+                        format!("[{}:a]{}{}[aout]", audio_index, mic_chain, mix_tail)
+                    }
+                }
+            } else {
+                match options.ducking {
+                    Some(ducking) => format!(
+                        "[{mic}:a]{chain}[mic];[mic]asplit=2[mic_mix][mic_sc];\
+                         [{sys}:a][mic_sc]{duck}[sys_ducked];\
+                         [mic_mix][sys_ducked]amix=inputs=2:duration=longest:normalize=0{tail}[aout]",
+                        mic = audio_index,
+                        chain = mic_chain,
+                        sys = system_index,
+                        duck = sidechain_filter(&ducking),
+                        tail = mix_tail
+                    ),
+                    None => format!(
+                        "[{mic}:a]{chain}[mic];[mic][{sys}:a]amix=inputs=2:duration=longest:normalize=0{tail}[aout]",
+                        mic = audio_index,
+                        chain = mic_chain,
+                        sys = system_index,
+                        tail = mix_tail
+                    ),
+                }
+            };
+            filter_complex = Some(match filter_complex {
+                Some(video_graph) => format!("{};{}", video_graph, audio_graph),
+                None => audio_graph,
+            });
+            audio_map = Some("[aout]".to_string());
+        } else {
+            mic_filters.extend(limiter);
+            if !mic_filters.is_empty() {
+                let chain = mic_filters.join(",");
+                cmd.arg("-filter:a").arg(&chain);
+                simple_audio_filter = Some(chain);
+            }
+        }
+    }
+
+    let stream = options.stream.as_ref().map(StreamTarget::srt).transpose()?;
+    let mut stream_video_map = video_map.clone();
+    let mut stream_audio_map = audio_map.clone();
+    if stream.is_some() {
+        // A filter graph output can only be mapped once; split it for the stream
+        let mut splits = Vec::new();
+        if video_map.as_deref() == Some("[vout]") {
+            splits.push("[vout]split=2[vfile][vstream]".to_string());
+            video_map = Some("[vfile]".to_string());
+            stream_video_map = Some("[vstream]".to_string());
+        }
+        if audio_map.as_deref() == Some("[aout]") {
+            match desktop_map.take() {
+                // The stream gets one track, so the separate tracks are mixed for it
+                Some(desktop) => {
+                    let desktop_source = if desktop.starts_with('[') {
+                        splits.push(format!("{}asplit=2[dfile][dstream]", desktop));
+                        desktop_map = Some("[dfile]".to_string());
+                        "[dstream]".to_string()
+                    } else {
+                        desktop_map = Some(desktop.clone());
+                        format!("[{}]", desktop)
+                    };
+                    splits.push(format!(
+                        "[aout]asplit=2[afile][amic];[amic]{}amix=inputs=2:duration=longest:normalize=0[astream]",
+                        desktop_source
+                    ));
+                }
+                None => splits.push("[aout]asplit=2[afile][astream]".to_string()),
+            }
+            audio_map = Some("[afile]".to_string());
+            stream_audio_map = Some("[astream]".to_string());
+        }
+        if !splits.is_empty() {
+            filter_complex = filter_complex.map(|graph| format!("{};{}", graph, splits.join(";")));
         }
     }
 
-    if filter_complex.is_none() && needs_even_scale {
-        cmd.arg("-vf").arg(format!("scale={}", even_scale_filter));
+    if let (Some(upload), Some(map)) = (hw_upload, video_map.as_deref().filter(|map| map.starts_with('['))) {
+        filter_complex = filter_complex.map(|graph| format!("{};{}{}[vhw]", graph, map, upload));
+        video_map = Some("[vhw]".to_string());
     }
 
     if let Some(filter) = filter_complex {
+        if let Some(threads) = options.threads {
+            // The overlay graphs do the heavy lifting; keep them within the cap too
+            cmd.arg("-filter_complex_threads").arg(threads.to_string());
+        }
         cmd.arg("-filter_complex").arg(filter);
     }
 
@@ -385,19 +1545,19 @@ fn build_ffmpeg(options: &RecorderOptions) -> Result<(Child, RecordingOutputs)>
         }
     }
 
-    if options.include_audio {
-        let volume_scale = 10f32.powf(options.audio_gain_db / 20.0);
-        if (volume_scale - 1.0).abs() > f32::EPSILON {
-            cmd.arg("-filter:a").arg(format!("volume={:.3}", volume_scale));
-        }
-    }
-
     cmd.arg("-shortest");
     
     // Compute outputs based on effective stream availability
-    let outputs = prepare_output_paths_effective(options, effective_include_video || effective_include_webcam)?;
+    let outputs = prepare_output_paths_effective(
+        options,
+        effective_include_video || effective_include_webcam,
+        separate_webcam_map.is_some(),
+        desktop_map.is_some(),
+        base_name,
+    )?;
 
-    if options.separate_outputs && options.include_audio && (effective_include_video || effective_include_webcam) {
+    let pipe_url = options.output_target.ffmpeg_url();
+    if pipe_url.is_none() && options.separate_outputs && options.include_audio && (effective_include_video || effective_include_webcam) {
         let video_stream = video_map
             .clone()
             .ok_or_else(|| anyhow!("Video output requested but no video stream available"))?;
@@ -406,13 +1566,10 @@ fn build_ffmpeg(options: &RecorderOptions) -> Result<(Child, RecordingOutputs)>
             .as_ref()
             .ok_or_else(|| anyhow!("Expected video-only output path"))?;
 
-        cmd.arg("-map").arg(video_stream)
-            .arg("-c:v").arg("libx264")
-            .arg("-preset").arg(preset_for_quality(options.video_quality))
-            .arg("-crf").arg(crf_for_quality(options.video_quality).to_string())
-            .arg("-pix_fmt").arg("yuv420p")
-            .arg("-b:v").arg(format!("{}k", options.video_bitrate_kbps))
-            .arg(video_output);
+        cmd.arg("-map").arg(video_stream);
+        push_video_codec(&mut cmd, options);
+        push_container_flags(&mut cmd, options.output_container);
+        cmd.arg(video_output);
 
         let audio_stream = audio_map
             .clone()
@@ -422,103 +1579,780 @@ fn build_ffmpeg(options: &RecorderOptions) -> Result<(Child, RecordingOutputs)>
             .as_ref()
             .ok_or_else(|| anyhow!("Expected audio-only output path"))?;
 
-        // Standalone audio file uses FLAC codec to match .flac container
-        cmd.arg("-map").arg(audio_stream)
-            .arg("-c:a").arg("flac")
-            .arg("-ar").arg(options.audio_sample_rate.to_string())
-            .arg(audio_output);
-    } else {
-        let combined_output = outputs
-            .combined
-            .as_ref()
-            .ok_or_else(|| anyhow!("Expected combined output path"))?;
+        cmd.arg("-map").arg(audio_stream);
+        push_audio_file_codec(&mut cmd, options);
+        cmd.arg(audio_output);
 
+        if let (Some(desktop), Some(desktop_output)) = (&desktop_map, outputs.desktop_audio.as_ref()) {
+            cmd.arg("-map").arg(desktop);
+            push_audio_file_codec(&mut cmd, options);
+            cmd.arg(desktop_output);
+        }
+    } else {
         if let Some(video_stream) = video_map.clone() {
-            cmd.arg("-map").arg(video_stream)
-                .arg("-c:v").arg("libx264")
-                .arg("-preset").arg(preset_for_quality(options.video_quality))
-                .arg("-crf").arg(crf_for_quality(options.video_quality).to_string())
-                .arg("-pix_fmt").arg("yuv420p")
-                .arg("-b:v").arg(format!("{}k", options.video_bitrate_kbps));
+            cmd.arg("-map").arg(video_stream);
+            push_video_codec(&mut cmd, options);
         }
 
         if let Some(audio_stream) = audio_map {
-            // If combined has no video (audio-only flac), use FLAC codec. Otherwise AAC for MKV with video
-            let use_flac = video_map.is_none();
-            if use_flac {
-                cmd.arg("-map").arg(audio_stream)
-                    .arg("-c:a").arg("flac")
-                    .arg("-ar").arg(options.audio_sample_rate.to_string());
+            // Audio-only recordings use the export format; with video the
+            // audio codec decides, whatever the FLAC settings
+            if video_map.is_none() {
+                cmd.arg("-map").arg(audio_stream);
+                push_audio_file_codec(&mut cmd, options);
             } else {
-                cmd.arg("-map").arg(audio_stream)
-                    .arg("-c:a").arg("aac")
-                    .arg("-b:a").arg(format!("{}k", options.audio_bitrate_kbps))
-                    .arg("-ar").arg(options.audio_sample_rate.to_string());
+                cmd.arg("-map").arg(audio_stream);
+                push_combined_audio_codec(&mut cmd, options);
+            }
+        }
+        // A file with video or a pipe holds both tracks; audio formats get a file each
+        let desktop_file = video_map.is_none() && pipe_url.is_none();
+        if let Some(desktop) = desktop_map.as_ref().filter(|_| !desktop_file) {
+            cmd.arg("-map").arg(desktop)
+                .arg("-metadata:s:a:0").arg("title=Microphone")
+                .arg("-metadata:s:a:1").arg("title=Desktop");
+        }
+
+        match &pipe_url {
+            // Matroska can be written without seeking and takes every codec used here
+            Some(url) => cmd.arg("-f").arg("matroska").arg(url),
+            None => {
+                let combined_output = outputs
+                    .combined
+                    .as_ref()
+                    .ok_or_else(|| anyhow!("Expected combined output path"))?;
+                if video_map.is_some() {
+                    push_container_flags(&mut cmd, options.output_container);
+                }
+                cmd.arg(combined_output)
+            }
+        };
+
+        if let (true, Some(desktop), Some(desktop_output)) = (desktop_file, &desktop_map, outputs.desktop_audio.as_ref()) {
+            cmd.arg("-map").arg(desktop);
+            push_audio_file_codec(&mut cmd, options);
+            cmd.arg(desktop_output);
+        }
+    }
+
+    // The webcam input feeds the overlay graph and, mapped directly, its own file
+    if let (Some(webcam_stream), Some(webcam_output)) = (separate_webcam_map, outputs.webcam.as_ref()) {
+        let upload = hw_upload.map(|upload| format!(",{}", upload)).unwrap_or_default();
+        cmd.arg("-map").arg(webcam_stream)
+            .arg("-vf").arg(format!("scale={}{}", even_scale_filter, upload));
+        push_video_codec(&mut cmd, options);
+        cmd.arg(webcam_output);
+    }
+
+    if let Some(stream) = stream {
+        let has_video = effective_include_video || effective_include_webcam;
+        if let Some(video_stream) = stream_video_map.filter(|_| has_video) {
+            cmd.arg("-map").arg(video_stream);
+            if video_scaled_by_vf {
+                cmd.arg("-vf").arg(&screen_scale);
             }
+            push_stream_video_codec(&mut cmd, options);
         }
+        if let Some(audio_stream) = stream_audio_map {
+            cmd.arg("-map").arg(audio_stream);
+            if let Some(chain) = &simple_audio_filter {
+                cmd.arg("-filter:a").arg(chain);
+            }
+            cmd.arg("-c:a").arg("aac")
+                .arg("-b:a").arg(format!("{}k", options.audio_bitrate_kbps.min(320)))
+                .arg("-ar").arg(options.audio_sample_rate.min(48000).to_string());
+        }
+        cmd.arg("-f").arg("tee").arg(stream.tee_output());
+    }
+
+    // Nothing is left on disk to post-process
+    let outputs = if pipe_url.is_some() {
+        RecordingOutputs { combined: None, video_only: None, audio_only: None, desktop_audio: None, ..outputs }
+    } else {
+        outputs
+    };
+    Ok((cmd.0, outputs))
+}
+
+/// x264 tuned for live delivery whatever the recording quality: no lookahead
+/// or B-frames, a capped bitrate the link can plan for and a keyframe every two
+/// seconds so a receiver can join quickly.
+fn push_stream_video_codec(cmd: &mut FfmpegArgs, options: &RecorderOptions) {
+    let kbps = options.video_bitrate_kbps;
+    cmd.arg("-c:v").arg("libx264")
+        .arg("-preset").arg("veryfast")
+        .arg("-tune").arg("zerolatency")
+        .arg("-pix_fmt").arg("yuv420p")
+        .arg("-b:v").arg(format!("{}k", kbps))
+        .arg("-maxrate").arg(format!("{}k", kbps))
+        .arg("-bufsize").arg(format!("{}k", kbps * 2))
+        .arg("-g").arg((options.frame_rate.max(1) * 2).to_string());
+    push_encoder_threads(cmd, options);
+}
+
+fn push_video_codec(cmd: &mut FfmpegArgs, options: &RecorderOptions) {
+    let hw_accel = recording_hw_accel(options);
+    if hw_accel != HwAccel::Software {
+        cmd.0.extend(hw_codec_args(hw_accel, options.video_quality, options.video_bitrate_kbps));
+        push_encoder_threads(cmd, options);
+        return;
+    }
+    cmd.0.extend(codec_args(options.video_codec, options.video_quality, options.video_bitrate_kbps));
+    if options.low_latency && options.video_codec == VideoCodec::H264 {
+        // No lookahead or B-frames, so frames leave the encoder as they arrive
+        cmd.arg("-tune").arg("zerolatency")
+            .arg("-flags").arg("+low_delay");
+    }
+    push_encoder_threads(cmd, options);
+}
 
-        cmd.arg(combined_output);
+/// The leading `-threads` only reaches the first input's decoder, so a cap
+/// has to be repeated for each encoder.
+fn push_encoder_threads(cmd: &mut FfmpegArgs, options: &RecorderOptions) {
+    if let Some(threads) = options.threads {
+        cmd.arg("-threads").arg(threads.to_string());
     }
+}
 
-    let child = cmd.spawn().context("Failed to spawn ffmpeg process")?;
-    Ok((child, outputs))
+/// Input options that stop ffmpeg buffering a capture input, skipping any
+/// the input's own arguments already set (x11grab has `+nobuffer`).
+fn low_latency_input_args(input: &[String]) -> Vec<String> {
+    let mut args = Vec::new();
+    if !input.iter().any(|a| a == "-fflags") {
+        args.extend(["-fflags".to_string(), "+nobuffer".to_string()]);
+    }
+    if !input.iter().any(|a| a == "-flags") {
+        args.extend(["-flags".to_string(), "+low_delay".to_string()]);
+    }
+    args
 }
 
-struct ScreenCaptureInput {
-    display_input: String,
-    video_size: String,
+/// x264 settings for the quality. Lossless uses `-qp 0` and no bitrate cap, so
+/// it bypasses the CRF mapping entirely, and keeps full-resolution chroma.
+pub fn video_codec_args(quality: VideoQuality, bitrate_kbps: u32) -> Vec<String> {
+    let mut cmd = FfmpegArgs(Vec::new());
+    cmd.arg("-c:v").arg("libx264");
+    if quality == VideoQuality::Lossless {
+        // ultrafast keeps up with capture; the files are huge either way
+        cmd.arg("-preset").arg("ultrafast")
+            .arg("-qp").arg("0")
+            // 4:2:0 would halve the color resolution and undo the point
+            .arg("-pix_fmt").arg("yuv444p");
+        return cmd.0;
+    }
+    cmd.arg("-preset").arg(preset_for_quality(quality))
+        .arg("-crf").arg(crf_for_quality(quality).to_string())
+        .arg("-pix_fmt").arg("yuv420p")
+        .arg("-b:v").arg(format!("{}k", bitrate_kbps));
+    cmd.0
 }
 
-fn determine_screen_input(screen_index: Option<usize>) -> Result<ScreenCaptureInput> {
-    let display = env::var("DISPLAY").unwrap_or_else(|_| ":0.0".to_string());
+/// Video codec settings for a file in `container`: VP9 for WebM, x264 for
+/// the others.
+pub fn container_codec_args(container: OutputContainer, quality: VideoQuality, bitrate_kbps: u32) -> Vec<String> {
+    codec_args(container.default_codecs().0, quality, bitrate_kbps)
+}
+
+/// Software encoder settings for `codec`.
+pub fn codec_args(codec: VideoCodec, quality: VideoQuality, bitrate_kbps: u32) -> Vec<String> {
+    if codec == VideoCodec::H264 {
+        return video_codec_args(quality, bitrate_kbps);
+    }
+    let mut cmd = FfmpegArgs(Vec::new());
+    // Realtime deadline with row threading is what lets libvpx keep up with capture
+    cmd.arg("-c:v").arg("libvpx-vp9")
+        .arg("-deadline").arg("realtime")
+        .arg("-cpu-used").arg("8")
+        .arg("-row-mt").arg("1")
+        .arg("-pix_fmt").arg("yuv420p");
+    if quality == VideoQuality::Lossless {
+        cmd.arg("-lossless").arg("1");
+        return cmd.0;
+    }
+    cmd.arg("-crf").arg(vp9_crf_for_quality(quality).to_string())
+        .arg("-b:v").arg(format!("{}k", bitrate_kbps));
+    cmd.0
+}
+
+/// Video arguments for re-encoding a file into `container` with `accel`:
+/// the ones that go before the input, then the encoder settings. WebM
+/// always encodes VP9 in software.
+pub fn file_video_args(
+    container: OutputContainer,
+    accel: HwAccel,
+    quality: VideoQuality,
+    bitrate_kbps: u32,
+) -> (Vec<String>, Vec<String>) {
+    let accel = if container == OutputContainer::WebM { HwAccel::Software } else { accel };
+    if accel == HwAccel::Software {
+        return (Vec::new(), container_codec_args(container, quality, bitrate_kbps));
+    }
+    let mut cmd = FfmpegArgs(Vec::new());
+    if accel == HwAccel::Vaapi {
+        cmd.arg("-vf").arg(VAAPI_UPLOAD_FILTER);
+    }
+    cmd.0.extend(hw_codec_args(accel, quality, bitrate_kbps));
+    (hw_device_args(accel), cmd.0)
+}
+
+/// The hardware encoder the recording uses; the hardware encoders are all
+/// H.264, so VP9 stays in software.
+fn recording_hw_accel(options: &RecorderOptions) -> HwAccel {
+    if options.video_codec == VideoCodec::Vp9 {
+        HwAccel::Software
+    } else {
+        options.hw_accel
+    }
+}
+
+/// H.264 settings for a hardware encoder, mapping the quality onto its
+/// constant-quality mode like the x264 CRF.
+pub fn hw_codec_args(accel: HwAccel, quality: VideoQuality, bitrate_kbps: u32) -> Vec<String> {
+    let mut cmd = FfmpegArgs(Vec::new());
+    cmd.arg("-c:v").arg(accel.encoder());
+    match accel {
+        HwAccel::Software => return video_codec_args(quality, bitrate_kbps),
+        HwAccel::Nvenc if quality == VideoQuality::Lossless => {
+            cmd.arg("-preset").arg("p1")
+                .arg("-tune").arg("lossless")
+                .arg("-pix_fmt").arg("yuv444p");
+        }
+        HwAccel::Nvenc => {
+            cmd.arg("-preset").arg("p4")
+                .arg("-rc").arg("vbr")
+                .arg("-cq").arg(crf_for_quality(quality).to_string())
+                .arg("-b:v").arg(format!("{}k", bitrate_kbps))
+                .arg("-pix_fmt").arg("yuv420p");
+        }
+        HwAccel::Vaapi => {
+            // Most drivers reject QP 0, so Lossless gets the finest they take
+            cmd.arg("-rc_mode").arg("CQP")
+                .arg("-qp").arg(crf_for_quality(quality).max(1).to_string());
+        }
+    }
+    cmd.0
+}
+
+/// Audio codec for the track muxed with video.
+fn push_combined_audio_codec(cmd: &mut FfmpegArgs, options: &RecorderOptions) {
+    match options.audio_codec {
+        AudioCodec::Aac => {
+            cmd.arg("-c:a").arg("aac")
+                .arg("-b:a").arg(format!("{}k", options.audio_bitrate_kbps))
+                .arg("-ar").arg(options.audio_sample_rate.to_string());
+        }
+        AudioCodec::Opus => {
+            // Opus runs at 48 kHz and tops out at 512 kbps
+            cmd.arg("-c:a").arg("libopus")
+                .arg("-b:a").arg(format!("{}k", options.audio_bitrate_kbps.min(512)))
+                .arg("-ar").arg("48000");
+        }
+    }
+}
+
+/// Muxer options for a file in `container`. MP4 moves its index to the front
+/// so players can start before the whole file has loaded.
+fn push_container_flags(cmd: &mut FfmpegArgs, container: OutputContainer) {
+    if container == OutputContainer::Mp4 {
+        cmd.arg("-movflags").arg("+faststart");
+    }
+}
+
+/// Codec arguments for a standalone audio file, matching its extension.
+fn push_audio_file_codec(cmd: &mut FfmpegArgs, options: &RecorderOptions) {
+    match options.audio_export_format {
+        AudioExportFormat::Flac => {
+            cmd.arg("-c:a").arg("flac")
+                .arg("-ar").arg(options.audio_sample_rate.to_string());
+            // The FLAC encoder takes 24-bit input as s32 with the depth marked separately
+            match options.flac_bit_depth {
+                FlacBitDepth::Bits16 => cmd.arg("-sample_fmt").arg("s16"),
+                FlacBitDepth::Bits24 => cmd.arg("-sample_fmt").arg("s32").arg("-bits_per_raw_sample").arg("24"),
+            };
+        }
+        AudioExportFormat::Mp3 => {
+            // MPEG-1 Layer III tops out at 48 kHz and 320 kbps
+            cmd.arg("-c:a").arg("libmp3lame")
+                .arg("-b:a").arg(format!("{}k", options.audio_bitrate_kbps.min(320)))
+                .arg("-ar").arg(options.audio_sample_rate.min(48000).to_string());
+        }
+    }
+}
+
+/// The command line a recording started now would run, quoted for a POSIX shell.
+pub fn dry_run_command(options: &RecorderOptions) -> Result<String> {
+    let name = recording_base_name(&options.file_name_template, Local::now());
+    let (args, _) = build_ffmpeg_args(options, &unique_base_name(&options.output_directory, &name))?;
+    Ok(std::iter::once(options.ffmpeg_path.as_str())
+        .chain(args.iter().map(String::as_str))
+        .map(shell_quote)
+        .collect::<Vec<_>>()
+        .join(" "))
+}
+
+pub(crate) fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=+,@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Adds the screen capture input. Returns the size to scale the capture back
+/// up to when it is a window following the cursor.
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn push_screen_input(cmd: &mut FfmpegArgs, options: &RecorderOptions) -> Result<ScreenInputSize> {
+    let wayland = std::env::var("WAYLAND_DISPLAY").is_ok();
+    // Derive preference from environment to avoid struct field coupling
+    let prefer_pipewire = std::env::var("OCTOCORD_USE_PIPEWIRE")
+        .map(|v| matches!(v.as_str(), "1" | "true" | "TRUE"))
+        .unwrap_or(false);
+    let have_display = std::env::var("DISPLAY").is_ok();
+
+    if wayland && prefer_pipewire && !have_display {
+        cmd.0.extend(pipewire_screen_input());
+        info!("Video input: pipewire (Wayland)");
+        if options.cursor_zoom.is_some() {
+            warn!("Cursor zoom needs x11grab; recording the full screen");
+        }
+        // The portal stream's size isn't known up front, so the region is cropped from it
+        Ok(ScreenInputSize { crop: options.capture_region, ..ScreenInputSize::default() })
+    } else {
+        let screen_input = determine_screen_input(options.selected_screen, options.capture_region)?;
+        let (width, height) = screen_input.size;
+        // follow_mouse roams the whole screen, so it can't stay inside a region
+        if options.cursor_zoom.is_some() && options.capture_region.is_some() {
+            warn!("Cursor zoom isn't combined with a capture region; recording the region unzoomed");
+        }
+        let window = options
+            .cursor_zoom
+            .filter(|_| options.capture_region.is_none())
+            .map(|zoom| cursor_zoom_window(width, height, zoom));
+        let (capture_w, capture_h) = window.unwrap_or((width, height));
+        let video_size_str = format!("{}x{}", capture_w, capture_h);
+        cmd.arg("-thread_queue_size").arg("2048")
+            .arg("-f").arg("x11grab")
+            .arg("-framerate").arg(options.frame_rate.to_string())
+            .arg("-probesize").arg("50M")
+            .arg("-fflags").arg("+nobuffer")
+            .arg("-use_wallclock_as_timestamps").arg("1");
+        if window.is_some() {
+            // x11grab moves the grab area so the pointer stays in the middle
+            cmd.arg("-follow_mouse").arg("centered");
+        }
+        cmd.arg("-video_size").arg(video_size_str.clone())
+            .arg("-i").arg(screen_input.display_input);
+        info!("Video input: x11grab {}", video_size_str);
+        Ok(ScreenInputSize { size: Some((width, height)), zoomed: window.is_some(), crop: None })
+    }
+}
+
+/// What a screen input captures, where the platform knows it up front.
+#[derive(Debug, Clone, Copy, Default)]
+struct ScreenInputSize {
+    /// The screen or region, before any cursor-zoom window is cut out of it.
+    size: Option<(u32, u32)>,
+    /// A cursor-following window is captured and scaled back up to `size`.
+    zoomed: bool,
+    /// Capture region the input can't select itself, left for a `crop` filter.
+    crop: Option<(u32, u32, u32, u32)>,
+}
+
+/// Even-sized part of a `width` x `height` screen that, scaled back up,
+/// magnifies it `zoom` times.
+pub fn cursor_zoom_window(width: u32, height: u32, zoom: f32) -> (u32, u32) {
+    let zoom = zoom.clamp(1.0, 8.0);
+    let shrink = |side: u32| ((side as f32 / zoom) as u32 & !1).clamp(2, side.max(2));
+    (shrink(width), shrink(height))
+}
+
+/// Experimental PipeWire screen capture (requires xdg-desktop-portal + ffmpeg pipewire)
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+pub fn pipewire_screen_input() -> Vec<String> {
+    ["-thread_queue_size", "2048", "-f", "pipewire", "-i", "0"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect()
+}
+
+#[cfg(target_os = "windows")]
+fn push_screen_input(cmd: &mut FfmpegArgs, options: &RecorderOptions) -> Result<ScreenInputSize> {
+    // gdigrab captures the whole virtual desktop; the selected monitor, or the
+    // region within it, is cut out with an offset into it.
+    let screen = select_screen(options.selected_screen)?;
+    let info = screen.display_info;
+    let (x, y, width, height) = match options.capture_region {
+        Some(region) => clamp_capture_region(region, info.width, info.height).ok_or_else(|| {
+            anyhow!("Capture region {:?} lies outside the {}x{} screen", region, info.width, info.height)
+        })?,
+        None => (0, 0, info.width, info.height),
+    };
+    let (offset_x, offset_y) = (info.x + x as i32, info.y + y as i32);
+    let video_size_str = format!("{}x{}", width, height);
+    cmd.arg("-thread_queue_size").arg("2048")
+        .arg("-f").arg("gdigrab")
+        .arg("-framerate").arg(options.frame_rate.to_string())
+        .arg("-draw_mouse").arg("1")
+        .arg("-offset_x").arg(offset_x.to_string())
+        .arg("-offset_y").arg(offset_y.to_string())
+        .arg("-video_size").arg(video_size_str.clone())
+        .arg("-i").arg("desktop");
+    info!("Video input: gdigrab {} at {},{}", video_size_str, offset_x, offset_y);
+    if options.cursor_zoom.is_some() {
+        warn!("Cursor zoom is not supported by gdigrab; recording the full screen");
+    }
+    Ok(ScreenInputSize { size: Some((width, height)), zoomed: false, crop: None })
+}
+
+#[cfg(target_os = "macos")]
+fn push_screen_input(cmd: &mut FfmpegArgs, options: &RecorderOptions) -> Result<ScreenInputSize> {
+    ensure_screen_capture_permission()?;
+    let screen = avfoundation_screen_device(options.selected_screen.unwrap_or(0));
+    let audio = if options.include_audio { avfoundation_audio_device(options) } else { "none".to_string() };
+    let device = format!("{}:{}", screen, audio);
+    cmd.arg("-thread_queue_size").arg("2048")
+        .arg("-f").arg("avfoundation")
+        .arg("-framerate").arg(options.frame_rate.to_string())
+        .arg("-capture_cursor").arg("1")
+        .arg("-i").arg(&device);
+    info!("Video input: avfoundation {}", device);
+    if options.cursor_zoom.is_some() {
+        warn!("Cursor zoom is not supported by avfoundation; recording the full screen");
+    }
+    // avfoundation always grabs the whole display
+    Ok(ScreenInputSize { crop: options.capture_region, ..ScreenInputSize::default() })
+}
+
+#[cfg(target_os = "macos")]
+const SCREEN_INPUT_CARRIES_AUDIO: bool = true;
+#[cfg(not(target_os = "macos"))]
+const SCREEN_INPUT_CARRIES_AUDIO: bool = false;
+
+#[cfg(target_os = "macos")]
+#[link(name = "CoreGraphics", kind = "framework")]
+extern "C" {
+    fn CGPreflightScreenCaptureAccess() -> bool;
+    fn CGRequestScreenCaptureAccess() -> bool;
+}
+
+/// Without Screen Recording permission avfoundation silently captures only the
+/// desktop wallpaper, so check up front. The request call shows the system
+/// prompt the first time and is a no-op afterwards.
+#[cfg(target_os = "macos")]
+fn ensure_screen_capture_permission() -> Result<()> {
+    // SAFETY: both functions take no arguments and only query or prompt for TCC access.
+    if unsafe { CGPreflightScreenCaptureAccess() } {
+        return Ok(());
+    }
+    if unsafe { CGRequestScreenCaptureAccess() } {
+        return Ok(());
+    }
+    Err(anyhow!(
+        "Screen recording permission denied. Grant it in System Settings → Privacy & Security → Screen Recording, then restart the app"
+    ))
+}
+
+#[cfg(target_os = "macos")]
+fn avfoundation_audio_device(options: &RecorderOptions) -> String {
+    // avfoundation accepts CoreAudio device names as well as "default"
+    options
+        .audio_device
+        .clone()
+        .filter(|d| !d.is_empty())
+        .unwrap_or_else(|| "default".to_string())
+}
+
+/// avfoundation names displays "Capture screen N" in the same order the system
+/// reports them, after any cameras; addressing by name avoids depending on how
+/// many cameras are attached.
+#[cfg(target_os = "macos")]
+fn avfoundation_screen_device(screen_index: usize) -> String {
+    format!("Capture screen {}", screen_index)
+}
+
+#[cfg(target_os = "macos")]
+fn push_audio_input(cmd: &mut FfmpegArgs, options: &RecorderOptions) -> Result<()> {
+    let device = format!(":{}", avfoundation_audio_device(options));
+    cmd.arg("-thread_queue_size").arg("2048")
+        .arg("-f").arg("avfoundation")
+        .arg("-i").arg(&device);
+    info!("Audio input: avfoundation {}", device);
+    Ok(())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn push_audio_input(cmd: &mut FfmpegArgs, options: &RecorderOptions) -> Result<()> {
+    // Choose ffmpeg audio backend
+    // Default to pulse when available (common with PipeWire), else ALSA.
+    let ff_backend = std::env::var("OCTOCORD_AUDIO_BACKEND").ok().unwrap_or_else(|| {
+        if ffmpeg_supports_pulse(&options.ffmpeg_path) { "pulse".to_string() } else { "alsa".to_string() }
+    });
+    let ff_format = if ff_backend.eq_ignore_ascii_case("pulse") { "pulse" } else { "alsa" };
+    // Use provided device when compatible, else logical default to avoid busy ALSA hw nodes
+    let ff_device = match (ff_format, options.audio_device.clone()) {
+        ("pulse", Some(dev)) if dev != "default" => dev,
+        _ => "default".to_string(),
+    };
+
+    cmd.arg("-thread_queue_size").arg("2048")
+        .arg("-f").arg(ff_format)
+        .arg("-ac").arg("2")
+        .arg("-ar").arg(options.audio_sample_rate.to_string())
+        .arg("-i").arg(ff_device);
+    info!("Audio input: {}:{} @ {} Hz", ff_format, "default", options.audio_sample_rate);
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn push_audio_input(cmd: &mut FfmpegArgs, options: &RecorderOptions) -> Result<()> {
+    let device = dshow_audio_device(options)?;
+    cmd.arg("-thread_queue_size").arg("2048")
+        .arg("-f").arg("dshow")
+        .arg("-ac").arg("2")
+        .arg("-sample_rate").arg(options.audio_sample_rate.to_string())
+        .arg("-i").arg(format!("audio={}", device));
+    info!("Audio input: dshow:{} @ {} Hz", device, options.audio_sample_rate);
+    Ok(())
+}
+
+/// Maps the selected cpal device to a DirectShow source. WASAPI names match the
+/// DirectShow friendly names for capture devices, but DirectShow cannot open a
+/// render endpoint, so loopback selections go through a loopback-capable source
+/// (virtual-audio-capturer or Stereo Mix) instead.
+#[cfg(target_os = "windows")]
+fn dshow_audio_device(options: &RecorderOptions) -> Result<String> {
+    let available = list_dshow_audio_devices(&options.ffmpeg_path);
+    match options.audio_device.as_deref() {
+        Some(name) if name.ends_with(crate::audio::LOOPBACK_SUFFIX) => dshow_loopback_device(&available),
+        Some(name) if name != "default" => Ok(name.to_string()),
+        _ => available
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No DirectShow audio capture devices found")),
+    }
+}
+
+#[cfg(target_os = "windows")]
+fn dshow_loopback_device(available: &[String]) -> Result<String> {
+    available
+        .iter()
+        .find(|d| d.eq_ignore_ascii_case("virtual-audio-capturer") || d.to_lowercase().contains("stereo mix"))
+        .cloned()
+        .ok_or_else(|| anyhow!(
+            "Desktop audio capture needs a DirectShow loopback source; enable 'Stereo Mix' in the Sound control panel or install virtual-audio-capturer"
+        ))
+}
+
+#[cfg(target_os = "windows")]
+fn list_dshow_audio_devices(ffmpeg_path: &str) -> Vec<String> {
+    let output = match Command::new(ffmpeg_path)
+        .arg("-hide_banner")
+        .arg("-list_devices").arg("true")
+        .arg("-f").arg("dshow")
+        .arg("-i").arg("dummy")
+        .output()
+    {
+        Ok(output) => output,
+        Err(err) => {
+            warn!("Failed to list DirectShow devices: {}", err);
+            return Vec::new();
+        }
+    };
+
+    // Older builds group devices under section headers, newer ones tag each line
+    let mut in_audio_section = false;
+    let mut devices = Vec::new();
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        if line.contains("DirectShow audio devices") {
+            in_audio_section = true;
+            continue;
+        }
+        if line.contains("DirectShow video devices") {
+            in_audio_section = false;
+            continue;
+        }
+        if line.contains("Alternative name") {
+            continue;
+        }
+        let tagged_audio = line.trim_end().ends_with("(audio)");
+        if !(tagged_audio || in_audio_section) {
+            continue;
+        }
+        let mut quoted = line.split('"');
+        if let (Some(_), Some(name)) = (quoted.next(), quoted.next()) {
+            devices.push(name.to_string());
+        }
+    }
+    devices
+}
+
+/// `sidechaincompress` keyed by the second input; its threshold is linear and
+/// bottoms out around -60 dBFS.
+fn sidechain_filter(ducking: &DuckingSettings) -> String {
+    let threshold = 10f32.powf(ducking.threshold_db.clamp(-60.0, 0.0) / 20.0);
+    format!(
+        "sidechaincompress=threshold={:.4}:ratio={:.1}:attack={:.0}:release={:.0}",
+        threshold,
+        ducking.ratio.clamp(1.0, 20.0),
+        ducking.attack_ms.clamp(0.01, 2000.0),
+        ducking.release_ms.clamp(0.01, 9000.0)
+    )
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn push_system_audio_input(cmd: &mut FfmpegArgs, options: &RecorderOptions) -> Result<()> {
+    if !ffmpeg_supports_pulse(&options.ffmpeg_path) {
+        return Err(anyhow!("Desktop audio capture needs ffmpeg with PulseAudio (or PipeWire-Pulse) support"));
+    }
+    // The default sink's monitor carries whatever is currently playing
+    let device = options
+        .system_audio_device
+        .clone()
+        .unwrap_or_else(|| "@DEFAULT_MONITOR@".to_string());
+    cmd.arg("-thread_queue_size").arg("2048")
+        .arg("-f").arg("pulse")
+        .arg("-ac").arg("2")
+        .arg("-ar").arg(options.audio_sample_rate.to_string())
+        .arg("-i").arg(&device);
+    info!("Desktop audio input: pulse:{}", device);
+    Ok(())
+}
+
+#[cfg(target_os = "windows")]
+fn push_system_audio_input(cmd: &mut FfmpegArgs, options: &RecorderOptions) -> Result<()> {
+    let device = match options.system_audio_device.clone() {
+        Some(device) => device,
+        None => dshow_loopback_device(&list_dshow_audio_devices(&options.ffmpeg_path))?,
+    };
+    cmd.arg("-thread_queue_size").arg("2048")
+        .arg("-f").arg("dshow")
+        .arg("-ac").arg("2")
+        .arg("-sample_rate").arg(options.audio_sample_rate.to_string())
+        .arg("-i").arg(format!("audio={}", device));
+    info!("Desktop audio input: dshow:{}", device);
+    Ok(())
+}
+
+/// macOS has no built-in loopback device; a virtual driver such as BlackHole must
+/// be installed and named explicitly.
+#[cfg(target_os = "macos")]
+fn push_system_audio_input(cmd: &mut FfmpegArgs, options: &RecorderOptions) -> Result<()> {
+    let device = options.system_audio_device.clone().ok_or_else(|| {
+        anyhow!("Desktop audio on macOS needs a loopback driver (e.g. BlackHole); set system_audio_device in the config")
+    })?;
+    let device = format!(":{}", device);
+    cmd.arg("-thread_queue_size").arg("2048")
+        .arg("-f").arg("avfoundation")
+        .arg("-i").arg(&device);
+    info!("Desktop audio input: avfoundation {}", device);
+    Ok(())
+}
+
+/// Position and size of the display `screen_index` selects, or `None` if
+/// it is gone. Cheap enough to poll: nothing is captured.
+pub fn display_geometry(screen_index: Option<usize>) -> Option<(i32, i32, u32, u32)> {
+    let info = select_screen(screen_index).ok()?.display_info;
+    Some((info.x, info.y, info.width, info.height))
+}
+
+fn select_screen(screen_index: Option<usize>) -> Result<Screen> {
     let screens = catch_unwind(AssertUnwindSafe(Screen::all))
         .map_err(|_| anyhow!("Screen capture backend crashed (missing Wayland screencopy support?)"))?
         .context("Failed to enumerate screens")?;
 
-    let screen = if let Some(index) = screen_index {
-        screens
-            .get(index)
-            .ok_or_else(|| anyhow!("Invalid screen index {}", index))?
-    } else {
-        screens
-            .first()
-            .ok_or_else(|| anyhow!("No screens detected"))?
-    };
+    match screen_index {
+        Some(index) => screens
+            .into_iter()
+            .nth(index)
+            .ok_or_else(|| anyhow!("Invalid screen index {}", index)),
+        None => screens
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("No screens detected")),
+    }
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+struct ScreenCaptureInput {
+    display_input: String,
+    size: (u32, u32),
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+fn determine_screen_input(
+    screen_index: Option<usize>,
+    region: Option<(u32, u32, u32, u32)>,
+) -> Result<ScreenCaptureInput> {
+    let display = std::env::var("DISPLAY").unwrap_or_else(|_| ":0.0".to_string());
+    let screen = select_screen(screen_index)?;
 
     let image = catch_unwind(AssertUnwindSafe(|| screen.capture()))
         .map_err(|_| anyhow!("Screen capture unsupported by compositor (missing ZwlrScreencopy?)"))?
         .context("Failed to capture screen to determine resolution")?;
     let (width, height) = image.dimensions();
+    let (x, y, width, height) = match region {
+        Some(region) => clamp_capture_region(region, width, height)
+            .ok_or_else(|| anyhow!("Capture region {:?} lies outside the {}x{} screen", region, width, height))?,
+        None => (0, 0, width, height),
+    };
+    // x11grab offsets are in root window coordinates, where the screen may not start at 0,0
+    let info = screen.display_info;
     Ok(ScreenCaptureInput {
-        display_input: format!("{}+0,0", display),
-        video_size: format!("{}x{}", width, height),
+        display_input: format!("{}+{},{}", display, info.x + x as i32, info.y + y as i32),
+        size: (width, height),
     })
 }
 
-fn prepare_output_paths_effective(options: &RecorderOptions, any_video: bool) -> Result<RecordingOutputs> {
-    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-    let base_name = format!("recording_{}", timestamp);
+/// Fits a capture region into a `screen_w` x `screen_h` screen. The size is
+/// rounded down to even numbers, which yuv420p requires. `None` if nothing of
+/// the region is left.
+pub fn clamp_capture_region(
+    (x, y, w, h): (u32, u32, u32, u32),
+    screen_w: u32,
+    screen_h: u32,
+) -> Option<(u32, u32, u32, u32)> {
+    let x = x.min(screen_w);
+    let y = y.min(screen_h);
+    let w = w.min(screen_w - x) & !1;
+    let h = h.min(screen_h - y) & !1;
+    (w > 0 && h > 0).then_some((x, y, w, h))
+}
 
+fn prepare_output_paths_effective(
+    options: &RecorderOptions,
+    any_video: bool,
+    separate_webcam: bool,
+    desktop_track: bool,
+    base_name: &str,
+) -> Result<RecordingOutputs> {
+    let container = options.output_container.extension();
+    let audio_ext = options.audio_export_format.extension();
+    // Split microphone and desktop audio files are numbered in that order
+    let (mic_suffix, desktop_suffix) = if desktop_track { (".audio1", ".audio2") } else { (".audio", "") };
     let combined = if options.separate_outputs && options.include_audio && any_video {
         None
+    } else if any_video {
+        Some(options.output_directory.join(format!("{}.{}", base_name, container)))
     } else {
-        let ext = if any_video { "mkv" } else { "flac" };
-        Some(options.output_directory.join(format!("{}.{}", base_name, ext)))
+        let suffix = if desktop_track { mic_suffix } else { "" };
+        Some(options.output_directory.join(format!("{}{}.{}", base_name, suffix, audio_ext)))
     };
 
     let video_only = if options.separate_outputs && any_video && options.include_audio {
-        Some(options.output_directory.join(format!("{}.video.mkv", base_name)))
+        Some(options.output_directory.join(format!("{}.video.{}", base_name, container)))
     } else if !options.include_audio && any_video {
-        Some(options.output_directory.join(format!("{}.mkv", base_name)))
+        Some(options.output_directory.join(format!("{}.{}", base_name, container)))
     } else {
         None
     };
 
     let audio_only = if options.include_audio {
         if options.separate_outputs && any_video {
-            Some(options.output_directory.join(format!("{}.audio.flac", base_name)))
+            Some(options.output_directory.join(format!("{}{}.{}", base_name, mic_suffix, audio_ext)))
         } else if !any_video {
-            Some(options.output_directory.join(format!("{}.flac", base_name)))
+            combined.clone()
         } else {
             None
         }
@@ -526,10 +2360,18 @@ fn prepare_output_paths_effective(options: &RecorderOptions, any_video: bool) ->
         None
     };
 
+    // With video and no split, desktop audio is a second track in the combined file
+    let desktop_audio = (desktop_track && audio_only.is_some())
+        .then(|| options.output_directory.join(format!("{}{}.{}", base_name, desktop_suffix, audio_ext)));
+
+    let webcam = separate_webcam.then(|| options.output_directory.join(format!("{}.webcam.mkv", base_name)));
+
     Ok(RecordingOutputs {
         combined,
         video_only,
         audio_only,
+        webcam,
+        desktop_audio,
     })
 }
 
@@ -539,6 +2381,17 @@ fn crf_for_quality(quality: VideoQuality) -> u8 {
         VideoQuality::Medium => 23,
         VideoQuality::High => 20,
         VideoQuality::Ultra => 18,
+        VideoQuality::Lossless => 0,
+    }
+}
+
+fn vp9_crf_for_quality(quality: VideoQuality) -> u8 {
+    match quality {
+        VideoQuality::Low => 40,
+        VideoQuality::Medium => 33,
+        VideoQuality::High => 28,
+        VideoQuality::Ultra => 24,
+        VideoQuality::Lossless => 0,
     }
 }
 
@@ -1,8 +1,10 @@
-use anyhow::Result;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Local};
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 
 #[cfg(feature = "webcam")]
-use log::{info, error};
+use log::{info, error, warn};
 #[cfg(feature = "webcam")]
 use nokhwa::Camera;
 #[cfg(feature = "webcam")]
@@ -12,8 +14,9 @@ use nokhwa::utils::{CameraFormat, FrameFormat, Resolution};
 #[cfg(feature = "webcam")]
 use nokhwa::utils::{CameraIndex, RequestedFormat, RequestedFormatType};
 #[cfg(feature = "webcam")]
-use std::thread;
+use nokhwa::utils::{ControlValueDescription, ControlValueSetter, KnownCameraControl};
 #[cfg(feature = "webcam")]
+use std::thread;
 use std::time::Duration;
 #[cfg(feature = "webcam")]
 use image::{DynamicImage, GenericImageView, GenericImage};
@@ -21,61 +24,193 @@ use image::{DynamicImage, GenericImageView, GenericImage};
 use image::DynamicImage;
 #[cfg(feature = "webcam")]
 use crossbeam::channel::{Sender, Receiver, bounded};
+#[cfg(feature = "webcam")]
+use std::time::Instant;
+
+#[cfg(feature = "webcam")]
+use crate::config::ChromaKey;
+use crate::config::BackgroundBlur;
+use crate::frame_budget::FrameBudget;
+#[cfg(feature = "webcam")]
+use crate::frame_budget::{queue_newest, take_newest, PREVIEW_QUEUE_CAPACITY};
+use crate::pacing::FramePacing;
+#[cfg(feature = "webcam")]
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Consecutive failed frame grabs after which the camera is reopened.
+#[cfg_attr(not(feature = "webcam"), allow(dead_code))]
+pub const FAILURES_BEFORE_RECONNECT: u32 = 10;
+
+/// Reopen attempts before the capture gives up on a lost camera.
+pub const MAX_RECONNECT_ATTEMPTS: u32 = 6;
+
+/// Health of the webcam capture thread, for the preview.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(not(feature = "webcam"), allow(dead_code))]
+pub enum WebcamStatus {
+    #[default]
+    Streaming,
+    /// The camera stopped delivering frames and is being reopened.
+    Reconnecting { attempt: u32 },
+    /// Reconnecting failed; the capture thread has stopped.
+    Failed(String),
+}
+
+/// Wait before reopen `attempt` (from 1): half a second, doubling up to 8 s.
+#[cfg_attr(not(feature = "webcam"), allow(dead_code))]
+pub fn reconnect_delay(attempt: u32) -> Duration {
+    Duration::from_millis(500 << attempt.saturating_sub(1).min(4))
+}
+
+/// Camera setting the webcam section can offer a slider for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(not(feature = "webcam"), allow(dead_code))]
+pub enum CameraSetting {
+    Exposure,
+    WhiteBalance,
+    Gain,
+    Focus,
+}
+
+impl CameraSetting {
+    #[cfg_attr(not(feature = "webcam"), allow(dead_code))]
+    pub const ALL: [CameraSetting; 4] =
+        [CameraSetting::Exposure, CameraSetting::WhiteBalance, CameraSetting::Gain, CameraSetting::Focus];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            CameraSetting::Exposure => "Exposure",
+            CameraSetting::WhiteBalance => "White balance",
+            CameraSetting::Gain => "Gain",
+            CameraSetting::Focus => "Focus",
+        }
+    }
+
+    #[cfg(feature = "webcam")]
+    fn known_control(self) -> KnownCameraControl {
+        match self {
+            CameraSetting::Exposure => KnownCameraControl::Exposure,
+            CameraSetting::WhiteBalance => KnownCameraControl::WhiteBalance,
+            CameraSetting::Gain => KnownCameraControl::Gain,
+            CameraSetting::Focus => KnownCameraControl::Focus,
+        }
+    }
+}
+
+/// A setting the open camera supports, in the camera's own units.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CameraControlRange {
+    pub setting: CameraSetting,
+    pub min: i64,
+    pub max: i64,
+    pub step: i64,
+    pub value: i64,
+    pub default: i64,
+}
+
+impl CameraControlRange {
+    /// `value` clamped to the range and snapped to the nearest step from `min`.
+    #[cfg_attr(not(feature = "webcam"), allow(dead_code))]
+    pub fn snap(&self, value: i64) -> i64 {
+        let step = self.step.max(1);
+        let steps = (value.clamp(self.min, self.max) - self.min + step / 2) / step;
+        (self.min + steps * step).min(self.max)
+    }
+}
 
 #[cfg(feature = "webcam")]
 pub struct WebcamCapture {
     camera: Option<Camera>,
-    camera_index: CameraIndex,
+    camera_name: String,
+    /// Updated when a reconnect finds the camera under another index.
+    camera_index: Arc<Mutex<CameraIndex>>,
     format: CameraFormat,
+    status: Arc<Mutex<WebcamStatus>>,
+    /// Controls of the open camera, with the values last set.
+    controls: Arc<Mutex<Vec<CameraControlRange>>>,
+    /// Changes for the capture thread, which owns the camera, to apply.
+    control_changes: Arc<Mutex<Vec<(CameraSetting, i64)>>>,
     is_capturing: Arc<Mutex<bool>>,
     frame_sender: Sender<DynamicImage>,
     frame_receiver: Receiver<DynamicImage>,
     capture_thread: Option<thread::JoinHandle<()>>,
+    pacing: FramePacing,
+    budget: FrameBudget,
+    dropped_frames: Arc<AtomicU64>,
 }
 
 #[cfg(feature = "webcam")]
 impl WebcamCapture {
-    pub fn new(camera_name: &str) -> Result<Self> {
+    pub fn new(camera_name: &str, pacing: FramePacing, budget: FrameBudget) -> Result<Self> {
         let camera_index = find_camera_index(camera_name)?;
         let format = CameraFormat::new(Resolution::new(640, 480), FrameFormat::MJPEG, 30);
 
-        let (sender, receiver) = bounded(2);
+        let (sender, receiver) = bounded(PREVIEW_QUEUE_CAPACITY);
 
         Ok(Self {
             camera: None,
-            camera_index,
+            camera_name: camera_name.to_string(),
+            camera_index: Arc::new(Mutex::new(camera_index)),
             format,
+            status: Arc::new(Mutex::new(WebcamStatus::Streaming)),
+            controls: Arc::new(Mutex::new(Vec::new())),
+            control_changes: Arc::new(Mutex::new(Vec::new())),
             is_capturing: Arc::new(Mutex::new(false)),
             frame_sender: sender,
             frame_receiver: receiver,
             capture_thread: None,
+            pacing,
+            budget,
+            dropped_frames: Arc::new(AtomicU64::new(0)),
         })
     }
 
     pub fn start(&mut self) -> Result<()> {
         let requested_format = RequestedFormat::new::<RgbFormat>(RequestedFormatType::Closest(self.format));
-        let mut camera = Camera::new(self.camera_index.clone(), requested_format)?;
+        let camera_index = self.camera_index.lock().unwrap().clone();
+        let mut camera = Camera::new(camera_index, requested_format)?;
         camera.open_stream()?;
+        *self.controls.lock().unwrap() = supported_controls(&camera);
 
         self.camera = Some(camera);
 
         let is_capturing = Arc::clone(&self.is_capturing);
         let sender = self.frame_sender.clone();
+        let receiver = self.frame_receiver.clone();
         let mut camera = self.camera.take().unwrap();
+        let pacing = self.pacing.clone();
+        let budget = self.budget.clone();
+        let dropped_frames = Arc::clone(&self.dropped_frames);
+        let camera_name = self.camera_name.clone();
+        let camera_index = Arc::clone(&self.camera_index);
+        let status = Arc::clone(&self.status);
+        let controls = Arc::clone(&self.controls);
+        let control_changes = Arc::clone(&self.control_changes);
+        let format = self.format;
 
         *is_capturing.lock().unwrap() = true;
+        *status.lock().unwrap() = WebcamStatus::Streaming;
 
         self.capture_thread = Some(thread::spawn(move || {
             info!("Webcam capture thread started");
+            let mut failures = 0;
 
             while *is_capturing.lock().unwrap() {
+                for (setting, value) in control_changes.lock().unwrap().drain(..) {
+                    if let Err(e) = camera.set_camera_control(setting.known_control(), ControlValueSetter::Integer(value)) {
+                        warn!("Failed to set webcam {} to {}: {}", setting.label(), value, e);
+                    }
+                }
+                let frame_start = Instant::now();
                 match camera.frame() {
                     Ok(frame) => match frame.decode_image::<RgbFormat>() {
                         Ok(image_buffer) => {
+                            failures = 0;
                             let image = DynamicImage::ImageRgb8(image_buffer);
-                            if let Err(e) = sender.try_send(image) {
-                                log::debug!("Dropping webcam frame ({}): channel full", e);
-                                // Drop if channel is full
+                            let dropped = queue_newest(&sender, &receiver, &budget, image);
+                            if dropped > 0 {
+                                log::debug!("Dropped {} webcam frame(s): preview fell behind", dropped);
+                                dropped_frames.fetch_add(dropped, Ordering::Relaxed);
                             }
                         }
                         Err(e) => {
@@ -84,11 +219,27 @@ impl WebcamCapture {
                     },
                     Err(e) => {
                         error!("Failed to capture webcam frame: {}", e);
+                        failures += 1;
+                        if failures >= FAILURES_BEFORE_RECONNECT {
+                            // Release the device so it can be opened again
+                            let _ = camera.stop_stream();
+                            match reconnect(&camera_name, format, &camera_index, &status, &is_capturing) {
+                                Some(reopened) => {
+                                    camera = reopened;
+                                    // A replugged camera starts from its own defaults
+                                    *controls.lock().unwrap() = supported_controls(&camera);
+                                    failures = 0;
+                                }
+                                None => break,
+                            }
+                            continue;
+                        }
                         thread::sleep(Duration::from_millis(100));
                     }
                 }
 
-                thread::sleep(Duration::from_millis(33));
+                // camera.frame() blocks for the camera's own rate; only sleep off the rest
+                thread::sleep(pacing.preview_interval().saturating_sub(frame_start.elapsed()));
             }
 
             info!("Webcam capture thread stopped");
@@ -104,42 +255,182 @@ impl WebcamCapture {
         if let Some(thread) = self.capture_thread.take() {
             thread.join().ok();
         }
+        let _ = self.get_latest_frame();
 
         info!("Webcam capture stopped");
         Ok(())
     }
 
+    /// The newest captured frame, skipping any older ones still queued.
     pub fn get_latest_frame(&self) -> Option<DynamicImage> {
-        self.frame_receiver.try_recv().ok()
+        take_newest(&self.frame_receiver, &self.budget)
+    }
+
+    /// Frames dropped because the preview fell behind or the memory budget
+    /// was used up.
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames.load(Ordering::Relaxed)
+    }
+
+    /// Width-over-height shape of one captured pixel. nokhwa doesn't report a
+    /// pixel aspect ratio, so frames are treated as square until it does.
+    pub fn sample_aspect_ratio(&self) -> f32 {
+        1.0
+    }
+
+    pub fn status(&self) -> WebcamStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Exposure, white balance, gain and focus, as far as the open camera
+    /// exposes them as ranges; the rest are left out.
+    pub fn controls(&self) -> Vec<CameraControlRange> {
+        self.controls.lock().unwrap().clone()
+    }
+
+    /// Sets a control on the running camera; it takes effect before the next frame.
+    pub fn set_control(&self, setting: CameraSetting, value: i64) {
+        let mut controls = self.controls.lock().unwrap();
+        let Some(control) = controls.iter_mut().find(|c| c.setting == setting) else {
+            return;
+        };
+        control.value = control.snap(value);
+        self.control_changes.lock().unwrap().push((setting, control.value));
     }
 }
 
+/// The settings of `CameraSetting` that `camera` offers as integer ranges.
 #[cfg(feature = "webcam")]
-fn find_camera_index(camera_name: &str) -> Result<CameraIndex> {
-    let cameras = nokhwa::query(nokhwa::utils::ApiBackend::Auto)?;
+fn supported_controls(camera: &Camera) -> Vec<CameraControlRange> {
+    let controls = match camera.camera_controls() {
+        Ok(controls) => controls,
+        Err(e) => {
+            warn!("Couldn't read webcam controls: {}", e);
+            return Vec::new();
+        }
+    };
+    CameraSetting::ALL
+        .into_iter()
+        .filter_map(|setting| {
+            let control = controls.iter().find(|c| c.control() == setting.known_control())?;
+            match *control.description() {
+                ControlValueDescription::IntegerRange { min, max, value, step, default } if min < max => {
+                    Some(CameraControlRange { setting, min, max, step, value, default })
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
 
-    for (i, camera_info) in cameras.iter().enumerate() {
-        if camera_info.human_name().contains(camera_name) {
-            return Ok(CameraIndex::Index(i as u32));
+/// Reopens a camera that stopped delivering frames, looking it up by name
+/// again in case it came back under another index. Backs off between
+/// attempts and gives up after `MAX_RECONNECT_ATTEMPTS`, or when capture
+/// is stopped meanwhile.
+#[cfg(feature = "webcam")]
+fn reconnect(
+    camera_name: &str,
+    format: CameraFormat,
+    camera_index: &Mutex<CameraIndex>,
+    status: &Mutex<WebcamStatus>,
+    is_capturing: &Mutex<bool>,
+) -> Option<Camera> {
+    for attempt in 1..=MAX_RECONNECT_ATTEMPTS {
+        *status.lock().unwrap() = WebcamStatus::Reconnecting { attempt };
+        let wake = Instant::now() + reconnect_delay(attempt);
+        while Instant::now() < wake {
+            if !*is_capturing.lock().unwrap() {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        // The camera may come back under another index, so enumerate afresh
+        let opened = refresh().and_then(|_| find_camera_index(camera_name)).and_then(|index| {
+            let requested_format = RequestedFormat::new::<RgbFormat>(RequestedFormatType::Closest(format));
+            let mut camera = Camera::new(index.clone(), requested_format)?;
+            camera.open_stream()?;
+            Ok((index, camera))
+        });
+        match opened {
+            Ok((index, camera)) => {
+                info!("Webcam reconnected as camera {}", index);
+                *camera_index.lock().unwrap() = index;
+                *status.lock().unwrap() = WebcamStatus::Streaming;
+                return Some(camera);
+            }
+            Err(e) => warn!("Webcam reconnect attempt {} failed: {}", attempt, e),
         }
     }
 
-    if !cameras.is_empty() {
-        Ok(CameraIndex::Index(0))
-    } else {
-        Err(anyhow::anyhow!("No cameras found"))
+    let message = format!("Webcam lost; gave up after {} reconnect attempts", MAX_RECONNECT_ATTEMPTS);
+    error!("{}", message);
+    *status.lock().unwrap() = WebcamStatus::Failed(message);
+    None
+}
+
+#[cfg(feature = "webcam")]
+impl Drop for WebcamCapture {
+    fn drop(&mut self) {
+        let _ = self.stop();
     }
 }
 
+/// Names of the cameras from the last `refresh`, by nokhwa index; `None`
+/// until the first lookup.
+#[cfg_attr(not(feature = "webcam"), allow(dead_code))]
+static CAMERA_NAMES: Mutex<Option<Vec<String>>> = Mutex::new(None);
+
+/// Makes camera names unique by numbering repeats ("Cam", "Cam #2"), so two
+/// identical cameras can still be told apart by name.
+#[cfg_attr(not(feature = "webcam"), allow(dead_code))]
+pub fn unique_camera_names(names: &[String]) -> Vec<String> {
+    names
+        .iter()
+        .enumerate()
+        .map(|(i, name)| match names[..i].iter().filter(|n| *n == name).count() {
+            0 => name.clone(),
+            repeats => format!("{} #{}", name, repeats + 1),
+        })
+        .collect()
+}
+
+/// Index of `wanted` among `names`: an exact match, else the first name
+/// containing it, else the first camera.
+#[cfg_attr(not(feature = "webcam"), allow(dead_code))]
+pub fn match_camera(names: &[String], wanted: &str) -> Option<usize> {
+    names
+        .iter()
+        .position(|name| name == wanted)
+        .or_else(|| names.iter().position(|name| name.contains(wanted)))
+        .or(if names.is_empty() { None } else { Some(0) })
+}
+
+/// Enumerates the cameras again and rebuilds the name cache used to open
+/// them; returns the names.
 #[cfg(feature = "webcam")]
-pub fn get_available_webcams() -> Result<Vec<String>> {
+pub fn refresh() -> Result<Vec<String>> {
     let cameras = nokhwa::query(nokhwa::utils::ApiBackend::Auto)?;
+    let names = unique_camera_names(&cameras.iter().map(|camera| camera.human_name()).collect::<Vec<_>>());
+    *CAMERA_NAMES.lock().unwrap() = Some(names.clone());
+    Ok(names)
+}
 
-    let mut webcam_names = Vec::new();
+#[cfg(feature = "webcam")]
+fn find_camera_index(camera_name: &str) -> Result<CameraIndex> {
+    let cached = CAMERA_NAMES.lock().unwrap().clone();
+    let names = match cached {
+        Some(names) => names,
+        None => refresh()?,
+    };
+    match_camera(&names, camera_name)
+        .map(|index| CameraIndex::Index(index as u32))
+        .ok_or_else(|| anyhow::anyhow!("No cameras found"))
+}
 
-    for camera_info in cameras {
-        webcam_names.push(camera_info.human_name());
-    }
+#[cfg(feature = "webcam")]
+pub fn get_available_webcams() -> Result<Vec<String>> {
+    let mut webcam_names = refresh()?;
 
     if webcam_names.is_empty() {
         webcam_names.push("Default Webcam".to_string());
@@ -156,6 +447,7 @@ pub struct WebcamOverlay {
     opacity: f32,
     border_color: [u8; 4],
     border_width: u32,
+    chroma_key: Option<ChromaKey>,
 }
 
 #[cfg(feature = "webcam")]
@@ -168,6 +460,7 @@ impl WebcamOverlay {
             opacity: 1.0,
             border_color: [88, 101, 242, 255],
             border_width: 2,
+            chroma_key: None,
         }
     }
 
@@ -191,7 +484,10 @@ impl WebcamOverlay {
             if screen_x < screen_frame.width() && screen_y < screen_frame.height() {
                 let mut screen_pixel = screen_frame.get_pixel(screen_x, screen_y);
 
-                let alpha = pixel.0[3] as f32 / 255.0 * self.opacity;
+                let key_alpha = self.chroma_key.map_or(1.0, |key| {
+                    chroma_key_alpha([pixel.0[0], pixel.0[1], pixel.0[2]], key.color, key.similarity, key.blend)
+                });
+                let alpha = pixel.0[3] as f32 / 255.0 * self.opacity * key_alpha;
                 let inv_alpha = 1.0 - alpha;
 
                 screen_pixel.0[0] = (pixel.0[0] as f32 * alpha + screen_pixel.0[0] as f32 * inv_alpha) as u8;
@@ -248,6 +544,20 @@ impl WebcamOverlay {
     pub fn set_opacity(&mut self, opacity: f32) {
         self.opacity = opacity.clamp(0.0, 1.0);
     }
+
+    pub fn set_border_color(&mut self, color: [u8; 4]) {
+        self.border_color = color;
+    }
+
+    /// 0 disables the border.
+    pub fn set_border_width(&mut self, width: u32) {
+        self.border_width = width;
+    }
+
+    /// `None` or a disabled key leaves the webcam opaque.
+    pub fn set_chroma_key(&mut self, key: Option<ChromaKey>) {
+        self.chroma_key = key.filter(|k| k.enabled);
+    }
 }
 
 #[cfg(not(feature = "webcam"))]
@@ -257,7 +567,7 @@ pub struct WebcamCapture {
 
 #[cfg(not(feature = "webcam"))]
 impl WebcamCapture {
-    pub fn new(_camera_name: &str) -> Result<Self> {
+    pub fn new(_camera_name: &str, _pacing: FramePacing, _budget: FrameBudget) -> Result<Self> {
         Ok(Self {
             is_capturing: Arc::new(Mutex::new(false)),
         })
@@ -276,9 +586,116 @@ impl WebcamCapture {
     pub fn get_latest_frame(&self) -> Option<DynamicImage> {
         None
     }
+
+    pub fn dropped_frames(&self) -> u64 {
+        0
+    }
+
+    pub fn sample_aspect_ratio(&self) -> f32 {
+        1.0
+    }
+
+    pub fn status(&self) -> WebcamStatus {
+        WebcamStatus::Streaming
+    }
+
+    pub fn controls(&self) -> Vec<CameraControlRange> {
+        Vec::new()
+    }
+
+    pub fn set_control(&self, _setting: CameraSetting, _value: i64) {}
 }
 
 #[cfg(not(feature = "webcam"))]
 pub fn get_available_webcams() -> Result<Vec<String>> {
     Ok(vec!["Default Webcam".to_string()])
 }
+
+/// Opacity of `pixel` under a chroma key, matching ffmpeg's `colorkey`: 0
+/// within `similarity` of `key` (as a 0..=1 RGB distance), ramping up to 1
+/// over the next `blend`.
+#[cfg_attr(not(feature = "webcam"), allow(dead_code))]
+pub fn chroma_key_alpha(pixel: [u8; 3], key: [u8; 3], similarity: f32, blend: f32) -> f32 {
+    let distance = pixel
+        .iter()
+        .zip(key)
+        .map(|(&p, k)| (p as f32 - k as f32).powi(2))
+        .sum::<f32>()
+        .sqrt()
+        / (255.0 * 3f32.sqrt());
+    if blend > 0.0 {
+        ((distance - similarity) / blend).clamp(0.0, 1.0)
+    } else if distance > similarity {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+/// `frame` blurred outside the centered subject box of `blur`.
+pub fn blur_background(frame: &DynamicImage, blur: &BackgroundBlur) -> DynamicImage {
+    let (width, height) = (frame.width(), frame.height());
+    let box_width = (width as f32 * blur.subject_width.clamp(0.0, 1.0)) as u32;
+    let box_height = (height as f32 * blur.subject_height.clamp(0.0, 1.0)) as u32;
+    let (x, y) = ((width - box_width) / 2, (height - box_height) / 2);
+
+    let mut blurred = frame.fast_blur(blur.radius as f32 / 2.0);
+    let subject = frame.crop_imm(x, y, box_width, box_height);
+    image::imageops::replace(&mut blurred, &subject, x as i64, y as i64);
+    blurred
+}
+
+/// `left` and `right` next to each other, both scaled to the taller height.
+#[cfg_attr(not(feature = "webcam"), allow(dead_code))]
+pub fn side_by_side(left: &DynamicImage, right: &DynamicImage) -> DynamicImage {
+    let height = left.height().max(right.height()).max(1);
+    let scaled = |image: &DynamicImage| {
+        let width = (image.width() as u64 * height as u64 / image.height().max(1) as u64).max(1) as u32;
+        image.resize_exact(width, height, image::imageops::FilterType::Triangle)
+    };
+    let (left, right) = (scaled(left), scaled(right));
+    let mut out = DynamicImage::new_rgba8(left.width() + right.width(), height);
+    image::imageops::replace(&mut out, &left, 0, 0);
+    image::imageops::replace(&mut out, &right, left.width() as i64, 0);
+    out
+}
+
+/// `webcam_<date>_<time>.jpg` in `dir`.
+pub fn snapshot_path(dir: &Path, now: DateTime<Local>) -> PathBuf {
+    dir.join(format!("webcam_{}.jpg", now.format("%Y%m%d_%H%M%S")))
+}
+
+/// Writes `frame` as a timestamped JPEG in `dir` and returns its path.
+pub fn save_snapshot(frame: &DynamicImage, dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    let path = snapshot_path(dir, Local::now());
+    // JPEG has no alpha channel
+    frame
+        .to_rgb8()
+        .save(&path)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/// Opens `camera_name` just long enough to grab one frame. The first frames
+/// are skipped so auto exposure has a moment to settle.
+pub fn grab_frame(camera_name: &str, pacing: FramePacing, budget: FrameBudget) -> Result<DynamicImage> {
+    const WARM_UP: std::time::Duration = std::time::Duration::from_millis(500);
+    const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+    let mut capture = WebcamCapture::new(camera_name, pacing, budget)?;
+    capture.start()?;
+    let opened = std::time::Instant::now();
+    let mut frame = None;
+    while opened.elapsed() < TIMEOUT {
+        if let Some(latest) = capture.get_latest_frame() {
+            frame = Some(latest);
+            if opened.elapsed() >= WARM_UP {
+                break;
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    capture.stop()?;
+    frame.ok_or_else(|| anyhow!("the webcam sent no picture"))
+}